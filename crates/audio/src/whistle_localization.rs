@@ -0,0 +1,293 @@
+use std::{
+    f32::consts::{PI, TAU},
+    ops::Range,
+    sync::Arc,
+};
+
+use color_eyre::Result;
+use context_attribute::context;
+use coordinate_systems::Head;
+use framework::{deserialize_not_implemented, AdditionalOutput, MainOutput};
+use itertools::Itertools;
+use linear_algebra::{Point3, Vector3};
+use rustfft::{num_complex::Complex32, num_traits::Zero, Fft, FftPlanner};
+use serde::{Deserialize, Serialize};
+use types::{parameters::WhistleLocalizationParameters, samples::Samples, whistle::Whistle};
+
+use crate::whistle_detection::NUMBER_OF_AUDIO_SAMPLES;
+
+#[derive(Deserialize, Serialize)]
+pub struct WhistleLocalization {
+    #[serde(skip, default = "deserialize_not_implemented")]
+    forward_fft: Arc<dyn Fft<f32>>,
+    #[serde(skip, default = "deserialize_not_implemented")]
+    inverse_fft: Arc<dyn Fft<f32>>,
+    #[serde(skip)]
+    scratch: Vec<Complex32>,
+}
+
+#[context]
+pub struct CreationContext {}
+
+#[context]
+pub struct CycleContext {
+    detection_band: Parameter<Range<f32>, "whistle_detection.detection_band">,
+    parameters: Parameter<WhistleLocalizationParameters, "whistle_localization">,
+
+    detected_whistle: Input<Whistle, "detected_whistle">,
+    samples: Input<Samples, "samples">,
+
+    whistle_direction_scores: AdditionalOutput<Vec<(f32, f32)>, "whistle_direction_scores">,
+}
+
+#[context]
+#[derive(Default)]
+pub struct MainOutputs {
+    /// Horizontal direction towards the whistle in the head frame
+    pub whistle_direction: MainOutput<Option<Vector3<Head>>>,
+}
+
+impl WhistleLocalization {
+    pub fn new(_context: CreationContext) -> Result<Self> {
+        let mut planner = FftPlanner::new();
+        let forward_fft = planner.plan_fft_forward(NUMBER_OF_AUDIO_SAMPLES);
+        let inverse_fft = planner.plan_fft_inverse(NUMBER_OF_AUDIO_SAMPLES);
+        let scratch = vec![
+            Complex32::zero();
+            forward_fft
+                .get_inplace_scratch_len()
+                .max(inverse_fft.get_inplace_scratch_len())
+        ];
+        Ok(Self {
+            forward_fft,
+            inverse_fft,
+            scratch,
+        })
+    }
+
+    pub fn cycle(&mut self, mut context: CycleContext) -> Result<MainOutputs> {
+        let parameters = context.parameters;
+        let channels = &context.samples.channels_of_samples;
+        if !context.detected_whistle.is_detected.iter().any(|&x| x)
+            || channels.len() != parameters.microphone_positions.len()
+            || channels
+                .iter()
+                .any(|channel| channel.len() != NUMBER_OF_AUDIO_SAMPLES)
+        {
+            return Ok(MainOutputs::default());
+        }
+
+        let cross_correlations = self.cross_correlations(
+            channels,
+            context.samples.rate as f32,
+            context.detection_band,
+        );
+        let scores = steered_response_powers(
+            &cross_correlations,
+            &parameters.microphone_positions,
+            parameters.speed_of_sound,
+            context.samples.rate as f32,
+            parameters.number_of_directions,
+        );
+        context
+            .whistle_direction_scores
+            .fill_if_subscribed(|| scores.clone());
+        let whistle_direction = scores
+            .iter()
+            .max_by(|(_, left), (_, right)| left.total_cmp(right))
+            .map(|&(azimuth, _)| horizontal_direction(azimuth));
+
+        Ok(MainOutputs {
+            whistle_direction: whistle_direction.into(),
+        })
+    }
+
+    /// Band limited generalized cross correlations with phase transform (GCC-PHAT) of all
+    /// microphone pairs, indexed by lag in samples (negative lags wrap around)
+    fn cross_correlations(
+        &mut self,
+        channels: &[Vec<f32>],
+        sample_rate: f32,
+        detection_band: &Range<f32>,
+    ) -> Vec<PairCorrelation> {
+        let frequency_resolution = sample_rate / NUMBER_OF_AUDIO_SAMPLES as f32;
+        let spectrums: Vec<Vec<Complex32>> = channels
+            .iter()
+            .map(|channel| {
+                let mut spectrum: Vec<_> = channel
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &sample)| {
+                        let hann = (PI * i as f32 / NUMBER_OF_AUDIO_SAMPLES as f32)
+                            .sin()
+                            .powi(2);
+                        Complex32::new(hann * sample, 0.0)
+                    })
+                    .collect();
+                self.forward_fft
+                    .process_with_scratch(&mut spectrum, &mut self.scratch);
+                spectrum
+            })
+            .collect();
+
+        (0..channels.len())
+            .tuple_combinations()
+            .map(|(first, second)| {
+                let mut cross_spectrum: Vec<_> = spectrums[first]
+                    .iter()
+                    .zip(&spectrums[second])
+                    .enumerate()
+                    .map(|(index, (first, second))| {
+                        let frequency = index.min(NUMBER_OF_AUDIO_SAMPLES - index) as f32
+                            * frequency_resolution;
+                        if !detection_band.contains(&frequency) {
+                            return Complex32::zero();
+                        }
+                        let product = first * second.conj();
+                        let magnitude = product.norm();
+                        if magnitude > f32::EPSILON {
+                            product / magnitude
+                        } else {
+                            Complex32::zero()
+                        }
+                    })
+                    .collect();
+                self.inverse_fft
+                    .process_with_scratch(&mut cross_spectrum, &mut self.scratch);
+                PairCorrelation {
+                    first,
+                    second,
+                    correlation: cross_spectrum.iter().map(|value| value.re).collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+struct PairCorrelation {
+    first: usize,
+    second: usize,
+    correlation: Vec<f32>,
+}
+
+impl PairCorrelation {
+    fn at_lag(&self, lag: f32) -> f32 {
+        let length = self.correlation.len();
+        let lower = lag.floor();
+        let fraction = lag - lower;
+        let lower_index = (lower as isize).rem_euclid(length as isize) as usize;
+        let upper_index = (lower_index + 1) % length;
+        self.correlation[lower_index] * (1.0 - fraction) + self.correlation[upper_index] * fraction
+    }
+}
+
+/// Sums up the cross correlations at the time differences of arrival expected for sources in
+/// each horizontal direction, returns (azimuth, score) pairs
+fn steered_response_powers(
+    cross_correlations: &[PairCorrelation],
+    microphone_positions: &[Point3<Head>],
+    speed_of_sound: f32,
+    sample_rate: f32,
+    number_of_directions: usize,
+) -> Vec<(f32, f32)> {
+    (0..number_of_directions)
+        .map(|index| {
+            let azimuth = index as f32 * TAU / number_of_directions as f32 - PI;
+            let direction = horizontal_direction(azimuth);
+            let score = cross_correlations
+                .iter()
+                .map(|pair| {
+                    let baseline =
+                        microphone_positions[pair.first] - microphone_positions[pair.second];
+                    // the microphone closer to the source hears the whistle earlier
+                    let lag = -baseline.dot(direction) / speed_of_sound * sample_rate;
+                    pair.at_lag(lag)
+                })
+                .sum();
+            (azimuth, score)
+        })
+        .collect()
+}
+
+fn horizontal_direction(azimuth: f32) -> Vector3<Head> {
+    Vector3::x_axis() * azimuth.cos() + Vector3::y_axis() * azimuth.sin()
+}
+
+#[cfg(test)]
+mod tests {
+    use linear_algebra::point;
+
+    use super::*;
+
+    fn nao_microphone_positions() -> Vec<Point3<Head>> {
+        vec![
+            point![-0.0195, 0.0606, 0.0331],
+            point![-0.0195, -0.0606, 0.0331],
+            point![0.0206, 0.0309, 0.0986],
+            point![0.0206, -0.0309, 0.0986],
+        ]
+    }
+
+    fn whistle_from(azimuth: f32, microphone_positions: &[Point3<Head>]) -> Vec<Vec<f32>> {
+        let sample_rate = 44100.0;
+        let speed_of_sound = 343.0;
+        let direction = horizontal_direction(azimuth);
+        let frequencies = [2150.0, 2430.0, 2780.0, 3120.0, 3390.0, 3710.0];
+        microphone_positions
+            .iter()
+            .map(|position| {
+                let delay = -position.coords().dot(direction) / speed_of_sound;
+                (0..NUMBER_OF_AUDIO_SAMPLES)
+                    .map(|index| {
+                        let time = index as f32 / sample_rate - delay;
+                        frequencies
+                            .iter()
+                            .enumerate()
+                            .map(|(i, frequency)| (TAU * frequency * time + i as f32).sin())
+                            .sum()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn estimate_azimuth(azimuth: f32) -> f32 {
+        let microphone_positions = nao_microphone_positions();
+        let channels = whistle_from(azimuth, &microphone_positions);
+        let mut node = WhistleLocalization::new(CreationContext {}).unwrap();
+        let cross_correlations = node.cross_correlations(&channels, 44100.0, &(2000.0..4000.0));
+        let scores = steered_response_powers(
+            &cross_correlations,
+            &microphone_positions,
+            343.0,
+            44100.0,
+            360,
+        );
+        scores
+            .into_iter()
+            .max_by(|(_, left), (_, right)| left.total_cmp(right))
+            .unwrap()
+            .0
+    }
+
+    fn angle_difference(left: f32, right: f32) -> f32 {
+        ((left - right + PI).rem_euclid(TAU) - PI).abs()
+    }
+
+    #[test]
+    fn whistle_from_front_is_localized() {
+        assert!(angle_difference(estimate_azimuth(0.0), 0.0) < 0.2);
+    }
+
+    #[test]
+    fn whistle_from_rear_left_is_localized() {
+        let azimuth = 2.3;
+        assert!(angle_difference(estimate_azimuth(azimuth), azimuth) < 0.2);
+    }
+
+    #[test]
+    fn whistle_from_right_is_localized() {
+        let azimuth = -1.4;
+        assert!(angle_difference(estimate_azimuth(azimuth), azimuth) < 0.2);
+    }
+}
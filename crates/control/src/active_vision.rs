@@ -37,6 +37,7 @@ pub struct CycleContext {
     obstacles: Input<Vec<Obstacle>, "obstacles">,
     parameters: Parameter<LookActionParameters, "behavior.look_action">,
     ground_to_field: Input<Option<Isometry2<Ground, Field>>, "ground_to_field?">,
+    localization_confidence: Input<Option<f32>, "localization_confidence?">,
 }
 
 #[context]
@@ -62,6 +63,10 @@ impl ActiveVision {
                 || cycle_start_time.duration_since(self.last_point_of_interest_switch.unwrap())?
                     > context.parameters.position_of_interest_switch_interval
             {
+                let is_localization_uncertain = is_localization_uncertain(
+                    context.localization_confidence.copied(),
+                    context.parameters.minimum_localization_confidence,
+                );
                 self.current_point_of_interest = next_point_of_interest(
                     self.current_point_of_interest,
                     &self.field_mark_positions,
@@ -69,6 +74,7 @@ impl ActiveVision {
                     *context.parameters,
                     ground_to_field,
                     context.rule_ball.or(context.ball),
+                    is_localization_uncertain,
                 );
 
                 self.last_point_of_interest_switch = Some(cycle_start_time);
@@ -102,6 +108,13 @@ impl ActiveVision {
     }
 }
 
+fn is_localization_uncertain(
+    localization_confidence: Option<f32>,
+    minimum_localization_confidence: f32,
+) -> bool {
+    localization_confidence.is_some_and(|confidence| confidence < minimum_localization_confidence)
+}
+
 fn is_position_visible(position: Point2<Ground>, parameters: LookActionParameters) -> bool {
     Vector2::x_axis().angle(position.coords()).abs() < parameters.angle_threshold
         && position.coords().norm() < parameters.distance_threshold
@@ -125,7 +138,15 @@ fn closest_interesting_obstacle_visible(
 ) -> Option<Point2<Ground>> {
     obstacles
         .iter()
-        .filter(|obstacle| matches!(obstacle.kind, ObstacleKind::Robot | ObstacleKind::Unknown))
+        .filter(|obstacle| {
+            matches!(
+                obstacle.kind,
+                ObstacleKind::Robot
+                    | ObstacleKind::Teammate
+                    | ObstacleKind::Opponent
+                    | ObstacleKind::Unknown
+            )
+        })
         .map(|obstacle| obstacle.position)
         .filter(|obstacle_position| is_position_visible(*obstacle_position, parameters))
         .min_by_key(|position| NotNan::new(position.coords().norm()).unwrap())
@@ -171,7 +192,20 @@ fn next_point_of_interest(
     parameters: LookActionParameters,
     ground_to_field: Isometry2<Ground, Field>,
     ball: Option<&BallState>,
+    is_localization_uncertain: bool,
 ) -> PointOfInterest {
+    // every other point of interest is a field mark to relocalize
+    if is_localization_uncertain
+        && !matches!(current_point_of_interest, PointOfInterest::FieldMark { .. })
+    {
+        if let Some(field_mark_position) =
+            closest_field_mark_visible(field_mark_positions, parameters, ground_to_field)
+        {
+            return PointOfInterest::FieldMark {
+                absolute_position: ground_to_field * field_mark_position,
+            };
+        }
+    }
     match current_point_of_interest {
         PointOfInterest::Forward => {
             let field_mark_of_interest =
@@ -222,3 +256,75 @@ fn next_point_of_interest(
         PointOfInterest::Obstacle { .. } => PointOfInterest::Forward,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use linear_algebra::vector;
+    use types::support_foot::Side;
+
+    use super::*;
+
+    fn parameters() -> LookActionParameters {
+        LookActionParameters {
+            angle_threshold: 0.95,
+            distance_threshold: 3.0,
+            look_forward_position: point![1.0, 0.0],
+            position_of_interest_switch_interval: Duration::from_secs(1),
+            minimum_localization_confidence: 0.3,
+        }
+    }
+
+    fn ball_state() -> BallState {
+        BallState {
+            ball_in_ground: point![1.0, 0.0],
+            ball_in_field: point![-0.5, 0.0],
+            ball_in_ground_velocity: vector![0.0, 0.0],
+            last_seen_ball: UNIX_EPOCH,
+            penalty_shot_direction: None,
+            field_side: Side::Left,
+        }
+    }
+
+    fn next_point_of_interest_after_ball(localization_confidence: Option<f32>) -> PointOfInterest {
+        let field_mark_positions = generate_field_mark_positions(&FieldDimensions::SPL);
+        let ground_to_field = Isometry2::from(point![-1.5, 0.0]);
+        next_point_of_interest(
+            PointOfInterest::Ball,
+            &field_mark_positions,
+            &[],
+            parameters(),
+            ground_to_field,
+            Some(&ball_state()),
+            is_localization_uncertain(
+                localization_confidence,
+                parameters().minimum_localization_confidence,
+            ),
+        )
+    }
+
+    #[test]
+    fn confident_robot_looks_forward_after_the_ball() {
+        assert!(matches!(
+            next_point_of_interest_after_ball(Some(0.8)),
+            PointOfInterest::Forward
+        ));
+        assert!(matches!(
+            next_point_of_interest_after_ball(None),
+            PointOfInterest::Forward
+        ));
+    }
+
+    #[test]
+    fn uncertain_robot_looks_at_the_closest_field_mark_after_the_ball() {
+        let PointOfInterest::FieldMark { absolute_position } =
+            next_point_of_interest_after_ball(Some(0.1))
+        else {
+            panic!("expected a field mark to be looked at");
+        };
+
+        assert_eq!(absolute_position.x(), 0.0);
+        assert_eq!(absolute_position.y().abs(), 0.75);
+    }
+}
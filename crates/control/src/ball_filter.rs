@@ -1,12 +1,15 @@
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
-use color_eyre::Result;
-use nalgebra::{matrix, Matrix2, Matrix2x4, Matrix4, Matrix4x2};
+use color_eyre::{eyre::WrapErr, Result};
+use nalgebra::{matrix, vector, Matrix2, Matrix4, Matrix4x2, Vector4};
 use serde::{Deserialize, Serialize};
 
 use context_attribute::context;
 use coordinate_systems::{Ground, Pixel};
-use filtering::kalman_filter::KalmanFilter;
+use filtering::{
+    kalman_filter::KalmanFilter,
+    unscented_kalman_filter::{self, StateSpace, UnscentedTransformParameters},
+};
 use framework::{AdditionalOutput, HistoricInput, MainOutput, PerceptionInput};
 use geometry::circle::Circle;
 use linear_algebra::{point, Point2};
@@ -15,6 +18,7 @@ use types::{
     ball::Ball,
     ball_filter::Hypothesis,
     ball_position::BallPosition,
+    ball_trajectory::BallTrajectory,
     camera_matrix::{CameraMatrices, CameraMatrix},
     cycle_time::CycleTime,
     field_dimensions::FieldDimensions,
@@ -23,6 +27,8 @@ use types::{
     parameters::BallFilterParameters,
 };
 
+const MAXIMUM_NUMBER_OF_TRAJECTORY_SAMPLES: u32 = 1000;
+
 #[derive(Deserialize, Serialize)]
 pub struct BallFilter {
     hypotheses: Vec<Hypothesis>,
@@ -37,6 +43,7 @@ pub struct CycleContext {
     best_ball_hypothesis: AdditionalOutput<Option<Hypothesis>, "best_ball_hypothesis">,
     best_ball_state: AdditionalOutput<Option<MultivariateNormalDistribution<4>>, "best_ball_state">,
     chooses_resting_model: AdditionalOutput<bool, "chooses_resting_model">,
+    predicted_ball_trajectory: AdditionalOutput<Vec<Point2<Ground>>, "predicted_ball_trajectory">,
     filtered_balls_in_image_bottom:
         AdditionalOutput<Vec<Circle<Pixel>>, "filtered_balls_in_image_bottom">,
     filtered_balls_in_image_top:
@@ -61,6 +68,7 @@ pub struct CycleContext {
 #[derive(Default)]
 pub struct MainOutputs {
     pub ball_position: MainOutput<Option<BallPosition<Ground>>>,
+    pub ball_trajectory: MainOutput<Option<BallTrajectory<Ground>>>,
 }
 
 impl BallFilter {
@@ -94,14 +102,14 @@ impl BallFilter {
         &mut self,
         measurements: Vec<(&SystemTime, Vec<&Ball>)>,
         context: &CycleContext,
-    ) {
+    ) -> Result<()> {
         for (detection_time, balls) in measurements {
             let current_odometry_to_last_odometry = context
                 .current_odometry_to_last_odometry
                 .get(detection_time)
                 .expect("current_odometry_to_last_odometry should not be None");
             self.predict_hypotheses_with_odometry(
-                context.ball_filter_configuration.velocity_decay_factor,
+                context.ball_filter_configuration.rolling_deceleration,
                 current_odometry_to_last_odometry.inverse(),
                 Matrix4::from_diagonal(&context.ball_filter_configuration.process_noise),
             );
@@ -125,7 +133,7 @@ impl BallFilter {
                     ball.position,
                     *detection_time,
                     context.ball_filter_configuration,
-                );
+                )?;
             }
         }
 
@@ -134,11 +142,12 @@ impl BallFilter {
             context.ball_filter_configuration,
             context.field_dimensions,
         );
+        Ok(())
     }
 
     pub fn cycle(&mut self, mut context: CycleContext) -> Result<MainOutputs> {
         let persistent_updates = Self::persistent_balls_in_control_cycle(&context);
-        self.advance_all_hypotheses(persistent_updates, &context);
+        self.advance_all_hypotheses(persistent_updates, &context)?;
 
         context
             .ball_filter_hypotheses
@@ -179,8 +188,27 @@ impl BallFilter {
             hypothesis.selected_ball_position(context.ball_filter_configuration)
         });
 
+        let ball_trajectory = ball_position.map(|ball_position| BallTrajectory {
+            position: ball_position.position,
+            velocity: ball_position.velocity,
+            deceleration: context.ball_filter_configuration.rolling_deceleration,
+        });
+        context.predicted_ball_trajectory.fill_if_subscribed(|| {
+            ball_trajectory
+                .map(|trajectory| {
+                    sample_trajectory(
+                        &trajectory,
+                        context
+                            .ball_filter_configuration
+                            .trajectory_sampling_interval,
+                    )
+                })
+                .unwrap_or_default()
+        });
+
         Ok(MainOutputs {
             ball_position: ball_position.into(),
+            ball_trajectory: ball_trajectory.into(),
         })
     }
 
@@ -222,7 +250,7 @@ impl BallFilter {
 
     fn predict_hypotheses_with_odometry(
         &mut self,
-        velocity_decay_factor: f32,
+        rolling_deceleration: f32,
         last_odometry_to_current_odometry: nalgebra::Isometry2<f32>,
         process_noise: Matrix4<f32>,
     ) {
@@ -231,8 +259,8 @@ impl BallFilter {
             let constant_velocity_prediction = matrix![
                 1.0, 0.0, cycle_time, 0.0;
                 0.0, 1.0, 0.0, cycle_time;
-                0.0, 0.0, velocity_decay_factor, 0.0;
-                0.0, 0.0, 0.0, velocity_decay_factor;
+                0.0, 0.0, 1.0, 0.0;
+                0.0, 0.0, 0.0, 1.0;
             ];
             let rotation = last_odometry_to_current_odometry
                 .rotation
@@ -250,7 +278,23 @@ impl BallFilter {
                 state_prediction,
                 control_input_model,
                 odometry_translation,
-                process_noise,
+                Matrix4::zeros(),
+            );
+            // rolling friction is nonlinear in the velocity, propagate it into the covariance
+            // with its linearization at the mean
+            let friction_jacobian = rolling_friction_jacobian(
+                &hypothesis.moving_state.mean,
+                rolling_deceleration,
+                cycle_time,
+            );
+            hypothesis.moving_state.covariance = friction_jacobian
+                * hypothesis.moving_state.covariance
+                * friction_jacobian.transpose()
+                + process_noise;
+            apply_rolling_friction(
+                &mut hypothesis.moving_state.mean,
+                rolling_deceleration,
+                cycle_time,
             );
             hypothesis.resting_state.predict(
                 state_prediction,
@@ -258,6 +302,11 @@ impl BallFilter {
                 odometry_translation,
                 process_noise,
             );
+            apply_rolling_friction(
+                &mut hypothesis.resting_state.mean,
+                rolling_deceleration,
+                cycle_time,
+            );
         }
     }
 
@@ -266,25 +315,28 @@ impl BallFilter {
         detected_position: Point2<Ground>,
         detection_time: SystemTime,
         configuration: &BallFilterParameters,
-    ) {
-        hypothesis.moving_state.update(
-            Matrix2x4::identity(),
-            detected_position.inner.coords,
+    ) -> Result<()> {
+        update_with_position(
+            &mut hypothesis.moving_state,
+            detected_position,
             Matrix2::from_diagonal(&configuration.measurement_noise_moving)
                 * detected_position.coords().norm_squared(),
-        );
-        hypothesis.resting_state.update(
-            Matrix2x4::identity(),
-            detected_position.inner.coords,
+        )
+        .wrap_err("failed to update moving ball state")?;
+        update_with_position(
+            &mut hypothesis.resting_state,
+            detected_position,
             Matrix2::from_diagonal(&configuration.measurement_noise_resting)
                 * detected_position.coords().norm_squared(),
-        );
+        )
+        .wrap_err("failed to update resting ball state")?;
 
         if !hypothesis.is_resting(configuration) {
             hypothesis.resting_state.mean = hypothesis.moving_state.mean;
         }
         hypothesis.last_update = detection_time;
         hypothesis.validity += 1.0;
+        Ok(())
     }
 
     fn update_hypotheses_with_measurement(
@@ -292,7 +344,7 @@ impl BallFilter {
         detected_position: Point2<Ground>,
         detection_time: SystemTime,
         configuration: &BallFilterParameters,
-    ) {
+    ) -> Result<()> {
         let mut matching_hypotheses = self
             .hypotheses
             .iter_mut()
@@ -306,16 +358,16 @@ impl BallFilter {
 
         if matching_hypotheses.peek().is_none() {
             self.spawn_hypothesis(detected_position, detection_time, configuration);
-            return;
+            return Ok(());
         }
-        matching_hypotheses.for_each(|hypothesis| {
+        matching_hypotheses.try_for_each(|hypothesis| {
             Self::update_hypothesis_with_measurement(
                 hypothesis,
                 detected_position,
                 detection_time,
                 configuration,
             )
-        });
+        })
     }
 
     fn find_best_hypothesis(&self) -> Option<&Hypothesis> {
@@ -401,6 +453,90 @@ impl BallFilter {
     }
 }
 
+/// Updates the state with a directly measured ball position using the unscented Kalman filter
+fn update_with_position(
+    state: &mut MultivariateNormalDistribution<4>,
+    detected_position: Point2<Ground>,
+    measurement_noise: Matrix2<f32>,
+) -> Result<(), unscented_kalman_filter::Error> {
+    unscented_kalman_filter::UnscentedKalmanFilter::update(
+        state,
+        detected_position.inner.coords,
+        measurement_noise,
+        |state: Vector4<f32>| state.xy(),
+        &StateSpace::euclidean(),
+        &StateSpace::euclidean(),
+        &UnscentedTransformParameters::default(),
+    )
+}
+
+/// Reduces the speed by the constant carpet deceleration without reversing the direction
+fn apply_rolling_friction(state: &mut Vector4<f32>, rolling_deceleration: f32, cycle_time: f32) {
+    let velocity = vector![state.z, state.w];
+    let speed = velocity.norm();
+    if speed == 0.0 {
+        return;
+    }
+    let speed_reduction = (rolling_deceleration * cycle_time).min(speed);
+    let direction = velocity / speed;
+    // the position was predicted with the initial velocity, the average one is lower
+    let position_correction = direction * speed_reduction * cycle_time / 2.0;
+    let velocity_correction = direction * speed_reduction;
+    *state -= vector![
+        position_correction.x,
+        position_correction.y,
+        velocity_correction.x,
+        velocity_correction.y
+    ];
+}
+
+/// Derivative of [`apply_rolling_friction`] with respect to the state
+fn rolling_friction_jacobian(
+    state: &Vector4<f32>,
+    rolling_deceleration: f32,
+    cycle_time: f32,
+) -> Matrix4<f32> {
+    let velocity = vector![state.z, state.w];
+    let speed = velocity.norm();
+    let speed_reduction = rolling_deceleration * cycle_time;
+    // friction is not differentiable at rest, keep the velocity uncertainty of slow balls instead
+    // of collapsing it
+    let velocity_jacobian = if speed <= speed_reduction {
+        Matrix2::identity()
+    } else {
+        let direction = velocity / speed;
+        Matrix2::identity()
+            - (Matrix2::identity() - direction * direction.transpose()) * speed_reduction / speed
+    };
+    // the position correction is half the velocity correction times the cycle time
+    let position_jacobian = (velocity_jacobian - Matrix2::identity()) * cycle_time / 2.0;
+    let mut jacobian = Matrix4::identity();
+    jacobian
+        .fixed_view_mut::<2, 2>(0, 2)
+        .copy_from(&position_jacobian);
+    jacobian
+        .fixed_view_mut::<2, 2>(2, 2)
+        .copy_from(&velocity_jacobian);
+    jacobian
+}
+
+fn sample_trajectory(
+    trajectory: &BallTrajectory<Ground>,
+    sampling_interval: Duration,
+) -> Vec<Point2<Ground>> {
+    if sampling_interval.is_zero() {
+        return vec![trajectory.position, trajectory.resting_position()];
+    }
+    let time_to_rest = trajectory.time_to_rest();
+    // a ball without deceleration never comes to rest
+    let number_of_samples = ((time_to_rest.as_secs_f32() / sampling_interval.as_secs_f32()) as u32)
+        .min(MAXIMUM_NUMBER_OF_TRAJECTORY_SAMPLES);
+    (0..=number_of_samples)
+        .map(|index| trajectory.position_at(sampling_interval * index))
+        .chain([trajectory.resting_position()])
+        .collect()
+}
+
 fn project_to_image(
     ball_position: &[BallPosition<Ground>],
     camera_matrix: &CameraMatrix,
@@ -440,3 +576,179 @@ fn is_visible_to_camera(
         && (0.0..480.0).contains(&position_in_image.y())
         && is_above_limbs(position_in_image, projected_limbs)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use approx::assert_relative_eq;
+    use nalgebra::{Isometry2, Vector2};
+
+    use super::*;
+
+    const CYCLE_TIME: f32 = 0.012;
+
+    fn configuration() -> BallFilterParameters {
+        BallFilterParameters {
+            hypothesis_timeout: Duration::from_secs(5),
+            measurement_matching_distance: 1.0,
+            hypothesis_merge_distance: 1.0,
+            process_noise: vector![0.005, 0.005, 0.2, 0.2],
+            measurement_noise_moving: Vector2::new(0.5, 2.0),
+            measurement_noise_resting: Vector2::new(300.0, 500.0),
+            initial_covariance: vector![0.5, 0.5, 0.5, 0.5],
+            visible_validity_exponential_decay_factor: 0.96,
+            hidden_validity_exponential_decay_factor: 0.999,
+            validity_discard_threshold: 0.5,
+            rolling_deceleration: 0.4,
+            resting_ball_velocity_threshold: 0.25,
+            trajectory_sampling_interval: Duration::from_millis(200),
+        }
+    }
+
+    /// Feeds detections of a ball rolling with constant deceleration into the filter
+    fn filter_rolling_ball(
+        start: Point2<Ground>,
+        velocity: Vector2<f32>,
+        number_of_cycles: u32,
+    ) -> BallFilter {
+        let configuration = configuration();
+        let mut filter = BallFilter {
+            hypotheses: Vec::new(),
+        };
+        let trajectory = BallTrajectory {
+            position: start,
+            velocity: linear_algebra::vector![velocity.x, velocity.y],
+            deceleration: configuration.rolling_deceleration,
+        };
+        for cycle in 0..number_of_cycles {
+            let time = Duration::from_secs_f32(cycle as f32 * CYCLE_TIME);
+            filter.predict_hypotheses_with_odometry(
+                configuration.rolling_deceleration,
+                Isometry2::identity(),
+                Matrix4::from_diagonal(&configuration.process_noise),
+            );
+            filter
+                .update_hypotheses_with_measurement(
+                    trajectory.position_at(time),
+                    UNIX_EPOCH + time,
+                    &configuration,
+                )
+                .unwrap();
+        }
+        filter
+    }
+
+    #[test]
+    fn velocity_of_decelerating_ball_is_estimated() {
+        let filter = filter_rolling_ball(point![3.0, 0.5], Vector2::new(-1.5, 0.0), 100);
+        let configuration = configuration();
+
+        let ball = filter
+            .find_best_hypothesis()
+            .expect("ball should be tracked")
+            .selected_ball_position(&configuration);
+
+        // 1.2 s after the start the ball rolls with 1.5 m/s - 0.4 m/s² * 1.2 s
+        let expected_speed = 1.5 - 0.4 * 100.0 * CYCLE_TIME;
+        assert_relative_eq!(ball.velocity.x(), -expected_speed, epsilon = 0.1);
+        assert_relative_eq!(ball.velocity.y(), 0.0, epsilon = 0.1);
+    }
+
+    #[test]
+    fn resting_position_of_rolling_ball_is_predicted() {
+        let configuration = configuration();
+        let filter = filter_rolling_ball(point![3.0, 0.5], Vector2::new(-1.5, 0.0), 100);
+        let ball = filter
+            .find_best_hypothesis()
+            .expect("ball should be tracked")
+            .selected_ball_position(&configuration);
+
+        let trajectory = BallTrajectory {
+            position: ball.position,
+            velocity: ball.velocity,
+            deceleration: configuration.rolling_deceleration,
+        };
+
+        // the ball rolls 1.5² / (2 * 0.4) m in total
+        let expected_resting_position = point![3.0 - 1.5_f32.powi(2) / 0.8, 0.5];
+        assert_relative_eq!(
+            trajectory.resting_position(),
+            expected_resting_position,
+            epsilon = 0.3
+        );
+        assert!(trajectory.time_to_reach(point![1.0, 0.0]).is_some());
+        assert!(trajectory.time_to_reach(point![-1.0, 0.0]).is_none());
+    }
+
+    #[test]
+    fn friction_does_not_reverse_the_ball() {
+        let mut state = vector![1.0, 0.0, 0.001, 0.0];
+
+        apply_rolling_friction(&mut state, 0.4, CYCLE_TIME);
+
+        assert_relative_eq!(
+            state,
+            vector![1.0 - 0.001 * CYCLE_TIME / 2.0, 0.0, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn friction_jacobian_matches_numerical_derivative() {
+        let state = vector![1.0, 0.5, 0.8, -0.6];
+        let jacobian = rolling_friction_jacobian(&state, 0.4, CYCLE_TIME);
+
+        let step = 1e-2;
+        for column in 0..4 {
+            let mut forward = state;
+            forward[column] += step;
+            apply_rolling_friction(&mut forward, 0.4, CYCLE_TIME);
+            let mut backward = state;
+            backward[column] -= step;
+            apply_rolling_friction(&mut backward, 0.4, CYCLE_TIME);
+            let derivative = (forward - backward) / (2.0 * step);
+            assert_relative_eq!(
+                jacobian.column(column).into_owned(),
+                derivative,
+                epsilon = 1e-4
+            );
+        }
+    }
+
+    #[test]
+    fn position_update_matches_linear_kalman_filter() {
+        let initial = MultivariateNormalDistribution {
+            mean: vector![1.0, 0.5, -0.3, 0.2],
+            covariance: Matrix4::from_diagonal(&vector![0.5, 0.4, 0.3, 0.2]),
+        };
+        let measurement_noise = Matrix2::from_diagonal(&vector![0.1, 0.2]);
+
+        let mut expected = initial;
+        KalmanFilter::update(
+            &mut expected,
+            nalgebra::Matrix2x4::identity(),
+            vector![1.2, 0.4],
+            measurement_noise,
+        );
+        let mut unscented = initial;
+        update_with_position(&mut unscented, point![1.2, 0.4], measurement_noise).unwrap();
+
+        assert_relative_eq!(unscented.mean, expected.mean, epsilon = 1e-5);
+        assert_relative_eq!(unscented.covariance, expected.covariance, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn sampled_trajectory_ends_at_resting_position() {
+        let trajectory = BallTrajectory {
+            position: point![0.0, 0.0],
+            velocity: linear_algebra::vector![0.8, 0.0],
+            deceleration: 0.4,
+        };
+
+        let samples = sample_trajectory(&trajectory, Duration::from_millis(500));
+
+        assert_eq!(samples.len(), 6);
+        assert_relative_eq!(samples[1], point![0.35, 0.0], epsilon = 1e-5);
+        assert_relative_eq!(samples[5], point![0.8, 0.0], epsilon = 1e-5);
+    }
+}
@@ -0,0 +1,289 @@
+use std::time::Duration;
+
+use coordinate_systems::{Field, Ground};
+use geometry::line_segment::LineSegment;
+use linear_algebra::{Isometry2, Orientation2, Point, Point2};
+use spl_network_messages::{GamePhase, SubState};
+use types::{
+    ball_trajectory::BallTrajectory,
+    field_dimensions::FieldDimensions,
+    filtered_game_controller_state::FilteredGameControllerState,
+    motion_command::{ArmMotion, HeadMotion, JumpDirection, MotionCommand, OrientationMode},
+    parameters::BlockShotParameters,
+    planned_path::PathSegment,
+    primary_state::PrimaryState,
+    world_state::WorldState,
+};
+
+/// Predicted course of a ball rolling into the own goal
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShotPrediction {
+    /// Where the ball crosses the own goal line
+    pub goal_line_crossing: Point2<Field>,
+    pub time_to_goal_line: Duration,
+    /// Point of the trajectory closest to the keeper
+    pub closest_point: Point2<Field>,
+    pub time_to_closest_point: Duration,
+}
+
+pub fn execute(
+    world_state: &WorldState,
+    field_dimensions: &FieldDimensions,
+    parameters: &BlockShotParameters,
+    ball_trajectory: Option<BallTrajectory<Ground>>,
+) -> Option<MotionCommand> {
+    if world_state.robot.primary_state != PrimaryState::Playing {
+        return None;
+    }
+    if let Some(
+        FilteredGameControllerState {
+            game_phase: GamePhase::PenaltyShootout { .. },
+            ..
+        }
+        | FilteredGameControllerState {
+            sub_state: Some(SubState::PenaltyKick),
+            ..
+        },
+    ) = world_state.filtered_game_controller_state
+    {
+        return None;
+    }
+
+    let ball = world_state.ball?;
+    let ground_to_field = world_state.robot.ground_to_field?;
+    let ball_trajectory = ball_trajectory?;
+    let prediction = predict_shot(
+        BallTrajectory {
+            position: ground_to_field * ball_trajectory.position,
+            velocity: ground_to_field * ball_trajectory.velocity,
+            deceleration: ball_trajectory.deceleration,
+        },
+        ground_to_field,
+        field_dimensions,
+        parameters,
+    )?;
+    let closest_point = ground_to_field.inverse() * prediction.closest_point;
+
+    if prediction.time_to_closest_point > parameters.maximum_time_for_stepping {
+        if closest_point.coords().norm() > parameters.maximum_step_distance {
+            return None;
+        }
+        return Some(MotionCommand::Walk {
+            head: HeadMotion::LookAt {
+                target: ball.ball_in_ground,
+                camera: None,
+            },
+            path: vec![PathSegment::LineSegment(LineSegment(
+                Point::origin(),
+                closest_point,
+            ))],
+            left_arm: ArmMotion::Swing,
+            right_arm: ArmMotion::Swing,
+            orientation_mode: OrientationMode::Override(Orientation2::identity()),
+        });
+    }
+
+    let lateral_offset = closest_point.y();
+    if lateral_offset.abs() <= parameters.squat_reach {
+        Some(MotionCommand::ArmsUpSquat)
+    } else if lateral_offset.abs() <= parameters.jump_reach {
+        Some(MotionCommand::Jump {
+            direction: if lateral_offset > 0.0 {
+                JumpDirection::Left
+            } else {
+                JumpDirection::Right
+            },
+        })
+    } else {
+        None
+    }
+}
+
+/// Predicts whether the ball, decelerated by rolling friction, rolls into the own goal
+pub fn predict_shot(
+    trajectory: BallTrajectory<Field>,
+    ground_to_field: Isometry2<Ground, Field>,
+    field_dimensions: &FieldDimensions,
+    parameters: &BlockShotParameters,
+) -> Option<ShotPrediction> {
+    let velocity = trajectory.velocity;
+    let speed = velocity.norm();
+    if speed < parameters.minimum_ball_velocity || velocity.x() >= 0.0 {
+        return None;
+    }
+    let direction = velocity / speed;
+
+    let goal_line_x = -field_dimensions.length / 2.0;
+    let distance_to_goal_line = (goal_line_x - trajectory.position.x()) / direction.x();
+    if distance_to_goal_line < 0.0 || distance_to_goal_line > trajectory.rolling_distance() {
+        return None;
+    }
+
+    let goal_line_crossing = trajectory.position + direction * distance_to_goal_line;
+    let maximum_crossing_y = field_dimensions.goal_inner_width / 2.0 + parameters.goal_post_margin;
+    if goal_line_crossing.y().abs() > maximum_crossing_y {
+        return None;
+    }
+
+    let path = LineSegment::new(trajectory.position, goal_line_crossing);
+    let keeper_position = ground_to_field * Point2::origin();
+    if path.projection_factor(keeper_position) <= 0.0 {
+        // the ball already passed the keeper
+        return None;
+    }
+    let closest_point = path.closest_point(keeper_position);
+
+    Some(ShotPrediction {
+        goal_line_crossing,
+        time_to_goal_line: trajectory.time_to_reach(goal_line_crossing)?,
+        closest_point,
+        time_to_closest_point: trajectory.time_to_reach(closest_point)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use approx::assert_relative_eq;
+    use linear_algebra::{point, vector};
+    use types::{
+        support_foot::Side,
+        world_state::{BallState, RobotState},
+    };
+
+    use super::*;
+
+    const ROLLING_DECELERATION: f32 = 0.4;
+
+    fn parameters() -> BlockShotParameters {
+        BlockShotParameters {
+            minimum_ball_velocity: 0.3,
+            goal_post_margin: 0.1,
+            maximum_time_for_stepping: Duration::from_secs(1),
+            maximum_step_distance: 0.6,
+            squat_reach: 0.25,
+            jump_reach: 0.8,
+        }
+    }
+
+    fn field_dimensions() -> FieldDimensions {
+        FieldDimensions {
+            length: 9.0,
+            width: 6.0,
+            goal_inner_width: 1.5,
+            ..Default::default()
+        }
+    }
+
+    fn keeper_to_field() -> Isometry2<Ground, Field> {
+        Isometry2::new(vector![-4.2, 0.0], 0.0)
+    }
+
+    fn ball(position: Point2<Field>, velocity: [f32; 2]) -> BallState {
+        let ground_to_field = keeper_to_field();
+        BallState {
+            ball_in_ground: ground_to_field.inverse() * position,
+            ball_in_field: position,
+            ball_in_ground_velocity: ground_to_field.inverse() * vector![velocity[0], velocity[1]],
+            last_seen_ball: UNIX_EPOCH,
+            penalty_shot_direction: None,
+            field_side: Side::Left,
+        }
+    }
+
+    fn world_state(ball: BallState) -> WorldState {
+        WorldState {
+            ball: Some(ball),
+            robot: RobotState {
+                ground_to_field: Some(keeper_to_field()),
+                primary_state: PrimaryState::Playing,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn trajectory(position: Point2<Field>, velocity: [f32; 2]) -> BallTrajectory<Field> {
+        BallTrajectory {
+            position,
+            velocity: vector![velocity[0], velocity[1]],
+            deceleration: ROLLING_DECELERATION,
+        }
+    }
+
+    fn predict(position: Point2<Field>, velocity: [f32; 2]) -> Option<ShotPrediction> {
+        predict_shot(
+            trajectory(position, velocity),
+            keeper_to_field(),
+            &field_dimensions(),
+            &parameters(),
+        )
+    }
+
+    fn block(position: Point2<Field>, velocity: [f32; 2]) -> Option<MotionCommand> {
+        let field_to_ground = keeper_to_field().inverse();
+        let trajectory = trajectory(position, velocity);
+        execute(
+            &world_state(ball(position, velocity)),
+            &field_dimensions(),
+            &parameters(),
+            Some(BallTrajectory {
+                position: field_to_ground * trajectory.position,
+                velocity: field_to_ground * trajectory.velocity,
+                deceleration: trajectory.deceleration,
+            }),
+        )
+    }
+
+    #[test]
+    fn shot_into_goal_is_predicted() {
+        let prediction = predict(point![-2.5, 0.0], [-2.0, 0.4]).unwrap();
+
+        assert_relative_eq!(prediction.goal_line_crossing.x(), -4.5, epsilon = 1e-4);
+        assert_relative_eq!(prediction.goal_line_crossing.y(), 0.4, epsilon = 1e-4);
+        assert_relative_eq!(prediction.closest_point.x(), -4.2, epsilon = 0.1);
+        assert!(prediction.time_to_closest_point < prediction.time_to_goal_line);
+    }
+
+    #[test]
+    fn shot_next_to_goal_is_ignored() {
+        assert_eq!(predict(point![-2.5, 0.0], [-2.0, 1.2]), None);
+    }
+
+    #[test]
+    fn slow_ball_stopping_before_goal_is_ignored() {
+        assert_eq!(predict(point![-1.0, 0.0], [-1.0, 0.0]), None);
+    }
+
+    #[test]
+    fn ball_moving_away_is_ignored() {
+        assert_eq!(predict(point![-2.5, 0.0], [2.0, 0.0]), None);
+    }
+
+    #[test]
+    fn shot_at_keeper_is_blocked_by_squatting() {
+        let command = block(point![-3.0, 0.0], [-3.0, 0.0]);
+
+        assert!(matches!(command, Some(MotionCommand::ArmsUpSquat)));
+    }
+
+    #[test]
+    fn shot_beside_keeper_is_blocked_by_jumping() {
+        let command = block(point![-3.0, 0.0], [-3.0, -1.5]);
+
+        assert!(matches!(
+            command,
+            Some(MotionCommand::Jump {
+                direction: JumpDirection::Right
+            })
+        ));
+    }
+
+    #[test]
+    fn slow_shot_is_blocked_by_stepping() {
+        let command = block(point![-2.0, 0.0], [-1.5, 0.3]);
+
+        assert!(matches!(command, Some(MotionCommand::Walk { .. })));
+    }
+}
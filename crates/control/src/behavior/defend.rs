@@ -10,18 +10,20 @@ use types::{
     filtered_game_controller_state::FilteredGameControllerState,
     line::Line,
     motion_command::MotionCommand,
-    parameters::RolePositionsParameters,
+    parameters::{RolePositioningParameters, RolePositionsParameters},
     path_obstacles::PathObstacle,
+    positioning::PositioningHeatmap,
     support_foot::Side,
     world_state::{BallState, WorldState},
 };
 
-use super::{head::LookAction, walk_to_pose::WalkAndStand};
+use super::{head::LookAction, positioning::Positioning, walk_to_pose::WalkAndStand};
 
 pub struct Defend<'cycle> {
     world_state: &'cycle WorldState,
     field_dimensions: &'cycle FieldDimensions,
     role_positions: &'cycle RolePositionsParameters,
+    positioning: &'cycle Positioning<'cycle>,
     walk_and_stand: &'cycle WalkAndStand<'cycle>,
     look_action: &'cycle LookAction<'cycle>,
 }
@@ -31,6 +33,7 @@ impl<'cycle> Defend<'cycle> {
         world_state: &'cycle WorldState,
         field_dimensions: &'cycle FieldDimensions,
         role_positions: &'cycle RolePositionsParameters,
+        positioning: &'cycle Positioning,
         walk_and_stand: &'cycle WalkAndStand,
         look_action: &'cycle LookAction,
     ) -> Self {
@@ -38,6 +41,7 @@ impl<'cycle> Defend<'cycle> {
             world_state,
             field_dimensions,
             role_positions,
+            positioning,
             walk_and_stand,
             look_action,
         }
@@ -54,17 +58,27 @@ impl<'cycle> Defend<'cycle> {
 
     pub fn left(
         &self,
+        role_positioning: &RolePositioningParameters,
         path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
+        positioning_heatmap_output: &mut AdditionalOutput<PositioningHeatmap>,
     ) -> Option<MotionCommand> {
         let pose = defend_left_pose(self.world_state, self.field_dimensions, self.role_positions)?;
+        let pose =
+            self.positioning
+                .improve(pose, role_positioning, None, positioning_heatmap_output);
         self.with_pose(pose, path_obstacles_output)
     }
 
     pub fn right(
         &self,
+        role_positioning: &RolePositioningParameters,
         path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
+        positioning_heatmap_output: &mut AdditionalOutput<PositioningHeatmap>,
     ) -> Option<MotionCommand> {
         let pose = defend_right_pose(self.world_state, self.field_dimensions, self.role_positions)?;
+        let pose =
+            self.positioning
+                .improve(pose, role_positioning, None, positioning_heatmap_output);
         self.with_pose(pose, path_obstacles_output)
     }
 
@@ -2,6 +2,7 @@ use coordinate_systems::Ground;
 use geometry::look_at::LookAt;
 use linear_algebra::{Point, Pose};
 use types::{
+    kick_decision::KickDecision,
     motion_command::{HeadMotion, MotionCommand, OrientationMode},
     parameters::{DribblingParameters, InWalkKickInfoParameters, InWalkKicksParameters},
     planned_path::PathSegment,
@@ -16,13 +17,14 @@ pub fn execute(
     walk_path_planner: &WalkPathPlanner,
     in_walk_kicks: &InWalkKicksParameters,
     parameters: &DribblingParameters,
+    kick_decisions: Option<&[KickDecision]>,
     dribble_path: Option<Vec<PathSegment>>,
 ) -> Option<MotionCommand> {
     let ball_position = world_state.ball?.ball_in_ground;
     let head = HeadMotion::LookLeftAndRightOf {
         target: ball_position,
     };
-    let kick_decisions = world_state.kick_decisions.as_ref()?;
+    let kick_decisions = kick_decisions?;
     let instant_kick_decisions = world_state.instant_kick_decisions.as_ref()?;
 
     let available_kick = kick_decisions
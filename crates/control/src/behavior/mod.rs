@@ -1,3 +1,4 @@
+mod block_shot;
 mod calibrate;
 mod defend;
 mod dribble;
@@ -10,12 +11,16 @@ mod look_around;
 mod lost_ball;
 pub mod node;
 mod penalize;
+mod positioning;
 mod prepare_jump;
+mod receive_pass;
 mod search;
+mod set_play;
 mod sit_down;
 mod stand;
 mod stand_up;
 mod support;
+mod tree;
 mod unstiff;
 mod walk_to_kick_off;
 mod walk_to_penalty_kick;
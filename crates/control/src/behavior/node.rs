@@ -4,38 +4,40 @@ use color_eyre::Result;
 use serde::{Deserialize, Serialize};
 
 use context_attribute::context;
-use coordinate_systems::Field;
+use coordinate_systems::{Field, Ground};
 use framework::{AdditionalOutput, MainOutput};
 use linear_algebra::{point, Point2};
-use spl_network_messages::{GamePhase, SubState, Team};
 use types::{
     action::Action,
+    ball_trajectory::BallTrajectory,
     cycle_time::CycleTime,
+    dribble_plan::DribblePlan,
     field_dimensions::FieldDimensions,
-    filtered_game_controller_state::FilteredGameControllerState,
-    filtered_game_state::FilteredGameState,
-    motion_command::MotionCommand,
+    motion_command::{HeadMotion, MotionCommand},
     parameters::{
         BehaviorParameters, InWalkKicksParameters, InterceptBallParameters, LostBallParameters,
     },
     path_obstacles::PathObstacle,
     planned_path::PathSegment,
+    positioning::PositioningHeatmap,
     primary_state::PrimaryState,
-    roles::Role,
     step_plan::Step,
     support_foot::Side,
     world_state::WorldState,
 };
 
-use crate::dribble_path_planner;
+use crate::{dribble_path_planner, dribble_planner};
 
 use super::{
-    calibrate,
+    block_shot, calibrate,
     defend::Defend,
     dribble, fall_safely,
     head::LookAction,
-    initial, intercept_ball, jump, look_around, lost_ball, penalize, prepare_jump, search,
-    sit_down, stand, stand_up, support, unstiff, walk_to_kick_off, walk_to_penalty_kick,
+    initial, intercept_ball, jump, look_around, lost_ball, penalize,
+    positioning::Positioning,
+    prepare_jump, receive_pass, search, set_play, sit_down, stand, stand_up, support,
+    tree::{ActiveBehavior, BehaviorTreeState, Tick},
+    unstiff, walk_to_kick_off, walk_to_penalty_kick,
     walk_to_pose::{WalkAndStand, WalkPathPlanner},
 };
 
@@ -44,6 +46,7 @@ pub struct Behavior {
     last_motion_command: MotionCommand,
     last_known_ball_position: Point2<Field>,
     active_since: Option<SystemTime>,
+    tree_state: BehaviorTreeState,
 }
 
 #[context]
@@ -53,11 +56,15 @@ pub struct CreationContext {}
 pub struct CycleContext {
     path_obstacles_output: AdditionalOutput<Vec<PathObstacle>, "path_obstacles">,
     dribble_path_obstacles_output: AdditionalOutput<Vec<PathObstacle>, "dribble_path_obstacles">,
+    dribble_plan_output: AdditionalOutput<DribblePlan, "dribble_plan">,
     active_action_output: AdditionalOutput<Action, "active_action">,
+    active_behavior_path_output: AdditionalOutput<Vec<String>, "active_behavior_path">,
+    positioning_heatmap_output: AdditionalOutput<PositioningHeatmap, "positioning_heatmap">,
 
     has_ground_contact: Input<bool, "has_ground_contact">,
     world_state: Input<WorldState, "world_state">,
     cycle_time: Input<CycleTime, "cycle_time">,
+    ball_trajectory: Input<Option<BallTrajectory<Ground>>, "ball_trajectory?">,
 
     parameters: Parameter<BehaviorParameters, "behavior">,
     in_walk_kicks: Parameter<InWalkKicksParameters, "in_walk_kicks">,
@@ -81,6 +88,7 @@ impl Behavior {
             last_motion_command: MotionCommand::Unstiff,
             last_known_ball_position: point![0.0, 0.0],
             active_since: None,
+            tree_state: BehaviorTreeState::default(),
         })
     }
 
@@ -111,74 +119,10 @@ impl Behavior {
             (Some(_), _) => self.active_since = None,
         }
 
-        let mut actions = vec![
-            Action::Unstiff,
-            Action::SitDown,
-            Action::Penalize,
-            Action::Initial,
-            Action::FallSafely,
-            Action::StandUp,
-            Action::Stand,
-            Action::InterceptBall,
-            Action::Calibrate,
-        ];
-
-        if let Some(active_since) = self.active_since {
-            if now.duration_since(active_since)? < context.parameters.initial_lookaround_duration {
-                actions.push(Action::LookAround);
-            }
-        }
-
-        let filtered_game_state = world_state
-            .filtered_game_controller_state
-            .map(|filtered_game_controller_state| filtered_game_controller_state.game_state);
-
-        match world_state.robot.role {
-            Role::DefenderLeft => actions.push(Action::DefendLeft),
-            Role::DefenderRight => actions.push(Action::DefendRight),
-            Role::Keeper => match world_state.filtered_game_controller_state {
-                Some(FilteredGameControllerState {
-                    game_phase: GamePhase::PenaltyShootout { .. },
-                    ..
-                }) => {
-                    actions.push(Action::Jump);
-                    actions.push(Action::PrepareJump);
-                }
-                _ => actions.push(Action::DefendGoal),
-            },
-            Role::Loser => actions.push(Action::SearchForLostBall),
-            Role::MidfielderLeft => actions.push(Action::SupportLeft),
-            Role::MidfielderRight => actions.push(Action::SupportRight),
-            Role::ReplacementKeeper => actions.push(Action::DefendGoal),
-            Role::Searcher => actions.push(Action::Search),
-            Role::Striker => match filtered_game_state {
-                None
-                | Some(FilteredGameState::Playing {
-                    ball_is_free: true, ..
-                }) => {
-                    actions.push(Action::Dribble);
-                }
-                Some(FilteredGameState::Ready {
-                    kicking_team: Team::Hulks,
-                }) => match world_state.filtered_game_controller_state {
-                    Some(FilteredGameControllerState {
-                        sub_state: Some(SubState::PenaltyKick),
-                        ..
-                    }) => actions.push(Action::WalkToPenaltyKick),
-                    _ => actions.push(Action::WalkToKickOff),
-                },
-                _ => match world_state.filtered_game_controller_state {
-                    Some(FilteredGameControllerState {
-                        game_state: FilteredGameState::Ready { .. },
-                        sub_state: Some(SubState::PenaltyKick),
-                        kicking_team: Team::Opponent,
-                        ..
-                    }) => actions.push(Action::DefendPenaltyKick),
-                    _ => actions.push(Action::DefendKickOff),
-                },
-            },
-            Role::StrikerSupporter => actions.push(Action::SupportStriker),
-        };
+        let active_duration = self
+            .active_since
+            .map(|active_since| now.duration_since(active_since))
+            .transpose()?;
 
         let walk_path_planner = WalkPathPlanner::new(
             context.field_dimensions,
@@ -193,10 +137,16 @@ impl Behavior {
             &self.last_motion_command,
         );
         let look_action = LookAction::new(world_state);
+        let positioning = Positioning::new(
+            world_state,
+            context.field_dimensions,
+            &context.parameters.positioning,
+        );
         let defend = Defend::new(
             world_state,
             context.field_dimensions,
             &context.parameters.role_positions,
+            &positioning,
             &walk_and_stand,
             &look_action,
         );
@@ -211,141 +161,234 @@ impl Behavior {
         let dribble_path = dribble_path_planner::plan(
             &walk_path_planner,
             world_state,
+            world_state.kick_decisions.as_deref(),
             &context.parameters.dribbling,
             &mut dribble_path_obstacles_output,
         );
-        context
-            .dribble_path_obstacles_output
-            .fill_if_subscribed(|| dribble_path_obstacles.clone().unwrap_or_default());
 
-        let (action, motion_command) = actions
-            .iter()
-            .find_map(|action| {
-                let motion_command = match action {
-                    Action::Unstiff => unstiff::execute(world_state),
-                    Action::SitDown => sit_down::execute(world_state),
-                    Action::Penalize => penalize::execute(world_state),
-                    Action::Initial => initial::execute(world_state),
-                    Action::FallSafely => {
-                        fall_safely::execute(world_state, *context.has_ground_contact)
+        let execute = |action| match action {
+            Action::Unstiff => unstiff::execute(world_state),
+            Action::SitDown => sit_down::execute(world_state),
+            Action::Penalize => penalize::execute(world_state),
+            Action::Initial => initial::execute(world_state),
+            Action::FallSafely => fall_safely::execute(world_state, *context.has_ground_contact),
+            Action::StandUp => stand_up::execute(world_state),
+            Action::LookAround => look_around::execute(world_state),
+            Action::InterceptBall => intercept_ball::execute(
+                world_state,
+                *context.intercept_ball_parameters,
+                *context.maximum_step_size,
+            ),
+            Action::BlockShot => block_shot::execute(
+                world_state,
+                context.field_dimensions,
+                &context.parameters.block_shot,
+                context.ball_trajectory.copied(),
+            ),
+            Action::Calibrate => calibrate::execute(world_state),
+            Action::DefendGoal => defend.goal(&mut context.path_obstacles_output),
+            Action::DefendKickOff => defend.kick_off(&mut context.path_obstacles_output),
+            Action::DefendLeft => defend.left(
+                &context.parameters.positioning.defender_left,
+                &mut context.path_obstacles_output,
+                &mut context.positioning_heatmap_output,
+            ),
+            Action::DefendRight => defend.right(
+                &context.parameters.positioning.defender_right,
+                &mut context.path_obstacles_output,
+                &mut context.positioning_heatmap_output,
+            ),
+            Action::DefendPenaltyKick => defend.penalty_kick(&mut context.path_obstacles_output),
+            Action::DefendSetPlay => set_play::defend(
+                world_state,
+                context.field_dimensions,
+                &context.parameters.set_play,
+                &walk_and_stand,
+                &look_action,
+                &mut context.path_obstacles_output,
+            ),
+            Action::Stand => stand::execute(world_state, context.field_dimensions),
+            Action::Dribble => {
+                let dribble_plan = dribble_planner::plan(
+                    world_state,
+                    context.field_dimensions,
+                    context.in_walk_kicks,
+                    &context.parameters.dribbling.planning,
+                );
+                // the planned push only replaces the kick selector's decisions if it is cheaper
+                let planned_kick_decisions = dribble_plan
+                    .as_ref()
+                    .filter(|plan| !plan.follows_kick_selector)
+                    .map(|plan| vec![plan.steps[0].kick_decision]);
+                context
+                    .dribble_plan_output
+                    .fill_if_subscribed(|| dribble_plan.unwrap_or_default());
+                match planned_kick_decisions {
+                    Some(kick_decisions) => {
+                        let dribble_path = dribble_path_planner::plan(
+                            &walk_path_planner,
+                            world_state,
+                            Some(&kick_decisions),
+                            &context.parameters.dribbling,
+                            &mut dribble_path_obstacles_output,
+                        );
+                        dribble::execute(
+                            world_state,
+                            &walk_path_planner,
+                            context.in_walk_kicks,
+                            &context.parameters.dribbling,
+                            Some(&kick_decisions),
+                            dribble_path,
+                        )
                     }
-                    Action::StandUp => stand_up::execute(world_state),
-                    Action::LookAround => look_around::execute(world_state),
-                    Action::InterceptBall => intercept_ball::execute(
-                        world_state,
-                        *context.intercept_ball_parameters,
-                        *context.maximum_step_size,
-                    ),
-                    Action::Calibrate => calibrate::execute(world_state),
-                    Action::DefendGoal => defend.goal(&mut context.path_obstacles_output),
-                    Action::DefendKickOff => defend.kick_off(&mut context.path_obstacles_output),
-                    Action::DefendLeft => defend.left(&mut context.path_obstacles_output),
-                    Action::DefendRight => defend.right(&mut context.path_obstacles_output),
-                    Action::DefendPenaltyKick => {
-                        defend.penalty_kick(&mut context.path_obstacles_output)
-                    }
-                    Action::Stand => stand::execute(world_state, context.field_dimensions),
-                    Action::Dribble => dribble::execute(
+                    None => dribble::execute(
                         world_state,
                         &walk_path_planner,
                         context.in_walk_kicks,
                         &context.parameters.dribbling,
+                        world_state.kick_decisions.as_deref(),
                         dribble_path.clone(),
                     ),
-                    Action::Jump => jump::execute(world_state),
-                    Action::PrepareJump => prepare_jump::execute(world_state),
-                    Action::Search => search::execute(
-                        world_state,
-                        &walk_path_planner,
-                        &walk_and_stand,
-                        context.field_dimensions,
-                        &context.parameters.search,
-                        &mut context.path_obstacles_output,
-                    ),
-                    Action::SearchForLostBall => lost_ball::execute(
-                        world_state,
-                        self.last_known_ball_position,
-                        &walk_path_planner,
-                        context.lost_ball_parameters,
-                        &mut context.path_obstacles_output,
-                    ),
-                    Action::SupportLeft => support::execute(
-                        world_state,
-                        context.field_dimensions,
-                        Some(Side::Left),
-                        context
-                            .parameters
-                            .role_positions
-                            .left_midfielder_distance_to_ball,
-                        context
-                            .parameters
-                            .role_positions
-                            .left_midfielder_maximum_x_in_ready_and_when_ball_is_not_free,
-                        context.parameters.role_positions.left_midfielder_minimum_x,
-                        &walk_and_stand,
-                        &look_action,
-                        &mut context.path_obstacles_output,
-                    ),
-                    Action::SupportRight => support::execute(
-                        world_state,
-                        context.field_dimensions,
-                        Some(Side::Right),
-                        context
-                            .parameters
-                            .role_positions
-                            .right_midfielder_distance_to_ball,
-                        context
-                            .parameters
-                            .role_positions
-                            .right_midfielder_maximum_x_in_ready_and_when_ball_is_not_free,
-                        context.parameters.role_positions.right_midfielder_minimum_x,
-                        &walk_and_stand,
-                        &look_action,
-                        &mut context.path_obstacles_output,
-                    ),
-                    Action::SupportStriker => support::execute(
-                        world_state,
-                        context.field_dimensions,
-                        None,
-                        context
-                            .parameters
-                            .role_positions
-                            .striker_supporter_distance_to_ball,
-                        context
-                            .parameters
-                            .role_positions
-                            .striker_supporter_maximum_x_in_ready_and_when_ball_is_not_free,
-                        context
-                            .parameters
-                            .role_positions
-                            .striker_supporter_minimum_x,
-                        &walk_and_stand,
-                        &look_action,
-                        &mut context.path_obstacles_output,
-                    ),
-                    Action::WalkToKickOff => walk_to_kick_off::execute(
-                        world_state,
-                        &walk_and_stand,
-                        &look_action,
-                        &mut context.path_obstacles_output,
-                        *context.striker_set_position,
-                    ),
-                    Action::WalkToPenaltyKick => walk_to_penalty_kick::execute(
-                        world_state,
-                        &walk_and_stand,
-                        &look_action,
-                        &mut context.path_obstacles_output,
-                        context.field_dimensions,
-                    ),
-                }?;
-                Some((action, motion_command))
-            })
-            .unwrap_or_else(|| {
-                panic!(
-                    "there has to be at least one action available, world_state: {world_state:#?}",
-                )
+                }
+            }
+            Action::Jump => jump::execute(world_state),
+            Action::PrepareJump => prepare_jump::execute(world_state),
+            Action::PrepareSetPlay => set_play::prepare(
+                world_state,
+                &context.parameters.set_play,
+                &walk_and_stand,
+                &look_action,
+                &mut context.path_obstacles_output,
+            ),
+            Action::ReceivePass => receive_pass::execute(
+                world_state,
+                &walk_and_stand,
+                &look_action,
+                &mut context.path_obstacles_output,
+            ),
+            Action::Search => search::execute(
+                world_state,
+                &walk_path_planner,
+                &walk_and_stand,
+                context.field_dimensions,
+                &context.parameters.search,
+                &mut context.path_obstacles_output,
+            ),
+            Action::SearchForLostBall => lost_ball::execute(
+                world_state,
+                self.last_known_ball_position,
+                &walk_path_planner,
+                context.lost_ball_parameters,
+                &mut context.path_obstacles_output,
+            ),
+            Action::SupportLeft => support::execute(
+                world_state,
+                context.field_dimensions,
+                Some(Side::Left),
+                context
+                    .parameters
+                    .role_positions
+                    .left_midfielder_distance_to_ball,
+                context
+                    .parameters
+                    .role_positions
+                    .left_midfielder_maximum_x_in_ready_and_when_ball_is_not_free,
+                context.parameters.role_positions.left_midfielder_minimum_x,
+                &positioning,
+                &context.parameters.positioning.midfielder_left,
+                &walk_and_stand,
+                &look_action,
+                &mut context.path_obstacles_output,
+                &mut context.positioning_heatmap_output,
+            ),
+            Action::SupportRight => support::execute(
+                world_state,
+                context.field_dimensions,
+                Some(Side::Right),
+                context
+                    .parameters
+                    .role_positions
+                    .right_midfielder_distance_to_ball,
+                context
+                    .parameters
+                    .role_positions
+                    .right_midfielder_maximum_x_in_ready_and_when_ball_is_not_free,
+                context.parameters.role_positions.right_midfielder_minimum_x,
+                &positioning,
+                &context.parameters.positioning.midfielder_right,
+                &walk_and_stand,
+                &look_action,
+                &mut context.path_obstacles_output,
+                &mut context.positioning_heatmap_output,
+            ),
+            Action::SupportStriker => support::execute(
+                world_state,
+                context.field_dimensions,
+                None,
+                context
+                    .parameters
+                    .role_positions
+                    .striker_supporter_distance_to_ball,
+                context
+                    .parameters
+                    .role_positions
+                    .striker_supporter_maximum_x_in_ready_and_when_ball_is_not_free,
+                context
+                    .parameters
+                    .role_positions
+                    .striker_supporter_minimum_x,
+                &positioning,
+                &context.parameters.positioning.striker_supporter,
+                &walk_and_stand,
+                &look_action,
+                &mut context.path_obstacles_output,
+                &mut context.positioning_heatmap_output,
+            ),
+            Action::WalkToKickOff => walk_to_kick_off::execute(
+                world_state,
+                &walk_and_stand,
+                &look_action,
+                &mut context.path_obstacles_output,
+                *context.striker_set_position,
+            ),
+            Action::WalkToPenaltyKick => walk_to_penalty_kick::execute(
+                world_state,
+                &walk_and_stand,
+                &look_action,
+                &mut context.path_obstacles_output,
+                context.field_dimensions,
+            ),
+        };
+        let ActiveBehavior {
+            action,
+            motion_command,
+            path,
+        } = self
+            .tree_state
+            .tick(
+                &context.parameters.tree,
+                Tick {
+                    now,
+                    world_state,
+                    active_duration,
+                    execute,
+                },
+            )
+            // a tree without an unconditional fallback may not produce a motion command
+            .unwrap_or_else(|| ActiveBehavior {
+                action: Action::Stand,
+                motion_command: MotionCommand::Stand {
+                    head: HeadMotion::Center,
+                },
+                path: Vec::new(),
             });
-        context.active_action_output.fill_if_subscribed(|| *action);
+        context
+            .dribble_path_obstacles_output
+            .fill_if_subscribed(|| dribble_path_obstacles.clone().unwrap_or_default());
+        context.active_action_output.fill_if_subscribed(|| action);
+        context
+            .active_behavior_path_output
+            .fill_if_subscribed(|| path);
 
         self.last_motion_command = motion_command.clone();
 
@@ -0,0 +1,340 @@
+use coordinate_systems::{Field, Ground};
+use framework::AdditionalOutput;
+use geometry::line_segment::LineSegment;
+use linear_algebra::{distance, point, vector, Isometry2, Point2, Pose};
+use types::{
+    field_dimensions::FieldDimensions,
+    obstacles::ObstacleKind,
+    parameters::{PositioningParameters, RolePositioningParameters},
+    positioning::{PositionUtility, PositioningHeatmap},
+    rule_obstacles::RuleObstacle,
+    world_state::{BallState, WorldState},
+};
+
+/// Moves the fixed role positions to the position of highest utility in their surroundings
+pub struct Positioning<'cycle> {
+    world_state: &'cycle WorldState,
+    field_dimensions: &'cycle FieldDimensions,
+    parameters: &'cycle PositioningParameters,
+}
+
+impl<'cycle> Positioning<'cycle> {
+    pub fn new(
+        world_state: &'cycle WorldState,
+        field_dimensions: &'cycle FieldDimensions,
+        parameters: &'cycle PositioningParameters,
+    ) -> Self {
+        Self {
+            world_state,
+            field_dimensions,
+            parameters,
+        }
+    }
+
+    /// Candidates beyond `maximum_x` are infeasible, the orientation of the role pose is kept
+    pub fn improve(
+        &self,
+        role_pose: Pose<Ground>,
+        role_parameters: &RolePositioningParameters,
+        maximum_x: Option<f32>,
+        heatmap_output: &mut AdditionalOutput<PositioningHeatmap>,
+    ) -> Pose<Ground> {
+        if !role_parameters.enable {
+            return role_pose;
+        }
+        let Some(ground_to_field) = self.world_state.robot.ground_to_field else {
+            return role_pose;
+        };
+        let surroundings =
+            Surroundings::new(self.world_state, self.field_dimensions, ground_to_field);
+        let anchor = ground_to_field * role_pose.position();
+        let current_position = ground_to_field * Point2::origin();
+
+        let mut candidates: Vec<_> = self
+            .candidates(anchor, role_parameters.search_radius)
+            .into_iter()
+            .map(|position| (position, 0.0))
+            .collect();
+        if distance(current_position, anchor) <= role_parameters.search_radius {
+            candidates.push((current_position, self.parameters.hysteresis));
+        }
+        let evaluated: Vec<_> = candidates
+            .into_iter()
+            .map(|(position, bonus)| PositionUtility {
+                position,
+                utility: utility(position, anchor, &surroundings, role_parameters) + bonus,
+                is_feasible: self.is_feasible(position, maximum_x, &surroundings.rule_obstacles),
+            })
+            .collect();
+        let best = evaluated
+            .iter()
+            .filter(|candidate| candidate.is_feasible)
+            .max_by(|left, right| left.utility.total_cmp(&right.utility))
+            .map(|candidate| candidate.position);
+        heatmap_output.fill_if_subscribed(|| PositioningHeatmap {
+            cell_size: self.parameters.grid_spacing,
+            candidates: evaluated,
+        });
+
+        match best {
+            Some(position) => Pose::from_parts(
+                ground_to_field.inverse() * position,
+                role_pose.orientation(),
+            ),
+            None => role_pose,
+        }
+    }
+
+    fn candidates(&self, anchor: Point2<Field>, search_radius: f32) -> Vec<Point2<Field>> {
+        let spacing = self.parameters.grid_spacing;
+        let steps = (search_radius / spacing).floor() as i32;
+        (-steps..=steps)
+            .flat_map(|x| (-steps..=steps).map(move |y| vector![x as f32, y as f32] * spacing))
+            .filter(|offset| offset.norm() <= search_radius)
+            .map(|offset| anchor + offset)
+            .collect()
+    }
+
+    fn is_feasible(
+        &self,
+        position: Point2<Field>,
+        maximum_x: Option<f32>,
+        rule_obstacles: &[RuleObstacle],
+    ) -> bool {
+        let is_inside_field = position.x().abs() <= self.field_dimensions.length / 2.0
+            && position.y().abs() <= self.field_dimensions.width / 2.0;
+        let is_behind_maximum_x = maximum_x.map_or(true, |maximum_x| position.x() <= maximum_x);
+        let margin = self.parameters.rule_obstacle_margin;
+        let is_blocked = rule_obstacles.iter().any(|obstacle| match obstacle {
+            RuleObstacle::Circle(circle) => {
+                distance(circle.center, position) < circle.radius + margin
+            }
+            RuleObstacle::Rectangle(rectangle) => {
+                position.x() > rectangle.min.x() - margin
+                    && position.x() < rectangle.max.x() + margin
+                    && position.y() > rectangle.min.y() - margin
+                    && position.y() < rectangle.max.y() + margin
+            }
+        });
+        is_inside_field && is_behind_maximum_x && !is_blocked
+    }
+}
+
+struct Surroundings {
+    ball: Point2<Field>,
+    own_goal: Point2<Field>,
+    teammates: Vec<Point2<Field>>,
+    opponents: Vec<Point2<Field>>,
+    rule_obstacles: Vec<RuleObstacle>,
+}
+
+impl Surroundings {
+    fn new(
+        world_state: &WorldState,
+        field_dimensions: &FieldDimensions,
+        ground_to_field: Isometry2<Ground, Field>,
+    ) -> Self {
+        let ball = world_state
+            .rule_ball
+            .or(world_state.ball)
+            .unwrap_or_else(|| BallState::new_at_center(ground_to_field))
+            .ball_in_field;
+        let robots_of_kind = |kinds: &[ObstacleKind]| -> Vec<Point2<Field>> {
+            world_state
+                .obstacles
+                .iter()
+                .filter(|obstacle| kinds.contains(&obstacle.kind))
+                .map(|obstacle| ground_to_field * obstacle.position)
+                .collect()
+        };
+        Self {
+            ball,
+            own_goal: point![-field_dimensions.length / 2.0, 0.0],
+            teammates: robots_of_kind(&[ObstacleKind::Teammate]),
+            // unclassified robots may be opponents as well
+            opponents: robots_of_kind(&[ObstacleKind::Opponent, ObstacleKind::Robot]),
+            rule_obstacles: world_state.rule_obstacles.clone(),
+        }
+    }
+}
+
+fn utility(
+    position: Point2<Field>,
+    anchor: Point2<Field>,
+    surroundings: &Surroundings,
+    parameters: &RolePositioningParameters,
+) -> f32 {
+    let anchor_cost = distance(position, anchor);
+
+    let shot_line = LineSegment::new(surroundings.ball, surroundings.own_goal);
+    let goal_coverage = (1.0
+        - shot_line.shortest_distance_to_point(position) / parameters.goal_coverage_width)
+        .max(0.0);
+
+    let passing_lane = LineSegment::new(surroundings.ball, position);
+    let passing_lane_clearance = surroundings
+        .opponents
+        .iter()
+        .map(|&opponent| passing_lane.shortest_distance_to_point(opponent))
+        .fold(parameters.passing_lane_width, f32::min)
+        / parameters.passing_lane_width;
+
+    let teammate_crowding: f32 = surroundings
+        .teammates
+        .iter()
+        .map(|&teammate| {
+            (parameters.minimum_teammate_distance - distance(position, teammate)).max(0.0)
+        })
+        .sum();
+
+    let opponent_space = surroundings
+        .opponents
+        .iter()
+        .map(|&opponent| distance(position, opponent))
+        .fold(parameters.maximum_opponent_distance, f32::min)
+        / parameters.maximum_opponent_distance;
+
+    -parameters.anchor_weight * anchor_cost
+        + parameters.goal_coverage_weight * goal_coverage
+        + parameters.passing_lane_weight * passing_lane_clearance
+        - parameters.teammate_distance_weight * teammate_crowding
+        + parameters.opponent_distance_weight * opponent_space
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::circle::Circle;
+    use linear_algebra::Vector2;
+    use types::{obstacles::Obstacle, world_state::RobotState};
+
+    use super::*;
+
+    fn role_parameters() -> RolePositioningParameters {
+        RolePositioningParameters {
+            enable: true,
+            search_radius: 1.0,
+            anchor_weight: 1.0,
+            goal_coverage_weight: 0.0,
+            goal_coverage_width: 0.6,
+            passing_lane_weight: 2.0,
+            passing_lane_width: 0.5,
+            teammate_distance_weight: 2.0,
+            minimum_teammate_distance: 1.0,
+            opponent_distance_weight: 0.0,
+            maximum_opponent_distance: 1.0,
+        }
+    }
+
+    fn world_state() -> WorldState {
+        WorldState {
+            robot: RobotState {
+                ground_to_field: Some(Isometry2::identity()),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn improve(
+        world_state: &WorldState,
+        role_pose: Pose<Ground>,
+        maximum_x: Option<f32>,
+    ) -> Pose<Ground> {
+        let field_dimensions = FieldDimensions {
+            length: 9.0,
+            width: 6.0,
+            ..Default::default()
+        };
+        let parameters = PositioningParameters {
+            grid_spacing: 0.25,
+            rule_obstacle_margin: 0.2,
+            hysteresis: 0.5,
+            ..Default::default()
+        };
+        let positioning = Positioning::new(world_state, &field_dimensions, &parameters);
+        let mut heatmap = None;
+        positioning.improve(
+            role_pose,
+            &role_parameters(),
+            maximum_x,
+            &mut AdditionalOutput::new(false, &mut heatmap),
+        )
+    }
+
+    #[test]
+    fn unobstructed_role_position_is_kept() {
+        let world_state = world_state();
+        let role_pose = Pose::new(vector![-2.0, 1.0], 0.0);
+
+        let pose = improve(&world_state, role_pose, None);
+
+        assert!(distance(pose.position(), role_pose.position()) < 1e-3);
+    }
+
+    #[test]
+    fn position_leaves_rule_obstacle() {
+        let mut world_state = world_state();
+        world_state.rule_obstacles =
+            vec![RuleObstacle::Circle(Circle::new(point![-2.0, 1.0], 0.5))];
+        let role_pose = Pose::new(vector![-2.0, 1.0], 0.0);
+
+        let pose = improve(&world_state, role_pose, None);
+
+        assert!(distance(pose.position(), point![-2.0, 1.0]) >= 0.7);
+    }
+
+    #[test]
+    fn position_avoids_opponent_in_passing_lane() {
+        let mut world_state = world_state();
+        world_state.obstacles = vec![Obstacle {
+            kind: ObstacleKind::Opponent,
+            position: point![-1.0, 0.5],
+            radius_at_foot_height: 0.2,
+            radius_at_hip_height: 0.2,
+            velocity: Vector2::zeros(),
+        }];
+        let role_pose = Pose::new(vector![-2.0, 1.0], 0.0);
+
+        let pose = improve(&world_state, role_pose, None);
+
+        let lane = LineSegment::new(point![0.0, 0.0], pose.position());
+        assert!(lane.shortest_distance_to_point(point![-1.0, 0.5]) > 0.2);
+    }
+
+    #[test]
+    fn position_stays_behind_maximum_x() {
+        let mut world_state = world_state();
+        world_state.rule_obstacles =
+            vec![RuleObstacle::Circle(Circle::new(point![-2.0, 1.0], 0.5))];
+        let role_pose = Pose::new(vector![-2.0, 1.0], 0.0);
+
+        let pose = improve(&world_state, role_pose, Some(-2.0));
+
+        assert!(pose.position().x() <= -2.0 + 1e-3);
+        assert!(distance(pose.position(), point![-2.0, 1.0]) >= 0.7);
+    }
+
+    #[test]
+    fn orientation_of_role_is_kept() {
+        let mut world_state = world_state();
+        world_state.rule_obstacles =
+            vec![RuleObstacle::Circle(Circle::new(point![-2.0, 1.0], 0.5))];
+        let role_pose = Pose::new(vector![-2.0, 1.0], 1.0);
+
+        let pose = improve(&world_state, role_pose, None);
+
+        assert!(distance(pose.position(), point![-2.0, 1.0]) >= 0.7);
+        assert!((pose.angle() - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn robot_keeps_its_nearby_position() {
+        let mut world_state = world_state();
+        world_state.robot.ground_to_field = Some(Isometry2::new(vector![-2.1, 1.1], 0.0));
+        let role_pose = Pose::new(vector![0.1, -0.1], 0.0);
+
+        let pose = improve(&world_state, role_pose, None);
+
+        assert!(distance(pose.position(), Point2::origin()) < 1e-3);
+    }
+}
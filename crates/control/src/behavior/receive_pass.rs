@@ -0,0 +1,34 @@
+use framework::AdditionalOutput;
+use geometry::look_at::LookAt;
+use linear_algebra::Pose;
+use types::{
+    motion_command::MotionCommand, path_obstacles::PathObstacle, primary_state::PrimaryState,
+    world_state::WorldState,
+};
+
+use super::{head::LookAction, walk_to_pose::WalkAndStand};
+
+pub fn execute(
+    world_state: &WorldState,
+    walk_and_stand: &WalkAndStand,
+    look_action: &LookAction,
+    path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
+) -> Option<MotionCommand> {
+    if world_state.robot.primary_state != PrimaryState::Playing {
+        return None;
+    }
+    let expected_pass = world_state.expected_pass?;
+    let ground_to_field = world_state.robot.ground_to_field?;
+    let target = expected_pass.target;
+    // face the opponent goal if the ball is unknown
+    let orientation = match world_state.ball {
+        Some(ball) => target.look_at(&ball.ball_in_field).angle(),
+        None => 0.0,
+    };
+    let receive_pose = Pose::new(target.coords(), orientation);
+    walk_and_stand.execute(
+        ground_to_field.inverse() * receive_pose,
+        look_action.execute(),
+        path_obstacles_output,
+    )
+}
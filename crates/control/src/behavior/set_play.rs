@@ -0,0 +1,64 @@
+use framework::AdditionalOutput;
+use geometry::look_at::LookAt;
+use linear_algebra::{point, vector, Pose};
+use types::{
+    field_dimensions::FieldDimensions, motion_command::MotionCommand,
+    parameters::SetPlayParameters, path_obstacles::PathObstacle, rule_obstacles::RuleObstacle,
+    world_state::WorldState,
+};
+
+use super::{head::LookAction, walk_to_pose::WalkAndStand};
+
+/// Waits behind the pose of the planned kick so that teammates can get into position
+pub fn prepare(
+    world_state: &WorldState,
+    parameters: &SetPlayParameters,
+    walk_and_stand: &WalkAndStand,
+    look_action: &LookAction,
+    path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
+) -> Option<MotionCommand> {
+    let kick_pose = world_state.kick_decisions.as_ref()?.first()?.kick_pose;
+    let angle = kick_pose.angle();
+    let preparation_position =
+        kick_pose.position() - vector![angle.cos(), angle.sin()] * parameters.preparation_distance;
+    walk_and_stand.execute(
+        Pose::new(preparation_position.coords(), angle),
+        look_action.execute(),
+        path_obstacles_output,
+    )
+}
+
+/// Blocks the way from the ball to the own goal at the distance required by the rules
+pub fn defend(
+    world_state: &WorldState,
+    field_dimensions: &FieldDimensions,
+    parameters: &SetPlayParameters,
+    walk_and_stand: &WalkAndStand,
+    look_action: &LookAction,
+    path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
+) -> Option<MotionCommand> {
+    let ground_to_field = world_state.robot.ground_to_field?;
+    let ball = world_state.rule_ball.or(world_state.ball)?.ball_in_field;
+    let required_distance =
+        world_state
+            .rule_obstacles
+            .iter()
+            .find_map(|obstacle| match obstacle {
+                RuleObstacle::Circle(circle) if circle.contains(ball) => Some(circle.radius),
+                _ => None,
+            })?;
+
+    let own_goal = point![-field_dimensions.length / 2.0, 0.0];
+    let blocking_position = ball
+        + (own_goal - ball).normalize()
+            * (required_distance + parameters.defending_distance_margin);
+    let blocking_pose = Pose::new(
+        blocking_position.coords(),
+        blocking_position.look_at(&ball).angle(),
+    );
+    walk_and_stand.execute(
+        ground_to_field.inverse() * blocking_pose,
+        look_action.execute(),
+        path_obstacles_output,
+    )
+}
@@ -8,12 +8,14 @@ use types::{
     field_dimensions::FieldDimensions,
     filtered_game_state::FilteredGameState,
     motion_command::MotionCommand,
+    parameters::RolePositioningParameters,
     path_obstacles::PathObstacle,
+    positioning::PositioningHeatmap,
     support_foot::Side,
     world_state::{BallState, WorldState},
 };
 
-use super::{head::LookAction, walk_to_pose::WalkAndStand};
+use super::{head::LookAction, positioning::Positioning, walk_to_pose::WalkAndStand};
 
 #[allow(clippy::too_many_arguments)]
 pub fn execute(
@@ -23,9 +25,12 @@ pub fn execute(
     distance_to_ball: f32,
     maximum_x_in_ready_and_when_ball_is_not_free: f32,
     minimum_x: f32,
+    positioning: &Positioning,
+    role_positioning: &RolePositioningParameters,
     walk_and_stand: &WalkAndStand,
     look_action: &LookAction,
     path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
+    positioning_heatmap_output: &mut AdditionalOutput<PositioningHeatmap>,
 ) -> Option<MotionCommand> {
     let pose = support_pose(
         world_state,
@@ -35,6 +40,14 @@ pub fn execute(
         maximum_x_in_ready_and_when_ball_is_not_free,
         minimum_x,
     )?;
+    let maximum_x = restricted_maximum_x(world_state, maximum_x_in_ready_and_when_ball_is_not_free)
+        .map(|maximum_x| minimum_x.max(maximum_x));
+    let pose = positioning.improve(
+        pose,
+        role_positioning,
+        maximum_x,
+        positioning_heatmap_output,
+    );
     walk_and_stand.execute(pose, look_action.execute(), path_obstacles_output)
 }
 
@@ -58,22 +71,15 @@ fn support_pose(
     }) * -(Vector2::<Field>::x_axis() * distance_to_ball);
     let supporting_position = ball.ball_in_field + offset_vector;
 
-    let filtered_game_state = world_state
-        .filtered_game_controller_state
-        .map(|filtered_game_controller_state| filtered_game_controller_state.game_state);
-    let clamped_x = match filtered_game_state {
-        Some(FilteredGameState::Ready { .. })
-        | Some(FilteredGameState::Playing {
-            ball_is_free: false,
-            ..
-        }) => supporting_position.x().clamp(
-            minimum_x.min(maximum_x_in_ready_and_when_ball_is_not_free),
-            minimum_x.max(maximum_x_in_ready_and_when_ball_is_not_free),
-        ),
-        _ => supporting_position
-            .x()
-            .clamp(minimum_x, field_dimensions.length / 2.0),
-    };
+    let clamped_x =
+        match restricted_maximum_x(world_state, maximum_x_in_ready_and_when_ball_is_not_free) {
+            Some(maximum_x) => supporting_position
+                .x()
+                .clamp(minimum_x.min(maximum_x), minimum_x.max(maximum_x)),
+            None => supporting_position
+                .x()
+                .clamp(minimum_x, field_dimensions.length / 2.0),
+        };
     let clamped_y = supporting_position
         .y()
         .clamp(-field_dimensions.width / 2.0, field_dimensions.width / 2.0);
@@ -84,3 +90,20 @@ fn support_pose(
     );
     Some(ground_to_field.inverse() * support_pose)
 }
+
+fn restricted_maximum_x(
+    world_state: &WorldState,
+    maximum_x_in_ready_and_when_ball_is_not_free: f32,
+) -> Option<f32> {
+    let filtered_game_state = world_state
+        .filtered_game_controller_state
+        .map(|filtered_game_controller_state| filtered_game_controller_state.game_state);
+    match filtered_game_state {
+        Some(FilteredGameState::Ready { .. })
+        | Some(FilteredGameState::Playing {
+            ball_is_free: false,
+            ..
+        }) => Some(maximum_x_in_ready_and_when_ball_is_not_free),
+        _ => None,
+    }
+}
@@ -0,0 +1,559 @@
+use std::{
+    collections::BTreeMap,
+    mem::take,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use spl_network_messages::{GamePhase, SubState};
+use types::{
+    action::Action,
+    behavior_tree::{BehaviorTree, Condition},
+    filtered_game_controller_state::FilteredGameControllerState,
+    filtered_game_state::FilteredGameState,
+    motion_command::MotionCommand,
+    world_state::WorldState,
+};
+
+enum Status {
+    Failure,
+    Success,
+    Running {
+        action: Action,
+        motion_command: MotionCommand,
+    },
+}
+
+pub struct ActiveBehavior {
+    pub action: Action,
+    pub motion_command: MotionCommand,
+    /// Nodes from the root to the running action, prefixed with their index among their siblings
+    pub path: Vec<String>,
+}
+
+/// Inputs of one evaluation of the tree
+pub struct Tick<'a, Execute> {
+    pub now: SystemTime,
+    pub world_state: &'a WorldState,
+    /// Time since the robot entered ready, set or playing
+    pub active_duration: Option<Duration>,
+    pub execute: Execute,
+}
+
+/// State of the stateful decorators, identified by their position in the tree
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct BehaviorTreeState {
+    not_failing_since: BTreeMap<Vec<usize>, SystemTime>,
+}
+
+impl BehaviorTreeState {
+    pub fn tick<Execute>(
+        &mut self,
+        tree: &BehaviorTree,
+        tick: Tick<Execute>,
+    ) -> Option<ActiveBehavior>
+    where
+        Execute: FnMut(Action) -> Option<MotionCommand>,
+    {
+        let mut evaluation = Evaluation {
+            tick,
+            last_not_failing_since: take(&mut self.not_failing_since),
+            not_failing_since: BTreeMap::new(),
+            index_path: Vec::new(),
+            active_path: Vec::new(),
+        };
+        let status = evaluation.evaluate(tree);
+        self.not_failing_since = evaluation.not_failing_since;
+
+        match status {
+            Status::Running {
+                action,
+                motion_command,
+            } => {
+                let mut path = evaluation.active_path;
+                path.push(label(tree));
+                path.reverse();
+                Some(ActiveBehavior {
+                    action,
+                    motion_command,
+                    path,
+                })
+            }
+            Status::Failure | Status::Success => None,
+        }
+    }
+}
+
+struct Evaluation<'a, Execute> {
+    tick: Tick<'a, Execute>,
+    last_not_failing_since: BTreeMap<Vec<usize>, SystemTime>,
+    not_failing_since: BTreeMap<Vec<usize>, SystemTime>,
+    index_path: Vec<usize>,
+    /// Collected in reverse while returning from a running action
+    active_path: Vec<String>,
+}
+
+impl<'a, Execute> Evaluation<'a, Execute>
+where
+    Execute: FnMut(Action) -> Option<MotionCommand>,
+{
+    fn evaluate(&mut self, node: &BehaviorTree) -> Status {
+        match node {
+            BehaviorTree::Selector(children) => {
+                for (index, child) in children.iter().enumerate() {
+                    match self.evaluate_child(child, index) {
+                        Status::Failure => continue,
+                        status => return status,
+                    }
+                }
+                Status::Failure
+            }
+            BehaviorTree::Sequence(children) => {
+                for (index, child) in children.iter().enumerate() {
+                    match self.evaluate_child(child, index) {
+                        Status::Success => continue,
+                        status => return status,
+                    }
+                }
+                Status::Success
+            }
+            BehaviorTree::Condition(condition) => {
+                if is_fulfilled(condition, self.tick.world_state, self.tick.active_duration) {
+                    Status::Success
+                } else {
+                    Status::Failure
+                }
+            }
+            BehaviorTree::Inverter(child) => match self.evaluate_child(child, 0) {
+                Status::Failure => Status::Success,
+                Status::Success => Status::Failure,
+                running => running,
+            },
+            BehaviorTree::MaximumDuration { duration, child } => {
+                let status = self.evaluate_child(child, 0);
+                if matches!(status, Status::Failure) {
+                    return status;
+                }
+                let since = self
+                    .last_not_failing_since
+                    .get(&self.index_path)
+                    .copied()
+                    .unwrap_or(self.tick.now);
+                self.not_failing_since
+                    .insert(self.index_path.clone(), since);
+                let elapsed = self.tick.now.duration_since(since).unwrap_or_default();
+                if elapsed > *duration {
+                    self.active_path.clear();
+                    return Status::Failure;
+                }
+                status
+            }
+            BehaviorTree::Action(action) => match (self.tick.execute)(*action) {
+                Some(motion_command) => Status::Running {
+                    action: *action,
+                    motion_command,
+                },
+                None => Status::Failure,
+            },
+        }
+    }
+
+    fn evaluate_child(&mut self, child: &BehaviorTree, index: usize) -> Status {
+        self.index_path.push(index);
+        let status = self.evaluate(child);
+        self.index_path.pop();
+        if matches!(status, Status::Running { .. }) {
+            self.active_path.push(format!("{index}: {}", label(child)));
+        }
+        status
+    }
+}
+
+fn label(node: &BehaviorTree) -> String {
+    match node {
+        BehaviorTree::Selector(..) => "Selector".to_string(),
+        BehaviorTree::Sequence(..) => "Sequence".to_string(),
+        BehaviorTree::Condition(condition) => format!("{condition:?}"),
+        BehaviorTree::Inverter(..) => "Inverter".to_string(),
+        BehaviorTree::MaximumDuration { duration, .. } => format!("MaximumDuration({duration:?})"),
+        BehaviorTree::Action(action) => format!("{action:?}"),
+    }
+}
+
+fn is_fulfilled(
+    condition: &Condition,
+    world_state: &WorldState,
+    active_duration: Option<Duration>,
+) -> bool {
+    let game_controller_state = world_state.filtered_game_controller_state;
+    match condition {
+        Condition::Role(role) => world_state.robot.role == *role,
+        Condition::ActiveForLessThan(duration) => {
+            active_duration.is_some_and(|active_duration| active_duration < *duration)
+        }
+        Condition::BallIsFree => matches!(
+            game_controller_state,
+            None | Some(FilteredGameControllerState {
+                game_state: FilteredGameState::Playing {
+                    ball_is_free: true,
+                    ..
+                },
+                ..
+            })
+        ),
+        Condition::Ready => matches!(
+            game_controller_state,
+            Some(FilteredGameControllerState {
+                game_state: FilteredGameState::Ready { .. },
+                ..
+            })
+        ),
+        Condition::ReadyWithKickingTeam(team) => matches!(
+            game_controller_state,
+            Some(FilteredGameControllerState {
+                game_state: FilteredGameState::Ready { kicking_team },
+                ..
+            }) if kicking_team == *team
+        ),
+        Condition::KickingTeam(team) => game_controller_state
+            .is_some_and(|game_controller_state| game_controller_state.kicking_team == *team),
+        Condition::SubState(sub_state) => {
+            game_controller_state.is_some_and(|game_controller_state| {
+                game_controller_state.sub_state == Some(*sub_state)
+            })
+        }
+        Condition::SetPlay => matches!(
+            game_controller_state,
+            Some(FilteredGameControllerState {
+                sub_state: Some(
+                    SubState::KickIn
+                        | SubState::GoalKick
+                        | SubState::CornerKick
+                        | SubState::PushingFreeKick
+                ),
+                ..
+            })
+        ),
+        Condition::PenaltyShootout => matches!(
+            game_controller_state,
+            Some(FilteredGameControllerState {
+                game_phase: GamePhase::PenaltyShootout { .. },
+                ..
+            })
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use serde_json::Value;
+    use spl_network_messages::Team;
+    use types::{motion_command::HeadMotion, roles::Role};
+
+    use super::*;
+
+    fn stand() -> MotionCommand {
+        MotionCommand::Stand {
+            head: HeadMotion::Center,
+        }
+    }
+
+    fn tick_with<'a>(
+        world_state: &'a WorldState,
+        now: SystemTime,
+        available_actions: &'a [Action],
+    ) -> Tick<'a, impl FnMut(Action) -> Option<MotionCommand> + 'a> {
+        Tick {
+            now,
+            world_state,
+            active_duration: None,
+            execute: move |action| available_actions.contains(&action).then(stand),
+        }
+    }
+
+    fn role_subtree(role: Role, action: Action) -> BehaviorTree {
+        BehaviorTree::Sequence(vec![
+            BehaviorTree::Condition(Condition::Role(role)),
+            BehaviorTree::Action(action),
+        ])
+    }
+
+    #[test]
+    fn first_available_action_of_matching_role_is_selected() {
+        let tree = BehaviorTree::Selector(vec![
+            BehaviorTree::Action(Action::Unstiff),
+            role_subtree(Role::Keeper, Action::DefendGoal),
+            role_subtree(Role::Striker, Action::Dribble),
+        ]);
+        let mut world_state = WorldState::default();
+        world_state.robot.role = Role::Striker;
+
+        let active = BehaviorTreeState::default()
+            .tick(
+                &tree,
+                tick_with(
+                    &world_state,
+                    SystemTime::UNIX_EPOCH,
+                    &[Action::DefendGoal, Action::Dribble],
+                ),
+            )
+            .expect("an action should run");
+
+        assert_eq!(active.action, Action::Dribble);
+        assert_eq!(active.path, vec!["Selector", "2: Sequence", "1: Dribble"]);
+    }
+
+    #[test]
+    fn inverted_condition_guards_action() {
+        let tree = BehaviorTree::Selector(vec![
+            BehaviorTree::Sequence(vec![
+                BehaviorTree::Inverter(Box::new(BehaviorTree::Condition(Condition::Role(
+                    Role::Keeper,
+                )))),
+                BehaviorTree::Action(Action::Search),
+            ]),
+            BehaviorTree::Action(Action::DefendGoal),
+        ]);
+        let mut world_state = WorldState::default();
+        world_state.robot.role = Role::Keeper;
+
+        let active = BehaviorTreeState::default()
+            .tick(
+                &tree,
+                tick_with(
+                    &world_state,
+                    SystemTime::UNIX_EPOCH,
+                    &[Action::Search, Action::DefendGoal],
+                ),
+            )
+            .expect("an action should run");
+
+        assert_eq!(active.action, Action::DefendGoal);
+    }
+
+    #[test]
+    fn maximum_duration_falls_through_after_timeout_and_resets() {
+        let tree = BehaviorTree::Selector(vec![
+            BehaviorTree::MaximumDuration {
+                duration: Duration::from_secs(2),
+                child: Box::new(BehaviorTree::Action(Action::LookAround)),
+            },
+            BehaviorTree::Action(Action::Stand),
+        ]);
+        let world_state = WorldState::default();
+        let mut state = BehaviorTreeState::default();
+        let start = SystemTime::UNIX_EPOCH;
+        let mut run = |seconds: u64, available_actions: &[Action]| {
+            state
+                .tick(
+                    &tree,
+                    tick_with(
+                        &world_state,
+                        start + Duration::from_secs(seconds),
+                        available_actions,
+                    ),
+                )
+                .map(|active| active.action)
+        };
+        let both = [Action::LookAround, Action::Stand];
+
+        assert_eq!(run(0, &both), Some(Action::LookAround));
+        assert_eq!(run(1, &both), Some(Action::LookAround));
+        assert_eq!(run(3, &both), Some(Action::Stand));
+        assert_eq!(run(4, &[Action::Stand]), Some(Action::Stand));
+        assert_eq!(run(5, &both), Some(Action::LookAround));
+    }
+
+    /// Actions of the hard-coded list the tree replaced, in the order they were tried
+    fn hard_coded_actions(
+        world_state: &WorldState,
+        active_duration: Option<Duration>,
+    ) -> Vec<Action> {
+        let mut actions = vec![
+            Action::Unstiff,
+            Action::SitDown,
+            Action::Penalize,
+            Action::Initial,
+            Action::FallSafely,
+            Action::StandUp,
+            Action::Stand,
+            Action::InterceptBall,
+            Action::Calibrate,
+        ];
+        if active_duration.is_some_and(|active_duration| active_duration < Duration::from_secs(5)) {
+            actions.push(Action::LookAround);
+        }
+        let game_controller_state = world_state.filtered_game_controller_state;
+        let filtered_game_state =
+            game_controller_state.map(|game_controller_state| game_controller_state.game_state);
+        match world_state.robot.role {
+            Role::DefenderLeft => actions.push(Action::DefendLeft),
+            Role::DefenderRight => actions.push(Action::DefendRight),
+            Role::Keeper => match game_controller_state {
+                Some(FilteredGameControllerState {
+                    game_phase: GamePhase::PenaltyShootout { .. },
+                    ..
+                }) => {
+                    actions.push(Action::Jump);
+                    actions.push(Action::PrepareJump);
+                }
+                _ => actions.push(Action::DefendGoal),
+            },
+            Role::Loser => actions.push(Action::SearchForLostBall),
+            Role::MidfielderLeft => actions.push(Action::SupportLeft),
+            Role::MidfielderRight => actions.push(Action::SupportRight),
+            Role::ReplacementKeeper => actions.push(Action::DefendGoal),
+            Role::Searcher => actions.push(Action::Search),
+            Role::Striker => match filtered_game_state {
+                None
+                | Some(FilteredGameState::Playing {
+                    ball_is_free: true, ..
+                }) => actions.push(Action::Dribble),
+                Some(FilteredGameState::Ready {
+                    kicking_team: Team::Hulks,
+                }) => match game_controller_state {
+                    Some(FilteredGameControllerState {
+                        sub_state: Some(SubState::PenaltyKick),
+                        ..
+                    }) => actions.push(Action::WalkToPenaltyKick),
+                    _ => actions.push(Action::WalkToKickOff),
+                },
+                _ => match game_controller_state {
+                    Some(FilteredGameControllerState {
+                        game_state: FilteredGameState::Ready { .. },
+                        sub_state: Some(SubState::PenaltyKick),
+                        kicking_team: Team::Opponent,
+                        ..
+                    }) => actions.push(Action::DefendPenaltyKick),
+                    _ => actions.push(Action::DefendKickOff),
+                },
+            },
+            Role::StrikerSupporter => actions.push(Action::SupportStriker),
+        }
+        actions
+    }
+
+    fn game_controller_states() -> Vec<Option<FilteredGameControllerState>> {
+        let teams = [Team::Hulks, Team::Opponent, Team::Uncertain];
+        let game_states = [
+            FilteredGameState::Initial,
+            FilteredGameState::Ready {
+                kicking_team: Team::Hulks,
+            },
+            FilteredGameState::Ready {
+                kicking_team: Team::Opponent,
+            },
+            // after a whistle in playing the kicking team is uncertain
+            FilteredGameState::Ready {
+                kicking_team: Team::Uncertain,
+            },
+            FilteredGameState::Set,
+            FilteredGameState::Playing {
+                ball_is_free: true,
+                kick_off: false,
+            },
+            FilteredGameState::Playing {
+                ball_is_free: false,
+                kick_off: true,
+            },
+            FilteredGameState::Finished,
+        ];
+        let sub_states = [
+            None,
+            Some(SubState::GoalKick),
+            Some(SubState::PushingFreeKick),
+            Some(SubState::CornerKick),
+            Some(SubState::KickIn),
+            Some(SubState::PenaltyKick),
+        ];
+        let game_phases = [
+            GamePhase::Normal,
+            GamePhase::PenaltyShootout {
+                kicking_team: Team::Hulks,
+            },
+        ];
+        let mut states = vec![None];
+        for game_state in game_states {
+            for kicking_team in teams {
+                for sub_state in sub_states {
+                    for game_phase in game_phases {
+                        states.push(Some(FilteredGameControllerState {
+                            game_state,
+                            game_phase,
+                            kicking_team,
+                            sub_state,
+                            ..Default::default()
+                        }));
+                    }
+                }
+            }
+        }
+        states
+    }
+
+    #[test]
+    fn default_tree_tries_actions_in_the_hard_coded_order() -> color_eyre::Result<()> {
+        let parameters: Value =
+            serde_json::from_str(&read_to_string("../../etc/parameters/default.json")?)?;
+        let tree: BehaviorTree = serde_json::from_value(parameters["behavior"]["tree"].clone())?;
+        // actions of behaviors added after the hard-coded list was replaced
+        let added_actions = [
+            Action::BlockShot,
+            Action::ReceivePass,
+            Action::PrepareSetPlay,
+            Action::DefendSetPlay,
+        ];
+        let roles = [
+            Role::DefenderLeft,
+            Role::DefenderRight,
+            Role::Keeper,
+            Role::Loser,
+            Role::MidfielderLeft,
+            Role::MidfielderRight,
+            Role::ReplacementKeeper,
+            Role::Searcher,
+            Role::Striker,
+            Role::StrikerSupporter,
+        ];
+        let active_durations = [
+            None,
+            Some(Duration::from_secs(1)),
+            Some(Duration::from_secs(10)),
+        ];
+
+        for role in roles {
+            for game_controller_state in game_controller_states() {
+                for active_duration in active_durations {
+                    let mut world_state = WorldState::default();
+                    world_state.robot.role = role;
+                    world_state.filtered_game_controller_state = game_controller_state;
+                    let mut tried_actions = Vec::new();
+                    let active = BehaviorTreeState::default().tick(
+                        &tree,
+                        Tick {
+                            now: SystemTime::UNIX_EPOCH,
+                            world_state: &world_state,
+                            active_duration,
+                            execute: |action| {
+                                tried_actions.push(action);
+                                None
+                            },
+                        },
+                    );
+                    tried_actions.retain(|action| !added_actions.contains(action));
+
+                    assert!(active.is_none());
+                    assert_eq!(
+                        tried_actions,
+                        hard_coded_actions(&world_state, active_duration),
+                        "{role:?}, {game_controller_state:?}, {active_duration:?}"
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
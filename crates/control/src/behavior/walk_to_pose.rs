@@ -56,6 +56,11 @@ impl<'cycle> WalkPathPlanner<'cycle> {
             self.parameters.rotation_penalty_factor,
         );
         planner.with_obstacles(obstacles, self.parameters.robot_radius_at_hip_height);
+        planner.with_predicted_obstacles(
+            obstacles,
+            self.parameters.robot_radius_at_hip_height,
+            self.parameters.obstacle_prediction_horizon,
+        );
         planner.with_rule_obstacles(
             ground_to_field.inverse(),
             rule_obstacles,
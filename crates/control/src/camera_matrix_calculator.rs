@@ -1,19 +1,18 @@
 use color_eyre::Result;
-use nalgebra::{Rotation3, UnitQuaternion};
+use nalgebra::Rotation3;
 use serde::{Deserialize, Serialize};
 
 use context_attribute::context;
-use coordinate_systems::{Camera, Ground, Head, Pixel, Robot};
+use coordinate_systems::{Ground, Pixel, Robot};
 use framework::{AdditionalOutput, MainOutput};
-use linear_algebra::{point, IntoTransform, Isometry3};
+use linear_algebra::{point, Isometry3};
 use projection::Projection;
 use types::{
-    camera_matrix::{CameraMatrices, CameraMatrix, ProjectedFieldLines},
+    camera_matrix::{camera_to_head, CameraMatrices, CameraMatrix, ProjectedFieldLines},
     camera_position::CameraPosition,
     field_dimensions::FieldDimensions,
     line::{Line, Line2},
     parameters::CameraMatrixParameters,
-    robot_dimensions::RobotDimensions,
     robot_kinematics::RobotKinematics,
 };
 
@@ -105,30 +104,6 @@ impl CameraMatrixCalculator {
     }
 }
 
-pub fn camera_to_head(
-    camera_position: CameraPosition,
-    extrinsic_rotation: nalgebra::Vector3<f32>,
-) -> Isometry3<Camera, Head> {
-    let extrinsic_angles_in_radians = extrinsic_rotation.map(|a: f32| a.to_radians());
-    let extrinsic_rotation = UnitQuaternion::from_euler_angles(
-        extrinsic_angles_in_radians.x,
-        extrinsic_angles_in_radians.y,
-        extrinsic_angles_in_radians.z,
-    );
-    let neck_to_camera = match camera_position {
-        CameraPosition::Top => RobotDimensions::NECK_TO_TOP_CAMERA,
-        CameraPosition::Bottom => RobotDimensions::NECK_TO_BOTTOM_CAMERA,
-    };
-    let camera_pitch = match camera_position {
-        CameraPosition::Top => 1.2f32.to_radians(),
-        CameraPosition::Bottom => 39.7f32.to_radians(),
-    };
-    (nalgebra::Isometry3::from(neck_to_camera.inner)
-        * nalgebra::Isometry3::rotation(nalgebra::Vector3::y() * camera_pitch)
-        * extrinsic_rotation)
-        .framed_transform()
-}
-
 fn project_penalty_area_on_images(
     field_dimensions: &FieldDimensions,
     camera_matrix: &CameraMatrix,
@@ -2,8 +2,9 @@ use framework::AdditionalOutput;
 use spl_network_messages::Team;
 use std::f32::consts::PI;
 use types::{
-    filtered_game_controller_state::FilteredGameControllerState, parameters::DribblingParameters,
-    path_obstacles::PathObstacle, planned_path::PathSegment, world_state::WorldState,
+    filtered_game_controller_state::FilteredGameControllerState, kick_decision::KickDecision,
+    parameters::DribblingParameters, path_obstacles::PathObstacle, planned_path::PathSegment,
+    world_state::WorldState,
 };
 
 use crate::behavior::walk_to_pose::WalkPathPlanner;
@@ -11,10 +12,11 @@ use crate::behavior::walk_to_pose::WalkPathPlanner;
 pub fn plan(
     walk_path_planner: &WalkPathPlanner,
     world_state: &WorldState,
+    kick_decisions: Option<&[KickDecision]>,
     dribbling_parameters: &DribblingParameters,
     path_obstacles_output: &mut AdditionalOutput<Vec<PathObstacle>>,
 ) -> Option<Vec<PathSegment>> {
-    let kick_decisions = world_state.kick_decisions.as_ref()?;
+    let kick_decisions = kick_decisions?;
     let best_kick_decision = kick_decisions.first()?;
     let ball = world_state.ball?;
     let ground_to_field = world_state.robot.ground_to_field?;
@@ -0,0 +1,507 @@
+use coordinate_systems::{Field, Ground};
+use geometry::line_segment::LineSegment;
+use linear_algebra::{distance, point, vector, Isometry2, Point2, Pose};
+use types::{
+    dribble_plan::{DribblePlan, DribbleStep},
+    field_dimensions::FieldDimensions,
+    kick_decision::KickDecision,
+    motion_command::KickVariant,
+    obstacles::{Obstacle, ObstacleKind},
+    parameters::{DribblePlanningParameters, InWalkKicksParameters},
+    support_foot::Side,
+    world_state::WorldState,
+};
+
+use crate::kick_selector::compute_kick_pose;
+
+/// Searches the sequence of in-walk kick pushes which advances the ball the most while staying
+/// clear of the predicted obstacle positions and keeping the ball out of reach of opponents
+///
+/// The best decision of the kick selector is evaluated with the same costs and kept as first push
+/// unless a searched push is cheaper by at least the margin.
+pub fn plan(
+    world_state: &WorldState,
+    field_dimensions: &FieldDimensions,
+    in_walk_kicks: &InWalkKicksParameters,
+    parameters: &DribblePlanningParameters,
+) -> Option<DribblePlan> {
+    if !parameters.enable {
+        return None;
+    }
+    let ball = world_state.ball?;
+    let ground_to_field = world_state.robot.ground_to_field?;
+
+    let planner = Planner {
+        ground_to_field,
+        field_dimensions,
+        in_walk_kicks,
+        parameters,
+        variants: [KickVariant::Forward, KickVariant::Turn, KickVariant::Side]
+            .into_iter()
+            .filter(|&variant| in_walk_kicks[variant].enabled)
+            .collect(),
+        obstacles: world_state
+            .obstacles
+            .iter()
+            .filter(|obstacle| obstacle.kind != ObstacleKind::Ball)
+            .copied()
+            .collect(),
+        opponent_goal: ground_to_field.inverse() * point![field_dimensions.length / 2.0, 0.0],
+    };
+    let robot = Pose::new(vector![0.0, 0.0], 0.0);
+    let (cost, steps) = planner.search(robot, ball.ball_in_ground, 0.0, parameters.search_depth)?;
+    let kick_selector_plan = world_state
+        .kick_decisions
+        .as_ref()
+        .and_then(|kick_decisions| kick_decisions.first())
+        .and_then(|&kick_decision| {
+            planner.evaluate_kick_decision(
+                robot,
+                ball.ball_in_ground,
+                kick_decision,
+                parameters.search_depth,
+            )
+        });
+    match kick_selector_plan {
+        Some((kick_selector_cost, kick_selector_steps))
+            if kick_selector_cost <= cost + parameters.kick_selector_margin =>
+        {
+            Some(DribblePlan {
+                steps: kick_selector_steps,
+                cost: kick_selector_cost,
+                follows_kick_selector: true,
+            })
+        }
+        _ => Some(DribblePlan {
+            steps,
+            cost,
+            follows_kick_selector: false,
+        }),
+    }
+}
+
+struct Planner<'a> {
+    ground_to_field: Isometry2<Ground, Field>,
+    field_dimensions: &'a FieldDimensions,
+    in_walk_kicks: &'a InWalkKicksParameters,
+    parameters: &'a DribblePlanningParameters,
+    variants: Vec<KickVariant>,
+    obstacles: Vec<Obstacle>,
+    opponent_goal: Point2<Ground>,
+}
+
+struct Push {
+    step: DribbleStep,
+    cost: f32,
+    /// Time at which the kick is executed, relative to now
+    time: f32,
+    scores_goal: bool,
+}
+
+impl Planner<'_> {
+    /// Depth first search over all push sequences, returns the cheapest one
+    fn search(
+        &self,
+        robot: Pose<Ground>,
+        ball: Point2<Ground>,
+        time: f32,
+        depth: usize,
+    ) -> Option<(f32, Vec<DribbleStep>)> {
+        if depth == 0 {
+            return None;
+        }
+        let ball_to_goal = self.opponent_goal - ball;
+        let angle_to_goal = ball_to_goal.y().atan2(ball_to_goal.x());
+
+        self.variants
+            .iter()
+            .flat_map(|&variant| {
+                self.parameters
+                    .direction_angles
+                    .iter()
+                    .map(move |&angle| (variant, angle_to_goal + angle))
+            })
+            .filter_map(|(variant, angle)| self.push(robot, ball, time, variant, angle))
+            .map(|push| self.continue_after(push, depth))
+            .min_by(|(left, _), (right, _)| left.total_cmp(right))
+    }
+
+    /// Evaluates a kick decision as push in its kick direction followed by the cheapest pushes
+    fn evaluate_kick_decision(
+        &self,
+        robot: Pose<Ground>,
+        ball: Point2<Ground>,
+        kick_decision: KickDecision,
+        depth: usize,
+    ) -> Option<(f32, Vec<DribbleStep>)> {
+        if depth == 0 {
+            return None;
+        }
+        let orientation = self.in_walk_kicks[kick_decision.variant].orientation;
+        let angle = match kick_decision.kicking_side {
+            Side::Left => kick_decision.kick_pose.angle() - orientation,
+            Side::Right => kick_decision.kick_pose.angle() + orientation,
+        };
+        let mut push = self.push(robot, ball, 0.0, kick_decision.variant, angle)?;
+        push.step.kick_decision = kick_decision;
+        Some(self.continue_after(push, depth))
+    }
+
+    fn continue_after(&self, push: Push, depth: usize) -> (f32, Vec<DribbleStep>) {
+        let mut steps = vec![push.step];
+        let mut cost = push.cost;
+        if !push.scores_goal {
+            if let Some((remaining_cost, remaining_steps)) = self.search(
+                push.step.kick_decision.kick_pose,
+                push.step.ball_end,
+                push.time,
+                depth - 1,
+            ) {
+                cost += self.parameters.discount_factor * remaining_cost;
+                steps.extend(remaining_steps);
+            }
+        }
+        (cost, steps)
+    }
+
+    fn push(
+        &self,
+        robot: Pose<Ground>,
+        ball: Point2<Ground>,
+        time: f32,
+        variant: KickVariant,
+        angle: f32,
+    ) -> Option<Push> {
+        let kick_info = &self.in_walk_kicks[variant];
+        let ball_end = ball + vector![angle.cos(), angle.sin()] * kick_info.dribble_distance;
+
+        let scores_goal = self.scores_goal(ball, ball_end);
+        if !scores_goal
+            && !self
+                .field_dimensions
+                .is_inside_field(self.ground_to_field * ball_end)
+        {
+            return None;
+        }
+
+        let (kicking_side, kick_pose, walking_time) = [Side::Left, Side::Right]
+            .into_iter()
+            .map(|side| {
+                let kick_pose = compute_kick_pose(ball, ball_end, kick_info, side);
+                (side, kick_pose, self.walking_time(robot, kick_pose))
+            })
+            .min_by(|(_, _, left), (_, _, right)| left.total_cmp(right))?;
+        let kick_time = time + walking_time;
+
+        let ball_path = LineSegment::new(ball, ball_end);
+        let is_colliding = self.obstacles.iter().any(|obstacle| {
+            ball_path.shortest_distance_to_point(predicted_position(obstacle, kick_time))
+                < obstacle.radius_at_foot_height + self.parameters.obstacle_clearance
+        });
+        if is_colliding {
+            return None;
+        }
+
+        let progress = distance(ball, self.opponent_goal) - distance(ball_end, self.opponent_goal);
+        let robot_time_to_ball =
+            distance(kick_pose.position(), ball_end) / self.parameters.walking_speed;
+        let opponent_time_to_ball = self
+            .obstacles
+            .iter()
+            .filter(|obstacle| obstacle.kind == ObstacleKind::Opponent)
+            .map(|obstacle| {
+                (distance(predicted_position(obstacle, kick_time), ball_end)
+                    - obstacle.radius_at_foot_height)
+                    .max(0.0)
+                    / self.parameters.opponent_speed
+            })
+            .fold(f32::INFINITY, f32::min);
+        let ball_control_deficit = (self.parameters.ball_control_margin
+            - (opponent_time_to_ball - robot_time_to_ball))
+            .max(0.0);
+
+        Some(Push {
+            step: DribbleStep {
+                kick_decision: KickDecision {
+                    variant,
+                    kicking_side,
+                    kick_pose,
+                    strength: kick_info.dribble_distance / kick_info.shot_distance,
+                },
+                ball_start: ball,
+                ball_end,
+            },
+            cost: self.parameters.time_weight * walking_time
+                - self.parameters.progress_weight * progress
+                + self.parameters.ball_control_weight * ball_control_deficit,
+            time: kick_time,
+            scores_goal,
+        })
+    }
+
+    fn walking_time(&self, from: Pose<Ground>, to: Pose<Ground>) -> f32 {
+        let turn = to.angle() - from.angle();
+        let turn = turn.sin().atan2(turn.cos()).abs();
+        distance(from.position(), to.position()) / self.parameters.walking_speed
+            + turn / self.parameters.turning_speed
+    }
+
+    fn scores_goal(&self, ball: Point2<Ground>, ball_end: Point2<Ground>) -> bool {
+        let start = self.ground_to_field * ball;
+        let end = self.ground_to_field * ball_end;
+        let goal_line_x = self.field_dimensions.length / 2.0;
+        if start.x() > goal_line_x || end.x() <= goal_line_x {
+            return false;
+        }
+        let factor = (goal_line_x - start.x()) / (end.x() - start.x());
+        let crossing_y = start.y() + factor * (end.y() - start.y());
+        crossing_y.abs() < self.field_dimensions.goal_inner_width / 2.0
+    }
+}
+
+fn predicted_position(obstacle: &Obstacle, time: f32) -> Point2<Ground> {
+    obstacle.position + obstacle.velocity * time
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use approx::assert_relative_eq;
+    use linear_algebra::Vector2;
+    use types::{
+        parameters::InWalkKickInfoParameters,
+        world_state::{BallState, RobotState},
+    };
+
+    use super::*;
+
+    fn kick_info(
+        orientation: f32,
+        shot_distance: f32,
+        dribble_distance: f32,
+    ) -> InWalkKickInfoParameters {
+        InWalkKickInfoParameters {
+            position: nalgebra::point![-0.2, 0.05],
+            orientation,
+            shot_distance,
+            dribble_distance,
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    fn in_walk_kicks() -> InWalkKicksParameters {
+        InWalkKicksParameters {
+            forward: kick_info(0.0, 4.0, 0.8),
+            turn: kick_info(-1.0, 3.5, 0.6),
+            side: kick_info(-1.57, 0.5, 0.4),
+        }
+    }
+
+    fn parameters() -> DribblePlanningParameters {
+        DribblePlanningParameters {
+            enable: true,
+            search_depth: 2,
+            direction_angles: vec![-1.0, -0.5, 0.0, 0.5, 1.0],
+            walking_speed: 0.25,
+            turning_speed: 0.8,
+            opponent_speed: 0.25,
+            obstacle_clearance: 0.1,
+            ball_control_margin: 1.0,
+            time_weight: 0.1,
+            progress_weight: 1.0,
+            ball_control_weight: 0.5,
+            discount_factor: 0.8,
+            kick_selector_margin: 0.1,
+        }
+    }
+
+    fn field_dimensions() -> FieldDimensions {
+        FieldDimensions {
+            length: 9.0,
+            width: 6.0,
+            goal_inner_width: 1.5,
+            ..Default::default()
+        }
+    }
+
+    fn world_state(ball: Point2<Ground>, obstacles: Vec<Obstacle>) -> WorldState {
+        let ground_to_field = Isometry2::identity();
+        WorldState {
+            ball: Some(BallState {
+                ball_in_ground: ball,
+                ball_in_field: ground_to_field * ball,
+                ball_in_ground_velocity: Vector2::zeros(),
+                last_seen_ball: UNIX_EPOCH,
+                penalty_shot_direction: None,
+                field_side: Side::Left,
+            }),
+            obstacles,
+            robot: RobotState {
+                ground_to_field: Some(ground_to_field),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn opponent(position: Point2<Ground>, velocity: Vector2<Ground>) -> Obstacle {
+        Obstacle {
+            kind: ObstacleKind::Opponent,
+            position,
+            radius_at_foot_height: 0.2,
+            radius_at_hip_height: 0.15,
+            velocity,
+        }
+    }
+
+    fn first_step(world_state: &WorldState) -> DribbleStep {
+        plan(
+            world_state,
+            &field_dimensions(),
+            &in_walk_kicks(),
+            &parameters(),
+        )
+        .expect("a dribble plan should be found")
+        .steps[0]
+    }
+
+    #[test]
+    fn free_ball_is_pushed_towards_opponent_goal() {
+        let world_state = world_state(point![0.3, 0.0], Vec::new());
+
+        let step = first_step(&world_state);
+
+        let direction = step.ball_end - step.ball_start;
+        assert!(direction.x() > 0.0);
+        assert!(direction.y().abs() < 0.1);
+    }
+
+    #[test]
+    fn pushes_avoid_obstacle_in_front() {
+        let world_state = world_state(
+            point![0.3, 0.0],
+            vec![opponent(point![0.9, 0.0], Vector2::zeros())],
+        );
+
+        let step = first_step(&world_state);
+
+        let ball_path = LineSegment::new(step.ball_start, step.ball_end);
+        assert!(ball_path.shortest_distance_to_point(point![0.9, 0.0]) >= 0.3);
+    }
+
+    #[test]
+    fn pushes_avoid_predicted_opponent_position() {
+        let world_state = world_state(
+            point![0.3, 0.0],
+            vec![opponent(point![1.0, 1.0], vector![0.0, -2.0])],
+        );
+
+        let step = first_step(&world_state);
+
+        let direction = step.ball_end - step.ball_start;
+        let is_straight_forward_push =
+            step.kick_decision.variant == KickVariant::Forward && direction.y().abs() < 0.1;
+        assert!(!is_straight_forward_push);
+    }
+
+    #[test]
+    fn only_opponents_contest_the_ball() {
+        let plan_cost = |obstacles| {
+            plan(
+                &world_state(point![0.3, 0.0], obstacles),
+                &field_dimensions(),
+                &in_walk_kicks(),
+                &parameters(),
+            )
+            .expect("a dribble plan should be found")
+            .cost
+        };
+        let robot = Obstacle {
+            kind: ObstacleKind::Robot,
+            ..opponent(point![1.2, 0.7], Vector2::zeros())
+        };
+
+        let free_cost = plan_cost(Vec::new());
+        assert_relative_eq!(plan_cost(vec![robot]), free_cost);
+        assert!(plan_cost(vec![opponent(point![1.2, 0.7], Vector2::zeros())]) > free_cost);
+    }
+
+    #[test]
+    fn ball_is_not_pushed_out_of_field() {
+        let world_state = world_state(point![0.3, 2.9], Vec::new());
+
+        let plan = plan(
+            &world_state,
+            &field_dimensions(),
+            &in_walk_kicks(),
+            &parameters(),
+        )
+        .unwrap();
+
+        assert!(plan.steps.iter().all(|step| step.ball_end.y() < 3.0));
+    }
+
+    #[test]
+    fn disabled_planning_produces_no_plan() {
+        let world_state = world_state(point![0.3, 0.0], Vec::new());
+        let parameters = DribblePlanningParameters {
+            enable: false,
+            ..parameters()
+        };
+
+        assert!(plan(
+            &world_state,
+            &field_dimensions(),
+            &in_walk_kicks(),
+            &parameters
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn equally_good_kick_decision_is_kept() {
+        let mut world_state = world_state(point![0.3, 0.0], Vec::new());
+        let planned_step = first_step(&world_state);
+        world_state.kick_decisions = Some(vec![planned_step.kick_decision]);
+
+        let plan = plan(
+            &world_state,
+            &field_dimensions(),
+            &in_walk_kicks(),
+            &parameters(),
+        )
+        .unwrap();
+
+        assert!(plan.follows_kick_selector);
+    }
+
+    #[test]
+    fn worse_kick_decision_is_replaced() {
+        let mut world_state = world_state(point![0.3, 0.0], Vec::new());
+        let backwards_kick_pose = compute_kick_pose(
+            point![0.3, 0.0],
+            point![-1.0, 0.0],
+            &in_walk_kicks().forward,
+            Side::Left,
+        );
+        world_state.kick_decisions = Some(vec![KickDecision {
+            variant: KickVariant::Forward,
+            kicking_side: Side::Left,
+            kick_pose: backwards_kick_pose,
+            strength: 1.0,
+        }]);
+
+        let plan = plan(
+            &world_state,
+            &field_dimensions(),
+            &in_walk_kicks(),
+            &parameters(),
+        )
+        .unwrap();
+
+        assert!(!plan.follows_kick_selector);
+        assert!(plan.steps[0].ball_end.x() > 0.3);
+    }
+}
@@ -10,9 +10,11 @@ use framework::MainOutput;
 use spl_network_messages::HulkMessage;
 use types::{
     ball_position::BallPosition,
+    ball_trajectory::BallTrajectory,
     cycle_time::CycleTime,
+    detected_robots::DetectedRobot,
     fall_state::FallState,
-    filtered_whistle::FilteredWhistle,
+    filtered_whistle::{FilteredWhistle, HeardWhistle},
     game_controller_state::GameControllerState,
     joints::head::HeadJoints,
     obstacles::Obstacle,
@@ -44,12 +46,16 @@ pub struct CycleContext {
 #[derive(Default)]
 pub struct MainOutputs {
     pub ball_position: MainOutput<Option<BallPosition<Ground>>>,
+    pub ball_trajectory: MainOutput<Option<BallTrajectory<Ground>>>,
     pub cycle_time: MainOutput<CycleTime>,
+    pub detected_robots: MainOutput<Option<Vec<DetectedRobot>>>,
     pub fall_state: MainOutput<FallState>,
     pub filtered_whistle: MainOutput<FilteredWhistle>,
     pub game_controller_state: MainOutput<Option<GameControllerState>>,
     pub has_ground_contact: MainOutput<bool>,
+    pub heard_whistle: MainOutput<Option<HeardWhistle>>,
     pub hulk_messages: MainOutput<Vec<HulkMessage>>,
+    pub localization_confidence: MainOutput<Option<f32>>,
     pub obstacles: MainOutput<Vec<Obstacle>>,
     pub penalty_shot_direction: MainOutput<Option<PenaltyShotDirection>>,
     pub primary_state: MainOutput<PrimaryState>,
@@ -5,7 +5,9 @@ use context_attribute::context;
 use framework::{MainOutput, PerceptionInput};
 use serde::{Deserialize, Serialize};
 use types::{
-    cycle_time::CycleTime, game_controller_state::GameControllerState, messages::IncomingMessage,
+    cycle_time::CycleTime,
+    game_controller_state::{GameControllerState, JerseyColors},
+    messages::IncomingMessage,
 };
 
 #[derive(Deserialize, Serialize)]
@@ -69,6 +71,18 @@ impl GameControllerFilter {
                 sub_state: game_controller_state_message.sub_state,
                 hulks_team_is_home_after_coin_toss: game_controller_state_message
                     .hulks_team_is_home_after_coin_toss,
+                hulks_jersey_colors: JerseyColors {
+                    field_player: game_controller_state_message.hulks_team.field_player_color,
+                    goal_keeper: game_controller_state_message.hulks_team.goal_keeper_color,
+                },
+                opponent_jersey_colors: JerseyColors {
+                    field_player: game_controller_state_message
+                        .opponent_team
+                        .field_player_color,
+                    goal_keeper: game_controller_state_message
+                        .opponent_team
+                        .goal_keeper_color,
+                },
             });
         }
         Ok(MainOutputs {
@@ -0,0 +1,382 @@
+use std::{
+    fs::{create_dir_all, File},
+    io::{BufWriter, Write},
+    mem::discriminant,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::{eyre::WrapErr, Result};
+use context_attribute::context;
+use framework::PerceptionInput;
+use serde::{Deserialize, Serialize};
+use spl_network_messages::{
+    GameControllerStateMessage, GamePhase, GameState, Half, Penalty, PlayerNumber, SubState, Team,
+};
+use types::{
+    cycle_time::CycleTime, filtered_game_controller_state::FilteredGameControllerState,
+    filtered_game_state::FilteredGameState, filtered_whistle::FilteredWhistle,
+    messages::IncomingMessage, players::Players,
+};
+
+/// Appends game controller events to `logs/game_timeline.<seconds>.jsonl`
+///
+/// Enabled by default so that `pepsi postgame` finds a timeline of every game. Lines are only
+/// written when an event happens, which amounts to a few kilobytes per game next to the other logs.
+#[derive(Deserialize, Serialize)]
+pub struct GameTimelineRecorder {
+    #[serde(skip)]
+    recording: Option<BufWriter<File>>,
+    last_message: Option<GameControllerStateMessage>,
+    last_filtered_game_state: Option<FilteredGameState>,
+}
+
+#[context]
+pub struct CreationContext {}
+
+#[context]
+pub struct CycleContext {
+    enable: Parameter<bool, "game_timeline_recorder.enable">,
+
+    cycle_time: Input<CycleTime, "cycle_time">,
+    filtered_game_controller_state:
+        Input<Option<FilteredGameControllerState>, "filtered_game_controller_state?">,
+    filtered_whistle: Input<FilteredWhistle, "filtered_whistle">,
+    network_message: PerceptionInput<IncomingMessage, "SplNetwork", "message">,
+}
+
+#[context]
+#[derive(Default)]
+pub struct MainOutputs {}
+
+impl GameTimelineRecorder {
+    pub fn new(_context: CreationContext) -> Result<Self> {
+        Ok(Self {
+            recording: None,
+            last_message: None,
+            last_filtered_game_state: None,
+        })
+    }
+
+    pub fn cycle(&mut self, context: CycleContext) -> Result<MainOutputs> {
+        if !*context.enable {
+            return Ok(MainOutputs::default());
+        }
+
+        let mut entries = Vec::new();
+        for (&time, messages) in &context.network_message.persistent {
+            for message in messages {
+                let IncomingMessage::GameController(message) = message else {
+                    continue;
+                };
+                entries.extend(
+                    game_controller_events(self.last_message.as_ref(), message)
+                        .into_iter()
+                        .map(|event| TimelineEntry::new(time, message, event)),
+                );
+                self.last_message = Some(message.clone());
+            }
+        }
+
+        if let Some(last_message) = &self.last_message {
+            let filtered_game_state = context
+                .filtered_game_controller_state
+                .map(|filtered_game_controller_state| filtered_game_controller_state.game_state);
+            if filtered_game_state != self.last_filtered_game_state {
+                if let Some(game_state) = filtered_game_state {
+                    entries.push(TimelineEntry::new(
+                        context.cycle_time.start_time,
+                        last_message,
+                        TimelineEvent::FilteredGameState { game_state },
+                    ));
+                }
+                self.last_filtered_game_state = filtered_game_state;
+            }
+            if context.filtered_whistle.started_this_cycle {
+                entries.push(TimelineEntry::new(
+                    context.cycle_time.start_time,
+                    last_message,
+                    TimelineEvent::Whistle,
+                ));
+            }
+        }
+
+        if entries.is_empty() {
+            return Ok(MainOutputs::default());
+        }
+        let recording = match &mut self.recording {
+            Some(recording) => recording,
+            None => self.recording.insert(create_recording()?),
+        };
+        for entry in entries {
+            serde_json::to_writer(&mut *recording, &entry)
+                .wrap_err("failed to serialize game timeline entry")?;
+            recording
+                .write_all(b"\n")
+                .wrap_err("failed to write game timeline entry")?;
+        }
+        recording
+            .flush()
+            .wrap_err("failed to flush game timeline")?;
+
+        Ok(MainOutputs::default())
+    }
+}
+
+/// Created on the first entry, a robot that never receives a game controller message leaves no
+/// empty timeline behind
+fn create_recording() -> Result<BufWriter<File>> {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    create_dir_all("logs").wrap_err("failed to create logs directory")?;
+    Ok(BufWriter::new(
+        File::create(format!("logs/game_timeline.{seconds}.jsonl"))
+            .wrap_err("failed to create game timeline file")?,
+    ))
+}
+
+/// One line of the game timeline, times are in seconds
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TimelineEntry {
+    pub timestamp: f64,
+    pub half: Half,
+    pub remaining_time_in_half: f32,
+    #[serde(flatten)]
+    pub event: TimelineEvent,
+}
+
+impl TimelineEntry {
+    fn new(time: SystemTime, message: &GameControllerStateMessage, event: TimelineEvent) -> Self {
+        Self {
+            timestamp: time
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64(),
+            half: message.half,
+            remaining_time_in_half: message.remaining_time_in_half.as_secs_f32(),
+            event,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "event")]
+pub enum TimelineEvent {
+    GameState {
+        game_state: GameState,
+    },
+    FilteredGameState {
+        game_state: FilteredGameState,
+    },
+    GamePhase {
+        game_phase: GamePhase,
+    },
+    /// The new half is given by the entry itself
+    Half,
+    SetPlay {
+        sub_state: Option<SubState>,
+        kicking_team: Team,
+    },
+    Penalty {
+        team: Team,
+        player_number: PlayerNumber,
+        penalty: Option<Penalty>,
+    },
+    Goal {
+        team: Team,
+        hulks_score: u8,
+        opponent_score: u8,
+    },
+    Whistle,
+}
+
+fn game_controller_events(
+    last: Option<&GameControllerStateMessage>,
+    current: &GameControllerStateMessage,
+) -> Vec<TimelineEvent> {
+    let mut events = Vec::new();
+    if last.map_or(true, |last| last.half != current.half) {
+        events.push(TimelineEvent::Half);
+    }
+    if last.map_or(true, |last| last.game_phase != current.game_phase) {
+        events.push(TimelineEvent::GamePhase {
+            game_phase: current.game_phase,
+        });
+    }
+    if last.map_or(true, |last| last.game_state != current.game_state) {
+        events.push(TimelineEvent::GameState {
+            game_state: current.game_state,
+        });
+    }
+    if last.map_or(current.sub_state.is_some(), |last| {
+        last.sub_state != current.sub_state
+    }) {
+        events.push(TimelineEvent::SetPlay {
+            sub_state: current.sub_state,
+            kicking_team: current.kicking_team,
+        });
+    }
+    if let Some(last) = last {
+        let hulks_scored = current.hulks_team.score > last.hulks_team.score;
+        let opponent_scored = current.opponent_team.score > last.opponent_team.score;
+        for (team, scored) in [
+            (Team::Hulks, hulks_scored),
+            (Team::Opponent, opponent_scored),
+        ] {
+            if scored {
+                events.push(TimelineEvent::Goal {
+                    team,
+                    hulks_score: current.hulks_team.score,
+                    opponent_score: current.opponent_team.score,
+                });
+            }
+        }
+    }
+    for (team, last_team, current_team) in [
+        (
+            Team::Hulks,
+            last.map(|last| &last.hulks_team),
+            &current.hulks_team,
+        ),
+        (
+            Team::Opponent,
+            last.map(|last| &last.opponent_team),
+            &current.opponent_team,
+        ),
+    ] {
+        let last_penalties: Option<Players<Option<Penalty>>> =
+            last_team.map(|team| team.clone().into());
+        let current_penalties: Players<Option<Penalty>> = current_team.clone().into();
+        for (player_number, penalty) in current_penalties.iter() {
+            let last_penalty = last_penalties
+                .as_ref()
+                .and_then(|penalties| penalties[player_number]);
+            if penalty.as_ref().map(discriminant) != last_penalty.as_ref().map(discriminant) {
+                events.push(TimelineEvent::Penalty {
+                    team,
+                    player_number,
+                    penalty: *penalty,
+                });
+            }
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use spl_network_messages::{CompetitionPhase, CompetitionType, Player, TeamColor, TeamState};
+
+    use super::*;
+
+    fn team_state(score: u8) -> TeamState {
+        TeamState {
+            team_number: 24,
+            field_player_color: TeamColor::Blue,
+            goal_keeper_color: TeamColor::Black,
+            goal_keeper_player_number: PlayerNumber::One,
+            score,
+            penalty_shoot_index: 0,
+            penalty_shoots: Vec::new(),
+            remaining_amount_of_messages: 1200,
+            players: vec![Player { penalty: None }; 7],
+        }
+    }
+
+    fn message() -> GameControllerStateMessage {
+        GameControllerStateMessage {
+            competition_phase: CompetitionPhase::RoundRobin,
+            competition_type: CompetitionType::Normal,
+            game_phase: GamePhase::Normal,
+            game_state: GameState::Playing,
+            sub_state: None,
+            half: Half::First,
+            remaining_time_in_half: Duration::from_secs(600),
+            secondary_time: Duration::ZERO,
+            hulks_team: team_state(0),
+            opponent_team: team_state(0),
+            kicking_team: Team::Hulks,
+            hulks_team_is_home_after_coin_toss: true,
+        }
+    }
+
+    #[test]
+    fn first_message_records_initial_game_state() {
+        let events = game_controller_events(None, &message());
+
+        assert_eq!(
+            events,
+            vec![
+                TimelineEvent::Half,
+                TimelineEvent::GamePhase {
+                    game_phase: GamePhase::Normal
+                },
+                TimelineEvent::GameState {
+                    game_state: GameState::Playing
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unchanged_message_records_nothing() {
+        let last = message();
+        let mut current = message();
+        current.remaining_time_in_half = Duration::from_secs(590);
+
+        assert!(game_controller_events(Some(&last), &current).is_empty());
+    }
+
+    #[test]
+    fn goals_set_plays_and_penalties_are_recorded() {
+        let last = message();
+        let mut current = message();
+        current.opponent_team.score = 1;
+        current.sub_state = Some(SubState::CornerKick);
+        current.kicking_team = Team::Opponent;
+        current.hulks_team.players[2].penalty = Some(Penalty::PlayerPushing {
+            remaining: Duration::from_secs(45),
+        });
+
+        let events = game_controller_events(Some(&last), &current);
+
+        assert_eq!(
+            events,
+            vec![
+                TimelineEvent::SetPlay {
+                    sub_state: Some(SubState::CornerKick),
+                    kicking_team: Team::Opponent,
+                },
+                TimelineEvent::Goal {
+                    team: Team::Opponent,
+                    hulks_score: 0,
+                    opponent_score: 1,
+                },
+                TimelineEvent::Penalty {
+                    team: Team::Hulks,
+                    player_number: PlayerNumber::Three,
+                    penalty: Some(Penalty::PlayerPushing {
+                        remaining: Duration::from_secs(45),
+                    }),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn running_penalty_countdown_is_not_a_new_penalty() {
+        let mut last = message();
+        last.hulks_team.players[0].penalty = Some(Penalty::PlayerPushing {
+            remaining: Duration::from_secs(45),
+        });
+        let mut current = last.clone();
+        current.hulks_team.players[0].penalty = Some(Penalty::PlayerPushing {
+            remaining: Duration::from_secs(44),
+        });
+
+        assert!(game_controller_events(Some(&last), &current).is_empty());
+    }
+}
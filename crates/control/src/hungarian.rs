@@ -0,0 +1,167 @@
+/// Solves the assignment problem with the Hungarian algorithm in O(rows² · columns)
+///
+/// Every row is assigned to exactly one distinct column such that the sum of the costs is minimal.
+/// There must not be more rows than columns. Ties are broken by the order of rows and columns,
+/// i.e. the result is deterministic for identical inputs.
+///
+/// Returns the assigned column for every row or `None` if any cost is not finite, the algorithm
+/// would never terminate otherwise.
+pub fn solve(costs: &[Vec<f32>]) -> Option<Vec<usize>> {
+    let rows = costs.len();
+    if rows == 0 {
+        return Some(Vec::new());
+    }
+    if !costs.iter().flatten().all(|cost| cost.is_finite()) {
+        return None;
+    }
+    let columns = costs[0].len();
+    assert!(
+        rows <= columns,
+        "cannot assign {rows} rows to {columns} columns"
+    );
+
+    // potentials and matching are 1-indexed, index 0 is a virtual row/column
+    let mut row_potentials = vec![0.0; rows + 1];
+    let mut column_potentials = vec![0.0; columns + 1];
+    let mut row_of_column = vec![0; columns + 1];
+    let mut previous_column = vec![0; columns + 1];
+
+    for row in 1..=rows {
+        row_of_column[0] = row;
+        let mut current_column = 0;
+        let mut minimal_slack = vec![f32::INFINITY; columns + 1];
+        let mut used = vec![false; columns + 1];
+        loop {
+            used[current_column] = true;
+            let current_row = row_of_column[current_column];
+            let mut delta = f32::INFINITY;
+            let mut next_column = 0;
+            for column in 1..=columns {
+                if used[column] {
+                    continue;
+                }
+                let slack = costs[current_row - 1][column - 1]
+                    - row_potentials[current_row]
+                    - column_potentials[column];
+                if slack < minimal_slack[column] {
+                    minimal_slack[column] = slack;
+                    previous_column[column] = current_column;
+                }
+                if minimal_slack[column] < delta {
+                    delta = minimal_slack[column];
+                    next_column = column;
+                }
+            }
+            for column in 0..=columns {
+                if used[column] {
+                    row_potentials[row_of_column[column]] += delta;
+                    column_potentials[column] -= delta;
+                } else {
+                    minimal_slack[column] -= delta;
+                }
+            }
+            current_column = next_column;
+            if row_of_column[current_column] == 0 {
+                break;
+            }
+        }
+        // augment along the alternating path
+        while current_column != 0 {
+            let column = previous_column[current_column];
+            row_of_column[current_column] = row_of_column[column];
+            current_column = column;
+        }
+    }
+
+    let mut column_of_row = vec![0; rows];
+    for column in 1..=columns {
+        if row_of_column[column] != 0 {
+            column_of_row[row_of_column[column] - 1] = column - 1;
+        }
+    }
+    Some(column_of_row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_cost(costs: &[Vec<f32>], assignment: &[usize]) -> f32 {
+        assignment
+            .iter()
+            .enumerate()
+            .map(|(row, &column)| costs[row][column])
+            .sum()
+    }
+
+    fn permute(columns: &mut Vec<usize>, start: usize, permutations: &mut Vec<Vec<usize>>) {
+        if start == columns.len() {
+            permutations.push(columns.clone());
+            return;
+        }
+        for index in start..columns.len() {
+            columns.swap(start, index);
+            permute(columns, start + 1, permutations);
+            columns.swap(start, index);
+        }
+    }
+
+    #[test]
+    fn square_problem_is_solved_optimally() {
+        let costs = vec![
+            vec![4.0, 1.0, 3.0],
+            vec![2.0, 0.0, 5.0],
+            vec![3.0, 2.0, 2.0],
+        ];
+
+        let assignment = solve(&costs).unwrap();
+
+        assert_eq!(assignment, vec![1, 0, 2]);
+        assert_eq!(total_cost(&costs, &assignment), 5.0);
+    }
+
+    #[test]
+    fn surplus_columns_stay_unassigned() {
+        let costs = vec![vec![7.0, 3.0, 1.0, 9.0], vec![1.0, 4.0, 2.0, 8.0]];
+
+        let assignment = solve(&costs).unwrap();
+
+        assert_eq!(assignment, vec![2, 0]);
+    }
+
+    #[test]
+    fn matches_exhaustive_search() {
+        let costs = vec![
+            vec![9.0, 2.0, 7.0, 8.0, 6.5],
+            vec![6.0, 4.0, 3.0, 7.0, 1.5],
+            vec![5.0, 8.0, 1.0, 8.0, 4.0],
+            vec![7.0, 6.0, 9.0, 4.0, 2.5],
+            vec![3.5, 6.0, 2.0, 4.0, 9.0],
+        ];
+        let mut permutations = Vec::new();
+        permute(
+            &mut (0..costs.len()).collect::<Vec<_>>(),
+            0,
+            &mut permutations,
+        );
+        let optimal_cost = permutations
+            .iter()
+            .map(|permutation| total_cost(&costs, permutation))
+            .fold(f32::INFINITY, f32::min);
+
+        let assignment = solve(&costs).unwrap();
+
+        assert_eq!(total_cost(&costs, &assignment), optimal_cost);
+    }
+
+    #[test]
+    fn empty_problem_has_empty_assignment() {
+        assert_eq!(solve(&[]), Some(Vec::new()));
+    }
+
+    #[test]
+    fn non_finite_costs_are_rejected() {
+        assert_eq!(solve(&[vec![1.0, f32::NAN], vec![2.0, 3.0]]), None);
+        assert_eq!(solve(&[vec![1.0, f32::INFINITY], vec![2.0, 3.0]]), None);
+    }
+}
@@ -22,6 +22,7 @@ use types::{
     motion_command::KickVariant,
     obstacles::Obstacle,
     parameters::{FindKickTargetsParameters, InWalkKickInfoParameters, InWalkKicksParameters},
+    pass::PassTarget,
     support_foot::Side,
     world_state::BallState,
 };
@@ -37,6 +38,8 @@ pub struct CycleContext {
     ground_to_field: RequiredInput<Option<Isometry2<Ground, Field>>, "ground_to_field?">,
     ball_state: RequiredInput<Option<BallState>, "ball_state?">,
     obstacles: Input<Vec<Obstacle>, "obstacles">,
+    localization_confidence: Input<Option<f32>, "localization_confidence?">,
+    pass_target: Input<Option<PassTarget>, "pass_target?">,
 
     field_dimensions: Parameter<FieldDimensions, "field_dimensions">,
 
@@ -52,6 +55,10 @@ pub struct CycleContext {
 
     default_kick_strength: Parameter<f32, "kick_selector.default_kick_strength">,
     corner_kick_strength: Parameter<f32, "kick_selector.corner_kick_strength">,
+    minimum_localization_confidence_for_long_kicks:
+        Parameter<f32, "kick_selector.minimum_localization_confidence_for_long_kicks">,
+    maximum_kick_distance_when_uncertain:
+        Parameter<f32, "kick_selector.maximum_kick_distance_when_uncertain">,
 
     kick_targets: AdditionalOutput<Vec<KickTarget>, "kick_targets">,
     instant_kick_targets: AdditionalOutput<Vec<Point2<Ground>>, "instant_kick_targets">,
@@ -83,6 +90,12 @@ impl KickSelector {
             kick_variants.push(KickVariant::Side)
         }
 
+        let maximum_kick_distance = maximum_kick_distance(
+            context.localization_confidence.copied(),
+            *context.minimum_localization_confidence_for_long_kicks,
+            *context.maximum_kick_distance_when_uncertain,
+        );
+
         let obstacle_circles = generate_obstacle_circles(
             context.obstacles,
             *context.ball_radius_for_kick_target_selection,
@@ -100,9 +113,16 @@ impl KickSelector {
             &mut context.instant_kick_targets,
             *context.default_kick_strength,
             *context.goal_accuracy_margin,
+            maximum_kick_distance,
         );
 
-        let kick_targets = collect_kick_targets(
+        let pass_targets = context.pass_target.iter().map(|pass_target| {
+            KickTarget::new_with_strength(
+                context.ground_to_field.inverse() * pass_target.position,
+                pass_target.strength,
+            )
+        });
+        let kick_targets: Vec<_> = collect_kick_targets(
             *context.ground_to_field,
             context.field_dimensions,
             &obstacle_circles,
@@ -110,7 +130,19 @@ impl KickSelector {
             *context.max_kick_around_obstacle_angle,
             context.find_kick_targets,
             *context.corner_kick_strength,
-        );
+        )
+        .into_iter()
+        .chain(pass_targets)
+        .map(|target| match maximum_kick_distance {
+            Some(maximum_kick_distance) => shorten_kick_target(
+                target,
+                ball_position,
+                maximum_kick_distance,
+                *context.default_kick_strength,
+            ),
+            None => target,
+        })
+        .collect();
 
         context
             .kick_targets
@@ -159,6 +191,17 @@ impl KickSelector {
     }
 }
 
+/// Kicks are only limited if the localization reports a confidence that is too low
+fn maximum_kick_distance(
+    localization_confidence: Option<f32>,
+    minimum_localization_confidence_for_long_kicks: f32,
+    maximum_kick_distance_when_uncertain: f32,
+) -> Option<f32> {
+    let is_localization_uncertain = localization_confidence
+        .is_some_and(|confidence| confidence < minimum_localization_confidence_for_long_kicks);
+    is_localization_uncertain.then_some(maximum_kick_distance_when_uncertain)
+}
+
 fn generate_obstacle_circles(
     obstacles: &[Obstacle],
     ball_radius_for_kick_target_selection: f32,
@@ -189,12 +232,18 @@ fn generate_decisions_for_instant_kicks(
     instant_kick_targets: &mut AdditionalOutput<Vec<Point2<Ground>>>,
     default_kick_strength: f32,
     goal_accuracy_margin: f32,
+    maximum_kick_distance: Option<f32>,
 ) -> Vec<KickDecision> {
     let field_to_ground = ground_to_field.inverse();
     instant_kick_targets.fill_if_subscribed(Default::default);
     iproduct!(sides, kick_variants)
         .filter_map(|(&kicking_side, &variant)| {
             let kick_info = &in_walk_kicks[variant];
+            if maximum_kick_distance.is_some_and(|maximum_kick_distance| {
+                kick_info.shot_distance > maximum_kick_distance
+            }) {
+                return None;
+            }
             let shot_angle = match kicking_side {
                 Side::Left => UnitComplex::new(kick_info.orientation),
                 Side::Right => UnitComplex::new(kick_info.orientation).inverse(),
@@ -384,6 +433,26 @@ fn generate_goal_line_kick_targets(
     ]
 }
 
+/// Long shots only hit their target if the pose is accurate, when uncertain the ball is kicked
+/// towards the target but not farther than the maximum distance
+fn shorten_kick_target(
+    target: KickTarget,
+    ball_position: Point2<Ground>,
+    maximum_kick_distance: f32,
+    default_kick_strength: f32,
+) -> KickTarget {
+    let ball_to_target = target.position - ball_position;
+    let distance_to_target = ball_to_target.norm();
+    if distance_to_target <= maximum_kick_distance {
+        return target;
+    }
+    let shortening_factor = maximum_kick_distance / distance_to_target;
+    KickTarget::new_with_strength(
+        ball_position + ball_to_target * shortening_factor,
+        target.strength.unwrap_or(default_kick_strength) * shortening_factor,
+    )
+}
+
 fn kick_decisions_from_targets(
     targets_to_kick_to: &[KickTarget],
     in_walk_kicks: &InWalkKicksParameters,
@@ -436,7 +505,7 @@ fn mirror_kick_pose<Frame>(kick_pose: Pose<Frame>) -> Pose<Frame> {
     )
 }
 
-fn compute_kick_pose(
+pub fn compute_kick_pose(
     ball_position: Point2<Ground>,
     target_to_kick_to: Point2<Ground>,
     kick_info: &InWalkKickInfoParameters,
@@ -478,3 +547,88 @@ fn is_ball_in_opponents_corners(
         distance(ball_in_field, right_opponent_corner) < parameters.distance_from_corner;
     ball_near_left_opponent_corner || ball_near_right_opponent_corner
 }
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    fn in_walk_kicks() -> InWalkKicksParameters {
+        let kick = |orientation, shot_distance| InWalkKickInfoParameters {
+            position: nalgebra::point![-0.2, 0.05],
+            orientation,
+            shot_distance,
+            enabled: true,
+            ..Default::default()
+        };
+        InWalkKicksParameters {
+            forward: kick(0.0, 4.0),
+            turn: kick(-1.0, 3.5),
+            side: kick(-1.57, 0.5),
+        }
+    }
+
+    #[test]
+    fn only_low_localization_confidence_limits_kick_distance() {
+        assert_eq!(maximum_kick_distance(Some(0.3), 0.5, 2.0), Some(2.0));
+        assert_eq!(maximum_kick_distance(Some(0.8), 0.5, 2.0), None);
+        assert_eq!(maximum_kick_distance(None, 0.5, 2.0), None);
+    }
+
+    #[test]
+    fn distant_kick_target_is_shortened_towards_the_target() {
+        let ball_position = point![1.0, 0.0];
+        let target = KickTarget::new(point![5.0, 3.0]);
+
+        let shortened = shorten_kick_target(target, ball_position, 2.0, 1.0);
+
+        assert_relative_eq!(shortened.position, point![2.6, 1.2], epsilon = 1e-5);
+        assert_relative_eq!(shortened.strength.unwrap(), 0.4, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn close_kick_target_is_kept() {
+        let target = KickTarget::new(point![2.0, 0.0]);
+
+        let shortened = shorten_kick_target(target, point![1.0, 0.0], 2.0, 1.0);
+
+        assert_eq!(shortened.position, target.position);
+        assert_eq!(shortened.strength, None);
+    }
+
+    #[test]
+    fn low_localization_confidence_suppresses_long_instant_kicks() {
+        let in_walk_kicks = in_walk_kicks();
+        let kick_variants = [KickVariant::Forward, KickVariant::Turn, KickVariant::Side];
+        let ground_to_field = Isometry2::identity();
+        let mut instant_kick_targets = None;
+        let instant_kicks = |maximum_kick_distance, instant_kick_targets: &mut _| {
+            generate_decisions_for_instant_kicks(
+                &[Side::Left, Side::Right],
+                &kick_variants,
+                &in_walk_kicks,
+                point![0.5, 0.0],
+                &[],
+                &FieldDimensions::SPL,
+                ground_to_field,
+                1.0,
+                &mut AdditionalOutput::new(false, instant_kick_targets),
+                1.0,
+                0.25,
+                maximum_kick_distance,
+            )
+        };
+
+        let confident_decisions = instant_kicks(None, &mut instant_kick_targets);
+        let uncertain_decisions = instant_kicks(
+            maximum_kick_distance(Some(0.2), 0.5, 2.0),
+            &mut instant_kick_targets,
+        );
+
+        assert!(confident_decisions
+            .iter()
+            .any(|decision| decision.variant == KickVariant::Forward));
+        assert!(uncertain_decisions.is_empty());
+    }
+}
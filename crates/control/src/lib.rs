@@ -7,22 +7,27 @@ pub mod button_filter;
 pub mod camera_matrix_calculator;
 pub mod center_of_mass_provider;
 pub mod dribble_path_planner;
+pub mod dribble_planner;
 pub mod fake_data;
 pub mod fall_state_estimation;
 pub mod foot_bumper_filter;
 pub mod game_controller_filter;
 pub mod game_controller_state_filter;
+pub mod game_timeline_recorder;
 pub mod ground_contact_detector;
 pub mod ground_provider;
+pub mod hungarian;
 pub mod kick_selector;
 pub mod kinematics_provider;
 pub mod led_status;
 pub mod localization;
 pub mod localization_recorder;
+pub mod monte_carlo_localization;
 pub mod motion;
 pub mod obstacle_filter;
 pub mod odometry;
 pub mod orientation_filter;
+pub mod pass_planner;
 pub mod path_planner;
 pub mod penalty_shot_direction_estimation;
 pub mod primary_state_filter;
@@ -32,6 +37,7 @@ pub mod sensor_data_receiver;
 pub mod sole_pressure_filter;
 pub mod sonar_filter;
 pub mod support_foot_estimation;
+pub mod symmetry_disambiguation;
 pub mod time_to_reach_kick_position;
 pub mod visual_referee_filter;
 pub mod whistle_filter;
@@ -1,42 +1,80 @@
 use std::{
     f32::consts::{FRAC_PI_2, PI},
     mem::take,
+    time::SystemTime,
 };
 
 use approx::assert_relative_eq;
 use color_eyre::{eyre::WrapErr, Result};
 use nalgebra::{matrix, Matrix, Matrix2, Matrix3, Rotation2, Translation2, Vector2, Vector3};
 use ordered_float::NotNan;
+use rand::{rngs::StdRng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
 use context_attribute::context;
 use coordinate_systems::{Field, Ground};
 use filtering::pose_filter::PoseFilter;
-use framework::{AdditionalOutput, HistoricInput, MainOutput, PerceptionInput};
-use linear_algebra::{distance, point, vector, IntoTransform, Isometry2, Pose};
+use framework::{
+    deserialize_not_implemented, AdditionalOutput, HistoricInput, MainOutput, PerceptionInput,
+};
+use linear_algebra::{distance, point, vector, IntoTransform, Isometry2, Point2, Pose};
 use spl_network_messages::{GamePhase, Penalty, PlayerNumber, Team};
 use types::{
+    ball_position::BallPosition,
+    cycle_time::CycleTime,
     field_dimensions::FieldDimensions,
-    field_marks::{field_marks_from_field_dimensions, CorrespondencePoints, Direction, FieldMark},
+    field_marks::{
+        field_marks_from_field_dimensions, CorrespondencePoints, Direction, FieldMark,
+        IntersectionKind,
+    },
     filtered_game_controller_state::FilteredGameControllerState,
+    filtered_game_state::FilteredGameState,
     initial_pose::InitialPose,
+    landmarks::{landmarks_from_field_dimensions, Landmark, LandmarkKind},
     line::{Line, Line2},
     line_data::LineData,
-    localization::{ScoredPose, Update},
+    localization::{LocalizationBackend, Particle, ScoredPose, SymmetryEvent, Update},
+    messages::IncomingMessage,
     multivariate_normal_distribution::MultivariateNormalDistribution,
+    parameters::{
+        LocalizationConfidenceParameters, ParticleFilterParameters,
+        SymmetryDisambiguationParameters,
+    },
     players::Players,
     primary_state::PrimaryState,
     support_foot::Side,
 };
 
+use crate::{
+    monte_carlo_localization::{state_to_ground_to_field, Measurements, MonteCarloLocalization},
+    symmetry_disambiguation::{disambiguate, SymmetryEvidence, TeammateObservation},
+};
+
 #[derive(Deserialize, Serialize)]
 pub struct Localization {
     field_marks: Vec<FieldMark>,
+    filtered_fit_error: f32,
+    landmarks: Vec<Landmark>,
     last_primary_state: PrimaryState,
     hypotheses: Vec<ScoredPose>,
     hypotheses_when_entered_playing: Vec<ScoredPose>,
     is_penalized_with_motion_in_set: bool,
     was_picked_up_while_penalized_with_motion_in_set: bool,
+    monte_carlo_localization: MonteCarloLocalization,
+    #[serde(skip, default = "deserialize_not_implemented")]
+    random_number_generator: StdRng,
+    teammates: Players<Option<TeammateMessage>>,
+}
+
+/// Fixed seed of the particle filter sampling to make replays of the same recording reproducible
+const RANDOM_NUMBER_GENERATOR_SEED: u64 = 0;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct TeammateMessage {
+    position: Point2<Field>,
+    received_at: SystemTime,
+    ball: Option<Point2<Field>>,
+    ball_observed_at: SystemTime,
 }
 
 #[context]
@@ -48,20 +86,28 @@ pub struct CreationContext {
 pub struct CycleContext {
     correspondence_lines: AdditionalOutput<Vec<Line2<Field>>, "localization.correspondence_lines">,
     fit_errors: AdditionalOutput<Vec<Vec<Vec<Vec<f32>>>>, "localization.fit_errors">,
+    measured_landmarks_in_field:
+        AdditionalOutput<Vec<Point2<Field>>, "localization.measured_landmarks_in_field">,
     measured_lines_in_field:
         AdditionalOutput<Vec<Line2<Field>>, "localization.measured_lines_in_field">,
+    particles: AdditionalOutput<Vec<Particle>, "localization.particles">,
     pose_hypotheses: AdditionalOutput<Vec<ScoredPose>, "localization.pose_hypotheses">,
+    symmetry_events: AdditionalOutput<Vec<SymmetryEvent>, "localization.symmetry_events">,
     updates: AdditionalOutput<Vec<Vec<Update>>, "localization.updates">,
 
     current_odometry_to_last_odometry:
         HistoricInput<Option<nalgebra::Isometry2<f32>>, "current_odometry_to_last_odometry?">,
 
+    ball_position: Input<Option<BallPosition<Ground>>, "ball_position?">,
+    cycle_time: Input<CycleTime, "cycle_time">,
     filtered_game_controller_state:
         Input<Option<FilteredGameControllerState>, "filtered_game_controller_state?">,
     has_ground_contact: Input<bool, "has_ground_contact">,
     primary_state: Input<PrimaryState, "primary_state">,
 
+    backend: Parameter<LocalizationBackend, "localization.backend">,
     circle_measurement_noise: Parameter<Vector2<f32>, "localization.circle_measurement_noise">,
+    confidence: Parameter<LocalizationConfidenceParameters, "localization.confidence">,
     field_dimensions: Parameter<FieldDimensions, "field_dimensions">,
     good_matching_threshold: Parameter<f32, "localization.good_matching_threshold">,
     gradient_convergence_threshold: Parameter<f32, "localization.gradient_convergence_threshold">,
@@ -74,24 +120,37 @@ pub struct CycleContext {
         Parameter<Matrix3<f32>, "localization.initial_hypothesis_covariance">,
     initial_hypothesis_score: Parameter<f32, "localization.initial_hypothesis_score">,
     initial_poses: Parameter<Players<InitialPose>, "localization.initial_poses">,
+    landmark_measurement_noise: Parameter<Vector2<f32>, "localization.landmark_measurement_noise">,
     line_length_acceptance_factor: Parameter<f32, "localization.line_length_acceptance_factor">,
     line_measurement_noise: Parameter<Vector2<f32>, "localization.line_measurement_noise">,
     maximum_amount_of_gradient_descent_iterations:
         Parameter<usize, "localization.maximum_amount_of_gradient_descent_iterations">,
     maximum_amount_of_outer_iterations:
         Parameter<usize, "localization.maximum_amount_of_outer_iterations">,
+    maximum_landmark_association_distance:
+        Parameter<f32, "localization.maximum_landmark_association_distance">,
     minimum_fit_error: Parameter<f32, "localization.minimum_fit_error">,
     odometry_noise: Parameter<Vector3<f32>, "localization.odometry_noise">,
+    particle_filter: Parameter<ParticleFilterParameters, "localization.particle_filter">,
     player_number: Parameter<PlayerNumber, "player_number">,
     score_per_good_match: Parameter<f32, "localization.score_per_good_match">,
+    symmetry_disambiguation:
+        Parameter<SymmetryDisambiguationParameters, "localization.symmetry_disambiguation">,
+    use_landmark_measurements: Parameter<bool, "localization.use_landmark_measurements">,
     use_line_measurements: Parameter<bool, "localization.use_line_measurements">,
     injected_ground_to_field_of_home_after_coin_toss_before_second_half: Parameter<
         Option<Isometry2<Ground, Field>>,
         "injected_ground_to_field_of_home_after_coin_toss_before_second_half?",
     >,
 
+    goal_posts_bottom: PerceptionInput<Option<Vec<Point2<Ground>>>, "VisionBottom", "goal_posts?">,
+    goal_posts_top: PerceptionInput<Option<Vec<Point2<Ground>>>, "VisionTop", "goal_posts?">,
     line_data_bottom: PerceptionInput<Option<LineData>, "VisionBottom", "line_data?">,
     line_data_top: PerceptionInput<Option<LineData>, "VisionTop", "line_data?">,
+    network_message: PerceptionInput<IncomingMessage, "SplNetwork", "message">,
+    penalty_marks_bottom:
+        PerceptionInput<Option<Vec<Point2<Ground>>>, "VisionBottom", "penalty_marks?">,
+    penalty_marks_top: PerceptionInput<Option<Vec<Point2<Ground>>>, "VisionTop", "penalty_marks?">,
 
     ground_to_field: CyclerState<Isometry2<Ground, Field>, "ground_to_field">,
 }
@@ -102,6 +161,7 @@ pub struct MainOutputs {
     pub ground_to_field: MainOutput<Option<Isometry2<Ground, Field>>>,
     pub ground_to_field_of_home_after_coin_toss_before_second_half:
         MainOutput<Option<Isometry2<Ground, Field>>>,
+    pub localization_confidence: MainOutput<Option<f32>>,
 }
 
 impl Localization {
@@ -113,21 +173,46 @@ impl Localization {
                     context.field_dimensions,
                 ))
                 .collect(),
+            filtered_fit_error: 0.0,
+            landmarks: landmarks_from_field_dimensions(context.field_dimensions),
             last_primary_state: PrimaryState::Unstiff,
             hypotheses: vec![],
             hypotheses_when_entered_playing: vec![],
             is_penalized_with_motion_in_set: false,
             was_picked_up_while_penalized_with_motion_in_set: false,
+            monte_carlo_localization: Default::default(),
+            random_number_generator: StdRng::seed_from_u64(RANDOM_NUMBER_GENERATOR_SEED),
+            teammates: Default::default(),
         })
     }
 
+    /// Continues from a single hypothesis at the given pose as if the robot had already been in
+    /// the given primary state, used to replay recordings which start during a game
+    pub fn reset_to(
+        &mut self,
+        primary_state: PrimaryState,
+        ground_to_field: Isometry2<Ground, Field>,
+        covariance: Matrix3<f32>,
+        score: f32,
+    ) {
+        self.hypotheses = vec![ScoredPose::from_isometry(
+            ground_to_field.as_pose(),
+            covariance,
+            score,
+        )];
+        self.hypotheses_when_entered_playing = self.hypotheses.clone();
+        self.last_primary_state = primary_state;
+        self.filtered_fit_error = 0.0;
+        self.monte_carlo_localization = Default::default();
+    }
+
     fn reset_state(
         &mut self,
         primary_state: PrimaryState,
         game_phase: Option<GamePhase>,
         context: &CycleContext,
         penalty: &Option<Penalty>,
-    ) {
+    ) -> bool {
         match (self.last_primary_state, primary_state, game_phase) {
             (PrimaryState::Initial, PrimaryState::Ready, _) => {
                 let initial_pose = generate_initial_pose(
@@ -140,6 +225,7 @@ impl Localization {
                     *context.initial_hypothesis_score,
                 )];
                 self.hypotheses_when_entered_playing = self.hypotheses.clone();
+                true
             }
             (
                 PrimaryState::Set,
@@ -159,6 +245,7 @@ impl Localization {
                     *context.initial_hypothesis_score,
                 )];
                 self.hypotheses_when_entered_playing = self.hypotheses.clone();
+                true
             }
             (
                 PrimaryState::Set,
@@ -175,9 +262,11 @@ impl Localization {
                     *context.initial_hypothesis_score,
                 )];
                 self.hypotheses_when_entered_playing = self.hypotheses.clone();
+                true
             }
             (PrimaryState::Set, PrimaryState::Playing, _) => {
                 self.hypotheses_when_entered_playing = self.hypotheses.clone();
+                false
             }
             (PrimaryState::Playing, PrimaryState::Penalized, _) => {
                 match penalty {
@@ -187,10 +276,12 @@ impl Localization {
                     Some(_) => {}
                     None => {}
                 };
+                false
             }
             (PrimaryState::Penalized, _, _) if primary_state != PrimaryState::Penalized => {
                 if self.is_penalized_with_motion_in_set {
-                    if self.was_picked_up_while_penalized_with_motion_in_set {
+                    let was_picked_up = self.was_picked_up_while_penalized_with_motion_in_set;
+                    if was_picked_up {
                         self.hypotheses = take(&mut self.hypotheses_when_entered_playing);
 
                         let penalized_poses = generate_penalized_poses(context.field_dimensions);
@@ -207,6 +298,7 @@ impl Localization {
                     }
                     self.is_penalized_with_motion_in_set = false;
                     self.was_picked_up_while_penalized_with_motion_in_set = false;
+                    was_picked_up
                 } else {
                     let penalized_poses = generate_penalized_poses(context.field_dimensions);
                     self.hypotheses = penalized_poses
@@ -220,6 +312,7 @@ impl Localization {
                         })
                         .collect();
                     self.hypotheses_when_entered_playing = self.hypotheses.clone();
+                    true
                 }
             }
             (PrimaryState::Unstiff, _, _) => {
@@ -235,8 +328,9 @@ impl Localization {
                     })
                     .collect();
                 self.hypotheses_when_entered_playing = self.hypotheses.clone();
+                true
             }
-            _ => {}
+            _ => false,
         }
     }
 
@@ -244,6 +338,9 @@ impl Localization {
         let mut fit_errors_per_measurement = vec![];
 
         context.measured_lines_in_field.fill_if_subscribed(Vec::new);
+        context
+            .measured_landmarks_in_field
+            .fill_if_subscribed(Vec::new);
         context.correspondence_lines.fill_if_subscribed(Vec::new);
         context
             .updates
@@ -263,8 +360,19 @@ impl Localization {
             let current_odometry_to_last_odometry = context
                 .current_odometry_to_last_odometry
                 .get(line_data_top_timestamp);
+            let Measurements {
+                intersections: measured_intersections,
+                landmarks: measured_landmarks,
+                ..
+            } = measurements(
+                context,
+                line_data_top_timestamp,
+                line_data_top,
+                line_data_bottom,
+            );
 
             let mut fit_errors_per_hypothesis = vec![];
+            let mut latest_fit_errors = vec![None; self.hypotheses.len()];
             for (hypothesis_index, scored_state) in self.hypotheses.iter_mut().enumerate() {
                 if let Some(current_odometry_to_last_odometry) = current_odometry_to_last_odometry {
                     predict(
@@ -275,6 +383,82 @@ impl Localization {
                     .wrap_err("failed to predict pose filter")?;
                     scored_state.score *= *context.hypothesis_prediction_score_reduction_factor;
                 }
+                if *context.use_landmark_measurements {
+                    let ground_to_field: Isometry2<Ground, Field> =
+                        scored_state.state.as_isometry().framed_transform();
+                    let associated_intersections =
+                        measured_intersections
+                            .iter()
+                            .map(|&(measured_intersection, kind)| {
+                                let measured_intersection_in_field =
+                                    ground_to_field * measured_intersection;
+                                (
+                                    measured_intersection,
+                                    measured_intersection_in_field,
+                                    associate_intersection(
+                                        &self.field_marks,
+                                        measured_intersection_in_field,
+                                        kind,
+                                    ),
+                                )
+                            });
+                    let associated_landmarks =
+                        measured_landmarks.iter().map(|&(measured_landmark, kind)| {
+                            let measured_landmark_in_field = ground_to_field * measured_landmark;
+                            (
+                                measured_landmark,
+                                measured_landmark_in_field,
+                                associate_landmark(
+                                    &self.landmarks,
+                                    measured_landmark_in_field,
+                                    kind,
+                                )
+                                .map(
+                                    |(reference_landmark, association_distance)| {
+                                        (reference_landmark.position, association_distance)
+                                    },
+                                ),
+                            )
+                        });
+                    for (measured_landmark, measured_landmark_in_field, association) in
+                        associated_intersections.chain(associated_landmarks)
+                    {
+                        context.measured_landmarks_in_field.mutate_if_subscribed(
+                            |measured_landmarks_in_field| {
+                                if let Some(measured_landmarks_in_field) =
+                                    measured_landmarks_in_field
+                                {
+                                    measured_landmarks_in_field.push(measured_landmark_in_field);
+                                }
+                            },
+                        );
+                        let Some((reference_landmark, association_distance)) = association else {
+                            continue;
+                        };
+                        if association_distance > *context.maximum_landmark_association_distance {
+                            continue;
+                        }
+                        let update = get_landmark_measurement(
+                            ground_to_field,
+                            measured_landmark_in_field,
+                            reference_landmark,
+                        );
+                        // landmarks far away are projected less accurately
+                        let distance_weight = 1.0 + measured_landmark.coords().norm_squared();
+                        scored_state
+                            .state
+                            .update_with_2d_translation(
+                                update,
+                                Matrix::from_diagonal(context.landmark_measurement_noise)
+                                    * distance_weight,
+                                |state| nalgebra::vector![state.x, state.y],
+                            )
+                            .context("Failed to update pose filter")?;
+                        if association_distance < *context.good_matching_threshold {
+                            scored_state.score += *context.score_per_good_match;
+                        }
+                    }
+                }
                 if *context.use_line_measurements {
                     let ground_to_field: Isometry2<Ground, Field> =
                         scored_state.state.as_isometry().framed_transform();
@@ -339,6 +523,7 @@ impl Localization {
                     if context.fit_errors.is_subscribed() {
                         fit_errors_per_hypothesis.push(fit_errors);
                     }
+                    latest_fit_errors[hypothesis_index] = Some(fit_error);
                     let clamped_fit_error = fit_error.max(*context.minimum_fit_error);
                     let number_of_measurements_weight =
                         1.0 / field_mark_correspondences.len() as f32;
@@ -349,10 +534,12 @@ impl Localization {
                                 ground_to_field,
                                 field_mark_correspondence,
                             ),
-                            FieldMark::Circle { .. } => get_2d_translation_measurement(
-                                ground_to_field,
-                                field_mark_correspondence,
-                            ),
+                            FieldMark::Circle { .. } | FieldMark::Intersection { .. } => {
+                                get_2d_translation_measurement(
+                                    ground_to_field,
+                                    field_mark_correspondence,
+                                )
+                            }
                         };
                         let line_length = field_mark_correspondence.measured_line_in_field.length();
                         let line_length_weight = if line_length == 0.0 {
@@ -391,10 +578,13 @@ impl Localization {
                                                     }
                                                 }
                                             }
-                                            FieldMark::Circle { .. } => nalgebra::Isometry2::new(
-                                                update,
-                                                ground_to_field.orientation().angle(),
-                                            ),
+                                            FieldMark::Circle { .. }
+                                            | FieldMark::Intersection { .. } => {
+                                                nalgebra::Isometry2::new(
+                                                    update,
+                                                    ground_to_field.orientation().angle(),
+                                                )
+                                            }
                                         }
                                         .framed_transform();
                                     Update {
@@ -429,15 +619,17 @@ impl Localization {
                                     },
                                 )
                                 .context("Failed to update pose filter")?,
-                            FieldMark::Circle { .. } => scored_state
-                                .state
-                                .update_with_2d_translation(
-                                    update,
-                                    Matrix::from_diagonal(context.circle_measurement_noise)
-                                        * uncertainty_weight,
-                                    |state| nalgebra::vector![state.x, state.y],
-                                )
-                                .context("Failed to update pose filter")?,
+                            FieldMark::Circle { .. } | FieldMark::Intersection { .. } => {
+                                scored_state
+                                    .state
+                                    .update_with_2d_translation(
+                                        update,
+                                        Matrix::from_diagonal(context.circle_measurement_noise)
+                                            * uncertainty_weight,
+                                        |state| nalgebra::vector![state.x, state.y],
+                                    )
+                                    .context("Failed to update pose filter")?
+                            }
                         }
                         if field_mark_correspondence.fit_error_sum()
                             < *context.good_matching_threshold
@@ -452,6 +644,36 @@ impl Localization {
             if context.fit_errors.is_subscribed() {
                 fit_errors_per_measurement.push(fit_errors_per_hypothesis);
             }
+            let fit_error_of_best_hypothesis = self
+                .hypotheses
+                .iter()
+                .zip(latest_fit_errors)
+                .max_by(|(left, _), (right, _)| left.score.total_cmp(&right.score))
+                .and_then(|(_, fit_error)| fit_error);
+            if let Some(fit_error) = fit_error_of_best_hypothesis {
+                self.filtered_fit_error += context.confidence.fit_error_smoothing_factor
+                    * (fit_error - self.filtered_fit_error);
+            }
+        }
+
+        if context.symmetry_disambiguation.enable {
+            let evidence_of_teammates = self.evidence_of_teammates(context);
+            let evidence = SymmetryEvidence {
+                teammates: &evidence_of_teammates,
+                ball: context.ball_position.map(|ball| ball.position),
+                is_in_own_half: is_in_own_half(
+                    context.filtered_game_controller_state,
+                    *context.player_number,
+                ),
+            };
+            let symmetry_events = disambiguate(
+                &mut self.hypotheses,
+                &evidence,
+                context.symmetry_disambiguation,
+            );
+            context
+                .symmetry_events
+                .fill_if_subscribed(|| symmetry_events);
         }
 
         let best_hypothesis = self
@@ -475,6 +697,124 @@ impl Localization {
         Ok(())
     }
 
+    fn update_teammates(&mut self, context: &CycleContext) {
+        for (&receive_time, messages) in &context.network_message.persistent {
+            for message in messages {
+                let IncomingMessage::Spl(message) = message else {
+                    continue;
+                };
+                if message.player_number == *context.player_number || message.fallen {
+                    continue;
+                }
+                let is_confident = message.localization_confidence.map_or(true, |confidence| {
+                    confidence
+                        >= context
+                            .symmetry_disambiguation
+                            .minimum_teammate_localization_confidence
+                });
+                self.teammates[message.player_number] = is_confident.then(|| TeammateMessage {
+                    position: message.pose.position(),
+                    received_at: receive_time,
+                    ball: message.ball_position.map(|ball| ball.position),
+                    ball_observed_at: message
+                        .ball_position
+                        .and_then(|ball| receive_time.checked_sub(ball.age))
+                        .unwrap_or(receive_time),
+                });
+            }
+        }
+    }
+
+    fn evidence_of_teammates(&self, context: &CycleContext) -> Vec<TeammateObservation> {
+        let maximum_age = context.symmetry_disambiguation.maximum_teammate_message_age;
+        let is_recent = |time: SystemTime| {
+            context
+                .cycle_time
+                .start_time
+                .duration_since(time)
+                .map_or(true, |age| age <= maximum_age)
+        };
+        self.teammates
+            .iter()
+            .filter_map(|(_, teammate)| *teammate)
+            .filter(|teammate| is_recent(teammate.received_at))
+            .map(|teammate| TeammateObservation {
+                position: teammate.position,
+                ball: teammate
+                    .ball
+                    .filter(|_| is_recent(teammate.ball_observed_at)),
+            })
+            .collect()
+    }
+
+    fn update_particle_filter(&mut self, context: &mut CycleContext) {
+        let parameters = context.particle_filter;
+        let random_number_generator = &mut self.random_number_generator;
+        if self.monte_carlo_localization.particles().is_empty() {
+            self.monte_carlo_localization.reset(
+                &self.hypotheses,
+                parameters.number_of_particles,
+                random_number_generator,
+            );
+        }
+
+        let line_datas = context
+            .line_data_top
+            .persistent
+            .iter()
+            .zip(context.line_data_bottom.persistent.iter());
+        for (
+            (line_data_top_timestamp, line_data_top),
+            (line_data_bottom_timestamp, line_data_bottom),
+        ) in line_datas
+        {
+            assert_eq!(line_data_top_timestamp, line_data_bottom_timestamp);
+            if let Some(current_odometry_to_last_odometry) = context
+                .current_odometry_to_last_odometry
+                .get(line_data_top_timestamp)
+            {
+                self.monte_carlo_localization.predict(
+                    current_odometry_to_last_odometry,
+                    parameters,
+                    random_number_generator,
+                );
+            }
+            let mut measurements = measurements(
+                context,
+                line_data_top_timestamp,
+                line_data_top,
+                line_data_bottom,
+            );
+            if !*context.use_line_measurements {
+                measurements.lines.clear();
+            }
+            if !*context.use_landmark_measurements {
+                measurements.intersections.clear();
+                measurements.landmarks.clear();
+            }
+            self.monte_carlo_localization.update(
+                &measurements,
+                &self.field_marks,
+                &self.landmarks,
+                context.field_dimensions,
+                parameters,
+                random_number_generator,
+            );
+        }
+
+        if let Some(estimate) = self.monte_carlo_localization.estimate(parameters) {
+            *context.ground_to_field = state_to_ground_to_field(estimate.state.mean);
+            self.hypotheses = vec![estimate];
+        }
+
+        context
+            .particles
+            .fill_if_subscribed(|| self.monte_carlo_localization.particles().to_vec());
+        context
+            .pose_hypotheses
+            .fill_if_subscribed(|| self.hypotheses.clone());
+    }
+
     pub fn cycle(&mut self, mut context: CycleContext) -> Result<MainOutputs> {
         let primary_state = *context.primary_state;
         let penalty = context
@@ -486,8 +826,20 @@ impl Localization {
             .filtered_game_controller_state
             .map(|game_controller_state| game_controller_state.game_phase);
 
-        self.reset_state(primary_state, game_phase, &context, &penalty);
+        let hypotheses_were_reset = self.reset_state(primary_state, game_phase, &context, &penalty);
         self.last_primary_state = primary_state;
+        if hypotheses_were_reset {
+            self.filtered_fit_error = 0.0;
+        }
+        if hypotheses_were_reset && *context.backend == LocalizationBackend::ParticleFilter {
+            self.monte_carlo_localization.reset(
+                &self.hypotheses,
+                context.particle_filter.number_of_particles,
+                &mut self.random_number_generator,
+            );
+        }
+
+        self.update_teammates(&context);
 
         if self.is_penalized_with_motion_in_set && !context.has_ground_contact {
             self.was_picked_up_while_penalized_with_motion_in_set = true;
@@ -495,11 +847,24 @@ impl Localization {
 
         let ground_to_field = match primary_state {
             PrimaryState::Ready | PrimaryState::Set | PrimaryState::Playing => {
-                self.update_state(&mut context)?;
+                match context.backend {
+                    LocalizationBackend::MultipleHypotheses => self.update_state(&mut context)?,
+                    LocalizationBackend::ParticleFilter => {
+                        self.update_particle_filter(&mut context)
+                    }
+                }
                 Some(*context.ground_to_field)
             }
             _ => None,
         };
+        let localization_confidence = ground_to_field.and_then(|_| {
+            Some(localization_confidence(
+                &self.hypotheses,
+                self.get_best_hypothesis()?,
+                self.filtered_fit_error,
+                context.confidence,
+            ))
+        });
         let ground_to_field_of_home_after_coin_toss_before_second_half = context
             .injected_ground_to_field_of_home_after_coin_toss_before_second_half
             .copied()
@@ -524,6 +889,7 @@ impl Localization {
             ground_to_field: ground_to_field.into(),
             ground_to_field_of_home_after_coin_toss_before_second_half:
                 ground_to_field_of_home_after_coin_toss_before_second_half.into(),
+            localization_confidence: localization_confidence.into(),
         })
     }
 
@@ -534,6 +900,102 @@ impl Localization {
     }
 }
 
+/// Lines, line intersections, goal posts and penalty marks measured in the vision cycle of the
+/// given time
+fn measurements(
+    context: &CycleContext,
+    timestamp: &SystemTime,
+    line_data_top: &[Option<&LineData>],
+    line_data_bottom: &[Option<&LineData>],
+) -> Measurements {
+    let mut measurements = Measurements::from_line_data(
+        line_data_top
+            .iter()
+            .chain(line_data_bottom.iter())
+            .flatten()
+            .copied(),
+    );
+    measurements.landmarks.extend(
+        [
+            (&context.goal_posts_top, LandmarkKind::GoalPost),
+            (&context.goal_posts_bottom, LandmarkKind::GoalPost),
+            (&context.penalty_marks_top, LandmarkKind::PenaltyMark),
+            (&context.penalty_marks_bottom, LandmarkKind::PenaltyMark),
+        ]
+        .into_iter()
+        .filter_map(|(detections, kind)| Some((detections.persistent.get(timestamp)?, kind)))
+        .flat_map(|(detections, kind)| {
+            detections
+                .iter()
+                .flatten()
+                .flat_map(move |positions| positions.iter().map(move |&position| (position, kind)))
+        }),
+    );
+    measurements
+}
+
+/// Robots stay in their own half until the kick-off ball is free, the goalkeeper all the time
+fn is_in_own_half(
+    filtered_game_controller_state: Option<&FilteredGameControllerState>,
+    player_number: PlayerNumber,
+) -> bool {
+    let Some(game_controller_state) = filtered_game_controller_state else {
+        return false;
+    };
+    if matches!(
+        game_controller_state.game_phase,
+        GamePhase::PenaltyShootout { .. }
+    ) {
+        return false;
+    }
+    match game_controller_state.game_state {
+        FilteredGameState::Set
+        | FilteredGameState::Playing {
+            ball_is_free: false,
+            kick_off: true,
+        } => true,
+        FilteredGameState::Playing { .. } => player_number == PlayerNumber::One,
+        _ => false,
+    }
+}
+
+/// Product of the certainty of the best hypothesis, its lead over distant competing hypotheses
+/// and how well the measured lines recently fitted
+fn localization_confidence(
+    hypotheses: &[ScoredPose],
+    best_hypothesis: &ScoredPose,
+    filtered_fit_error: f32,
+    parameters: &LocalizationConfidenceParameters,
+) -> f32 {
+    let covariance = best_hypothesis.state.covariance;
+    let position_standard_deviation = (covariance[(0, 0)] + covariance[(1, 1)]).max(0.0).sqrt();
+    let orientation_standard_deviation = covariance[(2, 2)].max(0.0).sqrt();
+    let covariance_confidence = (-position_standard_deviation
+        / parameters.position_standard_deviation_scale
+        - orientation_standard_deviation / parameters.orientation_standard_deviation_scale)
+        .exp();
+
+    let best_position = best_hypothesis.state.mean.xy();
+    let competing_score: f32 = hypotheses
+        .iter()
+        .filter(|hypothesis| {
+            (hypothesis.state.mean.xy() - best_position).norm()
+                > parameters.competing_hypothesis_distance
+        })
+        .map(|hypothesis| hypothesis.score.max(0.0))
+        .sum();
+    let best_score = best_hypothesis.score.max(0.0);
+    let score_confidence = if best_score + competing_score > 0.0 {
+        best_score / (best_score + competing_score)
+    } else {
+        0.0
+    };
+
+    let fit_error_confidence = (-filtered_fit_error / parameters.fit_error_scale).exp();
+
+    covariance_confidence * score_confidence * fit_error_confidence
+}
+
 pub fn goal_support_structure_line_marks_from_field_dimensions(
     field_dimensions: &FieldDimensions,
 ) -> Vec<FieldMark> {
@@ -794,10 +1256,8 @@ fn get_field_mark_correspondence(
                 .iter()
                 .filter_map(|field_mark| {
                     let transformed_line = correction.framed_transform() * measured_line_in_field;
-                    let field_mark_length = match field_mark {
-                        FieldMark::Line { line, direction: _ } => line.length(),
-                        FieldMark::Circle { center: _, radius } => *radius, // approximation
-                    };
+                    // measured lines are fitted to lines and circles only
+                    let field_mark_length = field_mark.length()?;
                     let measured_line_length = transformed_line.length();
                     if measured_line_length <= field_mark_length * line_length_acceptance_factor {
                         let correspondences = field_mark.to_correspondence_points(transformed_line);
@@ -961,6 +1421,57 @@ fn get_2d_translation_measurement(
     reference_robot_point.coords
 }
 
+/// Closest intersection field mark of the same kind, e.g. an L intersection never matches a T
+/// junction
+fn associate_intersection(
+    field_marks: &[FieldMark],
+    measured_intersection_in_field: Point2<Field>,
+    kind: IntersectionKind,
+) -> Option<(Point2<Field>, f32)> {
+    field_marks
+        .iter()
+        .filter_map(|field_mark| match *field_mark {
+            FieldMark::Intersection {
+                point,
+                kind: reference_kind,
+            } if reference_kind == kind => {
+                Some((point, distance(point, measured_intersection_in_field)))
+            }
+            _ => None,
+        })
+        .min_by(|(_, left), (_, right)| left.total_cmp(right))
+}
+
+/// Closest reference landmark of the same kind
+fn associate_landmark(
+    landmarks: &[Landmark],
+    measured_landmark_in_field: Point2<Field>,
+    kind: LandmarkKind,
+) -> Option<(&Landmark, f32)> {
+    landmarks
+        .iter()
+        .filter(|landmark| landmark.kind == kind)
+        .map(|landmark| {
+            (
+                landmark,
+                distance(landmark.position, measured_landmark_in_field),
+            )
+        })
+        .min_by(|(_, left), (_, right)| left.total_cmp(right))
+}
+
+fn get_landmark_measurement(
+    ground_to_field: Isometry2<Ground, Field>,
+    measured_landmark_in_field: Point2<Field>,
+    reference_landmark: Point2<Field>,
+) -> Vector2<f32> {
+    // robot position at which the measured landmark would coincide with its reference
+    let robot_position = ground_to_field.as_pose().position();
+    (robot_position + (reference_landmark - measured_landmark_in_field))
+        .inner
+        .coords
+}
+
 pub fn generate_initial_pose(
     initial_pose: &InitialPose,
     field_dimensions: &FieldDimensions,
@@ -1007,6 +1518,7 @@ mod tests {
     use std::f32::consts::FRAC_PI_4;
 
     use linear_algebra::Point2;
+    use types::line_data::Intersection;
 
     use super::*;
 
@@ -1687,4 +2199,90 @@ mod tests {
         let update = get_2d_translation_measurement(ground_to_field, field_mark_correspondence);
         assert_relative_eq!(update, nalgebra::vector![0.0, -2.0], epsilon = 0.0001);
     }
+
+    fn confidence_parameters() -> LocalizationConfidenceParameters {
+        LocalizationConfidenceParameters {
+            position_standard_deviation_scale: 0.5,
+            orientation_standard_deviation_scale: 0.3,
+            competing_hypothesis_distance: 1.0,
+            fit_error_scale: 0.5,
+            fit_error_smoothing_factor: 0.1,
+        }
+    }
+
+    #[test]
+    fn intersections_are_associated_with_field_marks_of_their_kind() {
+        let field_marks = field_marks_from_field_dimensions(&FieldDimensions::SPL);
+        let line_data = LineData {
+            intersections: vec![Intersection {
+                position: point![3.9, 1.0],
+                kind: IntersectionKind::T,
+                orientation: vector![-1.0, 0.0],
+            }],
+            ..Default::default()
+        };
+        let measurements = Measurements::from_line_data([&line_data]);
+        let &[(measured_intersection, kind)] = measurements.intersections.as_slice() else {
+            panic!("expected exactly one intersection measurement");
+        };
+        assert_eq!(kind, IntersectionKind::T);
+
+        // the goal box area corner at (3.9, 1.1) is closer but an L intersection
+        let measured_intersection_in_field =
+            Isometry2::<Ground, Field>::identity() * measured_intersection;
+        let (reference_intersection, association_distance) =
+            associate_intersection(&field_marks, measured_intersection_in_field, kind).unwrap();
+
+        assert_relative_eq!(reference_intersection, point![4.5, 1.1]);
+        assert_relative_eq!(association_distance, 0.6083, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn certain_single_hypothesis_is_confident() {
+        let hypothesis =
+            ScoredPose::from_isometry(Pose::from(point![1.0, 2.0]), Matrix3::zeros(), 1.0);
+
+        let confidence =
+            localization_confidence(&[hypothesis], &hypothesis, 0.0, &confidence_parameters());
+
+        assert_relative_eq!(confidence, 1.0);
+    }
+
+    #[test]
+    fn distant_competing_hypothesis_halves_confidence() {
+        let best = ScoredPose::from_isometry(Pose::from(point![1.0, 2.0]), Matrix3::zeros(), 1.0);
+        let nearby = ScoredPose::from_isometry(Pose::from(point![1.2, 2.0]), Matrix3::zeros(), 1.0);
+        let mirrored =
+            ScoredPose::from_isometry(Pose::from(point![-1.0, -2.0]), Matrix3::zeros(), 1.0);
+
+        let confidence = localization_confidence(
+            &[best, nearby, mirrored],
+            &best,
+            0.0,
+            &confidence_parameters(),
+        );
+
+        assert_relative_eq!(confidence, 0.5);
+    }
+
+    #[test]
+    fn uncertainty_and_fit_errors_reduce_confidence() {
+        let certain =
+            ScoredPose::from_isometry(Pose::from(point![1.0, 2.0]), Matrix3::zeros(), 1.0);
+        let uncertain = ScoredPose::from_isometry(
+            Pose::from(point![1.0, 2.0]),
+            Matrix3::from_diagonal(&nalgebra::vector![0.5, 0.5, 0.1]),
+            1.0,
+        );
+        let parameters = confidence_parameters();
+
+        let confidence_of_certain = localization_confidence(&[certain], &certain, 0.0, &parameters);
+        let confidence_of_uncertain =
+            localization_confidence(&[uncertain], &uncertain, 0.0, &parameters);
+        let confidence_with_fit_error =
+            localization_confidence(&[certain], &certain, 0.5, &parameters);
+
+        assert!(confidence_of_uncertain < 0.2 * confidence_of_certain);
+        assert_relative_eq!(confidence_with_fit_error, (-1.0_f32).exp());
+    }
 }
@@ -5,7 +5,7 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use bincode::serialize;
+use bincode::{serialize, serialize_into};
 use color_eyre::{eyre::Context, Result};
 use context_attribute::context;
 use coordinate_systems::{Field, Ground};
@@ -13,10 +13,16 @@ use framework::{HistoricInput, PerceptionInput};
 use linear_algebra::Isometry2;
 use serde::{Deserialize, Serialize};
 use types::{
-    filtered_game_controller_state::FilteredGameControllerState, line_data::LineData,
-    primary_state::PrimaryState,
+    cycle_time::CycleTime, filtered_game_controller_state::FilteredGameControllerState,
+    line_data::LineData, primary_state::PrimaryState,
 };
 
+/// Identifies localization recordings, recordings without it predate the format version
+pub const RECORDING_MAGIC: [u8; 4] = *b"HLOC";
+/// Written after the magic, increment it whenever [`RecordedCycleContext`] or a type contained in
+/// it changes
+pub const RECORDING_FORMAT_VERSION: u32 = 1;
+
 #[derive(Deserialize, Serialize)]
 pub struct LocalizationRecorder {
     #[serde(skip)]
@@ -37,6 +43,9 @@ pub struct CycleContext {
     current_odometry_to_last_odometry:
         HistoricInput<Option<nalgebra::Isometry2<f32>>, "current_odometry_to_last_odometry?">,
 
+    current_cycle_odometry_to_last_odometry:
+        Input<Option<nalgebra::Isometry2<f32>>, "current_odometry_to_last_odometry?">,
+    cycle_time: Input<CycleTime, "cycle_time">,
     filtered_game_controller_state:
         Input<Option<FilteredGameControllerState>, "filtered_game_controller_state?">,
     has_ground_contact: Input<bool, "has_ground_contact">,
@@ -58,11 +67,13 @@ impl LocalizationRecorder {
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_secs();
+            let mut recording = BufWriter::new(
+                File::create(format!("logs/localization.{seconds}.bincode")).wrap_err("failed")?,
+            );
+            serialize_into(&mut recording, &(RECORDING_MAGIC, RECORDING_FORMAT_VERSION))
+                .wrap_err("failed to write recording header")?;
             Ok(Self {
-                recording: Some(BufWriter::new(
-                    File::create(format!("logs/localization.{seconds}.bincode"))
-                        .wrap_err("failed")?,
-                )),
+                recording: Some(recording),
             })
         } else {
             Ok(Self { recording: None })
@@ -107,7 +118,11 @@ impl LocalizationRecorder {
             })
             .collect();
         let recorded_context = RecordedCycleContext {
+            cycle_start_time: context.cycle_time.start_time,
             current_odometry_to_last_odometry,
+            current_cycle_odometry_to_last_odometry: context
+                .current_cycle_odometry_to_last_odometry
+                .copied(),
             filtered_game_controller_state: context.filtered_game_controller_state.cloned(),
             has_ground_contact: *context.has_ground_contact,
             primary_state: *context.primary_state,
@@ -150,7 +165,10 @@ impl LocalizationRecorder {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct RecordedCycleContext {
+    pub cycle_start_time: SystemTime,
     pub current_odometry_to_last_odometry: BTreeMap<SystemTime, Option<nalgebra::Isometry2<f32>>>,
+    /// Odometry of this control cycle, the map above only contains the perception timestamps
+    pub current_cycle_odometry_to_last_odometry: Option<nalgebra::Isometry2<f32>>,
 
     pub filtered_game_controller_state: Option<FilteredGameControllerState>,
     pub has_ground_contact: bool,
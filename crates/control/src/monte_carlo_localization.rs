@@ -0,0 +1,913 @@
+use std::{
+    collections::HashMap,
+    f32::consts::{PI, TAU},
+};
+
+use itertools::iproduct;
+use nalgebra::{vector, Cholesky, Matrix3, Rotation2, Vector2, Vector3};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+use rand_distr::StandardNormal;
+use serde::{Deserialize, Serialize};
+
+use coordinate_systems::{Field, Ground};
+use geometry::angle::normalize_angle;
+use linear_algebra::{distance, distance_squared, IntoTransform, Isometry2, Point2};
+use types::{
+    field_dimensions::FieldDimensions,
+    field_marks::{FieldMark, IntersectionKind},
+    landmarks::{Landmark, LandmarkKind},
+    line::{Line, Line2},
+    line_data::LineData,
+    localization::{Particle, ScoredPose},
+    multivariate_normal_distribution::MultivariateNormalDistribution,
+    parameters::ParticleFilterParameters,
+};
+
+const SENSOR_RESET_OFFSET_STEP: f32 = 0.1;
+
+/// Lines, line intersections and landmarks measured within one vision cycle
+#[derive(Clone, Debug, Default)]
+pub struct Measurements {
+    pub lines: Vec<Line2<Ground>>,
+    pub intersections: Vec<(Point2<Ground>, IntersectionKind)>,
+    pub landmarks: Vec<(Point2<Ground>, LandmarkKind)>,
+}
+
+impl Measurements {
+    /// Lines and their intersections of all cameras
+    pub fn from_line_data<'a>(line_data: impl IntoIterator<Item = &'a LineData>) -> Self {
+        let mut measurements = Self::default();
+        for line_data in line_data {
+            measurements.lines.extend(line_data.lines.iter().copied());
+            measurements.intersections.extend(
+                line_data
+                    .intersections
+                    .iter()
+                    .map(|intersection| (intersection.position, intersection.kind)),
+            );
+        }
+        measurements
+    }
+
+    fn number_of_measurements(&self) -> usize {
+        self.lines.len() + self.intersections.len() + self.landmarks.len()
+    }
+}
+
+/// Monte-Carlo localization with sensor resetting (augmented MCL)
+///
+/// If the short-term average of the measurement likelihood drops below its long-term average
+/// (e.g. after being picked up), particles are replaced by poses explaining the observed lines.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct MonteCarloLocalization {
+    particles: Vec<Particle>,
+    slow_average_likelihood: Option<f32>,
+    fast_average_likelihood: Option<f32>,
+}
+
+impl MonteCarloLocalization {
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn reset(
+        &mut self,
+        hypotheses: &[ScoredPose],
+        number_of_particles: usize,
+        random_number_generator: &mut impl Rng,
+    ) {
+        self.particles = if hypotheses.is_empty() {
+            Vec::new()
+        } else {
+            (0..number_of_particles)
+                .map(|index| Particle {
+                    state: sample_state(
+                        &hypotheses[index % hypotheses.len()].state,
+                        random_number_generator,
+                    ),
+                    weight: 1.0 / number_of_particles as f32,
+                })
+                .collect()
+        };
+        self.slow_average_likelihood = None;
+        self.fast_average_likelihood = None;
+    }
+
+    pub fn predict(
+        &mut self,
+        current_odometry_to_last_odometry: &nalgebra::Isometry2<f32>,
+        parameters: &ParticleFilterParameters,
+        random_number_generator: &mut impl Rng,
+    ) {
+        let translation = current_odometry_to_last_odometry.translation.vector;
+        let rotation = current_odometry_to_last_odometry.rotation.angle();
+        let standard_deviations = parameters.process_noise
+            + parameters.odometry_noise.component_mul(&vector![
+                translation.x.abs(),
+                translation.y.abs(),
+                rotation.abs()
+            ]);
+        for particle in &mut self.particles {
+            let noisy_translation = translation
+                + vector![
+                    standard_deviations.x
+                        * random_number_generator.sample::<f32, _>(StandardNormal),
+                    standard_deviations.y
+                        * random_number_generator.sample::<f32, _>(StandardNormal)
+                ];
+            let noisy_rotation = rotation
+                + standard_deviations.z * random_number_generator.sample::<f32, _>(StandardNormal);
+            // rotate odometry from robot frame to field frame
+            let translation_in_field = Rotation2::new(particle.state.z) * noisy_translation;
+            particle.state = vector![
+                particle.state.x + translation_in_field.x,
+                particle.state.y + translation_in_field.y,
+                normalize_angle(particle.state.z + noisy_rotation)
+            ];
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        measurements: &Measurements,
+        field_marks: &[FieldMark],
+        landmarks: &[Landmark],
+        field_dimensions: &FieldDimensions,
+        parameters: &ParticleFilterParameters,
+        random_number_generator: &mut impl Rng,
+    ) {
+        if self.particles.is_empty() || measurements.number_of_measurements() == 0 {
+            return;
+        }
+
+        let log_likelihoods: Vec<f32> = self
+            .particles
+            .iter()
+            .map(|particle| {
+                log_likelihood(
+                    particle.state,
+                    measurements,
+                    field_marks,
+                    landmarks,
+                    parameters,
+                )
+            })
+            .collect();
+        // per measurement likelihood to be independent of the number of measurements
+        let average_likelihood: f32 = self
+            .particles
+            .iter()
+            .zip(&log_likelihoods)
+            .map(|(particle, log_likelihood)| {
+                particle.weight
+                    * (log_likelihood / measurements.number_of_measurements() as f32).exp()
+            })
+            .sum();
+        let maximum_log_likelihood = log_likelihoods
+            .iter()
+            .copied()
+            .fold(f32::NEG_INFINITY, f32::max);
+        for (particle, log_likelihood) in self.particles.iter_mut().zip(&log_likelihoods) {
+            particle.weight *= (log_likelihood - maximum_log_likelihood).exp();
+        }
+        normalize_weights(&mut self.particles);
+
+        let slow_average_likelihood =
+            self.slow_average_likelihood
+                .map_or(average_likelihood, |slow_average_likelihood| {
+                    slow_average_likelihood
+                        + parameters.slow_averaging_factor
+                            * (average_likelihood - slow_average_likelihood)
+                });
+        let fast_average_likelihood =
+            self.fast_average_likelihood
+                .map_or(average_likelihood, |fast_average_likelihood| {
+                    fast_average_likelihood
+                        + parameters.fast_averaging_factor
+                            * (average_likelihood - fast_average_likelihood)
+                });
+        self.slow_average_likelihood = Some(slow_average_likelihood);
+        self.fast_average_likelihood = Some(fast_average_likelihood);
+
+        let sensor_reset_fraction = if slow_average_likelihood > 0.0 {
+            (1.0 - fast_average_likelihood / slow_average_likelihood)
+                .clamp(0.0, parameters.maximum_sensor_reset_fraction)
+        } else {
+            0.0
+        };
+        let number_of_particles = self.particles.len();
+        let number_of_sensor_reset_particles =
+            (sensor_reset_fraction * number_of_particles as f32).round() as usize;
+        let effective_sample_size = 1.0
+            / self
+                .particles
+                .iter()
+                .map(|particle| particle.weight.powi(2))
+                .sum::<f32>();
+        if number_of_sensor_reset_particles == 0
+            && effective_sample_size
+                >= parameters.minimum_effective_sample_size_fraction * number_of_particles as f32
+        {
+            return;
+        }
+
+        let sensor_reset_particles = sample_from_observations(
+            measurements,
+            field_marks,
+            landmarks,
+            field_dimensions,
+            parameters,
+            number_of_sensor_reset_particles,
+            random_number_generator,
+        );
+        let mut particles = low_variance_resample(
+            &self.particles,
+            number_of_particles - sensor_reset_particles.len(),
+            random_number_generator,
+        );
+        particles.extend(sensor_reset_particles);
+        for particle in &mut particles {
+            particle.weight = 1.0 / number_of_particles as f32;
+        }
+        self.particles = particles;
+    }
+
+    /// Mean and covariance of the cluster of particles with the highest total weight, the score is
+    /// the weight of this cluster
+    ///
+    /// Particles are binned into cells of the cluster size and the cluster is centered at the mean
+    /// of the heaviest block of cells, which keeps the estimate linear in the number of particles.
+    pub fn estimate(&self, parameters: &ParticleFilterParameters) -> Option<ScoredPose> {
+        let center = densest_neighborhood_mean(&self.particles, parameters)?;
+        let is_in_cluster = |particle: &Particle| {
+            (particle.state.xy() - center.xy()).norm() < parameters.cluster_radius
+                && normalize_angle(particle.state.z - center.z).abs() < parameters.cluster_angle
+        };
+        let cluster: Vec<_> = self
+            .particles
+            .iter()
+            .filter(|particle| is_in_cluster(particle))
+            .collect();
+        let cluster_weight: f32 = cluster.iter().map(|particle| particle.weight).sum();
+        if cluster_weight <= 0.0 {
+            return None;
+        }
+
+        let weighted_sum = cluster.iter().fold(Vector3::zeros(), |sum, particle| {
+            sum + particle.weight * vector![particle.state.x, particle.state.y, 0.0]
+        });
+        let (sine_sum, cosine_sum) =
+            cluster
+                .iter()
+                .fold((0.0, 0.0), |(sine_sum, cosine_sum), particle| {
+                    (
+                        sine_sum + particle.weight * particle.state.z.sin(),
+                        cosine_sum + particle.weight * particle.state.z.cos(),
+                    )
+                });
+        let mean = vector![
+            weighted_sum.x / cluster_weight,
+            weighted_sum.y / cluster_weight,
+            sine_sum.atan2(cosine_sum)
+        ];
+        let covariance = cluster
+            .iter()
+            .fold(Matrix3::zeros(), |covariance, particle| {
+                let deviation = vector![
+                    particle.state.x - mean.x,
+                    particle.state.y - mean.y,
+                    normalize_angle(particle.state.z - mean.z)
+                ];
+                covariance + particle.weight * deviation * deviation.transpose()
+            })
+            / cluster_weight;
+
+        Some(ScoredPose {
+            state: MultivariateNormalDistribution { mean, covariance },
+            score: cluster_weight,
+        })
+    }
+}
+
+/// Weighted mean of the particles in the 3x3x3 block of cells with the highest total weight
+fn densest_neighborhood_mean(
+    particles: &[Particle],
+    parameters: &ParticleFilterParameters,
+) -> Option<Vector3<f32>> {
+    let number_of_angle_cells = (TAU / parameters.cluster_angle).ceil().max(1.0) as i32;
+    let cell_of = |state: Vector3<f32>| {
+        (
+            (state.x / parameters.cluster_radius).floor() as i32,
+            (state.y / parameters.cluster_radius).floor() as i32,
+            (((normalize_angle(state.z) + PI) / parameters.cluster_angle).floor() as i32)
+                .rem_euclid(number_of_angle_cells),
+        )
+    };
+    let neighborhood = |(x, y, angle): (i32, i32, i32)| {
+        let mut neighbors: Vec<_> = iproduct!(-1..=1, -1..=1, -1..=1)
+            .map(|(offset_x, offset_y, offset_angle)| {
+                (
+                    x + offset_x,
+                    y + offset_y,
+                    (angle + offset_angle).rem_euclid(number_of_angle_cells),
+                )
+            })
+            .collect();
+        // with less than three angle cells, wrapped neighbors coincide
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    };
+
+    let mut cell_weights: HashMap<(i32, i32, i32), f32> = HashMap::new();
+    for particle in particles {
+        *cell_weights.entry(cell_of(particle.state)).or_default() += particle.weight;
+    }
+    let (densest_cell, _) = cell_weights
+        .keys()
+        .map(|&cell| {
+            let weight: f32 = neighborhood(cell)
+                .iter()
+                .filter_map(|neighbor| cell_weights.get(neighbor))
+                .sum();
+            (cell, weight)
+        })
+        .max_by(|(left_cell, left), (right_cell, right)| {
+            left.total_cmp(right).then(right_cell.cmp(left_cell))
+        })?;
+
+    let neighbors = neighborhood(densest_cell);
+    let (weight, position_sum, sine_sum, cosine_sum) = particles
+        .iter()
+        .filter(|particle| neighbors.contains(&cell_of(particle.state)))
+        .fold(
+            (0.0, Vector2::zeros(), 0.0, 0.0),
+            |(weight, position_sum, sine_sum, cosine_sum), particle| {
+                (
+                    weight + particle.weight,
+                    position_sum + particle.weight * particle.state.xy(),
+                    sine_sum + particle.weight * particle.state.z.sin(),
+                    cosine_sum + particle.weight * particle.state.z.cos(),
+                )
+            },
+        );
+    if weight <= 0.0 {
+        return None;
+    }
+    let position = position_sum / weight;
+    Some(vector![position.x, position.y, sine_sum.atan2(cosine_sum)])
+}
+
+pub fn state_to_ground_to_field(state: Vector3<f32>) -> Isometry2<Ground, Field> {
+    nalgebra::Isometry2::new(state.xy(), state.z).framed_transform()
+}
+
+fn sample_state(
+    distribution: &MultivariateNormalDistribution<3>,
+    random_number_generator: &mut impl Rng,
+) -> Vector3<f32> {
+    let cholesky_factor = Cholesky::new(distribution.covariance)
+        .map(|cholesky| cholesky.l())
+        .unwrap_or_else(|| {
+            Matrix3::from_diagonal(
+                &distribution
+                    .covariance
+                    .diagonal()
+                    .map(|variance| variance.abs().sqrt()),
+            )
+        });
+    let standard_normal =
+        Vector3::from_fn(|_, _| random_number_generator.sample::<f32, _>(StandardNormal));
+    let state = distribution.mean + cholesky_factor * standard_normal;
+    vector![state.x, state.y, normalize_angle(state.z)]
+}
+
+fn log_likelihood(
+    state: Vector3<f32>,
+    measurements: &Measurements,
+    field_marks: &[FieldMark],
+    landmarks: &[Landmark],
+    parameters: &ParticleFilterParameters,
+) -> f32 {
+    let ground_to_field = state_to_ground_to_field(state);
+    let squared_line_error: f32 = measurements
+        .lines
+        .iter()
+        .map(|&measured_line| {
+            squared_line_error(
+                ground_to_field * measured_line,
+                field_marks,
+                parameters.maximum_line_point_distance,
+            )
+        })
+        .sum();
+    let squared_intersection_error: f32 = measurements
+        .intersections
+        .iter()
+        .map(|&(measured_intersection, kind)| {
+            squared_intersection_error(
+                ground_to_field * measured_intersection,
+                kind,
+                field_marks,
+                parameters.maximum_landmark_distance,
+            )
+        })
+        .sum();
+    let squared_landmark_error: f32 = measurements
+        .landmarks
+        .iter()
+        .map(|&(measured_landmark, kind)| {
+            squared_landmark_error(
+                ground_to_field * measured_landmark,
+                kind,
+                landmarks,
+                parameters.maximum_landmark_distance,
+            )
+        })
+        .sum();
+    -0.5 * (squared_line_error / parameters.line_measurement_standard_deviation.powi(2)
+        + (squared_intersection_error + squared_landmark_error)
+            / parameters.landmark_measurement_standard_deviation.powi(2))
+}
+
+/// Squared distances of both line end points to the best matching field mark
+fn squared_line_error(
+    measured_line_in_field: Line2<Field>,
+    field_marks: &[FieldMark],
+    maximum_line_point_distance: f32,
+) -> f32 {
+    let maximum_squared_distance = maximum_line_point_distance.powi(2);
+    field_marks
+        .iter()
+        .map(|field_mark| {
+            [measured_line_in_field.0, measured_line_in_field.1]
+                .into_iter()
+                .map(|point| squared_distance_to_field_mark(field_mark, point))
+                .map(|squared_distance| squared_distance.min(maximum_squared_distance))
+                .sum::<f32>()
+        })
+        .fold(2.0 * maximum_squared_distance, f32::min)
+}
+
+/// Squared distance to the closest landmark of the same kind
+fn squared_landmark_error(
+    measured_landmark_in_field: Point2<Field>,
+    kind: LandmarkKind,
+    landmarks: &[Landmark],
+    maximum_landmark_distance: f32,
+) -> f32 {
+    landmarks
+        .iter()
+        .filter(|landmark| landmark.kind == kind)
+        .map(|landmark| distance_squared(landmark.position, measured_landmark_in_field))
+        .fold(maximum_landmark_distance.powi(2), f32::min)
+}
+
+/// Squared distance to the closest intersection field mark of the same kind
+fn squared_intersection_error(
+    measured_intersection_in_field: Point2<Field>,
+    kind: IntersectionKind,
+    field_marks: &[FieldMark],
+    maximum_intersection_distance: f32,
+) -> f32 {
+    field_marks
+        .iter()
+        .filter_map(|field_mark| match field_mark {
+            FieldMark::Intersection {
+                point,
+                kind: reference_kind,
+            } if *reference_kind == kind => {
+                Some(distance_squared(*point, measured_intersection_in_field))
+            }
+            _ => None,
+        })
+        .fold(maximum_intersection_distance.powi(2), f32::min)
+}
+
+fn squared_distance_to_field_mark(field_mark: &FieldMark, point: Point2<Field>) -> f32 {
+    match field_mark {
+        FieldMark::Line { line, .. } => line.squared_distance_to_segment(point),
+        FieldMark::Circle { center, radius } => (distance(point, *center) - radius).powi(2),
+        FieldMark::Intersection {
+            point: intersection,
+            ..
+        } => distance_squared(point, *intersection),
+    }
+}
+
+/// Poses that align the longest measured line with each field mark line, sampled by their
+/// likelihood given all measurements
+fn sample_from_observations(
+    measurements: &Measurements,
+    field_marks: &[FieldMark],
+    landmarks: &[Landmark],
+    field_dimensions: &FieldDimensions,
+    parameters: &ParticleFilterParameters,
+    number_of_particles: usize,
+    random_number_generator: &mut impl Rng,
+) -> Vec<Particle> {
+    if number_of_particles == 0 {
+        return Vec::new();
+    }
+    let Some(&longest_line) = measurements
+        .lines
+        .iter()
+        .max_by(|left, right| left.length().total_cmp(&right.length()))
+    else {
+        return Vec::new();
+    };
+    let candidates: Vec<_> = field_marks
+        .iter()
+        .filter_map(|field_mark| match field_mark {
+            FieldMark::Line { line, .. } => Some(*line),
+            FieldMark::Circle { .. } | FieldMark::Intersection { .. } => None,
+        })
+        .flat_map(|field_mark_line| {
+            [longest_line, Line(longest_line.1, longest_line.0)]
+                .into_iter()
+                .flat_map(move |measured_line| {
+                    pose_candidates(measured_line, field_mark_line, parameters)
+                })
+        })
+        .filter(|state| {
+            state.x.abs() <= field_dimensions.length / 2.0 + field_dimensions.border_strip_width
+                && state.y.abs()
+                    <= field_dimensions.width / 2.0 + field_dimensions.border_strip_width
+        })
+        .collect();
+    if candidates.is_empty() {
+        return Vec::new();
+    }
+    let log_likelihoods: Vec<_> = candidates
+        .iter()
+        .map(|&state| log_likelihood(state, measurements, field_marks, landmarks, parameters))
+        .collect();
+    let maximum_log_likelihood = log_likelihoods
+        .iter()
+        .copied()
+        .fold(f32::NEG_INFINITY, f32::max);
+    let Ok(candidate_distribution) = WeightedIndex::new(
+        log_likelihoods
+            .iter()
+            .map(|log_likelihood| (log_likelihood - maximum_log_likelihood).exp()),
+    ) else {
+        return Vec::new();
+    };
+    (0..number_of_particles)
+        .map(|_| {
+            let state = candidates[candidate_distribution.sample(random_number_generator)];
+            let noise = Vector3::from_fn(|index, _| {
+                parameters.process_noise[index]
+                    * random_number_generator.sample::<f32, _>(StandardNormal)
+            });
+            Particle {
+                state: vector![
+                    state.x + noise.x,
+                    state.y + noise.y,
+                    normalize_angle(state.z + noise.z)
+                ],
+                weight: 0.0,
+            }
+        })
+        .collect()
+}
+
+fn pose_candidates(
+    measured_line: Line2<Ground>,
+    field_mark_line: Line2<Field>,
+    parameters: &ParticleFilterParameters,
+) -> Vec<Vector3<f32>> {
+    let measured_direction = (measured_line.1 - measured_line.0).inner;
+    let field_mark_direction = (field_mark_line.1 - field_mark_line.0).inner;
+    let slack = field_mark_direction.norm() - measured_direction.norm();
+    if slack < -2.0 * parameters.maximum_line_point_distance
+        || field_mark_direction.norm() <= f32::EPSILON
+    {
+        return Vec::new();
+    }
+    let angle = field_mark_direction.y.atan2(field_mark_direction.x)
+        - measured_direction.y.atan2(measured_direction.x);
+    let rotation = Rotation2::new(angle);
+    let number_of_offsets = (slack.max(0.0) / SENSOR_RESET_OFFSET_STEP).ceil() as usize + 1;
+    (0..number_of_offsets)
+        .map(|index| {
+            let offset = (index as f32 * SENSOR_RESET_OFFSET_STEP).min(slack.max(0.0));
+            let anchor = field_mark_line.0.inner.coords + field_mark_direction.normalize() * offset;
+            let translation = anchor - rotation * measured_line.0.inner.coords;
+            vector![translation.x, translation.y, normalize_angle(angle)]
+        })
+        .collect()
+}
+
+fn low_variance_resample(
+    particles: &[Particle],
+    number_of_particles: usize,
+    random_number_generator: &mut impl Rng,
+) -> Vec<Particle> {
+    if particles.is_empty() || number_of_particles == 0 {
+        return Vec::new();
+    }
+    let step = 1.0 / number_of_particles as f32;
+    let mut threshold = random_number_generator.gen_range(0.0..step);
+    let mut cumulative_weight = particles[0].weight;
+    let mut index = 0;
+    (0..number_of_particles)
+        .map(|_| {
+            while threshold > cumulative_weight && index + 1 < particles.len() {
+                index += 1;
+                cumulative_weight += particles[index].weight;
+            }
+            threshold += step;
+            particles[index]
+        })
+        .collect()
+}
+
+fn normalize_weights(particles: &mut [Particle]) {
+    let weight_sum: f32 = particles.iter().map(|particle| particle.weight).sum();
+    let is_degenerate = !weight_sum.is_normal();
+    let number_of_particles = particles.len();
+    for particle in particles.iter_mut() {
+        particle.weight = if is_degenerate {
+            1.0 / number_of_particles as f32
+        } else {
+            particle.weight / weight_sum
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linear_algebra::{point, Pose};
+    use rand::{rngs::StdRng, SeedableRng};
+    use types::{
+        field_marks::field_marks_from_field_dimensions, landmarks::landmarks_from_field_dimensions,
+    };
+
+    use super::*;
+
+    fn parameters() -> ParticleFilterParameters {
+        ParticleFilterParameters {
+            number_of_particles: 200,
+            process_noise: vector![0.01, 0.01, 0.01],
+            odometry_noise: vector![0.1, 0.1, 0.1],
+            line_measurement_standard_deviation: 0.15,
+            maximum_line_point_distance: 0.5,
+            landmark_measurement_standard_deviation: 0.2,
+            maximum_landmark_distance: 0.7,
+            slow_averaging_factor: 0.02,
+            fast_averaging_factor: 0.3,
+            maximum_sensor_reset_fraction: 0.2,
+            minimum_effective_sample_size_fraction: 0.5,
+            cluster_radius: 0.5,
+            cluster_angle: 0.5,
+        }
+    }
+
+    /// Parts of the field mark lines within three meters around the robot
+    fn observed_lines(state: Vector3<f32>, field_marks: &[FieldMark]) -> Vec<Line2<Ground>> {
+        let field_to_ground = state_to_ground_to_field(state).inverse();
+        let robot_position = point![state.x, state.y];
+        field_marks
+            .iter()
+            .filter_map(|field_mark| match field_mark {
+                FieldMark::Line { line, .. } => Some(*line),
+                FieldMark::Circle { .. } | FieldMark::Intersection { .. } => None,
+            })
+            .filter_map(|line| {
+                let visible_points: Vec<_> = (0..=100)
+                    .map(|index| line.0 + (line.1 - line.0) * (index as f32 / 100.0))
+                    .filter(|&point| distance(point, robot_position) < 3.0)
+                    .collect();
+                let first = *visible_points.first()?;
+                let last = *visible_points.last()?;
+                (distance(first, last) > 0.3)
+                    .then(|| Line(field_to_ground * first, field_to_ground * last))
+            })
+            .collect()
+    }
+
+    fn is_close_to(estimate: Vector3<f32>, expected: Vector3<f32>) -> bool {
+        (estimate.xy() - expected.xy()).norm() < 0.3
+            && normalize_angle(estimate.z - expected.z).abs() < 0.2
+    }
+
+    /// The field is point symmetric, lines alone cannot tell both halves apart
+    fn is_close_to_or_mirrored(estimate: Vector3<f32>, expected: Vector3<f32>) -> bool {
+        is_close_to(estimate, expected)
+            || is_close_to(
+                estimate,
+                vector![-expected.x, -expected.y, normalize_angle(expected.z + PI)],
+            )
+    }
+
+    fn hypothesis(state: Vector3<f32>, variance: f32) -> ScoredPose {
+        ScoredPose::from_isometry(
+            Pose::new(linear_algebra::vector![state.x, state.y], state.z),
+            Matrix3::from_diagonal_element(variance),
+            1.0,
+        )
+    }
+
+    #[test]
+    fn particles_follow_odometry() {
+        let mut random_number_generator = StdRng::seed_from_u64(42);
+        let mut parameters = parameters();
+        parameters.process_noise = Vector3::zeros();
+        parameters.odometry_noise = Vector3::zeros();
+        let mut localization = MonteCarloLocalization::default();
+        localization.reset(
+            &[hypothesis(vector![1.0, 2.0, PI / 2.0], 0.0)],
+            10,
+            &mut random_number_generator,
+        );
+
+        localization.predict(
+            &nalgebra::Isometry2::new(vector![0.5, 0.0], 0.1),
+            &parameters,
+            &mut random_number_generator,
+        );
+
+        for particle in localization.particles() {
+            assert!(is_close_to(
+                particle.state,
+                vector![1.0, 2.5, PI / 2.0 + 0.1]
+            ));
+        }
+    }
+
+    #[test]
+    fn wrong_hypothesis_is_discarded() {
+        let mut random_number_generator = StdRng::seed_from_u64(42);
+        let field_marks = field_marks_from_field_dimensions(&FieldDimensions::SPL);
+        let true_state = vector![-3.0, 1.0, 0.3];
+        let mut localization = MonteCarloLocalization::default();
+        localization.reset(
+            &[
+                hypothesis(true_state, 0.01),
+                hypothesis(vector![-2.0, 0.0, 0.3], 0.01),
+            ],
+            200,
+            &mut random_number_generator,
+        );
+
+        for _ in 0..10 {
+            localization.update(
+                &Measurements {
+                    lines: observed_lines(true_state, &field_marks),
+                    intersections: Vec::new(),
+                    landmarks: Vec::new(),
+                },
+                &field_marks,
+                &[],
+                &FieldDimensions::SPL,
+                &parameters(),
+                &mut random_number_generator,
+            );
+        }
+
+        let estimate = localization.estimate(&parameters()).unwrap();
+        assert!(is_close_to(estimate.state.mean, true_state));
+    }
+
+    #[test]
+    fn landmarks_discard_wrong_hypothesis() {
+        let mut random_number_generator = StdRng::seed_from_u64(42);
+        let landmarks = landmarks_from_field_dimensions(&FieldDimensions::SPL);
+        let true_state = vector![-3.0, 0.0, PI];
+        let field_to_ground = state_to_ground_to_field(true_state).inverse();
+        let measured_goal_posts: Vec<_> = landmarks
+            .iter()
+            .filter(|landmark| {
+                landmark.kind == LandmarkKind::GoalPost && landmark.position.x() < 0.0
+            })
+            .map(|landmark| (field_to_ground * landmark.position, landmark.kind))
+            .collect();
+        let mut localization = MonteCarloLocalization::default();
+        localization.reset(
+            &[
+                hypothesis(true_state, 0.01),
+                hypothesis(vector![-3.0, 0.6, PI], 0.01),
+            ],
+            200,
+            &mut random_number_generator,
+        );
+
+        for _ in 0..10 {
+            localization.update(
+                &Measurements {
+                    lines: Vec::new(),
+                    intersections: Vec::new(),
+                    landmarks: measured_goal_posts.clone(),
+                },
+                &[],
+                &landmarks,
+                &FieldDimensions::SPL,
+                &parameters(),
+                &mut random_number_generator,
+            );
+        }
+
+        let estimate = localization.estimate(&parameters()).unwrap();
+        assert!(is_close_to(estimate.state.mean, true_state));
+    }
+
+    #[test]
+    fn intersection_field_marks_discard_wrong_hypothesis() {
+        let mut random_number_generator = StdRng::seed_from_u64(42);
+        let field_marks = field_marks_from_field_dimensions(&FieldDimensions::SPL);
+        let true_state = vector![-3.0, 0.0, PI];
+        let field_to_ground = state_to_ground_to_field(true_state).inverse();
+        let measured_corners: Vec<_> = field_marks
+            .iter()
+            .filter_map(|field_mark| match *field_mark {
+                FieldMark::Intersection {
+                    point,
+                    kind: IntersectionKind::L,
+                } if distance(point, point![-3.0, 0.0]) < 3.0 => {
+                    Some((field_to_ground * point, IntersectionKind::L))
+                }
+                _ => None,
+            })
+            .collect();
+        let mut localization = MonteCarloLocalization::default();
+        localization.reset(
+            &[
+                hypothesis(true_state, 0.01),
+                hypothesis(vector![-3.0, 0.6, PI], 0.01),
+            ],
+            200,
+            &mut random_number_generator,
+        );
+
+        for _ in 0..10 {
+            localization.update(
+                &Measurements {
+                    lines: Vec::new(),
+                    intersections: measured_corners.clone(),
+                    landmarks: Vec::new(),
+                },
+                &field_marks,
+                &[],
+                &FieldDimensions::SPL,
+                &parameters(),
+                &mut random_number_generator,
+            );
+        }
+
+        let estimate = localization.estimate(&parameters()).unwrap();
+        assert!(is_close_to(estimate.state.mean, true_state));
+    }
+
+    #[test]
+    fn sensor_resetting_recovers_from_kidnapping() {
+        let mut random_number_generator = StdRng::seed_from_u64(42);
+        let field_marks = field_marks_from_field_dimensions(&FieldDimensions::SPL);
+        let state_before_kidnapping = vector![-3.0, 1.0, 0.3];
+        let state_after_kidnapping = vector![2.5, -1.5, -2.0];
+        let mut localization = MonteCarloLocalization::default();
+        localization.reset(
+            &[hypothesis(state_before_kidnapping, 0.01)],
+            200,
+            &mut random_number_generator,
+        );
+        for _ in 0..20 {
+            localization.update(
+                &Measurements {
+                    lines: observed_lines(state_before_kidnapping, &field_marks),
+                    intersections: Vec::new(),
+                    landmarks: Vec::new(),
+                },
+                &field_marks,
+                &[],
+                &FieldDimensions::SPL,
+                &parameters(),
+                &mut random_number_generator,
+            );
+        }
+
+        for _ in 0..50 {
+            localization.update(
+                &Measurements {
+                    lines: observed_lines(state_after_kidnapping, &field_marks),
+                    intersections: Vec::new(),
+                    landmarks: Vec::new(),
+                },
+                &field_marks,
+                &[],
+                &FieldDimensions::SPL,
+                &parameters(),
+                &mut random_number_generator,
+            );
+        }
+
+        let estimate = localization.estimate(&parameters()).unwrap();
+        assert!(is_close_to_or_mirrored(
+            estimate.state.mean,
+            state_after_kidnapping
+        ));
+    }
+}
@@ -6,12 +6,14 @@ use coordinate_systems::{Field, Ground};
 use filtering::kalman_filter::KalmanFilter;
 use framework::{AdditionalOutput, HistoricInput, MainOutput, PerceptionInput};
 use itertools::{chain, iproduct};
-use linear_algebra::{distance, point, IntoFramed, Isometry2, Point2};
-use nalgebra::Matrix2;
+use linear_algebra::{distance, point, vector, Isometry2, Point2};
+use nalgebra::{matrix, Matrix2, Matrix2x4, Matrix4, Matrix4x2};
 use serde::{Deserialize, Serialize};
+use spl_network_messages::Team;
 use types::{
     cycle_time::CycleTime,
     detected_feet::DetectedFeet,
+    detected_robots::DetectedRobot,
     field_dimensions::FieldDimensions,
     foot_bumper_obstacle::FootBumperObstacle,
     multivariate_normal_distribution::MultivariateNormalDistribution,
@@ -26,6 +28,7 @@ use types::{
 pub struct ObstacleFilter {
     hypotheses: Vec<Hypothesis>,
     last_primary_state: PrimaryState,
+    last_prediction_time: Option<SystemTime>,
 }
 
 #[context]
@@ -56,6 +59,10 @@ pub struct CycleContext {
 
     detected_feet_bottom: PerceptionInput<DetectedFeet, "VisionBottom", "detected_feet">,
     detected_feet_top: PerceptionInput<DetectedFeet, "VisionTop", "detected_feet">,
+    detected_robots_bottom:
+        PerceptionInput<Option<Vec<DetectedRobot>>, "VisionBottom", "detected_robots?">,
+    detected_robots_top:
+        PerceptionInput<Option<Vec<DetectedRobot>>, "VisionTop", "detected_robots?">,
 }
 
 #[context]
@@ -69,26 +76,39 @@ impl ObstacleFilter {
         Ok(Self {
             hypotheses: Vec::new(),
             last_primary_state: PrimaryState::Unstiff,
+            last_prediction_time: None,
         })
     }
 
     pub fn cycle(&mut self, mut context: CycleContext) -> Result<MainOutputs> {
         let field_dimensions = context.field_dimensions;
+        let parameters = context.obstacle_filter_parameters;
         let cycle_start_time = context.cycle_time.start_time;
         let measurements = context
             .detected_feet_top
             .persistent
             .iter()
-            .zip(context.detected_feet_bottom.persistent.values());
-        for ((detection_time, feet_top), feet_bottom) in measurements {
+            .zip(context.detected_feet_bottom.persistent.values())
+            .zip(context.detected_robots_top.persistent.values())
+            .zip(context.detected_robots_bottom.persistent.values());
+        for ((((detection_time, feet_top), feet_bottom), robots_top), robots_bottom) in measurements
+        {
             let current_odometry_to_last_odometry = context
                 .current_odometry_to_last_odometry
                 .get(detection_time)
                 .expect("current_odometry_to_last_odometry should not be None");
+            let time_step = self
+                .last_prediction_time
+                .and_then(|last_prediction_time| {
+                    detection_time.duration_since(last_prediction_time).ok()
+                })
+                .unwrap_or_default();
+            self.last_prediction_time = Some(*detection_time);
 
             self.predict_hypotheses_with_odometry(
                 current_odometry_to_last_odometry.inverse(),
-                Matrix2::from_diagonal(&context.obstacle_filter_parameters.process_noise),
+                time_step,
+                Matrix4::from_diagonal(&parameters.process_noise),
             );
 
             let network_robot_obstacles = context.network_robot_obstacles.get(detection_time);
@@ -96,97 +116,101 @@ impl ObstacleFilter {
             let goal_posts =
                 calculate_goal_post_positions(current_ground_to_field.copied(), field_dimensions);
 
-            for network_robot_obstacle in network_robot_obstacles {
-                self.update_hypotheses_with_measurement(
-                    *network_robot_obstacle,
+            self.update_hypotheses_with_measurements(
+                network_robot_obstacles,
+                ObstacleKind::Teammate,
+                *detection_time,
+                parameters.network_robot_measurement_matching_distance,
+                Matrix2::from_diagonal(&parameters.network_robot_measurement_noise),
+                parameters,
+            );
+
+            if parameters.use_feet_detection_measurements {
+                let measured_positions_in_control_cycle: Vec<_> = feet_top
+                    .iter()
+                    .chain(feet_bottom.iter())
+                    .flat_map(|obstacles| obstacles.positions.iter().copied())
+                    .collect();
+                self.update_hypotheses_with_measurements(
+                    &measured_positions_in_control_cycle,
                     ObstacleKind::Robot,
                     *detection_time,
-                    context
-                        .obstacle_filter_parameters
-                        .network_robot_measurement_matching_distance,
-                    Matrix2::from_diagonal(
-                        &context
-                            .obstacle_filter_parameters
-                            .network_robot_measurement_noise,
-                    ),
+                    parameters.feet_detection_measurement_matching_distance,
+                    Matrix2::from_diagonal(&parameters.feet_measurement_noise),
+                    parameters,
                 );
             }
 
-            if context
-                .obstacle_filter_parameters
-                .use_feet_detection_measurements
-            {
-                let measured_positions_in_control_cycle = feet_top
+            if parameters.use_robot_detection_measurements {
+                let detected_robots: Vec<_> = robots_top
                     .iter()
-                    .chain(feet_bottom.iter())
-                    .flat_map(|obstacles| obstacles.positions.iter());
-
-                for position in measured_positions_in_control_cycle {
-                    self.update_hypotheses_with_measurement(
-                        *position,
-                        ObstacleKind::Robot,
+                    .chain(robots_bottom.iter())
+                    .flatten()
+                    .flat_map(|robots| robots.iter())
+                    .collect();
+                for kind in [
+                    ObstacleKind::Teammate,
+                    ObstacleKind::Opponent,
+                    ObstacleKind::Robot,
+                ] {
+                    let positions: Vec<_> = detected_robots
+                        .iter()
+                        .filter(|robot| obstacle_kind_of_team(robot.team) == kind)
+                        .map(|robot| robot.position)
+                        .collect();
+                    self.update_hypotheses_with_measurements(
+                        &positions,
+                        kind,
                         *detection_time,
-                        context
-                            .obstacle_filter_parameters
-                            .feet_detection_measurement_matching_distance,
-                        Matrix2::from_diagonal(
-                            &context.obstacle_filter_parameters.feet_measurement_noise,
-                        ),
+                        parameters.robot_detection_measurement_matching_distance,
+                        Matrix2::from_diagonal(&parameters.robot_measurement_noise),
+                        parameters,
                     );
                 }
             }
 
-            for sonar_obstacle in context.sonar_obstacles.get(detection_time) {
+            if parameters.use_sonar_measurements {
                 // TODO: Use a clever more intelligent metric
-
-                if context.obstacle_filter_parameters.use_sonar_measurements
-                    && goal_posts.clone().into_iter().all(|goal_post| {
-                        distance(goal_post, sonar_obstacle.position)
-                            > context
-                                .obstacle_filter_parameters
-                                .goal_post_measurement_matching_distance
+                let sonar_positions: Vec<_> = context
+                    .sonar_obstacles
+                    .get(detection_time)
+                    .iter()
+                    .map(|sonar_obstacle| sonar_obstacle.position)
+                    .filter(|position| {
+                        goal_posts.iter().all(|goal_post| {
+                            distance(*goal_post, *position)
+                                > parameters.goal_post_measurement_matching_distance
+                        })
                     })
-                {
-                    self.update_hypotheses_with_measurement(
-                        sonar_obstacle.position,
-                        ObstacleKind::Unknown,
-                        *detection_time,
-                        context
-                            .obstacle_filter_parameters
-                            .sonar_goal_post_matching_distance,
-                        Matrix2::from_diagonal(
-                            &context.obstacle_filter_parameters.sonar_measurement_noise,
-                        ),
-                    );
-                }
+                    .collect();
+                self.update_hypotheses_with_measurements(
+                    &sonar_positions,
+                    ObstacleKind::Unknown,
+                    *detection_time,
+                    parameters.sonar_goal_post_matching_distance,
+                    Matrix2::from_diagonal(&parameters.sonar_measurement_noise),
+                    parameters,
+                );
             }
-            for foot_bumper_obstacle in context.foot_bumper_obstacles.get(detection_time) {
-                // TODO: Use a clever more intelligent metric
-
-                if context
-                    .obstacle_filter_parameters
-                    .use_foot_bumper_measurements
-                {
-                    self.update_hypotheses_with_measurement(
-                        foot_bumper_obstacle.position,
-                        ObstacleKind::Unknown,
-                        *detection_time,
-                        context
-                            .obstacle_filter_parameters
-                            .feet_detection_measurement_matching_distance,
-                        Matrix2::from_diagonal(
-                            &context.obstacle_filter_parameters.feet_measurement_noise,
-                        ),
-                    );
-                }
+            if parameters.use_foot_bumper_measurements {
+                let foot_bumper_positions: Vec<_> = context
+                    .foot_bumper_obstacles
+                    .get(detection_time)
+                    .iter()
+                    .map(|foot_bumper_obstacle| foot_bumper_obstacle.position)
+                    .collect();
+                self.update_hypotheses_with_measurements(
+                    &foot_bumper_positions,
+                    ObstacleKind::Unknown,
+                    *detection_time,
+                    parameters.feet_detection_measurement_matching_distance,
+                    Matrix2::from_diagonal(&parameters.feet_measurement_noise),
+                    parameters,
+                );
             }
         }
 
-        self.remove_hypotheses(
-            cycle_start_time,
-            context.obstacle_filter_parameters.hypothesis_timeout,
-            context.obstacle_filter_parameters.hypothesis_merge_distance,
-        );
+        self.remove_hypotheses(cycle_start_time, parameters);
 
         if self.last_primary_state == PrimaryState::Penalized
             && *context.primary_state != PrimaryState::Penalized
@@ -199,10 +223,7 @@ impl ObstacleFilter {
             .hypotheses
             .iter()
             .filter(|hypothesis| {
-                hypothesis.measurement_count
-                    > context
-                        .obstacle_filter_parameters
-                        .measurement_count_threshold
+                hypothesis.measurement_count > parameters.measurement_count_threshold
             })
             .map(|hypothesis| {
                 let (radius_at_hip_height, radius_at_foot_height) = match hypothesis.obstacle_kind {
@@ -210,7 +231,7 @@ impl ObstacleFilter {
                         *context.goal_post_obstacle_radius,
                         *context.goal_post_obstacle_radius,
                     ),
-                    ObstacleKind::Robot => (
+                    ObstacleKind::Robot | ObstacleKind::Teammate | ObstacleKind::Opponent => (
                         *context.robot_obstacle_radius_at_hip_height,
                         *context.robot_obstacle_radius_at_foot_height,
                     ),
@@ -221,10 +242,12 @@ impl ObstacleFilter {
                     _ => panic!("Unexpected obstacle radius"),
                 };
                 Obstacle {
-                    position: hypothesis.state.mean.framed().as_point(),
+                    position: point![hypothesis.state.mean.x, hypothesis.state.mean.y],
                     kind: hypothesis.obstacle_kind,
                     radius_at_hip_height,
                     radius_at_foot_height,
+                    velocity: vector![hypothesis.state.mean.z, hypothesis.state.mean.w]
+                        .cap_magnitude(parameters.maximum_robot_velocity),
                 }
             })
             .collect::<Vec<_>>();
@@ -247,16 +270,31 @@ impl ObstacleFilter {
     fn predict_hypotheses_with_odometry(
         &mut self,
         last_odometry_to_current_odometry: nalgebra::Isometry2<f32>,
-        process_noise: Matrix2<f32>,
+        time_step: Duration,
+        process_noise: Matrix4<f32>,
     ) {
+        let time_step = time_step.as_secs_f32();
+        let constant_velocity_prediction = matrix![
+            1.0, 0.0, time_step, 0.0;
+            0.0, 1.0, 0.0, time_step;
+            0.0, 0.0, 1.0, 0.0;
+            0.0, 0.0, 0.0, 1.0;
+        ];
+        let rotation = last_odometry_to_current_odometry
+            .rotation
+            .to_rotation_matrix();
+        let state_rotation = matrix![
+            rotation[(0, 0)], rotation[(0, 1)], 0.0, 0.0;
+            rotation[(1, 0)], rotation[(1, 1)], 0.0, 0.0;
+            0.0, 0.0, rotation[(0, 0)], rotation[(0, 1)];
+            0.0, 0.0, rotation[(1, 0)], rotation[(1, 1)];
+        ];
+        let state_prediction = constant_velocity_prediction * state_rotation;
+        let control_input_model = Matrix4x2::identity();
+        let odometry_translation = last_odometry_to_current_odometry.translation.vector;
         for hypothesis in self.hypotheses.iter_mut() {
-            let state_prediction = last_odometry_to_current_odometry
-                .rotation
-                .to_rotation_matrix();
-            let control_input_model = Matrix2::identity();
-            let odometry_translation = last_odometry_to_current_odometry.translation.vector;
             hypothesis.state.predict(
-                *state_prediction.matrix(),
+                state_prediction,
                 control_input_model,
                 odometry_translation,
                 process_noise,
@@ -264,44 +302,38 @@ impl ObstacleFilter {
         }
     }
 
-    fn update_hypotheses_with_measurement(
+    fn update_hypotheses_with_measurements(
         &mut self,
-        detected_position: Point2<Ground>,
+        detected_positions: &[Point2<Ground>],
         detected_obstacle_kind: ObstacleKind,
         detection_time: SystemTime,
         matching_distance: f32,
         measurement_noise: Matrix2<f32>,
+        parameters: &ObstacleFilterParameters,
     ) {
-        let mut matching_hypotheses = self
-            .hypotheses
-            .iter_mut()
-            .filter(|hypothesis| {
-                (hypothesis.state.mean - detected_position.inner.coords).norm() < matching_distance
-            })
-            .peekable();
-        if matching_hypotheses.peek().is_none() {
-            self.spawn_hypothesis(
-                detected_position,
-                detected_obstacle_kind,
-                detection_time,
-                measurement_noise,
-            );
-            return;
-        }
-        matching_hypotheses.for_each(|hypothesis| {
+        let assignments = associate(&self.hypotheses, detected_positions, matching_distance);
+        for (&detected_position, assignment) in detected_positions.iter().zip(assignments) {
+            let Some(hypothesis_index) = assignment else {
+                self.spawn_hypothesis(
+                    detected_position,
+                    detected_obstacle_kind,
+                    detection_time,
+                    measurement_noise,
+                    parameters.initial_velocity_covariance,
+                );
+                continue;
+            };
+            let hypothesis = &mut self.hypotheses[hypothesis_index];
             hypothesis.state.update(
-                Matrix2::identity(),
+                Matrix2x4::identity(),
                 detected_position.inner.coords,
                 measurement_noise * detected_position.coords().norm_squared(),
             );
-            hypothesis.obstacle_kind = match hypothesis.obstacle_kind {
-                ObstacleKind::Robot => hypothesis.obstacle_kind,
-                ObstacleKind::Unknown => detected_obstacle_kind,
-                _ => panic!("Unexpected obstacle kind"),
-            };
+            hypothesis.obstacle_kind =
+                combined_obstacle_kind(hypothesis.obstacle_kind, detected_obstacle_kind);
             hypothesis.measurement_count += 1;
             hypothesis.last_update = detection_time;
-        });
+        }
     }
 
     fn spawn_hypothesis(
@@ -310,30 +342,40 @@ impl ObstacleFilter {
         obstacle_kind: ObstacleKind,
         detection_time: SystemTime,
         initial_covariance: Matrix2<f32>,
+        initial_velocity_covariance: nalgebra::Vector2<f32>,
     ) {
-        let initial_state = detected_position.inner.coords;
+        let initial_state =
+            nalgebra::vector![detected_position.x(), detected_position.y(), 0.0, 0.0];
         let new_hypothesis = Hypothesis {
             state: MultivariateNormalDistribution {
                 mean: initial_state,
-                covariance: initial_covariance,
+                covariance: Matrix4::from_diagonal(&nalgebra::vector![
+                    initial_covariance[(0, 0)],
+                    initial_covariance[(1, 1)],
+                    initial_velocity_covariance.x,
+                    initial_velocity_covariance.y
+                ]),
             },
             obstacle_kind,
             measurement_count: 1,
+            first_seen: detection_time,
             last_update: detection_time,
         };
         self.hypotheses.push(new_hypothesis);
     }
 
-    fn remove_hypotheses(
-        &mut self,
-        now: SystemTime,
-        hypothesis_timeout: Duration,
-        merge_distance: f32,
-    ) {
+    fn remove_hypotheses(&mut self, now: SystemTime, parameters: &ObstacleFilterParameters) {
         self.hypotheses.retain(|hypothesis| {
+            let is_tentative =
+                hypothesis.measurement_count <= parameters.measurement_count_threshold;
+            let timeout = if is_tentative {
+                parameters.tentative_hypothesis_timeout
+            } else {
+                parameters.hypothesis_timeout
+            };
             now.duration_since(hypothesis.last_update)
                 .expect("Time has run backwards")
-                < hypothesis_timeout
+                < timeout
         });
         let mut deduplicated_hypotheses = Vec::<Hypothesis>::new();
         for hypothesis in self.hypotheses.drain(..) {
@@ -341,21 +383,33 @@ impl ObstacleFilter {
                 deduplicated_hypotheses
                     .iter_mut()
                     .find(|existing_hypothesis| {
-                        (existing_hypothesis.state.mean - hypothesis.state.mean).norm()
-                            < merge_distance
+                        (existing_hypothesis.state.mean.xy() - hypothesis.state.mean.xy()).norm()
+                            < parameters.hypothesis_merge_distance
                     });
             match hypothesis_in_merge_distance {
                 Some(existing_hypothesis) => {
                     existing_hypothesis.state.update(
-                        Matrix2::identity(),
+                        Matrix4::identity(),
                         hypothesis.state.mean,
                         hypothesis.state.covariance,
                     );
-                    existing_hypothesis.obstacle_kind = match existing_hypothesis.obstacle_kind {
-                        ObstacleKind::Robot => existing_hypothesis.obstacle_kind,
-                        ObstacleKind::Unknown => hypothesis.obstacle_kind,
-                        _ => panic!("Unexpected obstacle kind"),
-                    };
+                    existing_hypothesis.obstacle_kind =
+                        if existing_hypothesis.last_update <= hypothesis.last_update {
+                            combined_obstacle_kind(
+                                existing_hypothesis.obstacle_kind,
+                                hypothesis.obstacle_kind,
+                            )
+                        } else {
+                            combined_obstacle_kind(
+                                hypothesis.obstacle_kind,
+                                existing_hypothesis.obstacle_kind,
+                            )
+                        };
+                    existing_hypothesis.measurement_count += hypothesis.measurement_count;
+                    existing_hypothesis.first_seen =
+                        existing_hypothesis.first_seen.min(hypothesis.first_seen);
+                    existing_hypothesis.last_update =
+                        existing_hypothesis.last_update.max(hypothesis.last_update);
                 }
                 None => deduplicated_hypotheses.push(hypothesis),
             }
@@ -364,6 +418,61 @@ impl ObstacleFilter {
     }
 }
 
+/// Greedy global nearest neighbour association
+///
+/// Pairs within the matching distance are assigned in order of increasing distance, so every
+/// hypothesis receives at most one measurement of a sensor per detection time. Returns the
+/// assigned hypothesis index per measurement.
+fn associate(
+    hypotheses: &[Hypothesis],
+    detected_positions: &[Point2<Ground>],
+    matching_distance: f32,
+) -> Vec<Option<usize>> {
+    let mut candidates: Vec<_> = iproduct!(
+        hypotheses.iter().enumerate(),
+        detected_positions.iter().enumerate()
+    )
+    .map(
+        |((hypothesis_index, hypothesis), (measurement_index, position))| {
+            let distance = (hypothesis.state.mean.xy() - position.inner.coords).norm();
+            (distance, hypothesis_index, measurement_index)
+        },
+    )
+    .filter(|(distance, _, _)| *distance < matching_distance)
+    .collect();
+    candidates.sort_by(|(left, _, _), (right, _, _)| left.total_cmp(right));
+
+    let mut assignments = vec![None; detected_positions.len()];
+    let mut is_hypothesis_assigned = vec![false; hypotheses.len()];
+    for (_, hypothesis_index, measurement_index) in candidates {
+        if is_hypothesis_assigned[hypothesis_index] || assignments[measurement_index].is_some() {
+            continue;
+        }
+        is_hypothesis_assigned[hypothesis_index] = true;
+        assignments[measurement_index] = Some(hypothesis_index);
+    }
+    assignments
+}
+
+fn obstacle_kind_of_team(team: Team) -> ObstacleKind {
+    match team {
+        Team::Hulks => ObstacleKind::Teammate,
+        Team::Opponent => ObstacleKind::Opponent,
+        Team::Uncertain => ObstacleKind::Robot,
+    }
+}
+
+/// Newer teammate and opponent identities replace older ones, anonymous detections only refine
+/// unknown obstacles
+fn combined_obstacle_kind(tracked: ObstacleKind, measured: ObstacleKind) -> ObstacleKind {
+    match (tracked, measured) {
+        (_, ObstacleKind::Teammate | ObstacleKind::Opponent) | (ObstacleKind::Unknown, _) => {
+            measured
+        }
+        _ => tracked,
+    }
+}
+
 fn calculate_goal_post_positions(
     ground_to_field: Option<Isometry2<Ground, Field>>,
     field_dimensions: &FieldDimensions,
@@ -387,3 +496,228 @@ fn calculate_goal_post_positions(
         .flatten()
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    fn parameters() -> ObstacleFilterParameters {
+        ObstacleFilterParameters {
+            hypothesis_timeout: Duration::from_secs(2),
+            tentative_hypothesis_timeout: Duration::from_millis(500),
+            maximum_robot_velocity: 0.4,
+            hypothesis_merge_distance: 0.3,
+            process_noise: nalgebra::vector![0.005, 0.005, 0.01, 0.01],
+            initial_velocity_covariance: nalgebra::vector![0.1, 0.1],
+            measurement_count_threshold: 10,
+            network_robot_measurement_matching_distance: 0.5,
+            ..Default::default()
+        }
+    }
+
+    fn filter() -> ObstacleFilter {
+        ObstacleFilter {
+            hypotheses: Vec::new(),
+            last_primary_state: PrimaryState::Playing,
+            last_prediction_time: None,
+        }
+    }
+
+    #[test]
+    fn close_measurements_are_assigned_to_distinct_hypotheses() {
+        let mut filter = filter();
+        let parameters = parameters();
+        let noise = Matrix2::identity();
+        filter.spawn_hypothesis(
+            point![1.0, 0.0],
+            ObstacleKind::Robot,
+            UNIX_EPOCH,
+            noise,
+            parameters.initial_velocity_covariance,
+        );
+        filter.spawn_hypothesis(
+            point![1.0, 0.4],
+            ObstacleKind::Robot,
+            UNIX_EPOCH,
+            noise,
+            parameters.initial_velocity_covariance,
+        );
+
+        // the first measurement is closer to the second hypothesis, but the second measurement
+        // fits it even better
+        let assignments = associate(
+            &filter.hypotheses,
+            &[point![1.0, 0.25], point![1.0, 0.38]],
+            0.5,
+        );
+
+        assert_eq!(assignments, vec![Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn velocity_of_walking_robot_is_estimated() {
+        let mut filter = filter();
+        let parameters = parameters();
+        let noise = Matrix2::from_diagonal(&nalgebra::vector![0.001, 0.001]);
+        let velocity = nalgebra::vector![-0.2, 0.1];
+        for cycle in 0..200 {
+            let time = Duration::from_millis(12 * cycle);
+            let position = nalgebra::vector![2.0, -0.5] + velocity * time.as_secs_f32();
+            filter.predict_hypotheses_with_odometry(
+                nalgebra::Isometry2::identity(),
+                Duration::from_millis(12),
+                Matrix4::from_diagonal(&parameters.process_noise),
+            );
+            filter.update_hypotheses_with_measurements(
+                &[point![position.x, position.y]],
+                ObstacleKind::Robot,
+                UNIX_EPOCH + time,
+                0.3,
+                noise,
+                &parameters,
+            );
+        }
+
+        assert_eq!(filter.hypotheses.len(), 1);
+        assert_relative_eq!(
+            filter.hypotheses[0]
+                .state
+                .mean
+                .fixed_rows::<2>(2)
+                .into_owned(),
+            velocity,
+            epsilon = 0.02
+        );
+    }
+
+    #[test]
+    fn robots_are_classified_by_teammate_messages() {
+        let mut filter = filter();
+        let parameters = parameters();
+        let noise = Matrix2::identity();
+        let feet = [point![1.0, 1.0], point![2.0, -1.0]];
+        filter.update_hypotheses_with_measurements(
+            &feet,
+            ObstacleKind::Robot,
+            UNIX_EPOCH,
+            0.3,
+            noise,
+            &parameters,
+        );
+        filter.update_hypotheses_with_measurements(
+            &[point![1.1, 1.0]],
+            ObstacleKind::Teammate,
+            UNIX_EPOCH + Duration::from_secs(1),
+            0.3,
+            noise,
+            &parameters,
+        );
+
+        assert_eq!(filter.hypotheses[0].obstacle_kind, ObstacleKind::Teammate);
+        assert_eq!(filter.hypotheses[1].obstacle_kind, ObstacleKind::Robot);
+    }
+
+    #[test]
+    fn silent_teammate_is_not_classified_as_opponent() {
+        let mut filter = filter();
+        let parameters = parameters();
+        // a teammate without network messages is only ever seen by its feet
+        for cycle in 0..500 {
+            filter.update_hypotheses_with_measurements(
+                &[point![2.0, -1.0]],
+                ObstacleKind::Robot,
+                UNIX_EPOCH + Duration::from_millis(12 * cycle),
+                0.3,
+                Matrix2::identity(),
+                &parameters,
+            );
+        }
+
+        assert_eq!(filter.hypotheses.len(), 1);
+        assert_eq!(filter.hypotheses[0].obstacle_kind, ObstacleKind::Robot);
+    }
+
+    #[test]
+    fn newer_identities_replace_older_ones() {
+        let mut filter = filter();
+        let parameters = parameters();
+        let noise = Matrix2::identity();
+        filter.update_hypotheses_with_measurements(
+            &[point![1.0, 1.0]],
+            ObstacleKind::Teammate,
+            UNIX_EPOCH,
+            0.3,
+            noise,
+            &parameters,
+        );
+        filter.update_hypotheses_with_measurements(
+            &[point![1.05, 1.0]],
+            ObstacleKind::Opponent,
+            UNIX_EPOCH + Duration::from_millis(100),
+            0.3,
+            noise,
+            &parameters,
+        );
+        filter.update_hypotheses_with_measurements(
+            &[point![1.05, 1.0]],
+            ObstacleKind::Robot,
+            UNIX_EPOCH + Duration::from_millis(200),
+            0.3,
+            noise,
+            &parameters,
+        );
+
+        assert_eq!(filter.hypotheses.len(), 1);
+        assert_eq!(filter.hypotheses[0].obstacle_kind, ObstacleKind::Opponent);
+    }
+
+    #[test]
+    fn detected_opponent_jersey_classifies_robot_immediately() {
+        let mut filter = filter();
+        let parameters = parameters();
+        let noise = Matrix2::identity();
+        filter.update_hypotheses_with_measurements(
+            &[point![1.0, 1.0]],
+            ObstacleKind::Robot,
+            UNIX_EPOCH,
+            0.3,
+            noise,
+            &parameters,
+        );
+        filter.update_hypotheses_with_measurements(
+            &[point![1.05, 1.0]],
+            obstacle_kind_of_team(Team::Opponent),
+            UNIX_EPOCH + Duration::from_millis(100),
+            0.3,
+            noise,
+            &parameters,
+        );
+
+        assert_eq!(filter.hypotheses.len(), 1);
+        assert_eq!(filter.hypotheses[0].obstacle_kind, ObstacleKind::Opponent);
+    }
+
+    #[test]
+    fn tentative_hypotheses_are_removed_earlier() {
+        let mut filter = filter();
+        let parameters = parameters();
+        filter.update_hypotheses_with_measurements(
+            &[point![1.0, 1.0], point![3.0, 0.0]],
+            ObstacleKind::Robot,
+            UNIX_EPOCH,
+            0.3,
+            Matrix2::identity(),
+            &parameters,
+        );
+        filter.hypotheses[1].measurement_count = 20;
+
+        filter.remove_hypotheses(UNIX_EPOCH + Duration::from_secs(1), &parameters);
+
+        assert_eq!(filter.hypotheses.len(), 1);
+        assert_relative_eq!(filter.hypotheses[0].state.mean.x, 3.0);
+    }
+}
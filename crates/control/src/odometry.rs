@@ -2,10 +2,11 @@ use color_eyre::Result;
 use context_attribute::context;
 use coordinate_systems::{Field, Robot};
 use framework::{AdditionalOutput, MainOutput};
-use linear_algebra::{Orientation2, Vector2};
-use nalgebra::{Isometry2, Translation2};
+use linear_algebra::{vector, Orientation2, Vector2};
+use nalgebra::Isometry2;
 use serde::{Deserialize, Serialize};
 use types::{
+    parameters::OdometryCorrectionParameters,
     robot_kinematics::RobotKinematics,
     support_foot::{Side, SupportFoot},
 };
@@ -28,7 +29,7 @@ pub struct CycleContext {
     robot_orientation: Input<Orientation2<Field>, "robot_orientation">,
     support_foot: Input<SupportFoot, "support_foot">,
 
-    odometry_scale_factor: Parameter<Vector2<Robot>, "odometry.odometry_scale_factor">,
+    correction: Parameter<OdometryCorrectionParameters, "odometry.correction">,
 }
 
 #[context]
@@ -56,17 +57,20 @@ impl Odometry {
             self.last_left_sole_to_right_sole,
         );
         self.last_left_sole_to_right_sole = left_sole_to_right_sole;
-        let corrected_offset_to_last_position =
-            offset_to_last_position.component_mul(*context.odometry_scale_factor);
 
         let orientation_offset = self
             .last_orientation
             .rotation_to(*context.robot_orientation);
         self.last_orientation = *context.robot_orientation;
 
-        let current_odometry_to_last_odometry = Isometry2::from_parts(
-            Translation2::from(corrected_offset_to_last_position.inner),
-            orientation_offset.inner,
+        let (corrected_offset_to_last_position, corrected_orientation_offset) = correct_odometry(
+            offset_to_last_position,
+            orientation_offset.angle(),
+            context.correction,
+        );
+        let current_odometry_to_last_odometry = Isometry2::new(
+            corrected_offset_to_last_position.inner,
+            corrected_orientation_offset,
         );
         self.accumulated_odometry = current_odometry_to_last_odometry * self.accumulated_odometry;
         context
@@ -90,3 +94,18 @@ fn calculate_offset_to_last_position(
         None => Vector2::zeros(),
     }
 }
+
+pub fn correct_odometry(
+    offset: Vector2<Robot>,
+    rotation: f32,
+    correction: &OdometryCorrectionParameters,
+) -> (Vector2<Robot>, f32) {
+    let corrected_offset = vector![
+        correction.forward_scale * offset.x(),
+        correction.lateral_per_forward * offset.x() + correction.lateral_scale * offset.y()
+    ];
+    let corrected_rotation = correction.rotation_scale * rotation
+        + correction.rotation_per_forward * offset.x()
+        + correction.rotation_per_lateral * offset.y();
+    (corrected_offset, corrected_rotation)
+}
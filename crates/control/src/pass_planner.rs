@@ -0,0 +1,438 @@
+use std::time::{Duration, SystemTime};
+
+use color_eyre::{eyre::ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use context_attribute::context;
+use coordinate_systems::{Field, Ground};
+use framework::{AdditionalOutput, MainOutput, PerceptionInput};
+use geometry::line_segment::LineSegment;
+use linear_algebra::{distance, point, Isometry2, Point2};
+use spl_network_messages::{PassAnnouncement, PlayerNumber};
+use types::{
+    ball_trajectory::BallTrajectory,
+    cycle_time::CycleTime,
+    field_dimensions::FieldDimensions,
+    messages::IncomingMessage,
+    obstacles::{Obstacle, ObstacleKind},
+    parameters::PassPlannerParameters,
+    pass::{AnnouncedPass, PassCandidate, PassTarget},
+    players::Players,
+    primary_state::PrimaryState,
+    roles::Role,
+    world_state::BallState,
+};
+
+#[derive(Deserialize, Serialize)]
+pub struct PassPlanner {
+    teammates: Players<Option<Teammate>>,
+    expected_pass: Option<ExpectedPass>,
+    last_receiver: Option<PlayerNumber>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct Teammate {
+    position: Point2<Field>,
+    received_at: SystemTime,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct ExpectedPass {
+    passer: PlayerNumber,
+    announcement: PassAnnouncement,
+    received_at: SystemTime,
+}
+
+#[context]
+pub struct CreationContext {
+    parameters: Parameter<PassPlannerParameters, "pass_planner">,
+    pose_message_send_interval: Parameter<Duration, "spl_network.pose_message_send_interval">,
+}
+
+#[context]
+pub struct CycleContext {
+    ground_to_field: Input<Option<Isometry2<Ground, Field>>, "ground_to_field?">,
+    ball_state: Input<Option<BallState>, "ball_state?">,
+    obstacles: Input<Vec<Obstacle>, "obstacles">,
+    role: Input<Role, "role">,
+    primary_state: Input<PrimaryState, "primary_state">,
+    cycle_time: Input<CycleTime, "cycle_time">,
+    network_message: PerceptionInput<IncomingMessage, "SplNetwork", "message">,
+    announced_pass: CyclerState<AnnouncedPass, "announced_pass">,
+
+    field_dimensions: Parameter<FieldDimensions, "field_dimensions">,
+    parameters: Parameter<PassPlannerParameters, "pass_planner">,
+    rolling_deceleration: Parameter<f32, "ball_filter.rolling_deceleration">,
+    player_number: Parameter<PlayerNumber, "player_number">,
+
+    pass_candidates: AdditionalOutput<Vec<PassCandidate>, "pass_candidates">,
+}
+
+#[context]
+#[derive(Default)]
+pub struct MainOutputs {
+    pub pass_target: MainOutput<Option<PassTarget>>,
+    pub expected_pass: MainOutput<Option<PassAnnouncement>>,
+}
+
+impl PassPlanner {
+    pub fn new(context: CreationContext) -> Result<Self> {
+        // non-strikers only share their pose every send interval and would drop out in between
+        ensure!(
+            context.parameters.maximum_teammate_message_age > *context.pose_message_send_interval,
+            "pass_planner.maximum_teammate_message_age ({:?}) has to exceed spl_network.pose_message_send_interval ({:?})",
+            context.parameters.maximum_teammate_message_age,
+            context.pose_message_send_interval
+        );
+        Ok(Self {
+            teammates: Default::default(),
+            expected_pass: None,
+            last_receiver: None,
+        })
+    }
+
+    pub fn cycle(&mut self, mut context: CycleContext) -> Result<MainOutputs> {
+        let now = context.cycle_time.start_time;
+        self.update_teammates(&context);
+
+        let announcement_timeout = context.parameters.announcement_timeout;
+        let expected_pass = self
+            .expected_pass
+            .filter(|expected_pass| {
+                now.duration_since(expected_pass.received_at)
+                    .map_or(true, |age| age <= announcement_timeout)
+            })
+            .map(|expected_pass| expected_pass.announcement);
+
+        let is_passing = context.parameters.enable
+            && *context.role == Role::Striker
+            && *context.primary_state == PrimaryState::Playing;
+        let (candidates, pass_target) =
+            match (is_passing, context.ground_to_field, context.ball_state) {
+                (true, Some(ground_to_field), Some(ball_state)) => {
+                    let ball_position = ball_state.ball_in_field;
+                    let candidates =
+                        self.evaluate_teammates(&context, *ground_to_field, ball_position);
+                    let pass_target = candidates
+                        .iter()
+                        .filter(|candidate| candidate.is_feasible)
+                        .max_by(|left, right| {
+                            self.score_with_hysteresis(left, context.parameters)
+                                .total_cmp(&self.score_with_hysteresis(right, context.parameters))
+                        })
+                        .map(|candidate| PassTarget {
+                            receiver: candidate.receiver,
+                            position: candidate.target,
+                            strength: kick_speed(
+                                distance(ball_position, candidate.target),
+                                *context.rolling_deceleration,
+                            ) / context.parameters.ball_speed_per_kick_strength,
+                        });
+                    (candidates, pass_target)
+                }
+                _ => (Vec::new(), None),
+            };
+        context
+            .pass_candidates
+            .fill_if_subscribed(|| candidates.clone());
+
+        self.last_receiver = pass_target.map(|pass_target| pass_target.receiver);
+        context.announced_pass.announcement = pass_target.map(|pass_target| PassAnnouncement {
+            receiver: pass_target.receiver,
+            target: pass_target.position,
+        });
+
+        Ok(MainOutputs {
+            pass_target: pass_target.into(),
+            expected_pass: expected_pass.into(),
+        })
+    }
+
+    fn update_teammates(&mut self, context: &CycleContext) {
+        for (&receive_time, messages) in &context.network_message.persistent {
+            for message in messages {
+                let IncomingMessage::Spl(message) = message else {
+                    continue;
+                };
+                if message.player_number == *context.player_number {
+                    continue;
+                }
+                self.teammates[message.player_number] = (!message.fallen).then_some(Teammate {
+                    position: message.pose.position(),
+                    received_at: receive_time,
+                });
+                match message.pass {
+                    Some(announcement) if announcement.receiver == *context.player_number => {
+                        self.expected_pass = Some(ExpectedPass {
+                            passer: message.player_number,
+                            announcement,
+                            received_at: receive_time,
+                        });
+                    }
+                    _ => {
+                        // the passer changed its mind
+                        if self.expected_pass.is_some_and(|expected_pass| {
+                            expected_pass.passer == message.player_number
+                        }) {
+                            self.expected_pass = None;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn evaluate_teammates(
+        &self,
+        context: &CycleContext,
+        ground_to_field: Isometry2<Ground, Field>,
+        ball_position: Point2<Field>,
+    ) -> Vec<PassCandidate> {
+        let now = context.cycle_time.start_time;
+        let maximum_age = context.parameters.maximum_teammate_message_age;
+        let opponents: Vec<_> = context
+            .obstacles
+            .iter()
+            .filter(|obstacle| {
+                !matches!(obstacle.kind, ObstacleKind::Ball | ObstacleKind::Teammate)
+            })
+            .map(|obstacle| {
+                (
+                    ground_to_field * obstacle.position,
+                    obstacle.radius_at_foot_height + context.field_dimensions.ball_radius,
+                )
+            })
+            .collect();
+        self.teammates
+            .iter()
+            .filter_map(|(player_number, teammate)| {
+                let teammate = teammate.filter(|teammate| {
+                    now.duration_since(teammate.received_at)
+                        .map_or(true, |age| age <= maximum_age)
+                })?;
+                evaluate_pass(
+                    player_number,
+                    teammate.position,
+                    ball_position,
+                    &opponents,
+                    context.field_dimensions,
+                    context.parameters,
+                    *context.rolling_deceleration,
+                )
+            })
+            .collect()
+    }
+
+    fn score_with_hysteresis(
+        &self,
+        candidate: &PassCandidate,
+        parameters: &PassPlannerParameters,
+    ) -> f32 {
+        if self.last_receiver == Some(candidate.receiver) {
+            candidate.score + parameters.receiver_hysteresis
+        } else {
+            candidate.score
+        }
+    }
+}
+
+/// Evaluates a pass in front of the receiver, opponents are given as positions and the distance
+/// at which they touch the ball, passes with non-finite timings are not evaluated
+fn evaluate_pass(
+    receiver: PlayerNumber,
+    receiver_position: Point2<Field>,
+    ball_position: Point2<Field>,
+    opponents: &[(Point2<Field>, f32)],
+    field_dimensions: &FieldDimensions,
+    parameters: &PassPlannerParameters,
+    rolling_deceleration: f32,
+) -> Option<PassCandidate> {
+    let opponent_goal_center = point![field_dimensions.length / 2.0, 0.0];
+    let target = match (opponent_goal_center - receiver_position).try_normalize(f32::EPSILON) {
+        Some(towards_goal) => receiver_position + towards_goal * parameters.lead_distance,
+        None => receiver_position,
+    };
+    let pass_distance = distance(ball_position, target);
+    let trajectory = pass_trajectory(ball_position, target, rolling_deceleration);
+    let ball_travel_time = trajectory.time_to_rest().as_secs_f32();
+    let receiver_time_to_reach =
+        distance(receiver_position, target) / parameters.receiver_walking_speed;
+
+    let pass_line = LineSegment::new(ball_position, target);
+    let interception_margin = opponents
+        .iter()
+        .map(|&(position, reach)| {
+            let closest_point = pass_line.closest_point(position);
+            let walking_distance = (distance(position, closest_point) - reach).max(0.0);
+            let ball_time = trajectory
+                .time_to_reach(closest_point)
+                .map_or(ball_travel_time, |time| time.as_secs_f32());
+            walking_distance / parameters.opponent_walking_speed - ball_time
+        })
+        .fold(ball_travel_time, f32::min);
+    let progress =
+        distance(ball_position, opponent_goal_center) - distance(target, opponent_goal_center);
+
+    let is_feasible = (parameters.minimum_pass_distance..=parameters.maximum_pass_distance)
+        .contains(&pass_distance)
+        && field_dimensions.is_inside_field(target)
+        && progress >= parameters.minimum_progress
+        && receiver_time_to_reach <= ball_travel_time
+        && interception_margin >= parameters.minimum_interception_margin
+        && trajectory.velocity.norm() / parameters.ball_speed_per_kick_strength
+            <= parameters.maximum_kick_strength;
+
+    Some(PassCandidate {
+        receiver,
+        target,
+        ball_travel_time: Duration::try_from_secs_f32(ball_travel_time).ok()?,
+        receiver_time_to_reach: Duration::try_from_secs_f32(receiver_time_to_reach).ok()?,
+        interception_margin,
+        score: progress + parameters.interception_margin_weight * interception_margin,
+        is_feasible,
+    })
+}
+
+/// Initial speed of a ball which comes to rest after the distance
+fn kick_speed(distance: f32, rolling_deceleration: f32) -> f32 {
+    (2.0 * rolling_deceleration * distance).sqrt()
+}
+
+/// Trajectory of a ball kicked from the ball position to come to rest at the target
+fn pass_trajectory(
+    ball_position: Point2<Field>,
+    target: Point2<Field>,
+    rolling_deceleration: f32,
+) -> BallTrajectory<Field> {
+    let direction = (target - ball_position)
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_default();
+    let speed = kick_speed(distance(ball_position, target), rolling_deceleration);
+    BallTrajectory {
+        position: ball_position,
+        velocity: direction * speed,
+        deceleration: rolling_deceleration,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    fn parameters() -> PassPlannerParameters {
+        PassPlannerParameters {
+            enable: true,
+            maximum_teammate_message_age: Duration::from_secs(6),
+            announcement_timeout: Duration::from_secs(3),
+            minimum_pass_distance: 1.0,
+            maximum_pass_distance: 4.0,
+            lead_distance: 0.3,
+            minimum_progress: 1.0,
+            receiver_walking_speed: 0.2,
+            opponent_walking_speed: 0.3,
+            minimum_interception_margin: 0.5,
+            interception_margin_weight: 0.2,
+            receiver_hysteresis: 0.5,
+            ball_speed_per_kick_strength: 2.5,
+            maximum_kick_strength: 1.5,
+        }
+    }
+
+    fn field_dimensions() -> FieldDimensions {
+        FieldDimensions {
+            ball_radius: 0.05,
+            length: 9.0,
+            width: 6.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ball_comes_to_rest_at_target() {
+        let trajectory = pass_trajectory(point![1.0, 0.0], point![1.0, 3.0], 0.4);
+
+        assert_relative_eq!(
+            trajectory.resting_position(),
+            point![1.0, 3.0],
+            epsilon = 0.001
+        );
+        assert_relative_eq!(trajectory.rolling_distance(), 3.0, epsilon = 0.001);
+        assert!(
+            trajectory
+                .time_to_reach(point![1.0, 1.5])
+                .expect("midpoint should be reached")
+                < trajectory.time_to_rest() / 2
+        );
+    }
+
+    #[test]
+    fn forward_pass_to_free_teammate_is_feasible() {
+        let candidate = evaluate_pass(
+            PlayerNumber::Four,
+            point![2.5, 1.0],
+            point![0.0, 0.0],
+            &[(point![1.0, -2.0], 0.35)],
+            &field_dimensions(),
+            &parameters(),
+            0.4,
+        )
+        .expect("pass timings should be finite");
+
+        assert!(candidate.is_feasible);
+        assert!(candidate.target.x() > 2.5);
+    }
+
+    #[test]
+    fn opponent_on_pass_line_makes_pass_infeasible() {
+        let candidate = evaluate_pass(
+            PlayerNumber::Four,
+            point![2.5, 1.0],
+            point![0.0, 0.0],
+            &[(point![1.25, 0.5], 0.35)],
+            &field_dimensions(),
+            &parameters(),
+            0.4,
+        )
+        .expect("pass timings should be finite");
+
+        assert!(!candidate.is_feasible);
+        assert!(candidate.interception_margin < 0.0);
+    }
+
+    #[test]
+    fn backward_pass_is_infeasible() {
+        let candidate = evaluate_pass(
+            PlayerNumber::Two,
+            point![-2.5, 0.0],
+            point![0.0, 0.0],
+            &[],
+            &field_dimensions(),
+            &parameters(),
+            0.4,
+        )
+        .expect("pass timings should be finite");
+
+        assert!(!candidate.is_feasible);
+    }
+
+    #[test]
+    fn standing_receiver_is_skipped() {
+        let parameters = PassPlannerParameters {
+            receiver_walking_speed: 0.0,
+            ..parameters()
+        };
+
+        assert!(evaluate_pass(
+            PlayerNumber::Four,
+            point![2.5, 1.0],
+            point![0.0, 0.0],
+            &[],
+            &field_dimensions(),
+            &parameters,
+            0.4,
+        )
+        .is_none());
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use color_eyre::{eyre::eyre, Result};
 use geometry::{arc::Arc, circle::Circle, direction::Direction, line_segment::LineSegment};
 use linear_algebra::{distance, point, vector, Isometry2, Orientation2, Point2};
@@ -80,6 +82,26 @@ impl PathPlanner {
         self.obstacles.extend(new_obstacles);
     }
 
+    /// Additionally avoids moving obstacles at their position after the prediction horizon
+    pub fn with_predicted_obstacles(
+        &mut self,
+        obstacles: &[Obstacle],
+        own_robot_radius: f32,
+        prediction_horizon: Duration,
+    ) {
+        let new_obstacles = obstacles
+            .iter()
+            .filter(|obstacle| obstacle.velocity.norm_squared() > f32::EPSILON)
+            .map(|obstacle| {
+                let center =
+                    obstacle.position + obstacle.velocity * prediction_horizon.as_secs_f32();
+                let radius = obstacle.radius_at_hip_height + own_robot_radius;
+                PathObstacle::from(PathObstacleShape::Circle(Circle { center, radius }))
+            });
+
+        self.obstacles.extend(new_obstacles);
+    }
+
     pub fn with_rule_obstacles(
         &mut self,
         field_to_robot: Isometry2<Field, Ground>,
@@ -1,4 +1,8 @@
-use std::time::{Duration, SystemTime};
+use std::{
+    f32::consts::FRAC_PI_4,
+    iter::repeat,
+    time::{Duration, SystemTime},
+};
 
 use color_eyre::{eyre::WrapErr, Result};
 use serde::{Deserialize, Serialize};
@@ -7,9 +11,10 @@ use context_attribute::context;
 use coordinate_systems::{Field, Ground};
 use framework::{MainOutput, PerceptionInput};
 use hardware::NetworkInterface;
-use linear_algebra::{Isometry2, Point2, Vector};
+use linear_algebra::{distance, point, Isometry2, Point2, Pose, UnitComplex, Vector, Vector2};
 use spl_network_messages::{
     GameControllerReturnMessage, GamePhase, HulkMessage, Penalty, PlayerNumber, Team,
+    WhistleObservation,
 };
 use types::{
     ball_position::BallPosition,
@@ -17,25 +22,42 @@ use types::{
     fall_state::FallState,
     field_dimensions::FieldDimensions,
     filtered_game_controller_state::FilteredGameControllerState,
+    filtered_whistle::HeardWhistle,
     initial_pose::InitialPose,
     messages::{IncomingMessage, OutgoingMessage},
-    parameters::SplNetworkParameters,
+    parameters::{
+        CostBasedRoleAssignmentParameters, RoleAssignmentMode, RolePositionsParameters,
+        SplNetworkParameters,
+    },
+    pass::AnnouncedPass,
     players::Players,
     primary_state::PrimaryState,
     roles::Role,
 };
 
-use crate::localization::generate_initial_pose;
+use crate::{hungarian, localization::generate_initial_pose};
 
 #[derive(Deserialize, Serialize)]
 pub struct RoleAssignment {
     last_received_spl_striker_message: Option<SystemTime>,
     last_system_time_transmitted_game_controller_return_message: Option<SystemTime>,
     last_transmitted_spl_striker_message: Option<SystemTime>,
+    last_transmitted_whistle: Option<SystemTime>,
+    last_transmitted_pose_message: Option<SystemTime>,
+    last_transmitted_pass_receiver: Option<PlayerNumber>,
     role: Role,
     role_initialized: bool,
     team_ball: Option<BallPosition<Field>>,
     last_time_keeper_penalized: Option<SystemTime>,
+    teammates: Players<Option<TeammateStatus>>,
+    cost_based_assignment: Players<Option<Role>>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct TeammateStatus {
+    pose: Pose<Field>,
+    fallen: bool,
+    last_update: SystemTime,
 }
 
 #[context]
@@ -47,18 +69,25 @@ pub struct CycleContext {
     fall_state: Input<FallState, "fall_state">,
     filtered_game_controller_state:
         Input<Option<FilteredGameControllerState>, "filtered_game_controller_state?">,
+    heard_whistle: Input<Option<HeardWhistle>, "heard_whistle?">,
     primary_state: Input<PrimaryState, "primary_state">,
     ground_to_field: Input<Option<Isometry2<Ground, Field>>, "ground_to_field?">,
+    localization_confidence: Input<Option<f32>, "localization_confidence?">,
     cycle_time: Input<CycleTime, "cycle_time">,
     network_message: PerceptionInput<IncomingMessage, "SplNetwork", "message">,
     time_to_reach_kick_position: CyclerState<Duration, "time_to_reach_kick_position">,
+    announced_pass: CyclerState<AnnouncedPass, "announced_pass">,
 
     field_dimensions: Parameter<FieldDimensions, "field_dimensions">,
     forced_role: Parameter<Option<Role>, "role_assignment.forced_role?">,
     keeper_replacementkeeper_switch_time:
         Parameter<Duration, "role_assignment.keeper_replacementkeeper_switch_time">,
+    mode: Parameter<RoleAssignmentMode, "role_assignment.mode">,
+    cost_based: Parameter<CostBasedRoleAssignmentParameters, "role_assignment.cost_based">,
     initial_poses: Parameter<Players<InitialPose>, "localization.initial_poses">,
     optional_roles: Parameter<Vec<Role>, "behavior.optional_roles">,
+    pass_planner_enable: Parameter<bool, "pass_planner.enable">,
+    role_positions: Parameter<RolePositionsParameters, "behavior.role_positions">,
     player_number: Parameter<PlayerNumber, "player_number">,
     spl_network: Parameter<SplNetworkParameters, "spl_network">,
 
@@ -79,10 +108,15 @@ impl RoleAssignment {
             last_received_spl_striker_message: None,
             last_system_time_transmitted_game_controller_return_message: None,
             last_transmitted_spl_striker_message: None,
+            last_transmitted_whistle: None,
+            last_transmitted_pose_message: None,
+            last_transmitted_pass_receiver: None,
             role: Role::Striker,
             role_initialized: false,
             team_ball: None,
             last_time_keeper_penalized: None,
+            teammates: Default::default(),
+            cost_based_assignment: Default::default(),
         })
     }
 
@@ -137,6 +171,20 @@ impl RoleAssignment {
             self.role_initialized = true;
             self.last_received_spl_striker_message = Some(cycle_start_time);
             self.team_ball = None;
+
+            // until teammates share their pose, they are assumed at their initial pose
+            let mut teammates = self.teammates;
+            for (player_number, _) in self.teammates.iter() {
+                teammates[player_number] = Some(TeammateStatus {
+                    pose: generate_initial_pose(
+                        &context.initial_poses[player_number],
+                        context.field_dimensions,
+                    ),
+                    fallen: false,
+                    last_update: cycle_start_time,
+                });
+            }
+            self.teammates = teammates;
         }
 
         let send_game_controller_return_message = self
@@ -189,7 +237,7 @@ impl RoleAssignment {
 
         let mut team_ball = self.team_ball;
 
-        if spl_striker_message_timeout {
+        if spl_striker_message_timeout && *context.mode == RoleAssignmentMode::StateMachine {
             match role {
                 Role::Keeper => {
                     team_ball = None;
@@ -220,8 +268,92 @@ impl RoleAssignment {
                 IncomingMessage::GameController(_) => None,
                 IncomingMessage::Spl(message) => Some(message),
             })
+            .filter(|message| !is_non_striker_message(message))
             .peekable();
-        if spl_messages.peek().is_none() {
+        if *context.mode == RoleAssignmentMode::CostBased {
+            for spl_message in context
+                .network_message
+                .persistent
+                .values()
+                .flatten()
+                .filter_map(|message| match message {
+                    IncomingMessage::GameController(_) => None,
+                    IncomingMessage::Spl(message) => Some(message),
+                })
+                .filter(|message| message.player_number != *context.player_number)
+            {
+                network_robot_obstacles
+                    .push(ground_to_field.inverse() * spl_message.pose.position());
+                remember_shared_information(
+                    spl_message,
+                    cycle_start_time,
+                    &mut self.teammates,
+                    &mut team_ball,
+                );
+            }
+            team_ball = team_ball.filter(|team_ball| {
+                cycle_start_time
+                    .duration_since(team_ball.last_seen)
+                    .is_ok_and(|age| age <= context.spl_network.striker_trusts_team_ball)
+            });
+
+            let previous_role = role;
+            match context.filtered_game_controller_state {
+                Some(FilteredGameControllerState {
+                    game_phase:
+                        GamePhase::PenaltyShootout {
+                            kicking_team: Team::Hulks,
+                        },
+                    ..
+                }) => {
+                    role = Role::Striker;
+                    send_spl_striker_message = false;
+                }
+                Some(FilteredGameControllerState {
+                    game_phase:
+                        GamePhase::PenaltyShootout {
+                            kicking_team: Team::Opponent,
+                        },
+                    ..
+                }) => {
+                    role = Role::Keeper;
+                    send_spl_striker_message = false;
+                }
+                _ if primary_state == PrimaryState::Playing => {
+                    // every robot, including this one, is considered with the information it shared
+                    let mut robots = self.teammates;
+                    for (player_number, robot) in self.teammates.iter() {
+                        let is_penalized = context.filtered_game_controller_state.is_some_and(
+                            |game_controller_state| {
+                                game_controller_state.penalties[player_number].is_some()
+                            },
+                        );
+                        robots[player_number] = robot.filter(|robot| {
+                            !is_penalized
+                                && cycle_start_time
+                                    .duration_since(robot.last_update)
+                                    .map_or(true, |age| age <= context.cost_based.teammate_timeout)
+                        });
+                    }
+                    self.cost_based_assignment = assign_roles_by_cost(
+                        &robots,
+                        shared_ball(team_ball),
+                        &self.cost_based_assignment,
+                        context.optional_roles,
+                        context.field_dimensions,
+                        context.role_positions,
+                        context.cost_based,
+                    );
+                    role = self.cost_based_assignment[*context.player_number].unwrap_or(role);
+                    // a new striker announces itself immediately
+                    send_spl_striker_message = role == Role::Striker
+                        && (send_spl_striker_message || previous_role != Role::Striker);
+                }
+                _ => {
+                    send_spl_striker_message = false;
+                }
+            }
+        } else if spl_messages.peek().is_none() {
             (role, send_spl_striker_message, team_ball) = process_role_state_machine(
                 role,
                 ground_to_field,
@@ -239,6 +371,10 @@ impl RoleAssignment {
             );
         } else {
             for spl_message in spl_messages {
+                let spl_message = &with_estimated_time_to_reach_kick_position(
+                    spl_message,
+                    context.spl_network.foreign_teammate_walking_speed,
+                );
                 self.last_received_spl_striker_message = Some(cycle_start_time);
                 let sender_position = ground_to_field.inverse() * spl_message.pose.position();
                 if spl_message.player_number != *context.player_number {
@@ -270,44 +406,133 @@ impl RoleAssignment {
             if self.role == Role::ReplacementKeeper
                 && !send_spl_striker_message
                 && deny_replacement_keeper_switch
+                && *context.mode == RoleAssignmentMode::StateMachine
             {
                 role = Role::ReplacementKeeper;
             }
         }
 
+        let pass_announcement = context.announced_pass.announcement;
+        let pass_receiver = pass_announcement.map(|announcement| announcement.receiver);
+        // the receiver needs to know about the pass as early as possible
+        if role == Role::Striker && pass_receiver != self.last_transmitted_pass_receiver {
+            send_spl_striker_message = true;
+        }
+
+        let whistle = context
+            .heard_whistle
+            .filter(|heard_whistle| {
+                self.last_transmitted_whistle != Some(heard_whistle.detection_time)
+            })
+            .map(|heard_whistle| WhistleObservation {
+                age: cycle_start_time
+                    .duration_since(heard_whistle.detection_time)
+                    .unwrap_or_default(),
+                direction: heard_whistle.direction,
+            });
+        let has_message_budget =
+            context
+                .filtered_game_controller_state
+                .is_some_and(|game_controller_state| {
+                    game_controller_state.remaining_number_of_messages
+                        > context
+                            .spl_network
+                            .remaining_amount_of_messages_to_stop_sending
+                });
+        let mut whistle_was_transmitted = false;
+
         if send_spl_striker_message
             && primary_state == PrimaryState::Playing
             && silence_interval_has_passed
         {
             self.last_transmitted_spl_striker_message = Some(cycle_start_time);
             self.last_received_spl_striker_message = Some(cycle_start_time);
-            if let Some(game_controller_state) = context.filtered_game_controller_state {
-                if game_controller_state.remaining_number_of_messages
-                    > context
-                        .spl_network
-                        .remaining_amount_of_messages_to_stop_sending
-                {
-                    let ball_position = if context.ball_position.is_none() && team_ball.is_some() {
-                        team_ball_to_network_ball_position(team_ball, cycle_start_time)
-                    } else {
-                        seen_ball_to_hulks_network_ball_position(
-                            context.ball_position,
-                            ground_to_field,
-                            cycle_start_time,
-                        )
-                    };
-                    context
-                        .hardware
-                        .write_to_network(OutgoingMessage::Spl(HulkMessage {
-                            player_number: *context.player_number,
-                            fallen: matches!(context.fall_state, FallState::Fallen { .. }),
-                            pose: ground_to_field.as_pose(),
-                            ball_position,
-                            time_to_reach_kick_position: Some(*context.time_to_reach_kick_position),
-                        }))?;
+            if has_message_budget {
+                let ball_position = if context.ball_position.is_none() && team_ball.is_some() {
+                    team_ball_to_network_ball_position(team_ball, cycle_start_time)
+                } else {
+                    seen_ball_to_hulks_network_ball_position(
+                        context.ball_position,
+                        ground_to_field,
+                        cycle_start_time,
+                    )
+                };
+                let message = HulkMessage {
+                    player_number: *context.player_number,
+                    fallen: matches!(context.fall_state, FallState::Fallen { .. }),
+                    pose: ground_to_field.as_pose(),
+                    localization_confidence: context.localization_confidence.copied(),
+                    ball_position,
+                    time_to_reach_kick_position: Some(*context.time_to_reach_kick_position),
+                    whistle,
+                    pass: pass_announcement,
+                };
+                context
+                    .hardware
+                    .write_to_network(OutgoingMessage::Spl(message))?;
+                if *context.mode == RoleAssignmentMode::CostBased {
+                    remember_shared_information(
+                        &message,
+                        cycle_start_time,
+                        &mut self.teammates,
+                        &mut team_ball,
+                    );
                 }
+                whistle_was_transmitted = whistle.is_some();
+                self.last_transmitted_pass_receiver = pass_receiver;
+            }
+        }
+
+        // teammates need our whistle observation to confirm the whistle and our pose to pass to us
+        // or to assign roles even if we are no striker
+        let whistle_is_pending = whistle.is_some()
+            && !whistle_was_transmitted
+            && matches!(primary_state, PrimaryState::Set | PrimaryState::Playing);
+        let teammates_need_pose =
+            *context.pass_planner_enable || *context.mode == RoleAssignmentMode::CostBased;
+        let pose_message_is_due = teammates_need_pose
+            && role != Role::Striker
+            && primary_state == PrimaryState::Playing
+            && self
+                .last_transmitted_pose_message
+                .map_or(true, |last_transmitted_pose_message| {
+                    cycle_start_time
+                        .duration_since(last_transmitted_pose_message)
+                        .is_ok_and(|duration| {
+                            duration > context.spl_network.pose_message_send_interval
+                        })
+                });
+        if (whistle_is_pending || pose_message_is_due) && has_message_budget {
+            let whistle = whistle.filter(|_| !whistle_was_transmitted);
+            whistle_was_transmitted |= whistle.is_some();
+            self.last_transmitted_pose_message = Some(cycle_start_time);
+            let message = HulkMessage {
+                player_number: *context.player_number,
+                fallen: matches!(context.fall_state, FallState::Fallen { .. }),
+                pose: ground_to_field.as_pose(),
+                localization_confidence: context.localization_confidence.copied(),
+                ball_position: None,
+                time_to_reach_kick_position: None,
+                whistle,
+                pass: None,
+            };
+            context
+                .hardware
+                .write_to_network(OutgoingMessage::Spl(message))?;
+            if *context.mode == RoleAssignmentMode::CostBased {
+                remember_shared_information(
+                    &message,
+                    cycle_start_time,
+                    &mut self.teammates,
+                    &mut team_ball,
+                );
             }
         }
+        if whistle_was_transmitted {
+            self.last_transmitted_whistle = context
+                .heard_whistle
+                .map(|heard_whistle| heard_whistle.detection_time);
+        }
 
         if let Some(forced_role) = context.forced_role {
             self.role = *forced_role;
@@ -610,6 +835,177 @@ fn decide_if_claiming_striker_or_other_role(
     }
 }
 
+/// Assigns roles to the available robots such that their summed time to reach the role positions,
+/// weighted by the priority of the roles, is minimal
+///
+/// Every robot evaluates the same costs from the same shared information and therefore arrives at
+/// the same assignment without negotiating roles via messages.
+fn assign_roles_by_cost(
+    robots: &Players<Option<TeammateStatus>>,
+    ball: Point2<Field>,
+    previous_assignment: &Players<Option<Role>>,
+    optional_roles: &[Role],
+    field_dimensions: &FieldDimensions,
+    role_positions: &RolePositionsParameters,
+    parameters: &CostBasedRoleAssignmentParameters,
+) -> Players<Option<Role>> {
+    let mut assignment = Players::<Option<Role>>::default();
+    let keeper_is_available = robots.one.is_some();
+    if keeper_is_available {
+        assignment.one = Some(Role::Keeper);
+    }
+    let field_players: Vec<_> = robots
+        .iter()
+        .filter(|(player_number, _)| !keeper_is_available || *player_number != PlayerNumber::One)
+        .filter_map(|(player_number, robot)| Some((player_number, (*robot)?)))
+        .collect();
+    // surplus robots search the ball
+    let roles: Vec<_> = (!keeper_is_available)
+        .then_some(Role::ReplacementKeeper)
+        .into_iter()
+        .chain([Role::Striker])
+        .chain(optional_roles.iter().copied())
+        .chain(repeat(Role::Searcher))
+        .take(field_players.len())
+        .collect();
+
+    let costs: Vec<Vec<f32>> = field_players
+        .iter()
+        .map(|(player_number, robot)| {
+            roles
+                .iter()
+                .zip(0..)
+                .map(|(&role, priority)| {
+                    let target = role_target(role, ball, field_dimensions, role_positions);
+                    let mut time_to_reach =
+                        distance(robot.pose.position(), target) / parameters.walking_speed;
+                    if robot.fallen {
+                        time_to_reach += parameters.fallen_penalty.as_secs_f32();
+                    }
+                    let mut cost = parameters.priority_decay.powi(priority) * time_to_reach;
+                    if previous_assignment[*player_number] == Some(role) {
+                        cost -= parameters.hysteresis.as_secs_f32();
+                    }
+                    cost
+                })
+                .collect()
+        })
+        .collect();
+    let Some(columns) = hungarian::solve(&costs) else {
+        return *previous_assignment;
+    };
+    for ((player_number, _), column) in field_players.iter().zip(columns) {
+        assignment[*player_number] = Some(roles[column]);
+    }
+    assignment
+}
+
+/// Keeps the newest pose and ball a robot shared with the team, both from sent and received
+/// messages, such that all robots assign roles from the same information
+fn remember_shared_information(
+    message: &HulkMessage,
+    cycle_start_time: SystemTime,
+    teammates: &mut Players<Option<TeammateStatus>>,
+    team_ball: &mut Option<BallPosition<Field>>,
+) {
+    teammates[message.player_number] = Some(TeammateStatus {
+        pose: message.pose,
+        fallen: message.fallen,
+        last_update: cycle_start_time,
+    });
+    let message_ball = team_ball_from_spl_message(cycle_start_time, message);
+    let is_newer = |ball: BallPosition<Field>| {
+        team_ball.map_or(true, |team_ball| ball.last_seen > team_ball.last_seen)
+    };
+    if message_ball.is_some_and(is_newer) {
+        *team_ball = message_ball;
+    }
+}
+
+/// Ball all robots agree on, the own ball is never shared in pose messages and would lead to
+/// different assignments on different robots
+fn shared_ball(team_ball: Option<BallPosition<Field>>) -> Point2<Field> {
+    team_ball.map_or(Point2::origin(), |team_ball| team_ball.position)
+}
+
+/// Approximates where a robot of the role will be positioned by the behavior
+fn role_target(
+    role: Role,
+    ball: Point2<Field>,
+    field_dimensions: &FieldDimensions,
+    role_positions: &RolePositionsParameters,
+) -> Point2<Field> {
+    let own_goal_x = -field_dimensions.length / 2.0;
+    let block_ball = |position_to_defend: Point2<Field>, radius: f32| {
+        (ball - position_to_defend)
+            .try_normalize(f32::EPSILON)
+            .map_or(position_to_defend, |direction| {
+                position_to_defend + direction * radius
+            })
+    };
+    let target = match role {
+        Role::Keeper | Role::ReplacementKeeper => {
+            point![own_goal_x + role_positions.keeper_x_offset, 0.0]
+        }
+        Role::DefenderLeft => block_ball(
+            point![own_goal_x, role_positions.defender_y_offset],
+            role_positions.defender_passive_ring_radius,
+        ),
+        Role::DefenderRight => block_ball(
+            point![own_goal_x, -role_positions.defender_y_offset],
+            role_positions.defender_passive_ring_radius,
+        ),
+        Role::MidfielderLeft => {
+            ball + UnitComplex::new(-FRAC_PI_4)
+                * -(Vector2::<Field>::x_axis() * role_positions.left_midfielder_distance_to_ball)
+        }
+        Role::MidfielderRight => {
+            ball + UnitComplex::new(FRAC_PI_4)
+                * -(Vector2::<Field>::x_axis() * role_positions.right_midfielder_distance_to_ball)
+        }
+        Role::StrikerSupporter => {
+            ball - Vector2::<Field>::x_axis() * role_positions.striker_supporter_distance_to_ball
+        }
+        Role::Striker | Role::Loser | Role::Searcher => ball,
+    };
+    point![
+        target.x().clamp(own_goal_x, -own_goal_x),
+        target
+            .y()
+            .clamp(-field_dimensions.width / 2.0, field_dimensions.width / 2.0)
+    ]
+}
+
+/// Teammates from other teams (mixed-team and drop-in games) do not send their time to reach the kick
+/// position, it is estimated from their distance to the ball instead
+fn with_estimated_time_to_reach_kick_position(
+    spl_message: &HulkMessage,
+    walking_speed: f32,
+) -> HulkMessage {
+    let mut spl_message = *spl_message;
+    if spl_message.time_to_reach_kick_position.is_none() {
+        spl_message.time_to_reach_kick_position = spl_message.ball_position.map(|ball_position| {
+            let distance = (ball_position.position - spl_message.pose.position()).norm();
+            Duration::try_from_secs_f32(distance / walking_speed).unwrap_or(Duration::MAX)
+        });
+    }
+    spl_message
+}
+
+/// Robots that are no striker only send their pose, optionally with a heard whistle, to their
+/// teammates, these messages take no part in claiming the striker role
+fn is_non_striker_message(spl_message: &HulkMessage) -> bool {
+    matches!(
+        spl_message,
+        HulkMessage {
+            ball_position: None,
+            time_to_reach_kick_position: None,
+            pass: None,
+            ..
+        }
+    )
+}
+
 fn seen_ball_to_game_controller_ball_position(
     ball: Option<&BallPosition<Ground>>,
     cycle_start_time: SystemTime,
@@ -775,3 +1171,160 @@ fn assign_keeper_or_replacement_keeper(
 
     unassigned_robots
 }
+
+#[cfg(test)]
+mod tests {
+    use linear_algebra::vector;
+
+    use super::*;
+
+    fn robot_at(x: f32, y: f32) -> Option<TeammateStatus> {
+        Some(TeammateStatus {
+            pose: Pose::new(vector![x, y], 0.0),
+            fallen: false,
+            last_update: SystemTime::UNIX_EPOCH,
+        })
+    }
+
+    fn assign(
+        robots: &Players<Option<TeammateStatus>>,
+        ball: Point2<Field>,
+        previous_assignment: &Players<Option<Role>>,
+    ) -> Players<Option<Role>> {
+        assign_roles_by_cost(
+            robots,
+            ball,
+            previous_assignment,
+            &[Role::DefenderLeft, Role::StrikerSupporter],
+            &FieldDimensions {
+                length: 9.0,
+                width: 6.0,
+                ..Default::default()
+            },
+            &RolePositionsParameters {
+                defender_passive_ring_radius: 1.7,
+                defender_y_offset: 0.8,
+                striker_supporter_distance_to_ball: 1.2,
+                ..Default::default()
+            },
+            &CostBasedRoleAssignmentParameters {
+                walking_speed: 0.2,
+                teammate_timeout: Duration::from_secs(20),
+                fallen_penalty: Duration::from_secs(8),
+                hysteresis: Duration::from_secs(3),
+                priority_decay: 0.7,
+            },
+        )
+    }
+
+    #[test]
+    fn closest_robots_take_the_roles() {
+        let robots = Players {
+            one: robot_at(-4.5, 0.0),
+            two: robot_at(2.0, 0.0),
+            three: robot_at(-3.0, 1.0),
+            ..Default::default()
+        };
+
+        let assignment = assign(&robots, point![2.5, 0.0], &Default::default());
+
+        assert_eq!(assignment.one, Some(Role::Keeper));
+        assert_eq!(assignment.two, Some(Role::Striker));
+        assert_eq!(assignment.three, Some(Role::DefenderLeft));
+        assert_eq!(assignment.four, None);
+    }
+
+    #[test]
+    fn replacement_keeper_is_assigned_if_keeper_is_unavailable() {
+        let robots = Players {
+            two: robot_at(-4.0, 0.0),
+            three: robot_at(1.0, 0.0),
+            ..Default::default()
+        };
+
+        let assignment = assign(&robots, point![2.0, 0.0], &Default::default());
+
+        assert_eq!(assignment.one, None);
+        assert_eq!(assignment.two, Some(Role::ReplacementKeeper));
+        assert_eq!(assignment.three, Some(Role::Striker));
+    }
+
+    #[test]
+    fn previous_assignment_is_kept_if_costs_are_similar() {
+        let robots = Players {
+            one: robot_at(-4.5, 0.0),
+            two: robot_at(1.0, 0.3),
+            three: robot_at(1.0, -0.2),
+            ..Default::default()
+        };
+        let previous_assignment = Players {
+            two: Some(Role::Striker),
+            three: Some(Role::DefenderLeft),
+            ..Default::default()
+        };
+
+        let assignment = assign(&robots, point![2.0, 0.0], &previous_assignment);
+
+        assert_eq!(assignment.two, Some(Role::Striker));
+        assert_eq!(assignment.three, Some(Role::DefenderLeft));
+    }
+
+    #[test]
+    fn fallen_robot_leaves_striker_role_to_standing_teammate() {
+        let mut robots = Players {
+            one: robot_at(-4.5, 0.0),
+            two: robot_at(1.5, 0.1),
+            three: robot_at(1.4, -0.1),
+            ..Default::default()
+        };
+        let assignment = assign(&robots, point![2.0, 0.0], &Default::default());
+        assert_eq!(assignment.two, Some(Role::Striker));
+
+        robots.two.as_mut().unwrap().fallen = true;
+        let assignment = assign(&robots, point![2.0, 0.0], &Default::default());
+
+        assert_eq!(assignment.two, Some(Role::DefenderLeft));
+        assert_eq!(assignment.three, Some(Role::Striker));
+    }
+
+    #[test]
+    fn ball_on_defended_position_keeps_assignment_finite() {
+        let robots = Players {
+            one: robot_at(-4.5, 0.0),
+            two: robot_at(1.0, 0.0),
+            three: robot_at(-3.0, 1.0),
+            ..Default::default()
+        };
+
+        let assignment = assign(&robots, point![-4.5, 0.8], &Default::default());
+
+        assert_eq!(assignment.one, Some(Role::Keeper));
+        assert!(assignment.two.is_some());
+        assert!(assignment.three.is_some());
+    }
+
+    #[test]
+    fn robots_with_different_own_balls_agree_without_team_ball() {
+        let robots = Players {
+            one: robot_at(-4.5, 0.0),
+            two: robot_at(1.0, 2.0),
+            three: robot_at(1.0, -2.0),
+            ..Default::default()
+        };
+        let own_ball_of_two = point![1.5, 2.0];
+        let own_ball_of_three = point![1.5, -2.0];
+        assert_eq!(
+            assign(&robots, own_ball_of_two, &Default::default()).two,
+            Some(Role::Striker)
+        );
+        assert_eq!(
+            assign(&robots, own_ball_of_three, &Default::default()).three,
+            Some(Role::Striker)
+        );
+
+        let assignment_of_two = assign(&robots, shared_ball(None), &Default::default());
+        let assignment_of_three = assign(&robots, shared_ball(None), &Default::default());
+
+        assert!(assignment_of_two.iter().eq(assignment_of_three.iter()));
+    }
+}
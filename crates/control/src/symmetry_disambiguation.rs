@@ -0,0 +1,331 @@
+use std::f32::consts::PI;
+
+use nalgebra::{vector, Matrix3, Rotation2, Vector3};
+
+use coordinate_systems::{Field, Ground};
+use linear_algebra::{distance, point, Point2};
+use types::{
+    localization::{ScoredPose, SymmetryAction, SymmetryEvent},
+    multivariate_normal_distribution::MultivariateNormalDistribution,
+    parameters::SymmetryDisambiguationParameters,
+};
+
+use crate::monte_carlo_localization::state_to_ground_to_field;
+
+/// Recent observations of a teammate which is confident about its own pose
+#[derive(Clone, Copy, Debug)]
+pub struct TeammateObservation {
+    pub position: Point2<Field>,
+    pub ball: Option<Point2<Field>>,
+}
+
+/// Everything the field symmetry can be broken with in the current cycle
+#[derive(Clone, Copy, Debug)]
+pub struct SymmetryEvidence<'a> {
+    pub teammates: &'a [TeammateObservation],
+    pub ball: Option<Point2<Ground>>,
+    /// The rules (or the goalkeeper role) keep this robot inside its own half
+    pub is_in_own_half: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Votes {
+    pub mirrored: usize,
+    pub original: usize,
+}
+
+impl Votes {
+    fn add(&mut self, original_agrees: bool, mirrored_agrees: bool) {
+        match (original_agrees, mirrored_agrees) {
+            (true, false) => self.original += 1,
+            (false, true) => self.mirrored += 1,
+            _ => {}
+        }
+    }
+}
+
+/// The pose indistinguishable from `state` on a point-symmetric field
+pub fn mirrored_state(state: Vector3<f32>) -> Vector3<f32> {
+    vector![-state.x, -state.y, Rotation2::new(state.z + PI).angle()]
+}
+
+/// Mirrors the mean and covariance of a pose distribution
+///
+/// The position is negated while the orientation is only offset, so the correlations between
+/// position and orientation change their sign.
+pub fn mirror_distribution(state: &mut MultivariateNormalDistribution<3>) {
+    let jacobian = Matrix3::from_diagonal(&vector![-1.0, -1.0, 1.0]);
+    state.mean = mirrored_state(state.mean);
+    state.covariance = jacobian * state.covariance * jacobian.transpose();
+}
+
+/// Counts the observations explained only by `state` or only by its mirrored pose
+pub fn count_votes(
+    state: Vector3<f32>,
+    evidence: &SymmetryEvidence,
+    parameters: &SymmetryDisambiguationParameters,
+) -> Votes {
+    let mut votes = Votes::default();
+    let position = point![state.x, state.y];
+    let mirrored_position = point![-state.x, -state.y];
+
+    if let Some(ball) = evidence.ball {
+        let ball_in_field = state_to_ground_to_field(state) * ball;
+        let mirrored_ball_in_field = point![-ball_in_field.x(), -ball_in_field.y()];
+        for teammate_ball in evidence
+            .teammates
+            .iter()
+            .filter_map(|teammate| teammate.ball)
+        {
+            votes.add(
+                distance(ball_in_field, teammate_ball) < parameters.ball_agreement_distance,
+                distance(mirrored_ball_in_field, teammate_ball)
+                    < parameters.ball_agreement_distance,
+            );
+        }
+    }
+
+    for teammate in evidence.teammates {
+        // two robots cannot stand at the same position, the free one is plausible
+        votes.add(
+            distance(mirrored_position, teammate.position) < parameters.minimum_teammate_distance,
+            distance(position, teammate.position) < parameters.minimum_teammate_distance,
+        );
+    }
+
+    if evidence.is_in_own_half {
+        votes.add(
+            position.x() < -parameters.own_half_margin,
+            position.x() > parameters.own_half_margin,
+        );
+    }
+
+    votes
+}
+
+/// Flips hypotheses which are better explained by their mirrored pose
+///
+/// If the mirrored pose is already tracked by another hypothesis, the mirrored one is removed
+/// instead of creating a duplicate.
+pub fn disambiguate(
+    hypotheses: &mut Vec<ScoredPose>,
+    evidence: &SymmetryEvidence,
+    parameters: &SymmetryDisambiguationParameters,
+) -> Vec<SymmetryEvent> {
+    let decisions: Vec<_> = hypotheses
+        .iter()
+        .enumerate()
+        .map(|(index, hypothesis)| {
+            let votes = count_votes(hypothesis.state.mean, evidence, parameters);
+            if votes.mirrored < parameters.minimum_votes_for_mirrored
+                || votes.mirrored <= votes.original
+            {
+                return None;
+            }
+            let mirrored = mirrored_state(hypothesis.state.mean);
+            let is_duplicate = hypotheses.iter().enumerate().any(|(other_index, other)| {
+                other_index != index
+                    && (other.state.mean.xy() - mirrored.xy()).norm()
+                        < parameters.duplicate_hypothesis_distance
+            });
+            let action = if is_duplicate {
+                SymmetryAction::Removed
+            } else {
+                SymmetryAction::Flipped
+            };
+            Some(SymmetryEvent {
+                action,
+                ground_to_field_before: state_to_ground_to_field(hypothesis.state.mean),
+                votes_for_mirrored: votes.mirrored,
+                votes_for_original: votes.original,
+            })
+        })
+        .collect();
+
+    let mut decisions_in_order = decisions.iter();
+    hypotheses.retain_mut(|hypothesis| match decisions_in_order.next() {
+        Some(Some(SymmetryEvent {
+            action: SymmetryAction::Removed,
+            ..
+        })) => false,
+        Some(Some(SymmetryEvent {
+            action: SymmetryAction::Flipped,
+            ..
+        })) => {
+            mirror_distribution(&mut hypothesis.state);
+            true
+        }
+        _ => true,
+    });
+
+    decisions.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use approx::assert_relative_eq;
+    use nalgebra::matrix;
+
+    use super::*;
+
+    fn parameters() -> SymmetryDisambiguationParameters {
+        SymmetryDisambiguationParameters {
+            enable: true,
+            ball_agreement_distance: 0.7,
+            minimum_teammate_distance: 0.3,
+            own_half_margin: 0.5,
+            minimum_votes_for_mirrored: 1,
+            duplicate_hypothesis_distance: 0.5,
+            ..Default::default()
+        }
+    }
+
+    fn hypothesis(x: f32, y: f32, angle: f32) -> ScoredPose {
+        ScoredPose {
+            state: MultivariateNormalDistribution {
+                mean: vector![x, y, angle],
+                covariance: Matrix3::identity(),
+            },
+            score: 1.0,
+        }
+    }
+
+    #[test]
+    fn hypothesis_in_opponent_half_during_set_is_flipped() {
+        let mut hypotheses = vec![hypothesis(2.0, 1.0, PI)];
+        let evidence = SymmetryEvidence {
+            teammates: &[],
+            ball: None,
+            is_in_own_half: true,
+        };
+
+        let events = disambiguate(&mut hypotheses, &evidence, &parameters());
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].action, SymmetryAction::Flipped);
+        assert_relative_eq!(
+            hypotheses[0].state.mean,
+            vector![-2.0, -1.0, 0.0],
+            epsilon = 1e-5
+        );
+    }
+
+    #[test]
+    fn ball_only_explained_by_mirrored_pose_flips_hypothesis() {
+        // the true pose is (-2, 0) facing the opponent goal, the ball lies 1m ahead
+        let teammates = [TeammateObservation {
+            position: point![-1.0, 2.0],
+            ball: Some(point![-1.0, 0.0]),
+        }];
+        let evidence = SymmetryEvidence {
+            teammates: &teammates,
+            ball: Some(point![1.0, 0.0]),
+            is_in_own_half: false,
+        };
+        let mut hypotheses = vec![hypothesis(2.0, 0.0, PI)];
+
+        let events = disambiguate(&mut hypotheses, &evidence, &parameters());
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].votes_for_mirrored, 1);
+        assert_eq!(events[0].votes_for_original, 0);
+        assert_relative_eq!(
+            hypotheses[0].state.mean,
+            vector![-2.0, 0.0, 0.0],
+            epsilon = 1e-5
+        );
+    }
+
+    #[test]
+    fn mirrored_hypothesis_is_removed_if_its_counterpart_is_tracked() {
+        let mut hypotheses = vec![
+            hypothesis(-3.0, 1.0, FRAC_PI_2),
+            hypothesis(3.0, -1.0, -FRAC_PI_2),
+        ];
+        let evidence = SymmetryEvidence {
+            teammates: &[],
+            ball: None,
+            is_in_own_half: true,
+        };
+
+        let events = disambiguate(&mut hypotheses, &evidence, &parameters());
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].action, SymmetryAction::Removed);
+        assert_eq!(hypotheses.len(), 1);
+        assert_relative_eq!(hypotheses[0].state.mean, vector![-3.0, 1.0, FRAC_PI_2]);
+    }
+
+    #[test]
+    fn ambiguous_evidence_keeps_hypothesis() {
+        // a teammate occupying the mirrored position outweighs the game context
+        let teammates = [TeammateObservation {
+            position: point![-2.0, -1.0],
+            ball: None,
+        }];
+        let evidence = SymmetryEvidence {
+            teammates: &teammates,
+            ball: None,
+            is_in_own_half: true,
+        };
+        let mut hypotheses = vec![hypothesis(2.0, 1.0, 0.0)];
+
+        let events = disambiguate(&mut hypotheses, &evidence, &parameters());
+
+        assert!(events.is_empty());
+        assert_relative_eq!(hypotheses[0].state.mean, vector![2.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn flipping_negates_correlations_between_position_and_orientation() {
+        let covariance = matrix![
+            0.04, 0.01, 0.02;
+            0.01, 0.09, -0.03;
+            0.02, -0.03, 0.1;
+        ];
+        let mut hypotheses = vec![ScoredPose {
+            state: MultivariateNormalDistribution {
+                mean: vector![2.0, 1.0, PI],
+                covariance,
+            },
+            score: 1.0,
+        }];
+        let evidence = SymmetryEvidence {
+            teammates: &[],
+            ball: None,
+            is_in_own_half: true,
+        };
+
+        disambiguate(&mut hypotheses, &evidence, &parameters());
+
+        assert_relative_eq!(
+            hypotheses[0].state.covariance,
+            matrix![
+                0.04, 0.01, -0.02;
+                0.01, 0.09, 0.03;
+                -0.02, 0.03, 0.1;
+            ]
+        );
+    }
+
+    #[test]
+    fn single_vote_does_not_flip_with_two_required_votes() {
+        let mut hypotheses = vec![hypothesis(2.0, 1.0, PI)];
+        let evidence = SymmetryEvidence {
+            teammates: &[],
+            ball: None,
+            is_in_own_half: true,
+        };
+        let parameters = SymmetryDisambiguationParameters {
+            minimum_votes_for_mirrored: 2,
+            ..parameters()
+        };
+
+        let events = disambiguate(&mut hypotheses, &evidence, &parameters);
+
+        assert!(events.is_empty());
+        assert_relative_eq!(hypotheses[0].state.mean, vector![2.0, 1.0, PI]);
+    }
+}
@@ -4,19 +4,21 @@ use std::time::{Duration, SystemTime};
 
 use color_eyre::{eyre::Context, Result};
 use context_attribute::context;
+use framework::PerceptionInput;
 use hardware::NetworkInterface;
 use serde::{Deserialize, Serialize};
 use spl_network_messages::{PlayerNumber, SubState, VisualRefereeDecision, VisualRefereeMessage};
 use types::{
     cycle_time::CycleTime, filtered_whistle::FilteredWhistle,
     game_controller_state::GameControllerState, messages::OutgoingMessage,
-    primary_state::PrimaryState,
+    primary_state::PrimaryState, referee_pose::RefereeGesture, support_foot::Side,
 };
 
 #[derive(Deserialize, Serialize)]
 pub struct VisualRefereeFilter {
     last_primary_state: PrimaryState,
     time_of_last_visual_referee_related_state_change: Option<SystemTime>,
+    detected_gestures: Vec<RefereeGesture>,
 }
 
 #[context]
@@ -28,7 +30,13 @@ pub struct CycleContext {
     game_controller_state: RequiredInput<Option<GameControllerState>, "game_controller_state?">,
     cycle_time: Input<CycleTime, "cycle_time">,
     filtered_whistle: Input<FilteredWhistle, "filtered_whistle">,
+    referee_gesture: PerceptionInput<Option<RefereeGesture>, "VisionTop", "referee_gesture?">,
+
     player_number: Parameter<PlayerNumber, "player_number">,
+    blue_team_is_left_of_referee:
+        Parameter<bool, "visual_referee_filter.blue_team_is_left_of_referee">,
+    minimum_number_of_detections:
+        Parameter<usize, "visual_referee_filter.minimum_number_of_detections">,
 
     hardware: HardwareInterface,
 }
@@ -42,6 +50,7 @@ impl VisualRefereeFilter {
         Ok(Self {
             last_primary_state: PrimaryState::Unstiff,
             time_of_last_visual_referee_related_state_change: None,
+            detected_gestures: Vec::new(),
         })
     }
 
@@ -56,11 +65,27 @@ impl VisualRefereeFilter {
             {
                 self.time_of_last_visual_referee_related_state_change =
                     Some(context.cycle_time.start_time);
+                self.detected_gestures.clear();
             }
             _ => {}
         }
         self.last_primary_state = *context.primary_state;
 
+        if self
+            .time_of_last_visual_referee_related_state_change
+            .is_some()
+        {
+            self.detected_gestures.extend(
+                context
+                    .referee_gesture
+                    .persistent
+                    .values()
+                    .flatten()
+                    .flatten()
+                    .copied(),
+            );
+        }
+
         if self
             .time_of_last_visual_referee_related_state_change
             .is_some_and(|time| {
@@ -88,9 +113,16 @@ impl VisualRefereeFilter {
                 duration_since_last_whistle = Duration::from_secs(8)
             }
 
-            // Initially a random visual referee decision
-            let mut rng = thread_rng();
-            let gesture = VisualRefereeDecision::from_u32(rng.gen_range(1..=13)).unwrap();
+            let gesture = most_frequent_gesture(
+                &self.detected_gestures,
+                *context.minimum_number_of_detections,
+            )
+            .map(|gesture| to_decision(gesture, *context.blue_team_is_left_of_referee))
+            .unwrap_or_else(|| {
+                // Guessing still has a chance to be right if nothing was detected
+                let mut rng = thread_rng();
+                VisualRefereeDecision::from_u32(rng.gen_range(1..=13)).unwrap()
+            });
 
             let message = OutgoingMessage::VisualReferee(VisualRefereeMessage {
                 player_number: *context.player_number,
@@ -103,7 +135,58 @@ impl VisualRefereeFilter {
                 .wrap_err("failed to write VisualRefereeMessage to hardware")?;
 
             self.time_of_last_visual_referee_related_state_change = None;
+            self.detected_gestures.clear();
         }
         Ok(MainOutputs::default())
     }
 }
+
+fn most_frequent_gesture(
+    detected_gestures: &[RefereeGesture],
+    minimum_number_of_detections: usize,
+) -> Option<RefereeGesture> {
+    let mut votes: Vec<(RefereeGesture, usize)> = Vec::new();
+    for gesture in detected_gestures {
+        match votes.iter_mut().find(|(candidate, _)| candidate == gesture) {
+            Some((_, count)) => *count += 1,
+            None => votes.push((*gesture, 1)),
+        }
+    }
+    votes
+        .into_iter()
+        .filter(|(_, count)| *count >= minimum_number_of_detections)
+        .max_by_key(|(_, count)| *count)
+        .map(|(gesture, _)| gesture)
+}
+
+fn to_decision(
+    gesture: RefereeGesture,
+    blue_team_is_left_of_referee: bool,
+) -> VisualRefereeDecision {
+    let is_blue_team = |side: Side| (side == Side::Left) == blue_team_is_left_of_referee;
+    match gesture {
+        RefereeGesture::KickIn { side } if is_blue_team(side) => {
+            VisualRefereeDecision::KickInBlueTeam
+        }
+        RefereeGesture::KickIn { .. } => VisualRefereeDecision::KickInRedTeam,
+        RefereeGesture::GoalKick { side } if is_blue_team(side) => {
+            VisualRefereeDecision::GoalKickBlueTeam
+        }
+        RefereeGesture::GoalKick { .. } => VisualRefereeDecision::GoalKickRedTeam,
+        RefereeGesture::CornerKick { side } if is_blue_team(side) => {
+            VisualRefereeDecision::CornerKickBlueTeam
+        }
+        RefereeGesture::CornerKick { .. } => VisualRefereeDecision::CornerKickRedTeam,
+        RefereeGesture::Goal { side } if is_blue_team(side) => VisualRefereeDecision::GoalBlueTeam,
+        RefereeGesture::Goal { .. } => VisualRefereeDecision::GoalRedTeam,
+        RefereeGesture::PushingFreeKick { side } if is_blue_team(side) => {
+            VisualRefereeDecision::PushingFreeKickBlueTeam
+        }
+        RefereeGesture::PushingFreeKick { .. } => VisualRefereeDecision::PushingFreeKickRedTeam,
+        RefereeGesture::Substitution { side } if is_blue_team(side) => {
+            VisualRefereeDecision::SubstitutionBlue
+        }
+        RefereeGesture::Substitution { .. } => VisualRefereeDecision::SubstitutionRed,
+        RefereeGesture::FullTime => VisualRefereeDecision::FullTime,
+    }
+}
@@ -1,16 +1,47 @@
-use std::{collections::VecDeque, time::SystemTime};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, SystemTime},
+};
 
 use color_eyre::Result;
 use context_attribute::context;
-use framework::{MainOutput, PerceptionInput};
+use coordinate_systems::{Field, Ground, Head, Robot};
+use framework::{AdditionalOutput, MainOutput, PerceptionInput};
+use linear_algebra::{Isometry2, Isometry3, Point2, Vector2, Vector3};
 use serde::{Deserialize, Serialize};
-use types::{cycle_time::CycleTime, filtered_whistle::FilteredWhistle, whistle::Whistle};
+use spl_network_messages::PlayerNumber;
+use types::{
+    cycle_time::CycleTime,
+    field_dimensions::FieldDimensions,
+    filtered_whistle::{FilteredWhistle, HeardWhistle},
+    game_controller_state::GameControllerState,
+    messages::IncomingMessage,
+    robot_kinematics::RobotKinematics,
+    whistle::Whistle,
+};
 
 #[derive(Deserialize, Serialize)]
 pub struct WhistleFilter {
     detection_buffer: VecDeque<bool>,
     was_detected_last_cycle: bool,
     last_detection: Option<SystemTime>,
+    pending_whistle: Option<PendingWhistle>,
+    teammate_observations: Vec<TeammateObservation>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct PendingWhistle {
+    heard: HeardWhistle,
+    position: Point2<Field>,
+    is_accepted: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct TeammateObservation {
+    player_number: PlayerNumber,
+    detection_time: SystemTime,
+    position: Point2<Field>,
+    direction: Option<Vector2<Field>>,
 }
 
 #[context]
@@ -18,17 +49,39 @@ pub struct CreationContext {}
 
 #[context]
 pub struct CycleContext {
+    whistle_sources: AdditionalOutput<Vec<Point2<Field>>, "whistle_sources">,
+
     cycle_time: Input<CycleTime, "cycle_time">,
+    game_controller_state: Input<Option<GameControllerState>, "game_controller_state?">,
+    robot_kinematics: Input<RobotKinematics, "robot_kinematics">,
+    robot_to_ground: Input<Option<Isometry3<Robot, Ground>>, "robot_to_ground?">,
 
     buffer_length: Parameter<usize, "whistle_filter.buffer_length">,
     minimum_detections: Parameter<usize, "whistle_filter.minimum_detections">,
+    maximum_source_distance_outside_field:
+        Parameter<f32, "whistle_filter.maximum_source_distance_outside_field">,
+    maximum_teammate_time_difference:
+        Parameter<Duration, "whistle_filter.maximum_teammate_time_difference">,
+    minimum_number_of_agreeing_teammates:
+        Parameter<usize, "whistle_filter.minimum_number_of_agreeing_teammates">,
+    teammate_agreement_timeout: Parameter<Duration, "whistle_filter.teammate_agreement_timeout">,
+    field_dimensions: Parameter<FieldDimensions, "field_dimensions">,
+    player_number: Parameter<PlayerNumber, "player_number">,
+    remaining_amount_of_messages_to_stop_sending:
+        Parameter<u16, "spl_network.remaining_amount_of_messages_to_stop_sending">,
+
     detected_whistle: PerceptionInput<Whistle, "Audio", "detected_whistle">,
+    whistle_direction: PerceptionInput<Option<Vector3<Head>>, "Audio", "whistle_direction?">,
+    network_message: PerceptionInput<IncomingMessage, "SplNetwork", "message">,
+
+    ground_to_field: CyclerState<Isometry2<Ground, Field>, "ground_to_field">,
 }
 
 #[context]
 #[derive(Default)]
 pub struct MainOutputs {
     pub filtered_whistle: MainOutput<FilteredWhistle>,
+    pub heard_whistle: MainOutput<Option<HeardWhistle>>,
 }
 
 impl WhistleFilter {
@@ -37,10 +90,12 @@ impl WhistleFilter {
             detection_buffer: Default::default(),
             was_detected_last_cycle: false,
             last_detection: None,
+            pending_whistle: None,
+            teammate_observations: Vec::new(),
         })
     }
 
-    pub fn cycle(&mut self, context: CycleContext) -> Result<MainOutputs> {
+    pub fn cycle(&mut self, mut context: CycleContext) -> Result<MainOutputs> {
         let cycle_start_time = context.cycle_time.start_time;
 
         for &is_detected in context
@@ -58,12 +113,123 @@ impl WhistleFilter {
             .iter()
             .filter(|&&was_detected| was_detected)
             .count();
-        let is_detected = number_of_detections > *context.minimum_detections;
-        let started_this_cycle = is_detected && !self.was_detected_last_cycle;
-        if started_this_cycle {
-            self.last_detection = Some(cycle_start_time);
+        let is_heard = number_of_detections > *context.minimum_detections;
+        let heard_this_cycle = is_heard && !self.was_detected_last_cycle;
+        self.was_detected_last_cycle = is_heard;
+
+        let ground_to_field = *context.ground_to_field;
+        let whistle_direction = context
+            .whistle_direction
+            .persistent
+            .values()
+            .flatten()
+            .filter_map(|direction| direction.copied())
+            .last()
+            .zip(context.robot_to_ground)
+            .map(|(direction, robot_to_ground)| {
+                let direction =
+                    *robot_to_ground * context.robot_kinematics.head_to_robot * direction;
+                ground_to_field * direction.xy()
+            })
+            .and_then(|direction| direction.try_normalize(f32::EPSILON));
+
+        if heard_this_cycle {
+            self.pending_whistle = Some(PendingWhistle {
+                heard: HeardWhistle {
+                    detection_time: cycle_start_time,
+                    direction: whistle_direction,
+                },
+                position: ground_to_field * Point2::origin(),
+                is_accepted: false,
+            });
+        }
+        if let Some(pending_whistle) = self.pending_whistle.as_mut() {
+            if is_heard && pending_whistle.heard.direction.is_none() {
+                pending_whistle.heard.direction = whistle_direction;
+            }
+        }
+
+        for (&receive_time, messages) in &context.network_message.persistent {
+            for message in messages {
+                let IncomingMessage::Spl(message) = message else {
+                    continue;
+                };
+                let Some(whistle) = message.whistle else {
+                    continue;
+                };
+                if message.player_number == *context.player_number {
+                    continue;
+                }
+                self.teammate_observations.push(TeammateObservation {
+                    player_number: message.player_number,
+                    detection_time: receive_time
+                        .checked_sub(whistle.age)
+                        .unwrap_or(receive_time),
+                    position: message.pose.position(),
+                    direction: whistle.direction,
+                });
+            }
+        }
+        let observation_timeout =
+            *context.teammate_agreement_timeout + *context.maximum_teammate_time_difference;
+        self.teammate_observations.retain(|observation| {
+            time_between(cycle_start_time, observation.detection_time) < observation_timeout
+        });
+
+        let mut started_this_cycle = false;
+        if let Some(pending_whistle) = self.pending_whistle.as_mut() {
+            if !pending_whistle.is_accepted {
+                let votes = teammate_votes(
+                    pending_whistle,
+                    &self.teammate_observations,
+                    *context.maximum_teammate_time_difference,
+                    context.field_dimensions,
+                    *context.maximum_source_distance_outside_field,
+                );
+                context
+                    .whistle_sources
+                    .fill_if_subscribed(|| votes.values().filter_map(|vote| vote.source).collect());
+                let number_of_possible_voters = number_of_possible_voters(
+                    context.game_controller_state,
+                    *context.player_number,
+                    *context.remaining_amount_of_messages_to_stop_sending,
+                );
+                let has_timed_out =
+                    time_between(cycle_start_time, pending_whistle.heard.detection_time)
+                        >= *context.teammate_agreement_timeout;
+                match decide(
+                    &votes,
+                    number_of_possible_voters,
+                    *context.minimum_number_of_agreeing_teammates,
+                    has_timed_out,
+                ) {
+                    Some(true) => {
+                        pending_whistle.is_accepted = true;
+                        started_this_cycle = true;
+                        self.last_detection = Some(pending_whistle.heard.detection_time);
+                    }
+                    Some(false) => self.pending_whistle = None,
+                    None => {}
+                }
+            }
+        }
+        let is_detected = self
+            .pending_whistle
+            .is_some_and(|pending_whistle| pending_whistle.is_accepted)
+            && (is_heard || started_this_cycle);
+
+        let heard_whistle = self
+            .pending_whistle
+            .map(|pending_whistle| pending_whistle.heard);
+        let pending_whistle_has_expired = self.pending_whistle.is_some_and(|pending_whistle| {
+            !is_heard
+                && (pending_whistle.is_accepted
+                    || time_between(cycle_start_time, pending_whistle.heard.detection_time)
+                        >= *context.teammate_agreement_timeout)
+        });
+        if pending_whistle_has_expired {
+            self.pending_whistle = None;
         }
-        self.was_detected_last_cycle = is_detected;
 
         Ok(MainOutputs {
             filtered_whistle: FilteredWhistle {
@@ -72,6 +238,372 @@ impl WhistleFilter {
                 started_this_cycle,
             }
             .into(),
+            heard_whistle: heard_whistle.into(),
+        })
+    }
+}
+
+/// Teammates on the field which are still allowed to send messages, without any of them the own
+/// detection is trusted alone
+fn number_of_possible_voters(
+    game_controller_state: Option<&GameControllerState>,
+    own_player_number: PlayerNumber,
+    remaining_amount_of_messages_to_stop_sending: u16,
+) -> usize {
+    let Some(game_controller_state) = game_controller_state else {
+        return 0;
+    };
+    if game_controller_state.remaining_amount_of_messages
+        <= remaining_amount_of_messages_to_stop_sending
+    {
+        return 0;
+    }
+    game_controller_state
+        .penalties
+        .iter()
+        .filter(|&(player_number, penalty)| player_number != own_player_number && penalty.is_none())
+        .count()
+}
+
+/// Accepts the own whistle as soon as enough teammates agree and none disagrees. A disagreeing
+/// teammate holds it back until the teammates which have not voted yet cannot outvote it anymore,
+/// otherwise it is rejected at the timeout if more teammates disagree than agree. `None` keeps the
+/// whistle pending.
+fn decide(
+    votes: &HashMap<PlayerNumber, Vote>,
+    number_of_possible_voters: usize,
+    minimum_number_of_agreeing_teammates: usize,
+    has_timed_out: bool,
+) -> Option<bool> {
+    let number_of_agreeing = votes.values().filter(|vote| vote.agrees).count();
+    let number_of_disagreeing = votes.len() - number_of_agreeing;
+    let number_of_missing_votes = number_of_possible_voters.saturating_sub(votes.len());
+    let minimum_number_of_agreeing =
+        minimum_number_of_agreeing_teammates.min(number_of_possible_voters);
+
+    let is_accepted = number_of_agreeing >= minimum_number_of_agreeing
+        && number_of_agreeing >= number_of_disagreeing;
+    let cannot_be_outvoted = number_of_agreeing >= number_of_disagreeing + number_of_missing_votes;
+    if is_accepted && (number_of_disagreeing == 0 || cannot_be_outvoted) {
+        return Some(true);
+    }
+    has_timed_out.then_some(is_accepted)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Vote {
+    agrees: bool,
+    source: Option<Point2<Field>>,
+}
+
+/// Every teammate that heard a whistle at about the same time votes once on whether it was the
+/// same whistle as ours and whether it came from our field
+fn teammate_votes(
+    pending_whistle: &PendingWhistle,
+    teammate_observations: &[TeammateObservation],
+    maximum_time_difference: Duration,
+    field_dimensions: &FieldDimensions,
+    maximum_source_distance_outside_field: f32,
+) -> HashMap<PlayerNumber, Vote> {
+    let mut closest_observations = HashMap::<PlayerNumber, &TeammateObservation>::new();
+    for observation in teammate_observations {
+        let time_difference = time_between(
+            observation.detection_time,
+            pending_whistle.heard.detection_time,
+        );
+        if time_difference > maximum_time_difference {
+            continue;
+        }
+        let closest = closest_observations
+            .entry(observation.player_number)
+            .or_insert(observation);
+        if time_difference
+            < time_between(closest.detection_time, pending_whistle.heard.detection_time)
+        {
+            *closest = observation;
+        }
+    }
+    closest_observations
+        .into_iter()
+        .map(|(player_number, observation)| {
+            let vote = match (pending_whistle.heard.direction, observation.direction) {
+                (Some(own_direction), Some(teammate_direction)) => vote_by_triangulation(
+                    pending_whistle.position,
+                    own_direction,
+                    observation.position,
+                    teammate_direction,
+                    field_dimensions,
+                    maximum_source_distance_outside_field,
+                ),
+                _ => Vote {
+                    agrees: true,
+                    source: None,
+                },
+            };
+            (player_number, vote)
         })
+        .collect()
+}
+
+/// Intersects the bearing rays of both robots, whistles from adjacent fields intersect far
+/// outside our field or not at all
+fn vote_by_triangulation(
+    own_position: Point2<Field>,
+    own_direction: Vector2<Field>,
+    teammate_position: Point2<Field>,
+    teammate_direction: Vector2<Field>,
+    field_dimensions: &FieldDimensions,
+    maximum_source_distance_outside_field: f32,
+) -> Vote {
+    let cross =
+        |left: Vector2<Field>, right: Vector2<Field>| left.x() * right.y() - left.y() * right.x();
+    let determinant = cross(own_direction, teammate_direction);
+    let offset = teammate_position - own_position;
+    if determinant.abs() < 1e-3 {
+        // parallel rays only point at the same source if the robots face each other on one line
+        let is_collinear = cross(offset, own_direction).abs() < 1e-3 * offset.norm().max(1.0);
+        return Vote {
+            agrees: is_collinear
+                && own_direction.dot(offset) > 0.0
+                && own_direction.dot(teammate_direction) < 0.0,
+            source: None,
+        };
+    }
+    let own_distance = cross(offset, teammate_direction) / determinant;
+    let teammate_distance = cross(offset, own_direction) / determinant;
+    if own_distance < 0.0 || teammate_distance < 0.0 {
+        return Vote {
+            agrees: false,
+            source: None,
+        };
+    }
+    let source = own_position + own_direction * own_distance;
+    let agrees = source.x().abs()
+        <= field_dimensions.length / 2.0 + maximum_source_distance_outside_field
+        && source.y().abs() <= field_dimensions.width / 2.0 + maximum_source_distance_outside_field;
+    Vote {
+        agrees,
+        source: Some(source),
+    }
+}
+
+fn time_between(left: SystemTime, right: SystemTime) -> Duration {
+    left.duration_since(right)
+        .or_else(|_| right.duration_since(left))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use linear_algebra::{point, vector};
+    use spl_network_messages::{GamePhase, GameState, Penalty, Team, TeamColor};
+    use types::{game_controller_state::JerseyColors, players::Players};
+
+    use super::*;
+
+    #[test]
+    fn referee_at_the_side_line_is_accepted() {
+        let referee = point![0.0, -3.5];
+        let own_position = point![-2.0, 0.0];
+        let teammate_position = point![2.0, 1.0];
+
+        let vote = vote_by_triangulation(
+            own_position,
+            (referee - own_position).normalize(),
+            teammate_position,
+            (referee - teammate_position).normalize(),
+            &FieldDimensions::SPL,
+            1.0,
+        );
+
+        assert!(vote.agrees);
+        assert!((vote.source.unwrap() - referee).norm() < 1e-3);
+    }
+
+    #[test]
+    fn whistle_from_adjacent_field_is_rejected() {
+        let referee = point![0.0, -12.0];
+        let own_position = point![-2.0, 0.0];
+        let teammate_position = point![2.0, 1.0];
+
+        let vote = vote_by_triangulation(
+            own_position,
+            (referee - own_position).normalize(),
+            teammate_position,
+            (referee - teammate_position).normalize(),
+            &FieldDimensions::SPL,
+            1.0,
+        );
+
+        assert!(!vote.agrees);
+    }
+
+    #[test]
+    fn diverging_directions_are_rejected() {
+        let vote = vote_by_triangulation(
+            point![-2.0, 0.0],
+            vector![0.0, 1.0],
+            point![2.0, 0.0],
+            vector![0.0, -1.0],
+            &FieldDimensions::SPL,
+            1.0,
+        );
+
+        assert!(!vote.agrees);
+    }
+
+    #[test]
+    fn each_teammate_votes_once() {
+        let detection_time = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        let pending_whistle = PendingWhistle {
+            heard: HeardWhistle {
+                detection_time,
+                direction: None,
+            },
+            position: point![0.0, 0.0],
+            is_accepted: false,
+        };
+        let observation = |player_number, milliseconds| TeammateObservation {
+            player_number,
+            detection_time: detection_time + Duration::from_millis(milliseconds),
+            position: point![1.0, 0.0],
+            direction: None,
+        };
+        let teammate_observations = [
+            observation(PlayerNumber::Two, 100),
+            observation(PlayerNumber::Two, 200),
+            observation(PlayerNumber::Three, 50),
+            observation(PlayerNumber::Four, 3000),
+        ];
+
+        let votes = teammate_votes(
+            &pending_whistle,
+            &teammate_observations,
+            Duration::from_millis(500),
+            &FieldDimensions::SPL,
+            1.0,
+        );
+
+        assert_eq!(votes.len(), 2);
+        assert!(votes.contains_key(&PlayerNumber::Two));
+        assert!(votes.contains_key(&PlayerNumber::Three));
+        assert!(votes.values().all(|vote| vote.agrees));
+    }
+
+    #[test]
+    fn whistle_stays_pending_until_disagreeing_teammate_rejects_it() {
+        let detection_time = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        let referee = point![0.0, -12.0];
+        let own_position = point![-2.0, 0.0];
+        let teammate_position = point![2.0, 1.0];
+        let pending_whistle = PendingWhistle {
+            heard: HeardWhistle {
+                detection_time,
+                direction: Some((referee - own_position).normalize()),
+            },
+            position: own_position,
+            is_accepted: false,
+        };
+        let votes = |teammate_observations: &[TeammateObservation]| {
+            teammate_votes(
+                &pending_whistle,
+                teammate_observations,
+                Duration::from_millis(500),
+                &FieldDimensions::SPL,
+                1.0,
+            )
+        };
+
+        let teammate_observations = [TeammateObservation {
+            player_number: PlayerNumber::Two,
+            detection_time: detection_time + Duration::from_millis(100),
+            position: teammate_position,
+            direction: Some((referee - teammate_position).normalize()),
+        }];
+        assert_eq!(decide(&votes(&teammate_observations), 4, 0, false), None);
+        assert_eq!(
+            decide(&votes(&teammate_observations), 4, 0, true),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn lone_whistle_is_accepted_immediately_while_teammates_are_silent() {
+        assert_eq!(decide(&HashMap::new(), 4, 0, false), Some(true));
+    }
+
+    #[test]
+    fn whistle_waits_for_enough_agreeing_teammates() {
+        let agreeing = HashMap::from([(
+            PlayerNumber::Two,
+            Vote {
+                agrees: true,
+                source: None,
+            },
+        )]);
+
+        assert_eq!(decide(&HashMap::new(), 4, 1, false), None);
+        assert_eq!(decide(&agreeing, 4, 1, false), Some(true));
+    }
+
+    #[test]
+    fn whistle_is_accepted_immediately_without_possible_voters() {
+        assert_eq!(decide(&HashMap::new(), 0, 1, false), Some(true));
+    }
+
+    #[test]
+    fn only_unpenalized_teammates_with_message_budget_can_vote() {
+        let substitute = Some(Penalty::Substitute {
+            remaining: Duration::ZERO,
+        });
+        let mut game_controller_state = GameControllerState {
+            game_state: GameState::Playing,
+            game_phase: GamePhase::PenaltyShootout {
+                kicking_team: Team::Hulks,
+            },
+            kicking_team: Team::Hulks,
+            last_game_state_change: SystemTime::UNIX_EPOCH,
+            penalties: Players {
+                one: None,
+                two: substitute,
+                three: substitute,
+                four: substitute,
+                five: None,
+                six: substitute,
+                seven: substitute,
+            },
+            remaining_amount_of_messages: 1200,
+            sub_state: None,
+            hulks_team_is_home_after_coin_toss: true,
+            hulks_jersey_colors: JerseyColors {
+                field_player: TeamColor::Blue,
+                goal_keeper: TeamColor::Black,
+            },
+            opponent_jersey_colors: JerseyColors {
+                field_player: TeamColor::Red,
+                goal_keeper: TeamColor::Gray,
+            },
+        };
+
+        assert_eq!(
+            number_of_possible_voters(Some(&game_controller_state), PlayerNumber::One, 20),
+            1
+        );
+        assert_eq!(
+            number_of_possible_voters(Some(&game_controller_state), PlayerNumber::Five, 20),
+            1
+        );
+        game_controller_state.penalties.one = substitute;
+        assert_eq!(
+            number_of_possible_voters(Some(&game_controller_state), PlayerNumber::Five, 20),
+            0
+        );
+        game_controller_state.penalties.one = None;
+        game_controller_state.remaining_amount_of_messages = 20;
+        assert_eq!(
+            number_of_possible_voters(Some(&game_controller_state), PlayerNumber::Five, 20),
+            0
+        );
+        assert_eq!(number_of_possible_voters(None, PlayerNumber::Five, 20), 0);
     }
 }
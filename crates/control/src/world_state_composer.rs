@@ -4,7 +4,7 @@ use coordinate_systems::{Field, Ground};
 use framework::MainOutput;
 use linear_algebra::{Isometry2, Point2};
 use serde::{Deserialize, Serialize};
-use spl_network_messages::PlayerNumber;
+use spl_network_messages::{PassAnnouncement, PlayerNumber};
 use types::{
     fall_state::FallState,
     filtered_game_controller_state::FilteredGameControllerState,
@@ -31,6 +31,7 @@ pub struct CycleContext {
     ground_to_field: Input<Option<Isometry2<Ground, Field>>, "ground_to_field?">,
     kick_decisions: Input<Option<Vec<KickDecision>>, "kick_decisions?">,
     instant_kick_decisions: Input<Option<Vec<KickDecision>>, "instant_kick_decisions?">,
+    expected_pass: Input<Option<PassAnnouncement>, "expected_pass?">,
 
     player_number: Parameter<PlayerNumber, "player_number">,
 
@@ -73,6 +74,7 @@ impl WorldStateComposer {
             robot,
             kick_decisions: context.kick_decisions.cloned(),
             instant_kick_decisions: context.instant_kick_decisions.cloned(),
+            expected_pass: context.expected_pass.copied(),
             filtered_game_controller_state: context.filtered_game_controller_state.copied(),
         };
 
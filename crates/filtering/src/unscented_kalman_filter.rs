@@ -0,0 +1,428 @@
+use geometry::angle::normalize_angle;
+use nalgebra::{SMatrix, SVector};
+use thiserror::Error;
+use types::multivariate_normal_distribution::MultivariateNormalDistribution;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to compute the cholesky decomposition of the covariance matrix")]
+    Cholesky,
+    #[error("failed to compute the inverse of the residual covariance matrix")]
+    Inverse,
+}
+
+/// Scaling of the sigma points, see Van der Merwe's scaled unscented transform
+#[derive(Clone, Copy, Debug)]
+pub struct UnscentedTransformParameters {
+    pub alpha: f32,
+    pub beta: f32,
+    pub kappa: f32,
+}
+
+impl Default for UnscentedTransformParameters {
+    fn default() -> Self {
+        Self {
+            alpha: 1.0,
+            beta: 2.0,
+            kappa: 0.0,
+        }
+    }
+}
+
+/// Describes which components of a state or measurement are angles in radians
+///
+/// Angular components are averaged on the unit circle and their differences are wrapped into
+/// [-π, π).
+#[derive(Clone, Copy, Debug)]
+pub struct StateSpace<const DIMENSION: usize> {
+    is_angle: [bool; DIMENSION],
+}
+
+impl<const DIMENSION: usize> StateSpace<DIMENSION> {
+    pub fn euclidean() -> Self {
+        Self {
+            is_angle: [false; DIMENSION],
+        }
+    }
+
+    pub fn with_angles(angle_indices: &[usize]) -> Self {
+        let mut is_angle = [false; DIMENSION];
+        for &index in angle_indices {
+            is_angle[index] = true;
+        }
+        Self { is_angle }
+    }
+
+    fn mean(&self, points: &[SVector<f32, DIMENSION>], weights: &[f32]) -> SVector<f32, DIMENSION> {
+        SVector::from_fn(|index, _| {
+            if self.is_angle[index] {
+                let (sine_sum, cosine_sum) = points.iter().zip(weights).fold(
+                    (0.0, 0.0),
+                    |(sine_sum, cosine_sum), (point, weight)| {
+                        (
+                            sine_sum + weight * point[index].sin(),
+                            cosine_sum + weight * point[index].cos(),
+                        )
+                    },
+                );
+                sine_sum.atan2(cosine_sum)
+            } else {
+                points
+                    .iter()
+                    .zip(weights)
+                    .map(|(point, weight)| weight * point[index])
+                    .sum()
+            }
+        })
+    }
+
+    fn difference(
+        &self,
+        left: &SVector<f32, DIMENSION>,
+        right: &SVector<f32, DIMENSION>,
+    ) -> SVector<f32, DIMENSION> {
+        SVector::from_fn(|index, _| {
+            let difference = left[index] - right[index];
+            if self.is_angle[index] {
+                normalize_angle(difference)
+            } else {
+                difference
+            }
+        })
+    }
+
+    fn add(
+        &self,
+        point: &SVector<f32, DIMENSION>,
+        offset: &SVector<f32, DIMENSION>,
+    ) -> SVector<f32, DIMENSION> {
+        SVector::from_fn(|index, _| {
+            let sum = point[index] + offset[index];
+            if self.is_angle[index] {
+                normalize_angle(sum)
+            } else {
+                sum
+            }
+        })
+    }
+}
+
+pub trait UnscentedKalmanFilter<const STATE_DIMENSION: usize> {
+    fn predict<StatePredictionFunction>(
+        &mut self,
+        state_prediction_function: StatePredictionFunction,
+        process_noise: SMatrix<f32, STATE_DIMENSION, STATE_DIMENSION>,
+        state_space: &StateSpace<STATE_DIMENSION>,
+        parameters: &UnscentedTransformParameters,
+    ) -> Result<(), Error>
+    where
+        StatePredictionFunction: Fn(SVector<f32, STATE_DIMENSION>) -> SVector<f32, STATE_DIMENSION>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn update<const MEASUREMENT_DIMENSION: usize, MeasurementPredictionFunction>(
+        &mut self,
+        measurement: SVector<f32, MEASUREMENT_DIMENSION>,
+        measurement_noise: SMatrix<f32, MEASUREMENT_DIMENSION, MEASUREMENT_DIMENSION>,
+        measurement_prediction_function: MeasurementPredictionFunction,
+        state_space: &StateSpace<STATE_DIMENSION>,
+        measurement_space: &StateSpace<MEASUREMENT_DIMENSION>,
+        parameters: &UnscentedTransformParameters,
+    ) -> Result<(), Error>
+    where
+        MeasurementPredictionFunction:
+            Fn(SVector<f32, STATE_DIMENSION>) -> SVector<f32, MEASUREMENT_DIMENSION>;
+}
+
+impl<const STATE_DIMENSION: usize> UnscentedKalmanFilter<STATE_DIMENSION>
+    for MultivariateNormalDistribution<STATE_DIMENSION>
+{
+    fn predict<StatePredictionFunction>(
+        &mut self,
+        state_prediction_function: StatePredictionFunction,
+        process_noise: SMatrix<f32, STATE_DIMENSION, STATE_DIMENSION>,
+        state_space: &StateSpace<STATE_DIMENSION>,
+        parameters: &UnscentedTransformParameters,
+    ) -> Result<(), Error>
+    where
+        StatePredictionFunction: Fn(SVector<f32, STATE_DIMENSION>) -> SVector<f32, STATE_DIMENSION>,
+    {
+        let weights = SigmaPointWeights::new(STATE_DIMENSION, parameters);
+        let sigma_points = sample_sigma_points(self, state_space, weights.scale)?;
+        let predicted_sigma_points: Vec<_> = sigma_points
+            .into_iter()
+            .map(state_prediction_function)
+            .collect();
+
+        let mean = state_space.mean(&predicted_sigma_points, &weights.mean);
+        let covariance = weighted_outer_product_sum(
+            &predicted_sigma_points,
+            &predicted_sigma_points,
+            |point| state_space.difference(point, &mean),
+            |point| state_space.difference(point, &mean),
+            &weights.covariance,
+        );
+        self.mean = mean;
+        self.covariance = into_symmetric(covariance + process_noise);
+
+        Ok(())
+    }
+
+    fn update<const MEASUREMENT_DIMENSION: usize, MeasurementPredictionFunction>(
+        &mut self,
+        measurement: SVector<f32, MEASUREMENT_DIMENSION>,
+        measurement_noise: SMatrix<f32, MEASUREMENT_DIMENSION, MEASUREMENT_DIMENSION>,
+        measurement_prediction_function: MeasurementPredictionFunction,
+        state_space: &StateSpace<STATE_DIMENSION>,
+        measurement_space: &StateSpace<MEASUREMENT_DIMENSION>,
+        parameters: &UnscentedTransformParameters,
+    ) -> Result<(), Error>
+    where
+        MeasurementPredictionFunction:
+            Fn(SVector<f32, STATE_DIMENSION>) -> SVector<f32, MEASUREMENT_DIMENSION>,
+    {
+        let weights = SigmaPointWeights::new(STATE_DIMENSION, parameters);
+        let sigma_points = sample_sigma_points(self, state_space, weights.scale)?;
+        let predicted_measurements: Vec<_> = sigma_points
+            .iter()
+            .copied()
+            .map(measurement_prediction_function)
+            .collect();
+
+        let predicted_measurement_mean =
+            measurement_space.mean(&predicted_measurements, &weights.mean);
+        let residual_covariance = weighted_outer_product_sum(
+            &predicted_measurements,
+            &predicted_measurements,
+            |measurement| measurement_space.difference(measurement, &predicted_measurement_mean),
+            |measurement| measurement_space.difference(measurement, &predicted_measurement_mean),
+            &weights.covariance,
+        ) + measurement_noise;
+        let cross_covariance = weighted_outer_product_sum(
+            &sigma_points,
+            &predicted_measurements,
+            |state| state_space.difference(state, &self.mean),
+            |measurement| measurement_space.difference(measurement, &predicted_measurement_mean),
+            &weights.covariance,
+        );
+        let kalman_gain = cross_covariance
+            * residual_covariance
+                .cholesky()
+                .ok_or(Error::Inverse)?
+                .inverse();
+
+        let residual = measurement_space.difference(&measurement, &predicted_measurement_mean);
+        self.mean = state_space.add(&self.mean, &(kalman_gain * residual));
+        self.covariance = into_symmetric(
+            self.covariance - kalman_gain * residual_covariance * kalman_gain.transpose(),
+        );
+
+        Ok(())
+    }
+}
+
+struct SigmaPointWeights {
+    scale: f32,
+    mean: Vec<f32>,
+    covariance: Vec<f32>,
+}
+
+impl SigmaPointWeights {
+    fn new(dimension: usize, parameters: &UnscentedTransformParameters) -> Self {
+        let dimension = dimension as f32;
+        let lambda = parameters.alpha.powi(2) * (dimension + parameters.kappa) - dimension;
+        let scale = dimension + lambda;
+        let number_of_sigma_points = 2 * dimension as usize + 1;
+        let outer_weight = 1.0 / (2.0 * scale);
+
+        let mut mean = vec![outer_weight; number_of_sigma_points];
+        let mut covariance = vec![outer_weight; number_of_sigma_points];
+        mean[0] = lambda / scale;
+        covariance[0] = lambda / scale + 1.0 - parameters.alpha.powi(2) + parameters.beta;
+        Self {
+            scale,
+            mean,
+            covariance,
+        }
+    }
+}
+
+fn sample_sigma_points<const DIMENSION: usize>(
+    distribution: &MultivariateNormalDistribution<DIMENSION>,
+    state_space: &StateSpace<DIMENSION>,
+    scale: f32,
+) -> Result<Vec<SVector<f32, DIMENSION>>, Error> {
+    let covariance_square_root = (distribution.covariance * scale)
+        .cholesky()
+        .ok_or(Error::Cholesky)?
+        .l();
+
+    let mut sigma_points = Vec::with_capacity(2 * DIMENSION + 1);
+    sigma_points.push(distribution.mean);
+    for column in covariance_square_root.column_iter() {
+        sigma_points.push(state_space.add(&distribution.mean, &column.into_owned()));
+        sigma_points.push(state_space.add(&distribution.mean, &-column.into_owned()));
+    }
+    Ok(sigma_points)
+}
+
+fn weighted_outer_product_sum<const LEFT: usize, const RIGHT: usize>(
+    left_points: &[SVector<f32, LEFT>],
+    right_points: &[SVector<f32, RIGHT>],
+    left_deviation: impl Fn(&SVector<f32, LEFT>) -> SVector<f32, LEFT>,
+    right_deviation: impl Fn(&SVector<f32, RIGHT>) -> SVector<f32, RIGHT>,
+    weights: &[f32],
+) -> SMatrix<f32, LEFT, RIGHT> {
+    left_points
+        .iter()
+        .zip(right_points)
+        .zip(weights)
+        .map(|((left, right), weight)| {
+            left_deviation(left) * right_deviation(right).transpose() * *weight
+        })
+        .sum()
+}
+
+fn into_symmetric<const DIMENSION: usize>(
+    matrix: SMatrix<f32, DIMENSION, DIMENSION>,
+) -> SMatrix<f32, DIMENSION, DIMENSION> {
+    0.5 * (matrix + matrix.transpose())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use approx::assert_relative_eq;
+    use nalgebra::{matrix, vector, Matrix1, Matrix2, Vector1};
+
+    use crate::kalman_filter::KalmanFilter;
+
+    use super::*;
+
+    #[test]
+    fn linear_prediction_matches_kalman_filter() {
+        let state_prediction = matrix![1.0, 0.1; 0.0, 1.0];
+        let process_noise = Matrix2::from_diagonal_element(0.01);
+        let initial = MultivariateNormalDistribution {
+            mean: vector![1.0, 2.0],
+            covariance: matrix![0.5, 0.1; 0.1, 0.3],
+        };
+
+        let mut expected = initial;
+        KalmanFilter::predict(
+            &mut expected,
+            state_prediction,
+            Matrix2::zeros(),
+            vector![0.0, 0.0],
+            process_noise,
+        );
+        let mut unscented = initial;
+        UnscentedKalmanFilter::predict(
+            &mut unscented,
+            |state| state_prediction * state,
+            process_noise,
+            &StateSpace::euclidean(),
+            &UnscentedTransformParameters::default(),
+        )
+        .unwrap();
+
+        assert_relative_eq!(unscented.mean, expected.mean, epsilon = 1e-5);
+        assert_relative_eq!(unscented.covariance, expected.covariance, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn linear_update_matches_kalman_filter() {
+        let measurement_prediction = matrix![1.0, 0.0];
+        let measurement_noise = Matrix1::new(0.2);
+        let initial = MultivariateNormalDistribution {
+            mean: vector![1.0, 2.0],
+            covariance: matrix![0.5, 0.1; 0.1, 0.3],
+        };
+
+        let mut expected = initial;
+        KalmanFilter::update(
+            &mut expected,
+            measurement_prediction,
+            Vector1::new(1.5),
+            measurement_noise,
+        );
+        let mut unscented = initial;
+        UnscentedKalmanFilter::update(
+            &mut unscented,
+            Vector1::new(1.5),
+            measurement_noise,
+            |state| measurement_prediction * state,
+            &StateSpace::euclidean(),
+            &StateSpace::euclidean(),
+            &UnscentedTransformParameters::default(),
+        )
+        .unwrap();
+
+        assert_relative_eq!(unscented.mean, expected.mean, epsilon = 1e-5);
+        assert_relative_eq!(unscented.covariance, expected.covariance, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn quadratic_prediction_has_exact_mean() {
+        // for x ~ N(μ, σ²), E[x²] = μ² + σ²
+        let mut distribution = MultivariateNormalDistribution {
+            mean: Vector1::new(2.0),
+            covariance: Matrix1::new(0.25),
+        };
+
+        UnscentedKalmanFilter::predict(
+            &mut distribution,
+            |state| state.map(|x| x * x),
+            Matrix1::zeros(),
+            &StateSpace::euclidean(),
+            &UnscentedTransformParameters::default(),
+        )
+        .unwrap();
+
+        assert_relative_eq!(distribution.mean.x, 4.25, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn angle_update_wraps_around_pi() {
+        let mut distribution = MultivariateNormalDistribution {
+            mean: vector![0.0, PI - 0.1],
+            covariance: Matrix2::from_diagonal_element(0.1),
+        };
+
+        UnscentedKalmanFilter::update(
+            &mut distribution,
+            Vector1::new(-PI + 0.1),
+            Matrix1::new(0.1),
+            |state| Vector1::new(state.y),
+            &StateSpace::with_angles(&[1]),
+            &StateSpace::with_angles(&[0]),
+            &UnscentedTransformParameters::default(),
+        )
+        .unwrap();
+
+        // the measurement is 0.2 rad away across ±π, equal noise moves the estimate halfway
+        assert_relative_eq!(distribution.mean.y.abs(), PI, epsilon = 1e-4);
+        assert_relative_eq!(distribution.covariance[(1, 1)], 0.05, epsilon = 1e-5);
+        assert_relative_eq!(distribution.mean.x, 0.0, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn angle_prediction_averages_on_unit_circle() {
+        let mut distribution = MultivariateNormalDistribution {
+            mean: vector![PI - 0.05],
+            covariance: Matrix1::new(0.04),
+        };
+
+        UnscentedKalmanFilter::predict(
+            &mut distribution,
+            |state| state.map(|angle| normalize_angle(angle + 0.1)),
+            Matrix1::zeros(),
+            &StateSpace::with_angles(&[0]),
+            &UnscentedTransformParameters::default(),
+        )
+        .unwrap();
+
+        assert_relative_eq!(distribution.mean.x, -PI + 0.05, epsilon = 1e-4);
+        assert_relative_eq!(distribution.covariance[(0, 0)], 0.04, epsilon = 1e-4);
+    }
+}
@@ -0,0 +1,21 @@
+use std::f32::consts::{PI, TAU};
+
+/// Wraps an angle in radians into [-π, π)
+pub fn normalize_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    #[test]
+    fn angles_are_wrapped_around() {
+        assert_relative_eq!(normalize_angle(0.5), 0.5);
+        assert_relative_eq!(normalize_angle(PI + 0.5), -PI + 0.5, epsilon = 1e-6);
+        assert_relative_eq!(normalize_angle(-PI - 0.5), PI - 0.5, epsilon = 1e-6);
+        assert_relative_eq!(normalize_angle(3.0 * TAU + 0.25), 0.25, epsilon = 1e-5);
+    }
+}
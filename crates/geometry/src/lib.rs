@@ -1,3 +1,4 @@
+pub mod angle;
 pub mod arc;
 pub mod circle;
 pub mod circle_tangents;
@@ -21,10 +21,14 @@ fn main() -> Result<()> {
                     "vision::feet_detection",
                     "vision::field_border_detection",
                     "vision::field_color_detection",
+                    "vision::goal_post_detection",
                     "vision::image_segmenter",
                     "vision::limb_projector",
                     "vision::line_detection",
+                    "vision::penalty_mark_detection",
                     "vision::perspective_grid_candidates_provider",
+                    "vision::referee_pose_detection",
+                    "vision::robot_detection",
                     "vision::segment_filter",
                 ],
             },
@@ -45,6 +49,7 @@ fn main() -> Result<()> {
                     "control::foot_bumper_filter",
                     "control::game_controller_filter",
                     "control::game_controller_state_filter",
+                    "control::game_timeline_recorder",
                     "control::ground_contact_detector",
                     "control::ground_provider",
                     "control::kick_selector",
@@ -74,6 +79,7 @@ fn main() -> Result<()> {
                     "control::obstacle_filter",
                     "control::odometry",
                     "control::orientation_filter",
+                    "control::pass_planner",
                     "control::penalty_shot_direction_estimation",
                     "control::primary_state_filter",
                     "control::role_assignment",
@@ -99,7 +105,7 @@ fn main() -> Result<()> {
                 kind: CyclerKind::Perception,
                 instances: vec![""],
                 setup_nodes: vec!["audio::microphone_recorder"],
-                nodes: vec!["audio::whistle_detection"],
+                nodes: vec!["audio::whistle_detection", "audio::whistle_localization"],
             },
         ],
     };
@@ -17,7 +17,7 @@ use color_eyre::{
 use hardware::{PathsInterface, RecordingInterface, SpeakerInterface};
 use parking_lot::Mutex;
 use serde::Deserialize;
-use spl_network::endpoint::{Endpoint, Ports};
+use spl_network::endpoint::{Endpoint, Ports, SplMessageCodec};
 use tokio::{
     runtime::{Builder, Runtime},
     select,
@@ -50,6 +50,8 @@ pub struct Parameters {
     pub paths: Paths,
     pub speakers: speakers::Parameters,
     pub spl_network_ports: Ports,
+    #[serde(default)]
+    pub spl_network_codec: SplMessageCodec,
 }
 
 pub struct HardwareInterface {
@@ -85,7 +87,10 @@ impl HardwareInterface {
                 .wrap_err("failed to initialize speakers")?,
             paths: parameters.paths,
             spl_network_endpoint: runtime
-                .block_on(Endpoint::new(parameters.spl_network_ports))
+                .block_on(Endpoint::new(
+                    parameters.spl_network_ports,
+                    parameters.spl_network_codec,
+                ))
                 .wrap_err("failed to initialize SPL network")?,
             async_runtime: runtime,
             camera_top: Camera::new(
@@ -16,7 +16,7 @@ use hardware::{
     PathsInterface, RecordingInterface, SensorInterface, SpeakerInterface, TimeInterface,
 };
 use serde::Deserialize;
-use spl_network::endpoint::{Endpoint, Ports};
+use spl_network::endpoint::{Endpoint, Ports, SplMessageCodec};
 use tokio::{
     runtime::{Builder, Runtime},
     select,
@@ -48,6 +48,8 @@ pub const SIMULATION_TIME_STEP: i32 = 10;
 pub struct Parameters {
     pub paths: Paths,
     pub spl_network_ports: Ports,
+    #[serde(default)]
+    pub spl_network_codec: SplMessageCodec,
 }
 
 pub struct HardwareInterface {
@@ -91,7 +93,10 @@ impl HardwareInterface {
             bottom_camera_requested: AtomicBool::new(false),
             paths: parameters.paths,
             spl_network_endpoint: runtime
-                .block_on(Endpoint::new(parameters.spl_network_ports))
+                .block_on(Endpoint::new(
+                    parameters.spl_network_ports,
+                    parameters.spl_network_codec,
+                ))
                 .wrap_err("failed to initialize SPL network")?,
             async_runtime: runtime,
             enable_recording: AtomicBool::new(false),
@@ -3,14 +3,20 @@ use std::{
     net::{Ipv4Addr, SocketAddr, SocketAddrV4},
 };
 
+use color_eyre::{
+    eyre::{bail, WrapErr},
+    Result,
+};
 use log::warn;
 use serde::Deserialize;
+use spl_network_messages::{HulkMessage, SplStandardMessage};
 use thiserror::Error;
 use tokio::{net::UdpSocket, select, sync::Mutex};
 use types::messages::{IncomingMessage, OutgoingMessage};
 
 pub struct Endpoint {
     ports: Ports,
+    codec: SplMessageCodec,
     game_controller_state_socket: UdpSocket,
     spl_socket: UdpSocket,
     last_game_controller_address: Mutex<Option<SocketAddr>>,
@@ -27,7 +33,7 @@ pub enum Error {
 }
 
 impl Endpoint {
-    pub async fn new(parameters: Ports) -> Result<Self, Error> {
+    pub async fn new(parameters: Ports, codec: SplMessageCodec) -> Result<Self, Error> {
         let game_controller_state_socket = UdpSocket::bind(SocketAddrV4::new(
             Ipv4Addr::UNSPECIFIED,
             parameters.game_controller_state,
@@ -42,6 +48,7 @@ impl Endpoint {
             .map_err(Error::EnableBroadcast)?;
         Ok(Self {
             ports: parameters,
+            codec,
             game_controller_state_socket,
             spl_socket,
             last_game_controller_address: Mutex::new(None),
@@ -68,7 +75,7 @@ impl Endpoint {
                 },
                 result = self.spl_socket.recv_from(&mut spl_buffer) => {
                     let (received_bytes, _address) = result.map_err(Error::ReadError)?;
-                    match bincode::deserialize(&spl_buffer[0..received_bytes]) {
+                    match self.codec.decode(&spl_buffer[0..received_bytes]) {
                         Ok(parsed_message) => {
                             break Ok(IncomingMessage::Spl(parsed_message));
                         }
@@ -89,13 +96,16 @@ impl Endpoint {
                 self.send_game_controller_visual_referee_message(message)
                     .await;
             }
-            OutgoingMessage::Spl(message) => match bincode::serialize(&message) {
+            OutgoingMessage::Spl(message) => match self.codec.encode(message) {
                 Ok(message) => {
                     if let Err(error) = self
                         .spl_socket
                         .send_to(
                             message.as_slice(),
-                            SocketAddr::new(Ipv4Addr::BROADCAST.into(), self.ports.spl),
+                            self.ports.spl_relay.unwrap_or(SocketAddr::new(
+                                Ipv4Addr::BROADCAST.into(),
+                                self.ports.spl,
+                            )),
                         )
                         .await
                     {
@@ -139,4 +149,57 @@ pub struct Ports {
     game_controller_state: u16,
     game_controller_return: u16,
     spl: u16,
+    /// Sends SPL messages to this address instead of broadcasting them (e.g. a network emulating relay)
+    #[serde(default)]
+    spl_relay: Option<SocketAddr>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub enum SplMessageCodec {
+    /// bincode encoded `HulkMessage`s, only understood by HULKs robots
+    #[default]
+    Bincode,
+    /// Official SPL standard message layout to play together with robots of other teams (e.g. in
+    /// mixed-team and drop-in games)
+    SplStandardMessage {
+        team_number: u8,
+        /// Team numbers besides `team_number` whose messages are accepted as teammate messages
+        #[serde(default)]
+        teammate_team_numbers: Vec<u8>,
+    },
+}
+
+impl SplMessageCodec {
+    pub fn decode(&self, buffer: &[u8]) -> Result<HulkMessage> {
+        match self {
+            SplMessageCodec::Bincode => {
+                bincode::deserialize(buffer).wrap_err("failed to deserialize HulkMessage")
+            }
+            SplMessageCodec::SplStandardMessage {
+                team_number,
+                teammate_team_numbers,
+            } => {
+                let message = SplStandardMessage::try_from(buffer)?;
+                if message.team_number != *team_number
+                    && !teammate_team_numbers.contains(&message.team_number)
+                {
+                    bail!("unexpected team number {}", message.team_number);
+                }
+                Ok(message.message)
+            }
+        }
+    }
+
+    pub fn encode(&self, message: HulkMessage) -> Result<Vec<u8>> {
+        match self {
+            SplMessageCodec::Bincode => {
+                bincode::serialize(&message).wrap_err("failed to serialize HulkMessage")
+            }
+            SplMessageCodec::SplStandardMessage { team_number, .. } => SplStandardMessage {
+                team_number: *team_number,
+                message,
+            }
+            .try_into(),
+        }
+    }
 }
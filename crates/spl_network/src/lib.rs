@@ -1,2 +1,3 @@
 pub mod endpoint;
 pub mod message_receiver;
+pub mod network_emulator;
@@ -0,0 +1,217 @@
+use std::time::{Duration, SystemTime};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use spl_network_messages::PlayerNumber;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct NetworkEmulatorParameters {
+    /// Probability in [0, 1] that a message is lost for a single receiver
+    pub packet_loss_probability: f32,
+    pub latency: Duration,
+    /// Maximum additional delay, drawn uniformly per receiver
+    pub jitter: Duration,
+    /// Shared medium capacity in bytes per second, unlimited if not set
+    pub maximum_bandwidth: Option<u32>,
+    /// Messages waiting longer than this for the shared medium are dropped
+    pub maximum_queueing_delay: Option<Duration>,
+    /// Pairs of players that cannot hear each other (in both directions)
+    pub partitions: Vec<(PlayerNumber, PlayerNumber)>,
+}
+
+impl NetworkEmulatorParameters {
+    pub fn is_partitioned(&self, sender: PlayerNumber, receiver: PlayerNumber) -> bool {
+        self.partitions.iter().any(|&(first, second)| {
+            (first == sender && second == receiver) || (first == receiver && second == sender)
+        })
+    }
+}
+
+struct InFlightMessage<Message> {
+    receiver: PlayerNumber,
+    arrival: SystemTime,
+    message: Message,
+}
+
+pub struct NetworkEmulator<Message> {
+    pub parameters: NetworkEmulatorParameters,
+    random_number_generator: StdRng,
+    medium_busy_until: Option<SystemTime>,
+    in_flight: Vec<InFlightMessage<Message>>,
+}
+
+impl<Message: Clone> NetworkEmulator<Message> {
+    pub fn new(parameters: NetworkEmulatorParameters, seed: u64) -> Self {
+        Self {
+            parameters,
+            random_number_generator: StdRng::seed_from_u64(seed),
+            medium_busy_until: None,
+            in_flight: Vec::new(),
+        }
+    }
+
+    /// Broadcasts a message of `size` bytes from `sender` to all `receivers` except the sender itself
+    pub fn send(
+        &mut self,
+        now: SystemTime,
+        sender: PlayerNumber,
+        receivers: impl IntoIterator<Item = PlayerNumber>,
+        size: usize,
+        message: Message,
+    ) {
+        let transmission_start = self
+            .medium_busy_until
+            .filter(|&busy_until| busy_until > now)
+            .unwrap_or(now);
+        let queueing_delay = transmission_start.duration_since(now).unwrap_or_default();
+        if self
+            .parameters
+            .maximum_queueing_delay
+            .is_some_and(|maximum_queueing_delay| queueing_delay > maximum_queueing_delay)
+        {
+            return;
+        }
+        let transmission_duration = match self.parameters.maximum_bandwidth {
+            Some(maximum_bandwidth) if maximum_bandwidth > 0 => {
+                Duration::from_nanos(size as u64 * 1_000_000_000 / maximum_bandwidth as u64)
+            }
+            _ => Duration::ZERO,
+        };
+        let transmission_end = transmission_start + transmission_duration;
+        self.medium_busy_until = Some(transmission_end);
+
+        for receiver in receivers {
+            if receiver == sender || self.parameters.is_partitioned(sender, receiver) {
+                continue;
+            }
+            if self
+                .random_number_generator
+                .gen_bool(self.parameters.packet_loss_probability.clamp(0.0, 1.0) as f64)
+            {
+                continue;
+            }
+            let jitter = self
+                .parameters
+                .jitter
+                .mul_f32(self.random_number_generator.gen_range(0.0..=1.0));
+            self.in_flight.push(InFlightMessage {
+                receiver,
+                arrival: transmission_end + self.parameters.latency + jitter,
+                message: message.clone(),
+            });
+        }
+    }
+
+    /// Returns all messages that arrived at `receiver` until `now`, ordered by arrival time
+    pub fn receive(&mut self, now: SystemTime, receiver: PlayerNumber) -> Vec<Message> {
+        let (mut arrived, in_flight): (Vec<_>, Vec<_>) = self
+            .in_flight
+            .drain(..)
+            .partition(|message| message.receiver == receiver && message.arrival <= now);
+        self.in_flight = in_flight;
+        arrived.sort_by_key(|message| message.arrival);
+        arrived.into_iter().map(|message| message.message).collect()
+    }
+
+    pub fn number_of_messages_in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+
+    const PLAYERS: [PlayerNumber; 3] = [PlayerNumber::One, PlayerNumber::Two, PlayerNumber::Three];
+
+    #[test]
+    fn perfect_network_delivers_immediately_to_everyone_else() {
+        let mut network = NetworkEmulator::new(NetworkEmulatorParameters::default(), 42);
+        network.send(UNIX_EPOCH, PlayerNumber::One, PLAYERS, 100, 1);
+
+        assert!(network.receive(UNIX_EPOCH, PlayerNumber::One).is_empty());
+        assert_eq!(network.receive(UNIX_EPOCH, PlayerNumber::Two), vec![1]);
+        assert_eq!(network.receive(UNIX_EPOCH, PlayerNumber::Three), vec![1]);
+        assert_eq!(network.number_of_messages_in_flight(), 0);
+    }
+
+    #[test]
+    fn latency_delays_delivery() {
+        let mut network = NetworkEmulator::new(
+            NetworkEmulatorParameters {
+                latency: Duration::from_millis(100),
+                ..Default::default()
+            },
+            42,
+        );
+        network.send(UNIX_EPOCH, PlayerNumber::One, PLAYERS, 100, 1);
+
+        assert!(network
+            .receive(UNIX_EPOCH + Duration::from_millis(99), PlayerNumber::Two)
+            .is_empty());
+        assert_eq!(
+            network.receive(UNIX_EPOCH + Duration::from_millis(100), PlayerNumber::Two),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn total_packet_loss_drops_everything() {
+        let mut network = NetworkEmulator::new(
+            NetworkEmulatorParameters {
+                packet_loss_probability: 1.0,
+                ..Default::default()
+            },
+            42,
+        );
+        network.send(UNIX_EPOCH, PlayerNumber::One, PLAYERS, 100, 1);
+
+        assert_eq!(network.number_of_messages_in_flight(), 0);
+    }
+
+    #[test]
+    fn partitioned_players_do_not_hear_each_other() {
+        let mut network = NetworkEmulator::new(
+            NetworkEmulatorParameters {
+                partitions: vec![(PlayerNumber::Two, PlayerNumber::One)],
+                ..Default::default()
+            },
+            42,
+        );
+        network.send(UNIX_EPOCH, PlayerNumber::One, PLAYERS, 100, 1);
+        network.send(UNIX_EPOCH, PlayerNumber::Two, PLAYERS, 100, 2);
+
+        assert!(network.receive(UNIX_EPOCH, PlayerNumber::One).is_empty());
+        assert!(network.receive(UNIX_EPOCH, PlayerNumber::Two).is_empty());
+        assert_eq!(network.receive(UNIX_EPOCH, PlayerNumber::Three), vec![1, 2]);
+    }
+
+    #[test]
+    fn bandwidth_cap_serializes_transmissions() {
+        let mut network = NetworkEmulator::new(
+            NetworkEmulatorParameters {
+                maximum_bandwidth: Some(1000),
+                maximum_queueing_delay: Some(Duration::from_millis(150)),
+                ..Default::default()
+            },
+            42,
+        );
+        network.send(UNIX_EPOCH, PlayerNumber::One, PLAYERS, 100, 1);
+        network.send(UNIX_EPOCH, PlayerNumber::One, PLAYERS, 100, 2);
+        network.send(UNIX_EPOCH, PlayerNumber::One, PLAYERS, 100, 3);
+
+        assert_eq!(
+            network.receive(UNIX_EPOCH + Duration::from_millis(100), PlayerNumber::Two),
+            vec![1]
+        );
+        assert_eq!(
+            network.receive(UNIX_EPOCH + Duration::from_millis(200), PlayerNumber::Two),
+            vec![2]
+        );
+        assert!(network
+            .receive(UNIX_EPOCH + Duration::from_secs(1), PlayerNumber::Two)
+            .is_empty());
+    }
+}
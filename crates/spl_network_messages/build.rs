@@ -5,6 +5,7 @@ use bindgen::{Builder, CargoCallbacks};
 fn main() {
     let bindings = Builder::default()
         .header("headers/RoboCupGameControlData.hpp")
+        .header("headers/SPLStandardMessage.hpp")
         .header("headers/VisualRefereeChallenge.hpp")
         .parse_callbacks(Box::new(CargoCallbacks))
         .layout_tests(false)
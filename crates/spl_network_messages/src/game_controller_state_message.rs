@@ -229,7 +229,9 @@ impl CompetitionType {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, SerializeHierarchy,
+)]
 pub enum GamePhase {
     #[default]
     Normal,
@@ -314,7 +316,9 @@ impl SubState {
     }
 }
 
-#[derive(Default, Clone, Copy, Debug, Deserialize, Serialize, SerializeHierarchy)]
+#[derive(
+    Default, Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, SerializeHierarchy,
+)]
 pub enum SubState {
     #[default]
     GoalKick,
@@ -355,7 +359,7 @@ pub struct TeamState {
     pub players: Vec<Player>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, SerializeHierarchy)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, SerializeHierarchy)]
 pub enum TeamColor {
     Blue,
     Red,
@@ -411,7 +415,7 @@ impl TryFrom<RobotInfo> for Player {
     }
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, Serialize, SerializeHierarchy)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, SerializeHierarchy)]
 pub enum Penalty {
     IllegalBallContact { remaining: Duration },
     PlayerPushing { remaining: Duration },
@@ -1,6 +1,7 @@
 mod bindings;
 mod game_controller_return_message;
 mod game_controller_state_message;
+mod spl_standard_message;
 mod visual_referee_message;
 
 use std::{
@@ -9,15 +10,16 @@ use std::{
 };
 
 use coordinate_systems::Field;
-use linear_algebra::{Point2, Pose};
+use linear_algebra::{Point2, Pose, Vector2};
 use serde::{Deserialize, Serialize};
 
 pub use game_controller_return_message::GameControllerReturnMessage;
 pub use game_controller_state_message::{
-    GameControllerStateMessage, GamePhase, GameState, Half, Penalty, PenaltyShoot, Player,
-    SubState, Team, TeamColor, TeamState,
+    CompetitionPhase, CompetitionType, GameControllerStateMessage, GamePhase, GameState, Half,
+    Penalty, PenaltyShoot, Player, SubState, Team, TeamColor, TeamState,
 };
 use serialize_hierarchy::SerializeHierarchy;
+pub use spl_standard_message::SplStandardMessage;
 pub use visual_referee_message::{VisualRefereeDecision, VisualRefereeMessage};
 
 #[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
@@ -25,8 +27,12 @@ pub struct HulkMessage {
     pub player_number: PlayerNumber,
     pub fallen: bool,
     pub pose: Pose<Field>,
+    /// Confidence in `pose` between 0 and 1, if the sender estimates it
+    pub localization_confidence: Option<f32>,
     pub ball_position: Option<BallPosition<Field>>,
     pub time_to_reach_kick_position: Option<Duration>,
+    pub whistle: Option<WhistleObservation>,
+    pub pass: Option<PassAnnouncement>,
 }
 
 #[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -35,6 +41,20 @@ pub struct BallPosition<Frame> {
     pub age: Duration,
 }
 
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct WhistleObservation {
+    pub age: Duration,
+    /// Direction from the sender towards the whistle, if it could be estimated
+    pub direction: Option<Vector2<Field>>,
+}
+
+/// Announces that the sender is about to pass the ball to the receiver
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, SerializeHierarchy)]
+pub struct PassAnnouncement {
+    pub receiver: PlayerNumber,
+    pub target: Point2<Field>,
+}
+
 pub const HULKS_TEAM_NUMBER: u8 = 24;
 
 #[derive(
@@ -71,9 +91,9 @@ impl Display for PlayerNumber {
 mod tests {
     use std::time::Duration;
 
-    use linear_algebra::{Point, Pose};
+    use linear_algebra::{vector, Point, Pose};
 
-    use crate::{BallPosition, HulkMessage, PlayerNumber};
+    use crate::{BallPosition, HulkMessage, PassAnnouncement, PlayerNumber, WhistleObservation};
 
     #[test]
     fn maximum_hulk_message_size() {
@@ -81,11 +101,20 @@ mod tests {
             player_number: PlayerNumber::Seven,
             fallen: false,
             pose: Pose::default(),
+            localization_confidence: Some(1.0),
             ball_position: Some(BallPosition {
                 position: Point::origin(),
                 age: Duration::MAX,
             }),
             time_to_reach_kick_position: Some(Duration::MAX),
+            whistle: Some(WhistleObservation {
+                age: Duration::MAX,
+                direction: Some(vector![1.0, 0.0]),
+            }),
+            pass: Some(PassAnnouncement {
+                receiver: PlayerNumber::Seven,
+                target: Point::origin(),
+            }),
         };
         assert!(bincode::serialize(&test_message).unwrap().len() <= 128)
     }
@@ -0,0 +1,338 @@
+use std::{ffi::c_char, mem::size_of, ptr::read_unaligned, slice::from_raw_parts, time::Duration};
+
+use color_eyre::{
+    eyre::{bail, WrapErr},
+    Report, Result,
+};
+use coordinate_systems::Ground;
+use linear_algebra::{point, vector, Pose};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bindings::{
+        SPLStandardMessage, SPL_STANDARD_MESSAGE_DATA_SIZE, SPL_STANDARD_MESSAGE_STRUCT_HEADER,
+        SPL_STANDARD_MESSAGE_STRUCT_VERSION,
+    },
+    BallPosition, HulkMessage, PassAnnouncement, PlayerNumber, WhistleObservation,
+};
+
+/// Marks the data section of standard messages sent by HULKs robots
+const HULKS_DATA_HEADER: [u8; 4] = *b"HULK";
+
+/// HULKs-specific fields of a `HulkMessage`, bincode encoded in the data section
+#[derive(Default, Deserialize, Serialize)]
+struct HulksData {
+    time_to_reach_kick_position: Option<Duration>,
+    whistle: Option<WhistleObservation>,
+    localization_confidence: Option<f32>,
+    pass: Option<PassAnnouncement>,
+}
+
+/// A `HulkMessage` in the official SPL standard message layout, understood by robots of other teams
+/// in mixed-team and drop-in games
+///
+/// Fields only HULKs robots know about (e.g. the time to reach the kick position) are transported in
+/// the data section and are `None` in messages of other teams.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct SplStandardMessage {
+    pub team_number: u8,
+    pub message: HulkMessage,
+}
+
+impl TryFrom<&[u8]> for SplStandardMessage {
+    type Error = Report;
+
+    fn try_from(buffer: &[u8]) -> Result<Self> {
+        let header_size = size_of::<SPLStandardMessage>() - SPL_STANDARD_MESSAGE_DATA_SIZE as usize;
+        if buffer.len() < header_size {
+            bail!("buffer too small");
+        }
+        // other teams are allowed to omit the unused part of the data section
+        let mut padded_buffer = [0; size_of::<SPLStandardMessage>()];
+        let length = buffer.len().min(padded_buffer.len());
+        padded_buffer[..length].copy_from_slice(&buffer[..length]);
+        let message =
+            unsafe { read_unaligned(padded_buffer.as_ptr() as *const SPLStandardMessage) };
+        message.try_into()
+    }
+}
+
+impl TryFrom<SPLStandardMessage> for SplStandardMessage {
+    type Error = Report;
+
+    fn try_from(message: SPLStandardMessage) -> Result<Self> {
+        if message
+            .header
+            .iter()
+            .zip(SPL_STANDARD_MESSAGE_STRUCT_HEADER)
+            .any(|(&received, &expected)| received != expected as c_char)
+        {
+            bail!("unexpected header");
+        }
+        if message.version != SPL_STANDARD_MESSAGE_STRUCT_VERSION {
+            bail!("unexpected version");
+        }
+        let pose = Pose::new(
+            vector![message.pose[0] / 1000.0, message.pose[1] / 1000.0],
+            message.pose[2],
+        );
+        let ball_position = if message.ballAge < 0.0 {
+            None
+        } else {
+            let ball_in_ground = point![message.ball[0] / 1000.0, message.ball[1] / 1000.0];
+            Some(BallPosition {
+                position: pose.as_transform::<Ground>() * ball_in_ground,
+                age: Duration::try_from_secs_f32(message.ballAge).wrap_err("invalid ball age")?,
+            })
+        };
+        let number_of_data_bytes =
+            (message.numOfDataBytes as usize).min(SPL_STANDARD_MESSAGE_DATA_SIZE as usize);
+        let hulks_data = hulks_data_from_data(&message.data[..number_of_data_bytes]);
+        Ok(Self {
+            team_number: message.teamNum,
+            message: HulkMessage {
+                player_number: match message.playerNum {
+                    1 => PlayerNumber::One,
+                    2 => PlayerNumber::Two,
+                    3 => PlayerNumber::Three,
+                    4 => PlayerNumber::Four,
+                    5 => PlayerNumber::Five,
+                    6 => PlayerNumber::Six,
+                    7 => PlayerNumber::Seven,
+                    _ => bail!("unexpected player number {}", message.playerNum),
+                },
+                fallen: match message.fallen {
+                    1 => true,
+                    0 => false,
+                    _ => bail!("unexpected fallen state"),
+                },
+                pose,
+                localization_confidence: hulks_data.localization_confidence,
+                ball_position,
+                time_to_reach_kick_position: hulks_data.time_to_reach_kick_position,
+                whistle: hulks_data.whistle,
+                pass: hulks_data.pass,
+            },
+        })
+    }
+}
+
+impl TryFrom<SplStandardMessage> for Vec<u8> {
+    type Error = Report;
+
+    fn try_from(message: SplStandardMessage) -> Result<Self> {
+        let message: SPLStandardMessage = message.try_into()?;
+        let header_size = size_of::<SPLStandardMessage>() - SPL_STANDARD_MESSAGE_DATA_SIZE as usize;
+        Ok(unsafe {
+            from_raw_parts(
+                &message as *const SPLStandardMessage as *const u8,
+                header_size + message.numOfDataBytes as usize,
+            )
+        }
+        .to_vec())
+    }
+}
+
+impl TryFrom<SplStandardMessage> for SPLStandardMessage {
+    type Error = Report;
+
+    fn try_from(message: SplStandardMessage) -> Result<Self> {
+        let SplStandardMessage {
+            team_number,
+            message,
+        } = message;
+        let (ball_position, ball_age) = match &message.ball_position {
+            Some(ball_position) => {
+                let ball_in_ground =
+                    message.pose.as_transform::<Ground>().inverse() * ball_position.position;
+                (
+                    [ball_in_ground.x() * 1000.0, ball_in_ground.y() * 1000.0],
+                    ball_position.age.as_secs_f32(),
+                )
+            }
+            None => ([0.0; 2], -1.0),
+        };
+        let hulks_data = bincode::serialize(&HulksData {
+            time_to_reach_kick_position: message.time_to_reach_kick_position,
+            whistle: message.whistle,
+            localization_confidence: message.localization_confidence,
+            pass: message.pass,
+        })
+        .wrap_err("failed to serialize HULKs data")?;
+        let number_of_data_bytes = HULKS_DATA_HEADER.len() + hulks_data.len();
+        if number_of_data_bytes > SPL_STANDARD_MESSAGE_DATA_SIZE as usize {
+            bail!("HULKs data does not fit into the data section");
+        }
+        let mut data = [0; SPL_STANDARD_MESSAGE_DATA_SIZE as usize];
+        data[..HULKS_DATA_HEADER.len()].copy_from_slice(&HULKS_DATA_HEADER);
+        data[HULKS_DATA_HEADER.len()..number_of_data_bytes].copy_from_slice(&hulks_data);
+        Ok(SPLStandardMessage {
+            header: [
+                SPL_STANDARD_MESSAGE_STRUCT_HEADER[0] as c_char,
+                SPL_STANDARD_MESSAGE_STRUCT_HEADER[1] as c_char,
+                SPL_STANDARD_MESSAGE_STRUCT_HEADER[2] as c_char,
+                SPL_STANDARD_MESSAGE_STRUCT_HEADER[3] as c_char,
+            ],
+            version: SPL_STANDARD_MESSAGE_STRUCT_VERSION,
+            playerNum: match message.player_number {
+                PlayerNumber::One => 1,
+                PlayerNumber::Two => 2,
+                PlayerNumber::Three => 3,
+                PlayerNumber::Four => 4,
+                PlayerNumber::Five => 5,
+                PlayerNumber::Six => 6,
+                PlayerNumber::Seven => 7,
+            },
+            teamNum: team_number,
+            fallen: u8::from(message.fallen),
+            pose: [
+                message.pose.position().x() * 1000.0,
+                message.pose.position().y() * 1000.0,
+                message.pose.orientation().angle(),
+            ],
+            ballAge: ball_age,
+            ball: ball_position,
+            numOfDataBytes: number_of_data_bytes as u16,
+            data,
+        })
+    }
+}
+
+/// Data sections of other teams are ignored
+fn hulks_data_from_data(data: &[u8]) -> HulksData {
+    match data.strip_prefix(&HULKS_DATA_HEADER) {
+        Some(hulks_data) => bincode::deserialize(hulks_data).unwrap_or_default(),
+        None => HulksData::default(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f32::consts::FRAC_PI_2;
+
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    fn hulk_message() -> HulkMessage {
+        HulkMessage {
+            player_number: PlayerNumber::Three,
+            fallen: false,
+            pose: Pose::new(vector![1.0, 2.0], FRAC_PI_2),
+            localization_confidence: Some(0.8),
+            ball_position: Some(BallPosition {
+                position: point![1.0, 3.0],
+                age: Duration::from_millis(500),
+            }),
+            time_to_reach_kick_position: Some(Duration::from_millis(4200)),
+            whistle: Some(WhistleObservation {
+                age: Duration::from_millis(200),
+                direction: Some(vector![0.0, 1.0]),
+            }),
+            pass: Some(PassAnnouncement {
+                receiver: PlayerNumber::Five,
+                target: point![2.0, -1.0],
+            }),
+        }
+    }
+
+    #[test]
+    fn ball_is_sent_relative_to_robot() {
+        let output_message: SPLStandardMessage = SplStandardMessage {
+            team_number: 24,
+            message: hulk_message(),
+        }
+        .try_into()
+        .unwrap();
+
+        assert_relative_eq!(output_message.ball[0], 1000.0, epsilon = 0.001);
+        assert_relative_eq!(output_message.ball[1], 0.0, epsilon = 0.001);
+        assert_relative_eq!(output_message.ballAge, 0.5);
+    }
+
+    #[test]
+    fn round_trip_keeps_hulks_fields() {
+        let buffer: Vec<u8> = SplStandardMessage {
+            team_number: 24,
+            message: hulk_message(),
+        }
+        .try_into()
+        .unwrap();
+        let input_message = SplStandardMessage::try_from(buffer.as_slice()).unwrap();
+
+        assert_eq!(input_message.team_number, 24);
+        assert_eq!(input_message.message.player_number, PlayerNumber::Three);
+        assert_relative_eq!(
+            input_message.message.pose,
+            hulk_message().pose,
+            epsilon = 0.001
+        );
+        assert_relative_eq!(
+            input_message.message.ball_position.unwrap().position,
+            point![1.0, 3.0],
+            epsilon = 0.001
+        );
+        assert_eq!(
+            input_message.message.time_to_reach_kick_position,
+            Some(Duration::from_millis(4200))
+        );
+        assert_relative_eq!(
+            input_message.message.whistle.unwrap().direction.unwrap(),
+            vector![0.0, 1.0]
+        );
+        assert_eq!(input_message.message.localization_confidence, Some(0.8));
+        let pass = input_message.message.pass.unwrap();
+        assert_eq!(pass.receiver, PlayerNumber::Five);
+        assert_relative_eq!(pass.target, point![2.0, -1.0]);
+    }
+
+    #[test]
+    fn foreign_data_is_ignored() {
+        let mut output_message: SPLStandardMessage = SplStandardMessage {
+            team_number: 5,
+            message: hulk_message(),
+        }
+        .try_into()
+        .unwrap();
+        output_message.data[0] = b'B';
+        let input_message: SplStandardMessage = output_message.try_into().unwrap();
+
+        assert_eq!(input_message.message.time_to_reach_kick_position, None);
+        assert!(input_message.message.whistle.is_none());
+        assert_eq!(input_message.message.localization_confidence, None);
+        assert!(input_message.message.pass.is_none());
+    }
+
+    #[test]
+    fn unseen_ball_is_none() {
+        let mut message = hulk_message();
+        message.ball_position = None;
+        message.time_to_reach_kick_position = None;
+        message.whistle = None;
+        let buffer: Vec<u8> = SplStandardMessage {
+            team_number: 24,
+            message,
+        }
+        .try_into()
+        .unwrap();
+        let input_message = SplStandardMessage::try_from(buffer.as_slice()).unwrap();
+
+        assert!(input_message.message.ball_position.is_none());
+        assert_eq!(input_message.message.time_to_reach_kick_position, None);
+    }
+
+    #[test]
+    fn invalid_ball_age_is_rejected() {
+        let mut output_message: SPLStandardMessage = SplStandardMessage {
+            team_number: 5,
+            message: hulk_message(),
+        }
+        .try_into()
+        .unwrap();
+        for ball_age in [f32::NAN, f32::INFINITY, f32::MAX] {
+            output_message.ballAge = ball_age;
+
+            assert!(SplStandardMessage::try_from(output_message).is_err());
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
 
-#[derive(Debug, Clone, Copy, SerializeHierarchy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, SerializeHierarchy, Serialize, Deserialize)]
 pub enum Action {
     Unstiff,
     SitDown,
@@ -11,6 +11,7 @@ pub enum Action {
     StandUp,
     Stand,
     LookAround,
+    BlockShot,
     InterceptBall,
     Calibrate,
     Dribble,
@@ -19,8 +20,11 @@ pub enum Action {
     DefendLeft,
     DefendRight,
     DefendPenaltyKick,
+    DefendSetPlay,
     Jump,
     PrepareJump,
+    PrepareSetPlay,
+    ReceivePass,
     SupportLeft,
     SupportRight,
     SupportStriker,
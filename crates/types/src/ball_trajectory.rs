@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serialize_hierarchy::SerializeHierarchy;
+
+use linear_algebra::{Point2, Vector2};
+
+/// Ball rolling on a straight line while carpet friction decelerates it at a constant rate
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, SerializeHierarchy)]
+pub struct BallTrajectory<Frame> {
+    pub position: Point2<Frame>,
+    pub velocity: Vector2<Frame>,
+    /// Positive deceleration in m/s²
+    pub deceleration: f32,
+}
+
+impl<Frame> BallTrajectory<Frame> {
+    /// Returns `Duration::MAX` if the ball never comes to rest, e.g. without deceleration
+    pub fn time_to_rest(&self) -> Duration {
+        let speed = self.velocity.norm();
+        if speed == 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::try_from_secs_f32(speed / self.deceleration()).unwrap_or(Duration::MAX)
+    }
+
+    pub fn resting_position(&self) -> Point2<Frame> {
+        self.position_at(self.time_to_rest())
+    }
+
+    pub fn rolling_distance(&self) -> f32 {
+        let speed_squared = self.velocity.norm_squared();
+        if speed_squared == 0.0 {
+            return 0.0;
+        }
+        speed_squared / (2.0 * self.deceleration())
+    }
+
+    pub fn position_at(&self, time: Duration) -> Point2<Frame> {
+        let time = time.min(self.time_to_rest()).as_secs_f32();
+        let speed = self.velocity.norm();
+        if speed == 0.0 {
+            return self.position;
+        }
+        let travelled_distance = speed * time - self.deceleration() * time.powi(2) / 2.0;
+        self.position + self.velocity * (travelled_distance / speed)
+    }
+
+    pub fn velocity_at(&self, time: Duration) -> Vector2<Frame> {
+        let speed = self.velocity.norm();
+        if speed == 0.0 {
+            return self.velocity;
+        }
+        let remaining_speed = (speed - self.deceleration() * time.as_secs_f32()).max(0.0);
+        self.velocity * (remaining_speed / speed)
+    }
+
+    /// Time until the ball passes the point closest to `point` on its trajectory
+    ///
+    /// Returns `None` if that point lies behind the ball or the ball comes to rest before it.
+    pub fn time_to_reach(&self, point: Point2<Frame>) -> Option<Duration> {
+        let direction = self.velocity.try_normalize(f32::EPSILON)?;
+        let distance_along_trajectory = (point - self.position).dot(direction);
+        if distance_along_trajectory < 0.0 || distance_along_trajectory > self.rolling_distance() {
+            return None;
+        }
+        let speed = self.velocity.norm();
+        let deceleration = self.deceleration();
+        if deceleration == 0.0 {
+            return Duration::try_from_secs_f32(distance_along_trajectory / speed).ok();
+        }
+        let discriminant =
+            (speed.powi(2) - 2.0 * deceleration * distance_along_trajectory).max(0.0);
+        Duration::try_from_secs_f32((speed - discriminant.sqrt()) / deceleration).ok()
+    }
+
+    /// Negative or NaN decelerations are treated as a ball rolling without friction
+    fn deceleration(&self) -> f32 {
+        self.deceleration.max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use coordinate_systems::Ground;
+    use linear_algebra::{point, vector};
+
+    use super::*;
+
+    fn trajectory() -> BallTrajectory<Ground> {
+        BallTrajectory {
+            position: point![1.0, 0.0],
+            velocity: vector![-2.0, 0.0],
+            deceleration: 0.5,
+        }
+    }
+
+    #[test]
+    fn ball_comes_to_rest_after_rolling_distance() {
+        let trajectory = trajectory();
+
+        assert_relative_eq!(trajectory.time_to_rest().as_secs_f32(), 4.0);
+        assert_relative_eq!(trajectory.rolling_distance(), 4.0);
+        assert_relative_eq!(trajectory.resting_position(), point![-3.0, 0.0]);
+        assert_relative_eq!(
+            trajectory.position_at(Duration::from_secs(10)),
+            point![-3.0, 0.0]
+        );
+        assert_relative_eq!(
+            trajectory.velocity_at(Duration::from_secs(1)),
+            vector![-1.5, 0.0]
+        );
+    }
+
+    #[test]
+    fn time_to_reach_point_on_trajectory() {
+        let trajectory = trajectory();
+
+        let time = trajectory
+            .time_to_reach(point![-2.0, 0.5])
+            .expect("point should be reached");
+
+        assert_relative_eq!(time.as_secs_f32(), 2.0, epsilon = 1e-5);
+        assert_relative_eq!(
+            trajectory.position_at(time),
+            point![-2.0, 0.0],
+            epsilon = 1e-5
+        );
+        assert_eq!(trajectory.time_to_reach(point![2.0, 0.0]), None);
+        assert_eq!(trajectory.time_to_reach(point![-3.5, 0.0]), None);
+    }
+
+    #[test]
+    fn ball_without_deceleration_never_comes_to_rest() {
+        for deceleration in [0.0, -1.0, f32::NAN] {
+            let trajectory = BallTrajectory {
+                deceleration,
+                ..trajectory()
+            };
+
+            assert_eq!(trajectory.time_to_rest(), Duration::MAX);
+            assert_eq!(trajectory.rolling_distance(), f32::INFINITY);
+            assert_relative_eq!(
+                trajectory.position_at(Duration::from_secs(1)),
+                point![-1.0, 0.0]
+            );
+            assert_relative_eq!(
+                trajectory
+                    .time_to_reach(point![-3.0, 0.0])
+                    .expect("point should be reached")
+                    .as_secs_f32(),
+                2.0
+            );
+        }
+    }
+}
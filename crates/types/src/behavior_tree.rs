@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serialize_hierarchy::SerializeHierarchy;
+use spl_network_messages::{SubState, Team};
+
+use crate::{action::Action, roles::Role};
+
+/// Node of a behavior tree, evaluated depth first from left to right
+///
+/// An action which produces a motion command ends the evaluation of the whole tree.
+#[derive(Clone, Debug, Deserialize, Serialize, SerializeHierarchy)]
+pub enum BehaviorTree {
+    /// Evaluates the children until one does not fail
+    Selector(Vec<BehaviorTree>),
+    /// Evaluates the children until one does not succeed
+    Sequence(Vec<BehaviorTree>),
+    /// Succeeds if the condition holds, fails otherwise
+    Condition(Condition),
+    /// Swaps success and failure of the child
+    Inverter(Box<BehaviorTree>),
+    /// Fails once the child has not failed for longer than the duration
+    MaximumDuration {
+        duration: Duration,
+        child: Box<BehaviorTree>,
+    },
+    /// Runs if the action produces a motion command, fails otherwise
+    Action(Action),
+}
+
+impl Default for BehaviorTree {
+    fn default() -> Self {
+        Self::Selector(Vec::new())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, SerializeHierarchy)]
+pub enum Condition {
+    Role(Role),
+    /// The robot is in ready, set or playing for less than the duration
+    ActiveForLessThan(Duration),
+    /// Playing with a free ball or no game controller is present
+    BallIsFree,
+    Ready,
+    /// Ready with the given kicking team, which is uncertain after a whistle in playing
+    ReadyWithKickingTeam(Team),
+    KickingTeam(Team),
+    SubState(SubState),
+    /// Any sub state except the penalty kick, i.e. a kick-in, goal kick, corner kick or pushing free
+    /// kick
+    SetPlay,
+    PenaltyShootout,
+}
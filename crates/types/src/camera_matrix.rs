@@ -1,4 +1,4 @@
-use nalgebra::{Matrix, Rotation3};
+use nalgebra::{Matrix, Rotation3, UnitQuaternion};
 use serde::{Deserialize, Serialize};
 
 use approx_derive::{AbsDiffEq, RelativeEq};
@@ -6,7 +6,10 @@ use coordinate_systems::{Camera, Ground, Head, Pixel, Robot};
 use linear_algebra::{IntoTransform, Isometry3, Point2};
 use serialize_hierarchy::SerializeHierarchy;
 
-use crate::{horizon::Horizon, line::Line2};
+use crate::{
+    camera_position::CameraPosition, horizon::Horizon, line::Line2,
+    robot_dimensions::RobotDimensions,
+};
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct CameraMatrices {
@@ -159,6 +162,30 @@ impl CameraMatrix {
     }
 }
 
+pub fn camera_to_head(
+    camera_position: CameraPosition,
+    extrinsic_rotation: nalgebra::Vector3<f32>,
+) -> Isometry3<Camera, Head> {
+    let extrinsic_angles_in_radians = extrinsic_rotation.map(|a: f32| a.to_radians());
+    let extrinsic_rotation = UnitQuaternion::from_euler_angles(
+        extrinsic_angles_in_radians.x,
+        extrinsic_angles_in_radians.y,
+        extrinsic_angles_in_radians.z,
+    );
+    let neck_to_camera = match camera_position {
+        CameraPosition::Top => RobotDimensions::NECK_TO_TOP_CAMERA,
+        CameraPosition::Bottom => RobotDimensions::NECK_TO_BOTTOM_CAMERA,
+    };
+    let camera_pitch = match camera_position {
+        CameraPosition::Top => 1.2f32.to_radians(),
+        CameraPosition::Bottom => 39.7f32.to_radians(),
+    };
+    (nalgebra::Isometry3::from(neck_to_camera.inner)
+        * nalgebra::Isometry3::rotation(nalgebra::Vector3::y() * camera_pitch)
+        * extrinsic_rotation)
+        .framed_transform()
+}
+
 #[cfg(test)]
 mod tests {
     use approx::assert_relative_eq;
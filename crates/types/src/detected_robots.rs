@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use serialize_hierarchy::SerializeHierarchy;
+
+use coordinate_systems::{Ground, Pixel};
+use geometry::rectangle::Rectangle;
+use linear_algebra::Point2;
+use spl_network_messages::{Team, TeamColor};
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, SerializeHierarchy)]
+pub struct DetectedRobot {
+    pub bounding_box: Rectangle<Pixel>,
+    pub confidence: f32,
+    /// Projection of the bottom center of the bounding box, i.e. between the feet
+    pub position: Point2<Ground>,
+    pub jersey_color: Option<TeamColor>,
+    pub team: Team,
+}
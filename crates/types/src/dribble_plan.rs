@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+use coordinate_systems::Ground;
+use linear_algebra::Point2;
+use serialize_hierarchy::SerializeHierarchy;
+
+use crate::kick_decision::KickDecision;
+
+/// A single ball push of a planned dribbling sequence
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, SerializeHierarchy)]
+pub struct DribbleStep {
+    pub kick_decision: KickDecision,
+    pub ball_start: Point2<Ground>,
+    pub ball_end: Point2<Ground>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct DribblePlan {
+    /// Pushes in the order they are executed, only the first one is acted upon
+    pub steps: Vec<DribbleStep>,
+    pub cost: f32,
+    /// The first push is the best decision of the kick selector, no searched push was cheaper
+    pub follows_kick_selector: bool,
+}
@@ -25,6 +25,25 @@ pub struct FieldDimensions {
 }
 
 impl FieldDimensions {
+    /// Dimensions of the standard SPL field, matching the default parameters
+    pub const SPL: Self = Self {
+        ball_radius: 0.05,
+        length: 9.0,
+        width: 6.0,
+        line_width: 0.05,
+        penalty_marker_size: 0.1,
+        goal_box_area_length: 0.6,
+        goal_box_area_width: 2.2,
+        penalty_area_length: 1.65,
+        penalty_area_width: 4.0,
+        penalty_marker_distance: 1.3,
+        center_circle_diameter: 1.5,
+        border_strip_width: 0.7,
+        goal_inner_width: 1.5,
+        goal_post_diameter: 0.1,
+        goal_depth: 0.5,
+    };
+
     pub fn is_inside_field(&self, position: Point2<Field>) -> bool {
         position.x().abs() < self.length / 2.0 && position.y().abs() < self.width / 2.0
     }
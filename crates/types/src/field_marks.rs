@@ -19,6 +19,18 @@ pub enum FieldMark {
         center: Point2<Field>,
         radius: f32,
     },
+    /// Point where lines meet, measured lines are never fitted to it
+    Intersection {
+        point: Point2<Field>,
+        kind: IntersectionKind,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum IntersectionKind {
+    L,
+    T,
+    X,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
@@ -28,6 +40,15 @@ pub enum Direction {
 }
 
 impl FieldMark {
+    /// Length of line and circle marks, for circles only approximated by the radius
+    pub fn length(&self) -> Option<f32> {
+        match self {
+            FieldMark::Line { line, direction: _ } => Some(line.length()),
+            FieldMark::Circle { center: _, radius } => Some(*radius),
+            FieldMark::Intersection { .. } => None,
+        }
+    }
+
     pub fn to_correspondence_points(self, measured_line: Line2<Field>) -> Correspondences {
         match self {
             FieldMark::Line {
@@ -141,6 +162,9 @@ impl FieldMark {
                     reference_direction,
                 }
             }
+            FieldMark::Intersection { .. } => {
+                panic!("measured lines cannot correspond to an intersection")
+            }
         }
     }
 }
@@ -159,7 +183,7 @@ pub struct CorrespondencePoints {
 }
 
 pub fn field_marks_from_field_dimensions(field_dimensions: &FieldDimensions) -> Vec<FieldMark> {
-    vec![
+    let lines_and_circles = [
         FieldMark::Line {
             line: Line(
                 point![-field_dimensions.length / 2.0, field_dimensions.width / 2.0],
@@ -419,5 +443,59 @@ pub fn field_marks_from_field_dimensions(field_dimensions: &FieldDimensions) ->
             ),
             direction: Direction::PositiveY,
         },
+    ];
+    lines_and_circles
+        .into_iter()
+        .chain(intersections_from_field_dimensions(field_dimensions))
+        .collect()
+}
+
+fn intersections_from_field_dimensions(field_dimensions: &FieldDimensions) -> Vec<FieldMark> {
+    let half_length = field_dimensions.length / 2.0;
+    let half_width = field_dimensions.width / 2.0;
+    let half_goal_box_area_width = field_dimensions.goal_box_area_width / 2.0;
+    let half_penalty_area_width = field_dimensions.penalty_area_width / 2.0;
+    let goal_box_area_x = half_length - field_dimensions.goal_box_area_length;
+    let penalty_area_x = half_length - field_dimensions.penalty_area_length;
+    let center_circle_radius = field_dimensions.center_circle_diameter / 2.0;
+
+    let mirrored_in_both_halves = |x: f32, y: f32, kind: IntersectionKind| {
+        [point![x, y], point![x, -y], point![-x, y], point![-x, -y]]
+            .map(|point| FieldMark::Intersection { point, kind })
+    };
+    let corners = mirrored_in_both_halves(half_length, half_width, IntersectionKind::L);
+    let goal_box_area_corners = mirrored_in_both_halves(
+        goal_box_area_x,
+        half_goal_box_area_width,
+        IntersectionKind::L,
+    );
+    let penalty_area_corners =
+        mirrored_in_both_halves(penalty_area_x, half_penalty_area_width, IntersectionKind::L);
+    let goal_box_area_junctions =
+        mirrored_in_both_halves(half_length, half_goal_box_area_width, IntersectionKind::T);
+    let penalty_area_junctions =
+        mirrored_in_both_halves(half_length, half_penalty_area_width, IntersectionKind::T);
+    let center_line_junctions =
+        [point![0.0, half_width], point![0.0, -half_width]].map(|point| FieldMark::Intersection {
+            point,
+            kind: IntersectionKind::T,
+        });
+    let center_circle_crossings = [
+        point![0.0, center_circle_radius],
+        point![0.0, -center_circle_radius],
     ]
+    .map(|point| FieldMark::Intersection {
+        point,
+        kind: IntersectionKind::X,
+    });
+
+    corners
+        .into_iter()
+        .chain(goal_box_area_corners)
+        .chain(penalty_area_corners)
+        .chain(goal_box_area_junctions)
+        .chain(penalty_area_junctions)
+        .chain(center_line_junctions)
+        .chain(center_circle_crossings)
+        .collect()
 }
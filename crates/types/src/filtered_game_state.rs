@@ -2,7 +2,9 @@ use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
 use spl_network_messages::Team;
 
-#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, SerializeHierarchy)]
+#[derive(
+    Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize, SerializeHierarchy,
+)]
 pub enum FilteredGameState {
     #[default]
     Initial,
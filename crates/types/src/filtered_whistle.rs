@@ -1,5 +1,7 @@
 use std::time::SystemTime;
 
+use coordinate_systems::Field;
+use linear_algebra::Vector2;
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
 
@@ -9,3 +11,11 @@ pub struct FilteredWhistle {
     pub started_this_cycle: bool,
     pub last_detection: Option<SystemTime>,
 }
+
+/// A whistle this robot heard itself, not yet confirmed by teammates
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, SerializeHierarchy)]
+pub struct HeardWhistle {
+    pub detection_time: SystemTime,
+    /// Direction from this robot towards the whistle, if it could be localized
+    pub direction: Option<Vector2<Field>>,
+}
@@ -2,7 +2,7 @@ use std::time::SystemTime;
 
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
-use spl_network_messages::{GamePhase, GameState, Penalty, SubState, Team};
+use spl_network_messages::{GamePhase, GameState, Penalty, SubState, Team, TeamColor};
 
 use crate::players::Players;
 
@@ -16,4 +16,12 @@ pub struct GameControllerState {
     pub remaining_amount_of_messages: u16,
     pub sub_state: Option<SubState>,
     pub hulks_team_is_home_after_coin_toss: bool,
+    pub hulks_jersey_colors: JerseyColors,
+    pub opponent_jersey_colors: JerseyColors,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, SerializeHierarchy)]
+pub struct JerseyColors {
+    pub field_player: TeamColor,
+    pub goal_keeper: TeamColor,
 }
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use coordinate_systems::Field;
+use linear_algebra::{point, Point2};
+
+use crate::field_dimensions::FieldDimensions;
+
+/// Line intersections are field marks, see [`crate::field_marks::FieldMark::Intersection`]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum LandmarkKind {
+    GoalPost,
+    PenaltyMark,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Landmark {
+    pub position: Point2<Field>,
+    pub kind: LandmarkKind,
+}
+
+pub fn landmarks_from_field_dimensions(field_dimensions: &FieldDimensions) -> Vec<Landmark> {
+    let half_length = field_dimensions.length / 2.0;
+    let half_goal_width =
+        (field_dimensions.goal_inner_width + field_dimensions.goal_post_diameter) / 2.0;
+    let penalty_marker_x = half_length - field_dimensions.penalty_marker_distance;
+
+    let goal_posts = [
+        point![half_length, half_goal_width],
+        point![half_length, -half_goal_width],
+        point![-half_length, half_goal_width],
+        point![-half_length, -half_goal_width],
+    ]
+    .map(|position| Landmark {
+        position,
+        kind: LandmarkKind::GoalPost,
+    });
+    let penalty_marks = [
+        point![penalty_marker_x, 0.0],
+        point![-penalty_marker_x, 0.0],
+    ]
+    .map(|position| Landmark {
+        position,
+        kind: LandmarkKind::PenaltyMark,
+    });
+
+    goal_posts.into_iter().chain(penalty_marks).collect()
+}
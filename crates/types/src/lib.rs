@@ -4,6 +4,8 @@ pub mod audio;
 pub mod ball;
 pub mod ball_filter;
 pub mod ball_position;
+pub mod ball_trajectory;
+pub mod behavior_tree;
 pub mod buttons;
 pub mod camera_matrix;
 pub mod camera_position;
@@ -11,6 +13,8 @@ pub mod color;
 pub mod condition_input;
 pub mod cycle_time;
 pub mod detected_feet;
+pub mod detected_robots;
+pub mod dribble_plan;
 pub mod fall_state;
 pub mod field_border;
 pub mod field_color;
@@ -35,6 +39,7 @@ pub mod joints_velocity;
 pub mod kick_decision;
 pub mod kick_step;
 pub mod kick_target;
+pub mod landmarks;
 pub mod led;
 pub mod limb;
 pub mod line;
@@ -50,13 +55,16 @@ pub mod obstacle_filter;
 pub mod obstacles;
 pub mod orientation_filter;
 pub mod parameters;
+pub mod pass;
 pub mod path_obstacles;
 pub mod penalty_shot_direction;
 pub mod perspective_grid_candidates;
 pub mod planned_path;
 pub mod players;
 pub mod point_of_interest;
+pub mod positioning;
 pub mod primary_state;
+pub mod referee_pose;
 pub mod robot_dimensions;
 pub mod robot_kinematics;
 pub mod robot_masses;
@@ -1,16 +1,29 @@
 use std::collections::HashSet;
 
 use coordinate_systems::{Ground, Pixel};
-use linear_algebra::Point2;
+use linear_algebra::{Point2, Vector2};
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
 
-use crate::line::Line2;
+use crate::{field_marks::IntersectionKind, line::Line2};
 
 #[derive(Clone, Default, Debug, Serialize, Deserialize, SerializeHierarchy)]
 pub struct LineData {
     pub lines: Vec<Line2<Ground>>,
     pub used_segments: HashSet<Point2<Pixel, u16>>,
+    /// Missing in data recorded before intersections were detected
+    #[serde(default)]
+    pub intersections: Vec<Intersection>,
+}
+
+/// Line intersection candidate measured in ground coordinates, localization associates it with
+/// the closest intersection field mark of the same kind
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Intersection {
+    pub position: Point2<Ground>,
+    pub kind: IntersectionKind,
+    /// L: bisector between both arms, T: along the stem, X: along the first line
+    pub orientation: Vector2<Ground>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, SerializeHierarchy)]
@@ -1,4 +1,4 @@
-use nalgebra::{vector, Matrix3};
+use nalgebra::{vector, Matrix3, Vector3};
 use serde::{Deserialize, Serialize};
 
 use coordinate_systems::{Field, Ground};
@@ -38,3 +38,35 @@ impl ScoredPose {
         }
     }
 }
+
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, SerializeHierarchy,
+)]
+pub enum LocalizationBackend {
+    #[default]
+    MultipleHypotheses,
+    ParticleFilter,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, SerializeHierarchy)]
+pub struct Particle {
+    /// x, y and orientation of the robot in the field
+    pub state: Vector3<f32>,
+    pub weight: f32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, SerializeHierarchy)]
+pub enum SymmetryAction {
+    /// The hypothesis was replaced by its point-mirrored pose
+    Flipped,
+    /// The mirrored pose is already covered by another hypothesis
+    Removed,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, SerializeHierarchy)]
+pub struct SymmetryEvent {
+    pub action: SymmetryAction,
+    pub ground_to_field_before: Isometry2<Ground, Field>,
+    pub votes_for_mirrored: usize,
+    pub votes_for_original: usize,
+}
@@ -8,8 +8,10 @@ use crate::{
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Hypothesis {
-    pub state: MultivariateNormalDistribution<2>,
+    /// Position and velocity in ground
+    pub state: MultivariateNormalDistribution<4>,
     pub measurement_count: usize,
+    pub first_seen: SystemTime,
     pub last_update: SystemTime,
     pub obstacle_kind: ObstacleKind,
 }
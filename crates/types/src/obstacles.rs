@@ -1,15 +1,24 @@
 use serde::{Deserialize, Serialize};
 
-use linear_algebra::Point2;
+use linear_algebra::{Point2, Vector2};
 use serialize_hierarchy::SerializeHierarchy;
 
 use coordinate_systems::Ground;
 
-#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, SerializeHierarchy,
+)]
 pub enum ObstacleKind {
     Ball,
     GoalPost,
     Robot,
+    /// Robot tracked at the position a teammate broadcasts or detected with our jersey color
+    Teammate,
+    /// Robot detected with the jersey color of the opposing team
+    ///
+    /// Without a shipped robot detection network no robot is detected, so no obstacle is ever
+    /// classified as opponent.
+    Opponent,
     #[default]
     Unknown,
 }
@@ -20,6 +29,7 @@ pub struct Obstacle {
     pub position: Point2<Ground>,
     pub radius_at_foot_height: f32,
     pub radius_at_hip_height: f32,
+    pub velocity: Vector2<Ground>,
 }
 
 impl Obstacle {
@@ -29,6 +39,7 @@ impl Obstacle {
             position,
             radius_at_foot_height: radius,
             radius_at_hip_height: radius,
+            velocity: Vector2::zeros(),
         }
     }
 
@@ -42,6 +53,7 @@ impl Obstacle {
             position,
             radius_at_foot_height,
             radius_at_hip_height,
+            velocity: Vector2::zeros(),
         }
     }
 
@@ -51,6 +63,7 @@ impl Obstacle {
             position,
             radius_at_foot_height: radius,
             radius_at_hip_height: radius,
+            velocity: Vector2::zeros(),
         }
     }
 }
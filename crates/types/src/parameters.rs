@@ -1,13 +1,16 @@
 use std::ops::{Index, Range};
 use std::{path::PathBuf, time::Duration};
 
-use coordinate_systems::{Field, Ground};
-use linear_algebra::{Point2, Vector2};
+use coordinate_systems::{Field, Ground, Head};
+use linear_algebra::{Point2, Point3, Vector2};
 use nalgebra::{Vector3, Vector4};
 use serde::{Deserialize, Serialize};
 use serialize_hierarchy::SerializeHierarchy;
+use spl_network_messages::TeamColor;
 
 use crate::{
+    behavior_tree::BehaviorTree,
+    color::YCbCr444,
     joints::{arm::ArmJoints, head::HeadJoints, leg::LegJoints},
     kick_step::KickStep,
     motion_command::{KickVariant, MotionCommand},
@@ -23,6 +26,14 @@ pub struct WhistleDetectionParameters {
     pub number_of_chunks: usize,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct WhistleLocalizationParameters {
+    /// In the order of the audio channels
+    pub microphone_positions: Vec<Point3<Head>>,
+    pub speed_of_sound: f32,
+    pub number_of_directions: usize,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct StepPlannerParameters {
     pub injected_step: Option<Step>,
@@ -39,12 +50,15 @@ pub struct BehaviorParameters {
     pub optional_roles: Vec<Role>,
     pub path_planning: PathPlanningParameters,
     pub role_positions: RolePositionsParameters,
+    pub positioning: PositioningParameters,
     pub walk_and_stand: WalkAndStandParameters,
     pub dribbling: DribblingParameters,
     pub search: SearchParameters,
     pub look_action: LookActionParameters,
     pub intercept_ball: InterceptBallParameters,
-    pub initial_lookaround_duration: Duration,
+    pub block_shot: BlockShotParameters,
+    pub set_play: SetPlayParameters,
+    pub tree: BehaviorTree,
 }
 
 #[derive(Copy, Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -53,6 +67,8 @@ pub struct LookActionParameters {
     pub distance_threshold: f32,
     pub look_forward_position: Point2<Ground>,
     pub position_of_interest_switch_interval: Duration,
+    /// Below this localization confidence, field marks are preferred over other points of interest
+    pub minimum_localization_confidence: f32,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -74,6 +90,50 @@ pub struct RolePositionsParameters {
     pub striker_set_position: Point2<Field>,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct PositioningParameters {
+    /// Distance between neighbouring candidate positions
+    pub grid_spacing: f32,
+    /// Candidates closer than this to a rule obstacle are infeasible
+    pub rule_obstacle_margin: f32,
+    /// Utility bonus for staying at the current position, avoids oscillating between candidates
+    pub hysteresis: f32,
+    pub defender_left: RolePositioningParameters,
+    pub defender_right: RolePositioningParameters,
+    pub midfielder_left: RolePositioningParameters,
+    pub midfielder_right: RolePositioningParameters,
+    pub striker_supporter: RolePositioningParameters,
+}
+
+/// Weights of the utility terms scoring candidates around the fixed role position
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct RolePositioningParameters {
+    pub enable: bool,
+    pub search_radius: f32,
+    /// Penalty per meter away from the fixed role position
+    pub anchor_weight: f32,
+    pub goal_coverage_weight: f32,
+    /// Distance to the line between ball and own goal at which coverage vanishes
+    pub goal_coverage_width: f32,
+    pub passing_lane_weight: f32,
+    /// Distance of the closest opponent to the lane from the ball above which the lane is free
+    pub passing_lane_width: f32,
+    /// Penalty per meter a teammate is closer than the minimum teammate distance
+    pub teammate_distance_weight: f32,
+    pub minimum_teammate_distance: f32,
+    pub opponent_distance_weight: f32,
+    /// Distance to the closest opponent above which more space is not rewarded
+    pub maximum_opponent_distance: f32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct SetPlayParameters {
+    /// Distance behind the kick pose at which the kicking robot waits for its teammates
+    pub preparation_distance: f32,
+    /// Kept in addition to the distance required by the rules when defending a set play
+    pub defending_distance_margin: f32,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct SearchParameters {
     pub position_reached_distance: f32,
@@ -114,6 +174,8 @@ pub struct InWalkKickInfoParameters {
     pub orientation: f32,
     pub reached_thresholds: Vector3<f32>,
     pub shot_distance: f32,
+    /// Distance the ball rolls when this kick is used to push the ball while dribbling
+    pub dribble_distance: f32,
     pub enabled: bool,
 }
 
@@ -123,6 +185,31 @@ pub struct DribblingParameters {
     pub distance_to_be_aligned: f32,
     pub angle_to_approach_ball_from_threshold: f32,
     pub ignore_robot_when_near_ball_radius: f32,
+    pub planning: DribblePlanningParameters,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct DribblePlanningParameters {
+    pub enable: bool,
+    /// Number of consecutive ball pushes evaluated by the search
+    pub search_depth: usize,
+    /// Push directions relative to the direction from the ball to the opponent goal
+    pub direction_angles: Vec<f32>,
+    pub walking_speed: f32,
+    pub turning_speed: f32,
+    /// Assumed speed of opponents walking towards the ball
+    pub opponent_speed: f32,
+    /// Added to the obstacle radius at foot height when checking the ball path for collisions
+    pub obstacle_clearance: f32,
+    /// Time the robot should reach the pushed ball earlier than any opponent
+    pub ball_control_margin: f32,
+    pub time_weight: f32,
+    pub progress_weight: f32,
+    pub ball_control_weight: f32,
+    /// Weight of each push relative to the one before, accounts for growing uncertainty
+    pub discount_factor: f32,
+    /// Cost advantage a searched push needs over the best decision of the kick selector
+    pub kick_selector_margin: f32,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -147,6 +234,20 @@ pub struct InterceptBallParameters {
     pub maximum_intercept_distance: f32,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct BlockShotParameters {
+    pub minimum_ball_velocity: f32,
+    /// Shots crossing the goal line up to this distance next to the goal posts are blocked as well
+    pub goal_post_margin: f32,
+    /// Balls arriving later than this are blocked by walking into their way instead of diving
+    pub maximum_time_for_stepping: Duration,
+    pub maximum_step_distance: f32,
+    /// Maximum lateral distance of the ball which is blocked by squatting
+    pub squat_reach: f32,
+    /// Maximum lateral distance of the ball which is blocked by jumping
+    pub jump_reach: f32,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct PathPlanningParameters {
     pub arc_walking_speed: f32,
@@ -157,6 +258,8 @@ pub struct PathPlanningParameters {
     pub minimum_robot_radius_at_foot_height: f32,
     pub robot_radius_at_foot_height: f32,
     pub robot_radius_at_hip_height: f32,
+    /// Moving obstacles are also avoided where they will be after this time
+    pub obstacle_prediction_horizon: Duration,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -237,6 +340,57 @@ pub struct KickStepsParameters {
     pub side: Vec<KickStep>,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct ParticleFilterParameters {
+    pub number_of_particles: usize,
+    /// Standard deviations of x, y and orientation added in every prediction
+    pub process_noise: Vector3<f32>,
+    /// Standard deviations of x, y and orientation relative to the odometry step
+    pub odometry_noise: Vector3<f32>,
+    pub line_measurement_standard_deviation: f32,
+    /// Line end points farther away from any field mark are treated as outliers
+    pub maximum_line_point_distance: f32,
+    pub landmark_measurement_standard_deviation: f32,
+    /// Landmarks farther away from any landmark of the same kind are treated as outliers
+    pub maximum_landmark_distance: f32,
+    pub slow_averaging_factor: f32,
+    pub fast_averaging_factor: f32,
+    pub maximum_sensor_reset_fraction: f32,
+    pub minimum_effective_sample_size_fraction: f32,
+    pub cluster_radius: f32,
+    pub cluster_angle: f32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct LocalizationConfidenceParameters {
+    /// Position standard deviation of the best hypothesis at which its confidence drops to 1/e
+    pub position_standard_deviation_scale: f32,
+    /// Orientation standard deviation of the best hypothesis at which its confidence drops to 1/e
+    pub orientation_standard_deviation_scale: f32,
+    /// Hypotheses closer to the best one than this do not compete with it
+    pub competing_hypothesis_distance: f32,
+    /// Smoothed line fit error at which the confidence drops to 1/e
+    pub fit_error_scale: f32,
+    pub fit_error_smoothing_factor: f32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct SymmetryDisambiguationParameters {
+    pub enable: bool,
+    /// Balls seen by a teammate and by us agree if they are closer than this
+    pub ball_agreement_distance: f32,
+    pub maximum_teammate_message_age: Duration,
+    /// Teammates reporting a lower localization confidence are ignored
+    pub minimum_teammate_localization_confidence: f32,
+    /// A hypothesis closer than this to a teammate's broadcast position is implausible
+    pub minimum_teammate_distance: f32,
+    /// Distance from the center line a hypothesis needs to be clearly inside one half
+    pub own_half_margin: f32,
+    pub minimum_votes_for_mirrored: usize,
+    /// Flipped hypotheses closer than this to another hypothesis are removed instead
+    pub duplicate_hypothesis_distance: f32,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct LookAtParameters {
     pub glance_angle: f32,
@@ -255,9 +409,40 @@ pub struct LookAroundParameters {
     pub halfway_right_positions: HeadJoints<f32>,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct PassPlannerParameters {
+    pub enable: bool,
+    /// Has to exceed `spl_network.pose_message_send_interval`, checked when the pass planner is
+    /// created
+    pub maximum_teammate_message_age: Duration,
+    /// Announced passes are expected for this long after the last announcement
+    pub announcement_timeout: Duration,
+    pub minimum_pass_distance: f32,
+    pub maximum_pass_distance: f32,
+    /// The ball is played this far in front of the receiver towards the opponent goal
+    pub lead_distance: f32,
+    /// Passes need to bring the ball at least this much closer to the opponent goal
+    pub minimum_progress: f32,
+    pub receiver_walking_speed: f32,
+    pub opponent_walking_speed: f32,
+    /// Obstacles reaching the pass line less than this many seconds after the ball make the pass
+    /// infeasible
+    pub minimum_interception_margin: f32,
+    pub interception_margin_weight: f32,
+    /// Added to the score of the current receiver to avoid switching between similar receivers
+    pub receiver_hysteresis: f32,
+    /// Speed of the ball at the kick is this factor times the kick strength
+    pub ball_speed_per_kick_strength: f32,
+    pub maximum_kick_strength: f32,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct SplNetworkParameters {
+    /// Used to estimate the time to reach the kick position of teammates from other teams
+    pub foreign_teammate_walking_speed: f32,
     pub game_controller_return_message_interval: Duration,
+    /// Robots that are no striker share their pose with this interval if passes are enabled
+    pub pose_message_send_interval: Duration,
     pub remaining_amount_of_messages_to_stop_sending: u16,
     pub silence_interval_between_messages: Duration,
     pub spl_striker_message_receive_timeout: Duration,
@@ -265,6 +450,36 @@ pub struct SplNetworkParameters {
     pub striker_trusts_team_ball: Duration,
 }
 
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, SerializeHierarchy,
+)]
+pub enum RoleAssignmentMode {
+    /// Robots claim the striker role by comparing their time to reach the kick position
+    #[default]
+    StateMachine,
+    /// Every robot solves the same optimal assignment of roles to robots from shared team messages
+    CostBased,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct CostBasedRoleAssignmentParameters {
+    /// Used to estimate the time every robot needs to reach the target position of a role
+    pub walking_speed: f32,
+    /// Robots which did not share their pose for this long are left out of the assignment
+    ///
+    /// Should exceed `spl_network.pose_message_send_interval`.
+    pub teammate_timeout: Duration,
+    /// Added to the time to reach of fallen robots for standing up
+    pub fallen_penalty: Duration,
+    /// Subtracted from the cost of the previously assigned role to prevent oscillations
+    pub hysteresis: Duration,
+    /// Factor between the cost weights of a role and the next more important one
+    ///
+    /// Without it, delays like standing up would add equally to all roles of a robot and never
+    /// change the assignment.
+    pub priority_decay: f32,
+}
+
 #[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub enum MedianModeParameters {
     #[default]
@@ -296,6 +511,47 @@ pub struct BallDetectionParameters {
     pub ball_radius_enlargement_factor: f32,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct RefereePoseDetectionParameters {
+    pub enable: bool,
+    /// No keypoint network is shipped in `etc/neural_networks` yet, the detection has to stay
+    /// disabled until one is added
+    pub neural_network: PathBuf,
+    pub minimum_keypoint_confidence: f32,
+    pub horizontal_arm_tolerance: f32,
+    pub vertical_arm_tolerance: f32,
+    pub lowered_arm_maximum_elevation: f32,
+    pub minimum_bent_elbow_angle: f32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct RobotDetectionParameters {
+    pub enable: bool,
+    /// No network is shipped yet, evaluate a trained one on annotato images with the
+    /// `robot_detection_evaluator` tool before enabling the detection
+    pub neural_network: PathBuf,
+    pub detection_threshold: f32,
+    /// Intersection over union above which the less confident of two detections is suppressed
+    pub maximum_overlap: f32,
+}
+
+/// Jersey color classification of detected robots, shared by both cameras
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct JerseyColorParameters {
+    pub references: Vec<JerseyColorReference>,
+    /// Weight of the luminance difference relative to the chromaticity difference
+    pub luminance_weight: f32,
+    pub maximum_distance: f32,
+    /// Fraction of the torso pixels which have to vote for a jersey color
+    pub minimum_vote_ratio: f32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct JerseyColorReference {
+    pub color: TeamColor,
+    pub reference: YCbCr444,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct BallFilterParameters {
     pub hypothesis_timeout: Duration,
@@ -308,26 +564,50 @@ pub struct BallFilterParameters {
     pub visible_validity_exponential_decay_factor: f32,
     pub hidden_validity_exponential_decay_factor: f32,
     pub validity_discard_threshold: f32,
-    pub velocity_decay_factor: f32,
+    /// Constant deceleration of a rolling ball caused by the carpet in m/s²
+    pub rolling_deceleration: f32,
     pub resting_ball_velocity_threshold: f32,
+    pub trajectory_sampling_interval: Duration,
+}
+
+/// Linear correction of the odometry measured from the feet and the orientation
+///
+/// Walking forward or sideways may also cause sideways drift or rotation which is not measured.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct OdometryCorrectionParameters {
+    pub forward_scale: f32,
+    pub lateral_scale: f32,
+    pub rotation_scale: f32,
+    /// Lateral offset per meter walked forward
+    pub lateral_per_forward: f32,
+    /// Rotation in radians per meter walked forward
+    pub rotation_per_forward: f32,
+    /// Rotation in radians per meter walked sideways
+    pub rotation_per_lateral: f32,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 pub struct ObstacleFilterParameters {
     pub hypothesis_timeout: Duration,
+    /// Hypotheses below the measurement count threshold are removed after this time without update
+    pub tentative_hypothesis_timeout: Duration,
+    pub maximum_robot_velocity: f32,
     pub network_robot_measurement_matching_distance: f32,
     pub sonar_goal_post_matching_distance: f32,
     pub feet_detection_measurement_matching_distance: f32,
+    pub robot_detection_measurement_matching_distance: f32,
     pub goal_post_measurement_matching_distance: f32,
     pub hypothesis_merge_distance: f32,
-    pub process_noise: nalgebra::Vector2<f32>,
+    pub process_noise: Vector4<f32>,
     pub feet_measurement_noise: nalgebra::Vector2<f32>,
     pub robot_measurement_noise: nalgebra::Vector2<f32>,
     pub sonar_measurement_noise: nalgebra::Vector2<f32>,
     pub network_robot_measurement_noise: nalgebra::Vector2<f32>,
     pub initial_covariance: nalgebra::Vector2<f32>,
+    pub initial_velocity_covariance: nalgebra::Vector2<f32>,
     pub measurement_count_threshold: usize,
     pub use_feet_detection_measurements: bool,
+    pub use_robot_detection_measurements: bool,
     pub use_sonar_measurements: bool,
     pub use_foot_bumper_measurements: bool,
     pub robot_obstacle_radius_at_hip_height: f32,
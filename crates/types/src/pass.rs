@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use coordinate_systems::Field;
+use linear_algebra::Point2;
+use serialize_hierarchy::SerializeHierarchy;
+use spl_network_messages::{PassAnnouncement, PlayerNumber};
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, SerializeHierarchy)]
+pub struct PassTarget {
+    pub receiver: PlayerNumber,
+    pub position: Point2<Field>,
+    pub strength: f32,
+}
+
+/// A teammate evaluated as receiver of a pass
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, SerializeHierarchy)]
+pub struct PassCandidate {
+    pub receiver: PlayerNumber,
+    pub target: Point2<Field>,
+    pub ball_travel_time: Duration,
+    pub receiver_time_to_reach: Duration,
+    /// Seconds the fastest obstacle is late to intercept the ball, negative if it is in time
+    ///
+    /// Margins longer than the travel time of the ball are irrelevant and capped.
+    pub interception_margin: f32,
+    pub score: f32,
+    pub is_feasible: bool,
+}
+
+/// Pass announced by the pass planner, read by the role assignment of the next cycle
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct AnnouncedPass {
+    pub announcement: Option<PassAnnouncement>,
+}
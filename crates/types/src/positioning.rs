@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use coordinate_systems::Field;
+use linear_algebra::Point2;
+use serialize_hierarchy::SerializeHierarchy;
+
+/// A candidate position scored by the utility based positioning
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, SerializeHierarchy)]
+pub struct PositionUtility {
+    pub position: Point2<Field>,
+    pub utility: f32,
+    /// Infeasible candidates lie outside the field or inside a rule obstacle
+    pub is_feasible: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct PositioningHeatmap {
+    /// Edge length of the square cell around each candidate
+    pub cell_size: f32,
+    pub candidates: Vec<PositionUtility>,
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use serialize_hierarchy::SerializeHierarchy;
+
+use coordinate_systems::Pixel;
+use linear_algebra::Point2;
+
+use crate::support_foot::Side;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct Keypoint {
+    pub point: Point2<Pixel>,
+    pub confidence: f32,
+}
+
+/// Upper body keypoints of a person, sides are anatomical (i.e. from the person's perspective)
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub struct HumanPose {
+    pub nose: Keypoint,
+    pub left_shoulder: Keypoint,
+    pub right_shoulder: Keypoint,
+    pub left_elbow: Keypoint,
+    pub right_elbow: Keypoint,
+    pub left_wrist: Keypoint,
+    pub right_wrist: Keypoint,
+    pub left_hip: Keypoint,
+    pub right_hip: Keypoint,
+}
+
+/// Gesture of the referee, `side` is the referee's arm indicating the team
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, SerializeHierarchy)]
+pub enum RefereeGesture {
+    KickIn { side: Side },
+    GoalKick { side: Side },
+    CornerKick { side: Side },
+    Goal { side: Side },
+    PushingFreeKick { side: Side },
+    Substitution { side: Side },
+    FullTime,
+}
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use coordinate_systems::{Field, Ground};
 use linear_algebra::{Isometry2, Point2, Vector2};
 use serialize_hierarchy::SerializeHierarchy;
-use spl_network_messages::PlayerNumber;
+use spl_network_messages::{PassAnnouncement, PlayerNumber};
 
 use crate::{
     fall_state::FallState, filtered_game_controller_state::FilteredGameControllerState,
@@ -23,6 +23,8 @@ pub struct WorldState {
     pub position_of_interest: Point2<Ground>,
     pub kick_decisions: Option<Vec<KickDecision>>,
     pub instant_kick_decisions: Option<Vec<KickDecision>>,
+    /// A teammate announced to pass the ball to us
+    pub expected_pass: Option<PassAnnouncement>,
     pub robot: RobotState,
 }
 
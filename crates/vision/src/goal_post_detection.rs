@@ -0,0 +1,298 @@
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use context_attribute::context;
+use coordinate_systems::{Ground, Pixel};
+use framework::{AdditionalOutput, MainOutput};
+use linear_algebra::{distance, point, Point2, Vector2};
+use projection::Projection;
+use types::{
+    camera_matrix::CameraMatrix,
+    color::Intensity,
+    field_border::FieldBorder,
+    field_dimensions::FieldDimensions,
+    image_segments::{EdgeType, ImageSegments, ScanLine, Segment},
+};
+
+#[derive(Deserialize, Serialize)]
+pub struct GoalPostDetection {}
+
+#[context]
+pub struct CreationContext {}
+
+#[context]
+pub struct CycleContext {
+    goal_post_feet_in_image:
+        AdditionalOutput<Vec<Point2<Pixel>>, "goal_post_detection.feet_in_image">,
+
+    enable: Parameter<bool, "goal_post_detection.$cycler_instance.enable">,
+    field_dimensions: Parameter<FieldDimensions, "field_dimensions">,
+    maximum_foot_distance:
+        Parameter<f32, "goal_post_detection.$cycler_instance.maximum_foot_distance">,
+    maximum_post_width: Parameter<f32, "goal_post_detection.$cycler_instance.maximum_post_width">,
+    minimum_luminance: Parameter<u8, "goal_post_detection.$cycler_instance.minimum_luminance">,
+    minimum_post_length_in_pixels:
+        Parameter<u16, "goal_post_detection.$cycler_instance.minimum_post_length_in_pixels">,
+
+    camera_matrix: RequiredInput<Option<CameraMatrix>, "camera_matrix?">,
+    field_border: RequiredInput<Option<FieldBorder>, "field_border?">,
+    image_segments: Input<ImageSegments, "image_segments">,
+}
+
+#[context]
+#[derive(Default)]
+pub struct MainOutputs {
+    pub goal_posts: MainOutput<Option<Vec<Point2<Ground>>>>,
+}
+
+struct PostFoot {
+    in_image: Point2<Pixel>,
+    in_ground: Point2<Ground>,
+}
+
+impl GoalPostDetection {
+    pub fn new(_context: CreationContext) -> Result<Self> {
+        Ok(Self {})
+    }
+
+    pub fn cycle(&mut self, mut context: CycleContext) -> Result<MainOutputs> {
+        if !context.enable {
+            return Ok(MainOutputs::default());
+        }
+
+        let feet: Vec<_> = context
+            .image_segments
+            .scan_grid
+            .vertical_scan_lines
+            .iter()
+            .filter_map(|scan_line| {
+                let foot_in_image = find_post_foot(
+                    scan_line,
+                    context.field_border,
+                    *context.minimum_luminance,
+                    *context.minimum_post_length_in_pixels,
+                )?;
+                let foot_in_ground = context
+                    .camera_matrix
+                    .pixel_to_ground(foot_in_image.map(|x| x as f32))
+                    .ok()?;
+                Some(PostFoot {
+                    in_image: foot_in_image.map(|x| x as f32),
+                    in_ground: foot_in_ground,
+                })
+            })
+            .collect();
+        context
+            .goal_post_feet_in_image
+            .fill_if_subscribed(|| feet.iter().map(|foot| foot.in_image).collect());
+
+        let post_radius = context.field_dimensions.goal_post_diameter / 2.0;
+        let goal_posts = group_adjacent_feet(&feet, *context.maximum_foot_distance)
+            .into_iter()
+            .filter(|group| {
+                distance(group[0].in_ground, group[group.len() - 1].in_ground)
+                    <= *context.maximum_post_width
+            })
+            .map(|group| {
+                let foot = group
+                    .iter()
+                    .map(|foot| foot.in_ground.coords())
+                    .sum::<Vector2<Ground>>()
+                    / group.len() as f32;
+                // the visible foot is the front of the post, its center lies further away
+                let direction = foot
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_else(Vector2::zeros);
+                (foot + direction * post_radius).as_point()
+            })
+            .collect();
+
+        Ok(MainOutputs {
+            goal_posts: Some(goal_posts).into(),
+        })
+    }
+}
+
+/// Lower end of bright segments crossing the field border or entering the image from above
+///
+/// The bottom camera usually sees no field border, posts then reach beyond the upper image border.
+fn find_post_foot(
+    scan_line: &ScanLine,
+    field_border: &FieldBorder,
+    minimum_luminance: u8,
+    minimum_post_length_in_pixels: u16,
+) -> Option<Point2<Pixel, u16>> {
+    let is_inside_field = |segment_position: u16| {
+        field_border.is_inside_field(point![scan_line.position as f32, segment_position as f32])
+    };
+    let is_bright = |segment: &Segment| {
+        segment.color.y >= minimum_luminance && segment.field_color != Intensity::High
+    };
+
+    let mut segments = scan_line.segments.iter().peekable();
+    while let Some(first) = segments.next() {
+        let starts_outside_image = first.start_edge_type == EdgeType::ImageBorder;
+        if !is_bright(first) || (is_inside_field(first.start) && !starts_outside_image) {
+            continue;
+        }
+        let mut end = first.end;
+        while let Some(next) = segments.next_if(|next| next.start == end && is_bright(next)) {
+            end = next.end;
+        }
+        if end - first.start >= minimum_post_length_in_pixels && is_inside_field(end) {
+            return Some(point![scan_line.position, end]);
+        }
+    }
+    None
+}
+
+fn group_adjacent_feet(feet: &[PostFoot], maximum_foot_distance: f32) -> Vec<Vec<&PostFoot>> {
+    let mut groups: Vec<Vec<&PostFoot>> = Vec::new();
+    for foot in feet {
+        match groups.last_mut() {
+            Some(group)
+                if distance(group[group.len() - 1].in_ground, foot.in_ground)
+                    <= maximum_foot_distance =>
+            {
+                group.push(foot)
+            }
+            _ => groups.push(vec![foot]),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use types::{color::YCbCr444, line::Line, ycbcr422_image::YCbCr422Image};
+
+    use crate::{
+        pipeline_for_tests::{bottom_camera_matrix, default_parameters, image_segments, parameter},
+        robot_detection_evaluation::{load_annotations, AnnotationClass},
+    };
+
+    use super::*;
+
+    fn segment(start: u16, end: u16, luminance: u8, field_color: Intensity) -> Segment {
+        Segment {
+            start,
+            end,
+            start_edge_type: EdgeType::Rising,
+            end_edge_type: EdgeType::Falling,
+            color: YCbCr444 {
+                y: luminance,
+                cb: 128,
+                cr: 128,
+            },
+            field_color,
+        }
+    }
+
+    fn field_border_at(height: f32) -> FieldBorder {
+        FieldBorder {
+            border_lines: vec![Line(point![0.0, height], point![640.0, height])],
+        }
+    }
+
+    #[test]
+    fn post_crossing_the_field_border_is_found() {
+        let scan_line = ScanLine {
+            position: 100,
+            segments: vec![
+                segment(0, 40, 80, Intensity::Low),
+                segment(40, 90, 200, Intensity::Low),
+                segment(90, 130, 210, Intensity::Low),
+                segment(130, 300, 90, Intensity::High),
+            ],
+        };
+
+        let foot = find_post_foot(&scan_line, &field_border_at(100.0), 150, 30);
+
+        assert_eq!(foot, Some(point![100, 130]));
+    }
+
+    #[test]
+    fn bright_segment_inside_field_is_ignored() {
+        let scan_line = ScanLine {
+            position: 100,
+            segments: vec![
+                segment(0, 100, 80, Intensity::Low),
+                segment(100, 120, 90, Intensity::High),
+                segment(120, 180, 200, Intensity::Low),
+                segment(180, 300, 90, Intensity::High),
+            ],
+        };
+
+        let foot = find_post_foot(&scan_line, &field_border_at(100.0), 150, 30);
+
+        assert_eq!(foot, None);
+    }
+
+    #[test]
+    fn post_entering_image_from_above_is_found_without_field_border() {
+        let mut first = segment(0, 90, 200, Intensity::Low);
+        first.start_edge_type = EdgeType::ImageBorder;
+        let scan_line = ScanLine {
+            position: 100,
+            segments: vec![first, segment(90, 300, 90, Intensity::High)],
+        };
+        let no_field_border = FieldBorder {
+            border_lines: vec![],
+        };
+
+        let foot = find_post_foot(&scan_line, &no_field_border, 150, 30);
+
+        assert_eq!(foot, Some(point![100, 90]));
+    }
+
+    #[test]
+    fn annotated_goal_post_is_detected_in_loaded_image() -> Result<()> {
+        let image_path = Path::new("../../tests/data/rome_bottom_ball.png");
+        let image = YCbCr422Image::load_from_444_png(image_path)?;
+        let annotated_post = load_annotations(image_path)?
+            .into_iter()
+            .find(|annotation| annotation.class == AnnotationClass::GoalPost)
+            .expect("stored image should have an annotated goal post")
+            .bounding_box();
+        let parameters = default_parameters()?;
+        let camera_matrix = bottom_camera_matrix(&image, &parameters)?;
+        let image_segments = image_segments(&image, &camera_matrix, &parameters)?;
+        let detection_parameters = &parameters["goal_post_detection"]["vision_bottom"];
+        // field border detection is disabled for the bottom camera and yields no border lines
+        let field_border = FieldBorder {
+            border_lines: vec![],
+        };
+
+        let mut feet_in_image = None;
+        let goal_posts = GoalPostDetection::new(CreationContext::new())?
+            .cycle(CycleContext::new(
+                AdditionalOutput::new(false, &mut feet_in_image),
+                &true,
+                &parameter(&parameters, "field_dimensions")?,
+                &parameter(detection_parameters, "maximum_foot_distance")?,
+                &parameter(detection_parameters, "maximum_post_width")?,
+                &parameter(detection_parameters, "minimum_luminance")?,
+                &parameter(detection_parameters, "minimum_post_length_in_pixels")?,
+                &camera_matrix,
+                &field_border,
+                &image_segments,
+            ))?
+            .goal_posts
+            .value
+            .unwrap();
+
+        assert!(
+            goal_posts.iter().any(|&goal_post| {
+                camera_matrix
+                    .ground_to_pixel(goal_post)
+                    .is_ok_and(|position| {
+                        (annotated_post.min.x()..=annotated_post.max.x()).contains(&position.x())
+                    })
+            }),
+            "no goal post detected within {annotated_post:?}, got {goal_posts:?}"
+        );
+        Ok(())
+    }
+}
@@ -3,10 +3,17 @@ pub mod camera_matrix_extractor;
 pub mod feet_detection;
 pub mod field_border_detection;
 pub mod field_color_detection;
+pub mod goal_post_detection;
 pub mod image_receiver;
 pub mod image_segmenter;
 pub mod limb_projector;
 pub mod line_detection;
+pub mod penalty_mark_detection;
 pub mod perspective_grid_candidates_provider;
+#[cfg(test)]
+mod pipeline_for_tests;
 mod ransac;
+pub mod referee_pose_detection;
+pub mod robot_detection;
+pub mod robot_detection_evaluation;
 pub mod segment_filter;
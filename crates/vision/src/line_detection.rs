@@ -1,27 +1,33 @@
 use std::{collections::HashSet, iter::Peekable, ops::Range};
 
 use color_eyre::Result;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 use context_attribute::context;
 use coordinate_systems::{Ground, Pixel};
-use framework::{AdditionalOutput, MainOutput};
+use framework::{deserialize_not_implemented, AdditionalOutput, MainOutput};
 use linear_algebra::{distance, point, vector, Point2, Vector2};
 use ordered_float::NotNan;
 use projection::Projection;
+use rand::{rngs::StdRng, thread_rng, SeedableRng};
 use types::{
     camera_matrix::CameraMatrix,
+    field_marks::IntersectionKind,
     filtered_segments::FilteredSegments,
     image_segments::{EdgeType, Segment},
     line::{Line, Line2},
-    line_data::{LineData, LineDiscardReason},
+    line_data::{Intersection, LineData, LineDiscardReason},
     ycbcr422_image::YCbCr422Image,
 };
 
 use crate::ransac::{Ransac, RansacResult};
 
 #[derive(Deserialize, Serialize)]
-pub struct LineDetection {}
+pub struct LineDetection {
+    #[serde(skip, default = "deserialize_not_implemented")]
+    random_number_generator: StdRng,
+}
 
 #[context]
 pub struct CreationContext {}
@@ -47,6 +53,10 @@ pub struct CycleContext {
     maximum_fit_distance_in_ground:
         Parameter<f32, "line_detection.$cycler_instance.maximum_fit_distance_in_ground">,
     maximum_gap_on_line: Parameter<f32, "line_detection.$cycler_instance.maximum_gap_on_line">,
+    maximum_intersection_angle_deviation:
+        Parameter<f32, "line_detection.$cycler_instance.maximum_intersection_angle_deviation">,
+    maximum_intersection_endpoint_distance:
+        Parameter<f32, "line_detection.$cycler_instance.maximum_intersection_endpoint_distance">,
     maximum_merge_gap_in_pixels:
         Parameter<u16, "line_detection.$cycler_instance.maximum_merge_gap_in_pixels">,
     maximum_number_of_lines:
@@ -70,7 +80,9 @@ pub struct MainOutputs {
 
 impl LineDetection {
     pub fn new(_context: CreationContext) -> Result<Self> {
-        Ok(Self {})
+        Ok(Self {
+            random_number_generator: StdRng::from_rng(thread_rng())?,
+        })
     }
 
     pub fn cycle(&mut self, mut context: CycleContext) -> Result<MainOutputs> {
@@ -97,7 +109,10 @@ impl LineDetection {
                 .collect()
         });
 
-        let mut ransac = Ransac::new(line_points);
+        let mut ransac = Ransac::with_random_number_generator(
+            line_points,
+            StdRng::from_rng(&mut self.random_number_generator)?,
+        );
         let mut lines_in_ground = Vec::new();
         for _ in 0..*context.maximum_number_of_lines {
             if ransac.unused_points.len() < *context.minimum_number_of_points_on_line {
@@ -180,9 +195,15 @@ impl LineDetection {
                 image_lines.push(Line(start_point_in_ground, end_point_in_ground));
             }
         }
+        let intersections = find_intersections(
+            &lines_in_ground,
+            *context.maximum_intersection_angle_deviation,
+            *context.maximum_intersection_endpoint_distance,
+        );
         let line_data = LineData {
             lines: lines_in_ground,
             used_segments,
+            intersections,
         };
 
         context.lines_in_image.fill_if_subscribed(|| {
@@ -217,6 +238,68 @@ impl LineDetection {
     }
 }
 
+fn find_intersections(
+    lines: &[Line2<Ground>],
+    maximum_angle_deviation: f32,
+    maximum_endpoint_distance: f32,
+) -> Vec<Intersection> {
+    lines
+        .iter()
+        .tuple_combinations()
+        .filter_map(|(first, second)| {
+            if first.signed_acute_angle_to_orthogonal(*second).abs() > maximum_angle_deviation {
+                return None;
+            }
+            let position = first.intersection(second);
+            let first_contact = contact_with_line(first, position, maximum_endpoint_distance)?;
+            let second_contact = contact_with_line(second, position, maximum_endpoint_distance)?;
+            let (kind, orientation) = match (first_contact, second_contact) {
+                (LineContact::End(first_arm), LineContact::End(second_arm)) => (
+                    IntersectionKind::L,
+                    (first_arm + second_arm).try_normalize(f32::EPSILON)?,
+                ),
+                (LineContact::End(stem), LineContact::Through)
+                | (LineContact::Through, LineContact::End(stem)) => (IntersectionKind::T, stem),
+                (LineContact::Through, LineContact::Through) => {
+                    (IntersectionKind::X, (first.1 - first.0).normalize())
+                }
+            };
+            Some(Intersection {
+                position,
+                kind,
+                orientation,
+            })
+        })
+        .collect()
+}
+
+enum LineContact {
+    /// The line ends at the intersection and continues in this direction
+    End(Vector2<Ground>),
+    Through,
+}
+
+fn contact_with_line(
+    line: &Line2<Ground>,
+    intersection: Point2<Ground>,
+    maximum_endpoint_distance: f32,
+) -> Option<LineContact> {
+    let direction = (line.1 - line.0).try_normalize(f32::EPSILON)?;
+    let length = line.length();
+    let position_on_line = (intersection - line.0).dot(direction);
+    if position_on_line < -maximum_endpoint_distance
+        || position_on_line > length + maximum_endpoint_distance
+    {
+        None
+    } else if position_on_line < maximum_endpoint_distance {
+        Some(LineContact::End(direction))
+    } else if position_on_line > length - maximum_endpoint_distance {
+        Some(LineContact::End(-direction))
+    } else {
+        Some(LineContact::Through)
+    }
+}
+
 fn get_gradient(image: &YCbCr422Image, point: Point2<Pixel, u16>) -> Vector2<f32> {
     if point.x() < 1
         || point.y() < 1
@@ -402,11 +485,164 @@ fn is_segment_shorter_than(
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
+    use approx::assert_relative_eq;
     use linear_algebra::IntoTransform;
     use nalgebra::{Isometry3, Translation, UnitQuaternion};
 
+    use crate::pipeline_for_tests::{bottom_camera_matrix, default_parameters, filtered_segments};
+
     use super::*;
 
+    fn detect_lines(
+        image: &YCbCr422Image,
+        camera_matrix: &CameraMatrix,
+        filtered_segments: &FilteredSegments,
+        seed: u64,
+    ) -> Result<LineData> {
+        let mut lines_in_image = None;
+        let mut discarded_lines = None;
+        let mut ransac_input = None;
+        let context = CycleContext {
+            lines_in_image: AdditionalOutput::new(false, &mut lines_in_image),
+            discarded_lines: AdditionalOutput::new(false, &mut discarded_lines),
+            ransac_input: AdditionalOutput::new(false, &mut ransac_input),
+            allowed_line_length_in_field: &(0.15..4.0),
+            check_edge_gradient: &false,
+            check_line_distance: &true,
+            check_line_length: &true,
+            check_line_segments_projection: &true,
+            gradient_alignment: &-0.95,
+            margin_for_point_inclusion: &0.025,
+            maximum_distance_to_robot: &6.0,
+            maximum_fit_distance_in_ground: &0.05,
+            maximum_gap_on_line: &0.5,
+            maximum_intersection_angle_deviation: &0.35,
+            maximum_intersection_endpoint_distance: &0.3,
+            maximum_merge_gap_in_pixels: &30,
+            maximum_number_of_lines: &10,
+            maximum_projected_segment_length: &0.3,
+            minimum_number_of_points_on_line: &4,
+            ransac_iterations: &20,
+            camera_matrix,
+            filtered_segments,
+            image,
+        };
+        let mut line_detection = LineDetection {
+            random_number_generator: StdRng::seed_from_u64(seed),
+        };
+
+        Ok(line_detection.cycle(context)?.line_data.value.unwrap())
+    }
+
+    #[test]
+    fn goal_box_junction_is_detected_in_loaded_image() -> Result<()> {
+        let image =
+            YCbCr422Image::load_from_444_png(Path::new("../../tests/data/rome_bottom_ball.png"))?;
+        let parameters = default_parameters()?;
+        let camera_matrix = bottom_camera_matrix(&image, &parameters)?;
+        let filtered_segments = filtered_segments(&image, &camera_matrix, &parameters)?;
+        // the goal box line ends on the goal line next to the goal post
+        let junction_in_image = point![252.0, 103.0];
+
+        let line_data = detect_lines(&image, &camera_matrix, &filtered_segments, 42)?;
+
+        let junction = line_data
+            .intersections
+            .iter()
+            .find(|intersection| {
+                camera_matrix
+                    .ground_to_pixel(intersection.position)
+                    .is_ok_and(|position| distance(position, junction_in_image) < 20.0)
+            })
+            .expect("junction should be detected");
+        assert_ne!(junction.kind, IntersectionKind::X);
+        Ok(())
+    }
+
+    #[test]
+    fn seeded_detection_is_reproducible() -> Result<()> {
+        let image =
+            YCbCr422Image::load_from_444_png(Path::new("../../tests/data/rome_bottom_ball.png"))?;
+        let parameters = default_parameters()?;
+        let camera_matrix = bottom_camera_matrix(&image, &parameters)?;
+        let filtered_segments = filtered_segments(&image, &camera_matrix, &parameters)?;
+
+        let first = detect_lines(&image, &camera_matrix, &filtered_segments, 7)?;
+        let second = detect_lines(&image, &camera_matrix, &filtered_segments, 7)?;
+
+        let endpoints = |line_data: &LineData| -> Vec<_> {
+            line_data
+                .lines
+                .iter()
+                .map(|line| (line.0, line.1))
+                .collect()
+        };
+        assert_eq!(endpoints(&first), endpoints(&second));
+        Ok(())
+    }
+
+    #[test]
+    fn corner_of_two_ending_lines_is_l_intersection() {
+        let lines = [
+            Line(point![1.0, 0.0], point![3.0, 0.0]),
+            Line(point![1.0, 2.0], point![1.05, 0.1]),
+        ];
+
+        let intersections = find_intersections(&lines, 0.35, 0.3);
+
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].kind, IntersectionKind::L);
+        assert!(distance(intersections[0].position, point![1.0525, 0.0]) < 0.01);
+        assert!(intersections[0].orientation.x() > 0.0 && intersections[0].orientation.y() > 0.0);
+    }
+
+    #[test]
+    fn line_ending_on_another_line_is_t_intersection() {
+        let lines = [
+            Line(point![2.0, -2.0], point![2.0, 2.0]),
+            Line(point![1.8, 0.5], point![0.5, 0.5]),
+        ];
+
+        let intersections = find_intersections(&lines, 0.35, 0.3);
+
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].kind, IntersectionKind::T);
+        assert_relative_eq!(intersections[0].position, point![2.0, 0.5], epsilon = 0.001);
+        assert_relative_eq!(
+            intersections[0].orientation,
+            vector![-1.0, 0.0],
+            epsilon = 0.001
+        );
+    }
+
+    #[test]
+    fn crossing_lines_are_x_intersection() {
+        let lines = [
+            Line(point![1.0, -1.0], point![1.0, 1.0]),
+            Line(point![0.0, 0.0], point![2.0, 0.0]),
+        ];
+
+        let intersections = find_intersections(&lines, 0.35, 0.3);
+
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].kind, IntersectionKind::X);
+    }
+
+    #[test]
+    fn non_orthogonal_or_distant_lines_do_not_intersect() {
+        let lines = [
+            Line(point![0.0, 0.0], point![2.0, 0.0]),
+            Line(point![0.0, 1.0], point![2.0, 2.0]),
+            Line(point![4.0, 1.0], point![4.0, 3.0]),
+        ];
+
+        let intersections = find_intersections(&lines, 0.35, 0.3);
+
+        assert!(intersections.is_empty());
+    }
+
     #[test]
     fn check_fixed_segment_size() {
         let image_size = point![1.0, 1.0];
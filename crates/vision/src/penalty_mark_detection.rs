@@ -0,0 +1,187 @@
+use std::ops::Range;
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use context_attribute::context;
+use coordinate_systems::Ground;
+use framework::{AdditionalOutput, MainOutput};
+use linear_algebra::{distance, point, Point2, Vector2};
+use projection::Projection;
+use types::{
+    ball::Ball,
+    camera_matrix::CameraMatrix,
+    field_dimensions::FieldDimensions,
+    filtered_segments::FilteredSegments,
+    image_segments::{EdgeType, ScanLine, Segment},
+    line_data::LineData,
+};
+
+#[derive(Deserialize, Serialize)]
+pub struct PenaltyMarkDetection {}
+
+#[context]
+pub struct CreationContext {}
+
+#[context]
+pub struct CycleContext {
+    candidates_in_ground:
+        AdditionalOutput<Vec<Point2<Ground>>, "penalty_mark_detection.candidates_in_ground">,
+
+    enable: Parameter<bool, "penalty_mark_detection.$cycler_instance.enable">,
+    field_dimensions: Parameter<FieldDimensions, "field_dimensions">,
+    minimum_luminance: Parameter<u8, "penalty_mark_detection.$cycler_instance.minimum_luminance">,
+    minimum_number_of_segments:
+        Parameter<usize, "penalty_mark_detection.$cycler_instance.minimum_number_of_segments">,
+    minimum_line_distance:
+        Parameter<f32, "penalty_mark_detection.$cycler_instance.minimum_line_distance">,
+    size_tolerance: Parameter<Range<f32>, "penalty_mark_detection.$cycler_instance.size_tolerance">,
+
+    balls: Input<Option<Vec<Ball>>, "balls?">,
+    camera_matrix: RequiredInput<Option<CameraMatrix>, "camera_matrix?">,
+    filtered_segments: Input<FilteredSegments, "filtered_segments">,
+    line_data: RequiredInput<Option<LineData>, "line_data?">,
+}
+
+#[context]
+#[derive(Default)]
+pub struct MainOutputs {
+    pub penalty_marks: MainOutput<Option<Vec<Point2<Ground>>>>,
+}
+
+impl PenaltyMarkDetection {
+    pub fn new(_context: CreationContext) -> Result<Self> {
+        Ok(Self {})
+    }
+
+    pub fn cycle(&mut self, mut context: CycleContext) -> Result<MainOutputs> {
+        if !context.enable {
+            return Ok(MainOutputs::default());
+        }
+
+        let marker_size = context.field_dimensions.penalty_marker_size;
+        let allowed_size =
+            marker_size * context.size_tolerance.start..marker_size * context.size_tolerance.end;
+        let balls = context.balls.map(Vec::as_slice).unwrap_or_default();
+        let candidates: Vec<_> = context
+            .filtered_segments
+            .scan_grid
+            .vertical_scan_lines
+            .iter()
+            .flat_map(|scan_line| {
+                scan_line
+                    .segments
+                    .iter()
+                    .filter(|segment| {
+                        is_mark_segment(
+                            scan_line,
+                            segment,
+                            *context.minimum_luminance,
+                            context.line_data,
+                            balls,
+                        )
+                    })
+                    .filter_map(|segment| {
+                        let start = context
+                            .camera_matrix
+                            .pixel_to_ground(point![
+                                scan_line.position as f32,
+                                segment.start as f32
+                            ])
+                            .ok()?;
+                        let end = context
+                            .camera_matrix
+                            .pixel_to_ground(point![scan_line.position as f32, segment.end as f32])
+                            .ok()?;
+                        allowed_size
+                            .contains(&distance(start, end))
+                            .then(|| start + (end - start) / 2.0)
+                    })
+            })
+            .collect();
+        context
+            .candidates_in_ground
+            .fill_if_subscribed(|| candidates.clone());
+
+        let penalty_marks = cluster_candidates(&candidates, allowed_size.end)
+            .into_iter()
+            .filter(|cluster| cluster.len() >= *context.minimum_number_of_segments)
+            .map(|cluster| {
+                (cluster
+                    .iter()
+                    .map(|candidate| candidate.coords())
+                    .sum::<Vector2<Ground>>()
+                    / cluster.len() as f32)
+                    .as_point()
+            })
+            .filter(|center| {
+                context.line_data.lines.iter().all(|line| {
+                    line.squared_distance_to_segment(*center)
+                        >= context.minimum_line_distance.powi(2)
+                })
+            })
+            .collect();
+
+        Ok(MainOutputs {
+            penalty_marks: Some(penalty_marks).into(),
+        })
+    }
+}
+
+fn is_mark_segment(
+    scan_line: &ScanLine,
+    segment: &Segment,
+    minimum_luminance: u8,
+    line_data: &LineData,
+    balls: &[Ball],
+) -> bool {
+    let is_enclosed =
+        segment.start_edge_type == EdgeType::Rising && segment.end_edge_type == EdgeType::Falling;
+    let is_on_line = line_data
+        .used_segments
+        .contains(&point![scan_line.position, segment.start]);
+    let is_on_ball = balls.iter().any(|ball| {
+        ball.image_location
+            .contains(point![scan_line.position as f32, segment.center() as f32])
+    });
+    is_enclosed && segment.color.y >= minimum_luminance && !is_on_line && !is_on_ball
+}
+
+/// Candidates closer than the maximum marker size to the first candidate of a cluster
+fn cluster_candidates(
+    candidates: &[Point2<Ground>],
+    maximum_marker_size: f32,
+) -> Vec<Vec<Point2<Ground>>> {
+    let mut clusters: Vec<Vec<Point2<Ground>>> = Vec::new();
+    for &candidate in candidates {
+        match clusters
+            .iter_mut()
+            .find(|cluster| distance(cluster[0], candidate) <= maximum_marker_size)
+        {
+            Some(cluster) => cluster.push(candidate),
+            None => clusters.push(vec![candidate]),
+        }
+    }
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidates_of_one_mark_are_clustered() {
+        let candidates = [
+            point![3.0, 0.0],
+            point![3.02, 0.03],
+            point![1.0, 1.0],
+            point![3.01, -0.03],
+        ];
+
+        let clusters = cluster_candidates(&candidates, 0.15);
+
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].len(), 3);
+        assert_eq!(clusters[1], vec![point![1.0, 1.0]]);
+    }
+}
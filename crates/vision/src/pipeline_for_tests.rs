@@ -0,0 +1,110 @@
+//! Runs the vision nodes in front of a detection on a stored image with the default parameters
+
+use std::fs::read_to_string;
+
+use color_eyre::Result;
+use framework::AdditionalOutput;
+use linear_algebra::{point, IntoTransform};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use types::{
+    camera_matrix::{camera_to_head, CameraMatrix},
+    camera_position::CameraPosition,
+    filtered_segments::FilteredSegments,
+    image_segments::ImageSegments,
+    interpolated::Interpolated,
+    parameters::CameraMatrixParameters,
+    robot_dimensions::RobotDimensions,
+    ycbcr422_image::YCbCr422Image,
+};
+
+use crate::{
+    field_color_detection::{self, FieldColorDetection},
+    image_segmenter::{self, ImageSegmenter},
+    segment_filter::{self, SegmentFilter},
+};
+
+pub fn default_parameters() -> Result<Value> {
+    Ok(serde_json::from_str(&read_to_string(
+        "../../etc/parameters/default.json",
+    )?)?)
+}
+
+pub fn parameter<T: DeserializeOwned>(parameters: &Value, name: &str) -> Result<T> {
+    Ok(serde_json::from_value(parameters[name].clone())?)
+}
+
+/// Bottom camera of a robot walking upright, calibrated with the default parameters
+pub fn bottom_camera_matrix(image: &YCbCr422Image, parameters: &Value) -> Result<CameraMatrix> {
+    let calibration: CameraMatrixParameters =
+        parameter(&parameters["camera_matrix_parameters"], "vision_bottom")?;
+    let walk_hip_height: f32 = parameter(&parameters["walking_engine"], "walk_hip_height")?;
+    Ok(CameraMatrix::from_normalized_focal_and_center(
+        calibration.focal_lengths,
+        calibration.cc_optical_center,
+        point![image.width() as f32, image.height() as f32],
+        camera_to_head(CameraPosition::Bottom, calibration.extrinsic_rotations),
+        nalgebra::Isometry3::from(RobotDimensions::ROBOT_TO_NECK.inner).framed_transform(),
+        nalgebra::Isometry3::translation(0.0, 0.0, walk_hip_height).framed_transform(),
+    ))
+}
+
+/// Segments of field color detection and image segmenter of the bottom camera
+pub fn image_segments(
+    image: &YCbCr422Image,
+    camera_matrix: &CameraMatrix,
+    parameters: &Value,
+) -> Result<ImageSegments> {
+    let field_color_parameters = &parameters["field_color_detection"]["vision_bottom"];
+    let field_color = FieldColorDetection::new(field_color_detection::CreationContext::new())?
+        .cycle(field_color_detection::CycleContext::new(
+            &parameter::<Interpolated>(field_color_parameters, "blue_chromaticity_threshold")?,
+            &parameter::<Interpolated>(field_color_parameters, "green_luminance_threshold")?,
+            &parameter::<Interpolated>(
+                field_color_parameters,
+                "lower_green_chromaticity_threshold",
+            )?,
+            &parameter::<Interpolated>(field_color_parameters, "red_chromaticity_threshold")?,
+            &parameter::<Interpolated>(
+                field_color_parameters,
+                "upper_green_chromaticity_threshold",
+            )?,
+            None,
+        ))?
+        .field_color
+        .value;
+
+    let segmenter_parameters = &parameters["image_segmenter"]["vision_bottom"];
+    let mut image_segmenter_cycle_time = None;
+    Ok(
+        ImageSegmenter::new(image_segmenter::CreationContext::new())?
+            .cycle(image_segmenter::CycleContext::new(
+                AdditionalOutput::new(false, &mut image_segmenter_cycle_time),
+                image,
+                Some(camera_matrix),
+                None,
+                &field_color,
+                None,
+                &parameter(segmenter_parameters, "horizontal_stride")?,
+                &parameter(segmenter_parameters, "vertical_stride")?,
+                &parameter(segmenter_parameters, "vertical_edge_detection_source")?,
+                &parameter(segmenter_parameters, "vertical_edge_threshold")?,
+                &parameter(segmenter_parameters, "vertical_median_mode")?,
+            ))?
+            .image_segments
+            .value,
+    )
+}
+
+/// Segments passed on by the segment filter of the bottom camera, which sees no field border
+pub fn filtered_segments(
+    image: &YCbCr422Image,
+    camera_matrix: &CameraMatrix,
+    parameters: &Value,
+) -> Result<FilteredSegments> {
+    let image_segments = image_segments(image, camera_matrix, parameters)?;
+    Ok(SegmentFilter::new(segment_filter::CreationContext::new())?
+        .cycle(segment_filter::CycleContext::new(None, &image_segments))?
+        .filtered_segments
+        .value)
+}
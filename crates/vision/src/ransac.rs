@@ -16,10 +16,19 @@ pub struct Ransac<Frame> {
 
 impl<Frame> Ransac<Frame> {
     pub fn new(unused_points: Vec<Point2<Frame>>) -> Self {
+        Self::with_random_number_generator(
+            unused_points,
+            StdRng::from_rng(thread_rng()).expect("Failed to create random number generator"),
+        )
+    }
+
+    pub fn with_random_number_generator(
+        unused_points: Vec<Point2<Frame>>,
+        random_number_generator: StdRng,
+    ) -> Self {
         Self {
             unused_points,
-            random_number_generator: StdRng::from_rng(thread_rng())
-                .expect("Failed to create random number generator"),
+            random_number_generator,
         }
     }
 }
@@ -81,10 +90,7 @@ mod test {
     struct SomeFrame;
 
     fn ransac_with_seed(unused_points: Vec<Point2<SomeFrame>>, seed: u64) -> Ransac<SomeFrame> {
-        Ransac {
-            unused_points,
-            random_number_generator: StdRng::seed_from_u64(seed),
-        }
+        Ransac::with_random_number_generator(unused_points, StdRng::seed_from_u64(seed))
     }
 
     #[test]
@@ -0,0 +1,466 @@
+use color_eyre::{eyre::bail, Result};
+use compiled_nn::CompiledNN;
+use serde::{Deserialize, Serialize};
+
+use context_attribute::context;
+use framework::{deserialize_not_implemented, AdditionalOutput, MainOutput};
+use hardware::PathsInterface;
+use linear_algebra::point;
+use types::{
+    color::Rgb,
+    parameters::RefereePoseDetectionParameters,
+    referee_pose::{HumanPose, Keypoint, RefereeGesture},
+    support_foot::Side,
+    ycbcr422_image::YCbCr422Image,
+};
+
+const NUMBER_OF_COCO_KEYPOINTS: usize = 17;
+
+struct NeuralNetwork {
+    network: CompiledNN,
+    input_width: usize,
+    input_height: usize,
+}
+
+unsafe impl Send for NeuralNetwork {}
+
+#[derive(Deserialize, Serialize)]
+pub struct RefereePoseDetection {
+    #[serde(skip, default = "deserialize_not_implemented")]
+    neural_network: Option<NeuralNetwork>,
+}
+
+#[context]
+pub struct CreationContext {
+    hardware_interface: HardwareInterface,
+    parameters:
+        Parameter<RefereePoseDetectionParameters, "referee_pose_detection.$cycler_instance">,
+}
+
+#[context]
+pub struct CycleContext {
+    referee_pose: AdditionalOutput<Option<HumanPose>, "referee_pose">,
+
+    image: Input<YCbCr422Image, "image">,
+
+    parameters:
+        Parameter<RefereePoseDetectionParameters, "referee_pose_detection.$cycler_instance">,
+}
+
+#[context]
+#[derive(Default)]
+pub struct MainOutputs {
+    pub referee_gesture: MainOutput<Option<RefereeGesture>>,
+}
+
+impl RefereePoseDetection {
+    pub fn new(context: CreationContext<impl PathsInterface>) -> Result<Self> {
+        if !context.parameters.enable {
+            return Ok(Self {
+                neural_network: None,
+            });
+        }
+
+        let paths = context.hardware_interface.get_paths();
+        let neural_network_path = paths
+            .neural_networks
+            .join(&context.parameters.neural_network);
+        if !neural_network_path.exists() {
+            bail!(
+                "referee pose detection is enabled but no keypoint network exists at {neural_network_path:?}"
+            );
+        }
+        let mut neural_network = CompiledNN::default();
+        neural_network.compile(neural_network_path);
+
+        let &[input_height, input_width, 3] = neural_network.input(0).dimensions else {
+            bail!(
+                "referee pose network input has to be height x width x 3, got {:?}",
+                neural_network.input(0).dimensions
+            );
+        };
+        let output_size = neural_network.output(0).data.len();
+        if output_size < NUMBER_OF_COCO_KEYPOINTS * 3 {
+            bail!(
+                "referee pose network output has {output_size} values, expected at least {}",
+                NUMBER_OF_COCO_KEYPOINTS * 3
+            );
+        }
+
+        Ok(Self {
+            neural_network: Some(NeuralNetwork {
+                network: neural_network,
+                input_width: input_width as usize,
+                input_height: input_height as usize,
+            }),
+        })
+    }
+
+    pub fn cycle(&mut self, mut context: CycleContext) -> Result<MainOutputs> {
+        let neural_network = match (context.parameters.enable, self.neural_network.as_mut()) {
+            (true, Some(neural_network)) => neural_network,
+            _ => return Ok(MainOutputs::default()),
+        };
+
+        let pose = detect_pose(
+            &mut neural_network.network,
+            context.image,
+            neural_network.input_width,
+            neural_network.input_height,
+        );
+        context.referee_pose.fill_if_subscribed(|| Some(pose));
+
+        Ok(MainOutputs {
+            referee_gesture: classify_gesture(&pose, context.parameters).into(),
+        })
+    }
+}
+
+/// Runs a single-person keypoint network on the downscaled image.
+///
+/// The input size has to match the input shape of the network, which is checked when it is
+/// loaded.
+fn detect_pose(
+    network: &mut CompiledNN,
+    image: &YCbCr422Image,
+    input_width: usize,
+    input_height: usize,
+) -> HumanPose {
+    write_network_input(
+        &mut network.input_mut(0).data,
+        image,
+        input_width,
+        input_height,
+    );
+    network.apply();
+    read_pose(
+        &network.output(0).data,
+        image.width() as f32,
+        image.height() as f32,
+    )
+}
+
+/// Downscales the image into RGB values in [0, 1] (height x width x channel)
+fn write_network_input(
+    input: &mut [f32],
+    image: &YCbCr422Image,
+    input_width: usize,
+    input_height: usize,
+) {
+    let horizontal_scale = image.width() as f32 / input_width as f32;
+    let vertical_scale = image.height() as f32 / input_height as f32;
+    for y in 0..input_height {
+        for x in 0..input_width {
+            let pixel: Rgb = image
+                .at(
+                    (x as f32 * horizontal_scale) as u32,
+                    (y as f32 * vertical_scale) as u32,
+                )
+                .into();
+            let index = (x + y * input_width) * 3;
+            input[index] = pixel.r as f32 / 255.0;
+            input[index + 1] = pixel.g as f32 / 255.0;
+            input[index + 2] = pixel.b as f32 / 255.0;
+        }
+    }
+}
+
+/// Reads the 17 COCO keypoints as (x, y, confidence) with coordinates relative to the input size
+fn read_pose(output: &[f32], image_width: f32, image_height: f32) -> HumanPose {
+    let keypoint = |index: usize| {
+        assert!(index < NUMBER_OF_COCO_KEYPOINTS);
+        Keypoint {
+            point: point![
+                output[index * 3] * image_width,
+                output[index * 3 + 1] * image_height
+            ],
+            confidence: output[index * 3 + 2],
+        }
+    };
+    HumanPose {
+        nose: keypoint(0),
+        left_shoulder: keypoint(5),
+        right_shoulder: keypoint(6),
+        left_elbow: keypoint(7),
+        right_elbow: keypoint(8),
+        left_wrist: keypoint(9),
+        right_wrist: keypoint(10),
+        left_hip: keypoint(11),
+        right_hip: keypoint(12),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ArmPosture {
+    Lowered,
+    DiagonalDown,
+    Horizontal { is_bent: bool },
+    DiagonalUp,
+    Up,
+}
+
+fn classify_arm(
+    shoulder: Keypoint,
+    elbow: Keypoint,
+    wrist: Keypoint,
+    parameters: &RefereePoseDetectionParameters,
+) -> Option<ArmPosture> {
+    if [shoulder, elbow, wrist]
+        .iter()
+        .any(|keypoint| keypoint.confidence < parameters.minimum_keypoint_confidence)
+    {
+        return None;
+    }
+
+    let shoulder_to_wrist = wrist.point - shoulder.point;
+    // image y axis points downwards
+    let elevation = (-shoulder_to_wrist.y()).atan2(shoulder_to_wrist.x().abs());
+    let upper_arm = elbow.point - shoulder.point;
+    let forearm = wrist.point - elbow.point;
+    let elbow_angle = upper_arm.angle(forearm);
+
+    let posture = if elevation < parameters.lowered_arm_maximum_elevation {
+        ArmPosture::Lowered
+    } else if elevation.abs() <= parameters.horizontal_arm_tolerance {
+        ArmPosture::Horizontal {
+            is_bent: elbow_angle > parameters.minimum_bent_elbow_angle,
+        }
+    } else if elevation >= std::f32::consts::FRAC_PI_2 - parameters.vertical_arm_tolerance {
+        ArmPosture::Up
+    } else if elevation > 0.0 {
+        ArmPosture::DiagonalUp
+    } else {
+        ArmPosture::DiagonalDown
+    };
+    Some(posture)
+}
+
+pub fn classify_gesture(
+    pose: &HumanPose,
+    parameters: &RefereePoseDetectionParameters,
+) -> Option<RefereeGesture> {
+    let left_arm = classify_arm(
+        pose.left_shoulder,
+        pose.left_elbow,
+        pose.left_wrist,
+        parameters,
+    )?;
+    let right_arm = classify_arm(
+        pose.right_shoulder,
+        pose.right_elbow,
+        pose.right_wrist,
+        parameters,
+    )?;
+
+    let (side, raised_arm) = match (left_arm, right_arm) {
+        (ArmPosture::Up, ArmPosture::Up) => return Some(RefereeGesture::FullTime),
+        (ArmPosture::Horizontal { .. }, ArmPosture::Up) => {
+            return Some(RefereeGesture::Substitution { side: Side::Left })
+        }
+        (ArmPosture::Up, ArmPosture::Horizontal { .. }) => {
+            return Some(RefereeGesture::Substitution { side: Side::Right })
+        }
+        (ArmPosture::Lowered, ArmPosture::Lowered) => return None,
+        (raised_arm, ArmPosture::Lowered) => (Side::Left, raised_arm),
+        (ArmPosture::Lowered, raised_arm) => (Side::Right, raised_arm),
+        _ => return None,
+    };
+
+    let gesture = match raised_arm {
+        ArmPosture::Lowered => unreachable!("raised arm cannot be lowered"),
+        ArmPosture::DiagonalDown => RefereeGesture::CornerKick { side },
+        ArmPosture::Horizontal { is_bent: false } => RefereeGesture::KickIn { side },
+        ArmPosture::Horizontal { is_bent: true } => RefereeGesture::PushingFreeKick { side },
+        ArmPosture::DiagonalUp => RefereeGesture::GoalKick { side },
+        ArmPosture::Up => RefereeGesture::Goal { side },
+    };
+    Some(gesture)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::FRAC_PI_4, path::Path};
+
+    use super::*;
+
+    fn parameters() -> RefereePoseDetectionParameters {
+        RefereePoseDetectionParameters {
+            minimum_keypoint_confidence: 0.3,
+            horizontal_arm_tolerance: 0.35,
+            vertical_arm_tolerance: 0.35,
+            lowered_arm_maximum_elevation: -1.0,
+            minimum_bent_elbow_angle: FRAC_PI_4,
+            ..Default::default()
+        }
+    }
+
+    fn keypoint(x: f32, y: f32) -> Keypoint {
+        Keypoint {
+            point: point![x, y],
+            confidence: 1.0,
+        }
+    }
+
+    /// Referee facing the camera: the anatomical left arm appears on the right of the image
+    fn pose(
+        left_elbow: (f32, f32),
+        left_wrist: (f32, f32),
+        right_elbow: (f32, f32),
+        right_wrist: (f32, f32),
+    ) -> HumanPose {
+        HumanPose {
+            nose: keypoint(100.0, 50.0),
+            left_shoulder: keypoint(120.0, 100.0),
+            right_shoulder: keypoint(80.0, 100.0),
+            left_elbow: keypoint(left_elbow.0, left_elbow.1),
+            right_elbow: keypoint(right_elbow.0, right_elbow.1),
+            left_wrist: keypoint(left_wrist.0, left_wrist.1),
+            right_wrist: keypoint(right_wrist.0, right_wrist.1),
+            left_hip: keypoint(115.0, 200.0),
+            right_hip: keypoint(85.0, 200.0),
+        }
+    }
+
+    const LEFT_LOWERED: [(f32, f32); 2] = [(122.0, 140.0), (124.0, 180.0)];
+    const RIGHT_LOWERED: [(f32, f32); 2] = [(78.0, 140.0), (76.0, 180.0)];
+
+    #[test]
+    fn lowered_arms_are_no_gesture() {
+        let pose = pose(
+            LEFT_LOWERED[0],
+            LEFT_LOWERED[1],
+            RIGHT_LOWERED[0],
+            RIGHT_LOWERED[1],
+        );
+        assert_eq!(classify_gesture(&pose, &parameters()), None);
+    }
+
+    #[test]
+    fn horizontal_straight_arm_is_kick_in() {
+        let pose = pose(
+            (160.0, 100.0),
+            (200.0, 100.0),
+            RIGHT_LOWERED[0],
+            RIGHT_LOWERED[1],
+        );
+        assert_eq!(
+            classify_gesture(&pose, &parameters()),
+            Some(RefereeGesture::KickIn { side: Side::Left })
+        );
+    }
+
+    #[test]
+    fn horizontal_bent_arm_is_pushing_free_kick() {
+        let pose = pose(
+            LEFT_LOWERED[0],
+            LEFT_LOWERED[1],
+            (40.0, 100.0),
+            (40.0, 60.0),
+        );
+        let pose = HumanPose {
+            right_wrist: keypoint(50.0, 95.0),
+            right_elbow: keypoint(60.0, 80.0),
+            ..pose
+        };
+        assert_eq!(
+            classify_gesture(&pose, &parameters()),
+            Some(RefereeGesture::PushingFreeKick { side: Side::Right })
+        );
+    }
+
+    #[test]
+    fn diagonal_arms_are_goal_and_corner_kicks() {
+        let up = pose(LEFT_LOWERED[0], LEFT_LOWERED[1], (50.0, 70.0), (20.0, 40.0));
+        assert_eq!(
+            classify_gesture(&up, &parameters()),
+            Some(RefereeGesture::GoalKick { side: Side::Right })
+        );
+
+        let down = pose(
+            (150.0, 130.0),
+            (180.0, 160.0),
+            RIGHT_LOWERED[0],
+            RIGHT_LOWERED[1],
+        );
+        assert_eq!(
+            classify_gesture(&down, &parameters()),
+            Some(RefereeGesture::CornerKick { side: Side::Left })
+        );
+    }
+
+    #[test]
+    fn raised_arms_are_goal_full_time_and_substitution() {
+        let goal = pose(
+            (121.0, 60.0),
+            (122.0, 20.0),
+            RIGHT_LOWERED[0],
+            RIGHT_LOWERED[1],
+        );
+        assert_eq!(
+            classify_gesture(&goal, &parameters()),
+            Some(RefereeGesture::Goal { side: Side::Left })
+        );
+
+        let full_time = pose((121.0, 60.0), (122.0, 20.0), (79.0, 60.0), (78.0, 20.0));
+        assert_eq!(
+            classify_gesture(&full_time, &parameters()),
+            Some(RefereeGesture::FullTime)
+        );
+
+        let substitution = pose((121.0, 60.0), (122.0, 20.0), (40.0, 100.0), (0.0, 100.0));
+        assert_eq!(
+            classify_gesture(&substitution, &parameters()),
+            Some(RefereeGesture::Substitution { side: Side::Right })
+        );
+    }
+
+    #[test]
+    fn uncertain_keypoints_are_no_gesture() {
+        let mut pose = pose(
+            (160.0, 100.0),
+            (200.0, 100.0),
+            RIGHT_LOWERED[0],
+            RIGHT_LOWERED[1],
+        );
+        pose.left_wrist.confidence = 0.1;
+        assert_eq!(classify_gesture(&pose, &parameters()), None);
+    }
+
+    #[test]
+    fn stored_image_is_downscaled_into_network_input() -> Result<()> {
+        let image =
+            YCbCr422Image::load_from_444_png(Path::new("../../tests/data/rome_bottom_ball.png"))?;
+        let (input_width, input_height) = (32, 24);
+        let mut input = vec![f32::NAN; input_width * input_height * 3];
+
+        write_network_input(&mut input, &image, input_width, input_height);
+
+        assert!(input.iter().all(|value| (0.0..=1.0).contains(value)));
+        let (x, y) = (10, 5);
+        let expected: Rgb = image
+            .at(
+                x as u32 * image.width() / input_width as u32,
+                y as u32 * image.height() / input_height as u32,
+            )
+            .into();
+        let index = (x + y * input_width) * 3;
+        assert_eq!(input[index], expected.r as f32 / 255.0);
+        assert_eq!(input[index + 1], expected.g as f32 / 255.0);
+        assert_eq!(input[index + 2], expected.b as f32 / 255.0);
+        Ok(())
+    }
+
+    #[test]
+    fn relative_keypoints_are_scaled_to_the_image() {
+        let mut output = [0.0; NUMBER_OF_COCO_KEYPOINTS * 3];
+        output[..3].copy_from_slice(&[0.5, 0.25, 0.9]);
+        output[9 * 3..10 * 3].copy_from_slice(&[0.75, 1.0, 0.4]);
+
+        let pose = read_pose(&output, 640.0, 480.0);
+
+        assert_eq!(pose.nose.point, point![320.0, 120.0]);
+        assert_eq!(pose.nose.confidence, 0.9);
+        assert_eq!(pose.left_wrist.point, point![480.0, 480.0]);
+        assert_eq!(pose.left_wrist.confidence, 0.4);
+    }
+}
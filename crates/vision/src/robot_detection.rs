@@ -0,0 +1,528 @@
+use std::{cmp::Ordering, path::Path};
+
+use color_eyre::{eyre::bail, Result};
+use compiled_nn::CompiledNN;
+use serde::{Deserialize, Serialize};
+
+use context_attribute::context;
+use coordinate_systems::Pixel;
+use framework::{deserialize_not_implemented, AdditionalOutput, MainOutput};
+use geometry::rectangle::Rectangle;
+use hardware::PathsInterface;
+use linear_algebra::{point, vector};
+use projection::Projection;
+use spl_network_messages::{Team, TeamColor};
+use types::{
+    camera_matrix::CameraMatrix,
+    color::YCbCr444,
+    detected_robots::DetectedRobot,
+    game_controller_state::GameControllerState,
+    parameters::{JerseyColorParameters, JerseyColorReference, RobotDetectionParameters},
+    ycbcr422_image::YCbCr422Image,
+};
+
+const NUMBER_OF_CELL_OUTPUTS: usize = 5;
+const NUMBER_OF_CELL_OUTPUTS_U32: u32 = NUMBER_OF_CELL_OUTPUTS as u32;
+
+/// Robot detection network with input and grid sizes read from its shapes
+pub struct RobotDetectionNetwork {
+    network: CompiledNN,
+    input_width: usize,
+    input_height: usize,
+    grid_width: usize,
+    grid_height: usize,
+}
+
+unsafe impl Send for RobotDetectionNetwork {}
+
+#[derive(Deserialize, Serialize)]
+pub struct RobotDetection {
+    #[serde(skip, default = "deserialize_not_implemented")]
+    neural_network: Option<RobotDetectionNetwork>,
+}
+
+#[context]
+pub struct CreationContext {
+    hardware_interface: HardwareInterface,
+    parameters: Parameter<RobotDetectionParameters, "robot_detection.$cycler_instance">,
+}
+
+#[context]
+pub struct CycleContext {
+    candidates: AdditionalOutput<Vec<Rectangle<Pixel>>, "robot_detection.candidates">,
+
+    camera_matrix: RequiredInput<Option<CameraMatrix>, "camera_matrix?">,
+    game_controller_state: Input<Option<GameControllerState>, "Control", "game_controller_state?">,
+    image: Input<YCbCr422Image, "image">,
+
+    jersey_colors: Parameter<JerseyColorParameters, "jersey_color_classification">,
+    parameters: Parameter<RobotDetectionParameters, "robot_detection.$cycler_instance">,
+}
+
+#[context]
+#[derive(Default)]
+pub struct MainOutputs {
+    pub detected_robots: MainOutput<Option<Vec<DetectedRobot>>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Detection {
+    pub bounding_box: Rectangle<Pixel>,
+    pub confidence: f32,
+}
+
+impl RobotDetection {
+    pub fn new(context: CreationContext<impl PathsInterface>) -> Result<Self> {
+        if !context.parameters.enable {
+            return Ok(Self {
+                neural_network: None,
+            });
+        }
+
+        let paths = context.hardware_interface.get_paths();
+        Ok(Self {
+            neural_network: Some(RobotDetectionNetwork::load(
+                paths
+                    .neural_networks
+                    .join(&context.parameters.neural_network),
+            )?),
+        })
+    }
+
+    pub fn cycle(&mut self, mut context: CycleContext) -> Result<MainOutputs> {
+        let neural_network = match (context.parameters.enable, self.neural_network.as_mut()) {
+            (true, Some(neural_network)) => neural_network,
+            _ => return Ok(MainOutputs::default()),
+        };
+        let parameters = context.parameters;
+
+        let candidates = neural_network.candidates(context.image, parameters.detection_threshold);
+        context.candidates.fill_if_subscribed(|| {
+            candidates
+                .iter()
+                .map(|candidate| candidate.bounding_box)
+                .collect()
+        });
+
+        let candidate_colors = jersey_colors_in_game(context.game_controller_state);
+        let detected_robots = non_maximum_suppression(candidates, parameters.maximum_overlap)
+            .into_iter()
+            .filter_map(|detection| {
+                let bounding_box = detection.bounding_box;
+                let foot_point = point![
+                    (bounding_box.min.x() + bounding_box.max.x()) / 2.0,
+                    bounding_box.max.y()
+                ];
+                let position = context.camera_matrix.pixel_to_ground(foot_point).ok()?;
+                let jersey_color = classify_jersey(
+                    context.image,
+                    bounding_box,
+                    candidate_colors.as_deref(),
+                    context.jersey_colors,
+                );
+                Some(DetectedRobot {
+                    bounding_box,
+                    confidence: detection.confidence,
+                    position,
+                    jersey_color,
+                    team: team_of_jersey(jersey_color, context.game_controller_state),
+                })
+            })
+            .collect();
+
+        Ok(MainOutputs {
+            detected_robots: Some(detected_robots).into(),
+        })
+    }
+}
+
+impl RobotDetectionNetwork {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            bail!("no robot detection network exists at {path:?}");
+        }
+        let mut network = CompiledNN::default();
+        network.compile(path);
+
+        let &[input_height, input_width, 1] = network.input(0).dimensions else {
+            bail!(
+                "robot detection network input has to be height x width x 1, got {:?}",
+                network.input(0).dimensions
+            );
+        };
+        let &[grid_height, grid_width, NUMBER_OF_CELL_OUTPUTS_U32] = network.output(0).dimensions
+        else {
+            bail!(
+                "robot detection network output has to be a grid of {NUMBER_OF_CELL_OUTPUTS} values per cell, got {:?}",
+                network.output(0).dimensions
+            );
+        };
+
+        Ok(Self {
+            network,
+            input_width: input_width as usize,
+            input_height: input_height as usize,
+            grid_width: grid_width as usize,
+            grid_height: grid_height as usize,
+        })
+    }
+
+    /// Detections above `detection_threshold`, before non-maximum suppression
+    pub fn candidates(
+        &mut self,
+        image: &YCbCr422Image,
+        detection_threshold: f32,
+    ) -> Vec<Detection> {
+        let output = self.run(image);
+        decode_detections(
+            &output,
+            self.grid_width,
+            self.grid_height,
+            image.width() as f32,
+            image.height() as f32,
+            detection_threshold,
+        )
+    }
+
+    /// Runs the network on the luminance of the downscaled image.
+    ///
+    /// The network receives luminance values in [0, 1] (height x width) and returns a grid of
+    /// cells (height x width x 5) containing the confidence, the box center relative to the cell
+    /// and the box size relative to the image.
+    fn run(&mut self, image: &YCbCr422Image) -> Vec<f32> {
+        let horizontal_scale = image.width() as f32 / self.input_width as f32;
+        let vertical_scale = image.height() as f32 / self.input_height as f32;
+        let input = self.network.input_mut(0);
+        for y in 0..self.input_height {
+            for x in 0..self.input_width {
+                let pixel = image.at(
+                    (x as f32 * horizontal_scale) as u32,
+                    (y as f32 * vertical_scale) as u32,
+                );
+                input.data[x + y * self.input_width] = pixel.y as f32 / 255.0;
+            }
+        }
+        self.network.apply();
+
+        self.network.output(0).data.to_vec()
+    }
+}
+
+fn decode_detections(
+    output: &[f32],
+    grid_width: usize,
+    grid_height: usize,
+    image_width: f32,
+    image_height: f32,
+    detection_threshold: f32,
+) -> Vec<Detection> {
+    let cell_width = image_width / grid_width as f32;
+    let cell_height = image_height / grid_height as f32;
+    output
+        .chunks_exact(NUMBER_OF_CELL_OUTPUTS)
+        .take(grid_width * grid_height)
+        .enumerate()
+        .filter(|(_, cell)| cell[0] >= detection_threshold)
+        .map(|(index, cell)| {
+            let column = (index % grid_width) as f32;
+            let row = (index / grid_width) as f32;
+            let center = point![
+                (column + cell[1]) * cell_width,
+                (row + cell[2]) * cell_height
+            ];
+            let size = vector![cell[3] * image_width, cell[4] * image_height];
+            Detection {
+                bounding_box: Rectangle::new_with_center_and_size(center, size),
+                confidence: cell[0],
+            }
+        })
+        .collect()
+}
+
+pub fn intersection_over_union(first: Rectangle<Pixel>, second: Rectangle<Pixel>) -> f32 {
+    let intersection = first.rectangle_intersection(second);
+    let union = first.area() + second.area() - intersection;
+    if union <= 0.0 {
+        return 0.0;
+    }
+    intersection / union
+}
+
+/// Keeps the most confident detections and drops those overlapping an already kept one
+pub fn non_maximum_suppression(
+    mut detections: Vec<Detection>,
+    maximum_overlap: f32,
+) -> Vec<Detection> {
+    detections.sort_by(|first, second| {
+        second
+            .confidence
+            .partial_cmp(&first.confidence)
+            .unwrap_or(Ordering::Equal)
+    });
+    let mut kept: Vec<Detection> = Vec::new();
+    for detection in detections {
+        if kept.iter().all(|kept| {
+            intersection_over_union(kept.bounding_box, detection.bounding_box) <= maximum_overlap
+        }) {
+            kept.push(detection);
+        }
+    }
+    kept
+}
+
+/// Jersey colors announced by the game controller, `None` if any color may appear
+fn jersey_colors_in_game(
+    game_controller_state: Option<&GameControllerState>,
+) -> Option<Vec<TeamColor>> {
+    let state = game_controller_state?;
+    let mut colors = Vec::new();
+    for color in [
+        state.hulks_jersey_colors.field_player,
+        state.hulks_jersey_colors.goal_keeper,
+        state.opponent_jersey_colors.field_player,
+        state.opponent_jersey_colors.goal_keeper,
+    ] {
+        if !colors.contains(&color) {
+            colors.push(color);
+        }
+    }
+    Some(colors)
+}
+
+fn color_distance(reference: &JerseyColorReference, pixel: YCbCr444, luminance_weight: f32) -> f32 {
+    let luminance = (pixel.y as f32 - reference.reference.y as f32) * luminance_weight;
+    let cb = pixel.cb as f32 - reference.reference.cb as f32;
+    let cr = pixel.cr as f32 - reference.reference.cr as f32;
+    (luminance.powi(2) + cb.powi(2) + cr.powi(2)).sqrt()
+}
+
+/// Votes for the nearest reference color among the torso pixels of the bounding box
+///
+/// The torso is assumed to cover the middle half horizontally and 20% to 50% of the box height
+/// from the top, which holds for upright robots.
+fn classify_jersey(
+    image: &YCbCr422Image,
+    bounding_box: Rectangle<Pixel>,
+    candidate_colors: Option<&[TeamColor]>,
+    parameters: &JerseyColorParameters,
+) -> Option<TeamColor> {
+    let references: Vec<_> = parameters
+        .references
+        .iter()
+        .filter(|reference| {
+            candidate_colors.map_or(true, |colors| colors.contains(&reference.color))
+        })
+        .collect();
+    if references.is_empty() {
+        return None;
+    }
+
+    let size = bounding_box.max - bounding_box.min;
+    let left = (bounding_box.min.x() + size.x() * 0.25).max(0.0) as u32;
+    let right = (bounding_box.max.x() - size.x() * 0.25).max(0.0) as u32;
+    let top = (bounding_box.min.y() + size.y() * 0.2).max(0.0) as u32;
+    let bottom = (bounding_box.min.y() + size.y() * 0.5).max(0.0) as u32;
+
+    let mut votes = vec![0usize; references.len()];
+    let mut number_of_pixels = 0;
+    for y in top..bottom {
+        for x in left..right {
+            let Some(pixel) = image.try_at(x, y) else {
+                continue;
+            };
+            number_of_pixels += 1;
+            let nearest = references
+                .iter()
+                .enumerate()
+                .map(|(index, reference)| {
+                    (
+                        index,
+                        color_distance(reference, pixel, parameters.luminance_weight),
+                    )
+                })
+                .min_by(|(_, first), (_, second)| {
+                    first.partial_cmp(second).unwrap_or(Ordering::Equal)
+                });
+            if let Some((index, distance)) = nearest {
+                if distance <= parameters.maximum_distance {
+                    votes[index] += 1;
+                }
+            }
+        }
+    }
+
+    let (winner, &winner_votes) = votes.iter().enumerate().max_by_key(|(_, &votes)| votes)?;
+    let vote_ratio = winner_votes as f32 / number_of_pixels.max(1) as f32;
+    (winner_votes > 0 && vote_ratio >= parameters.minimum_vote_ratio)
+        .then_some(references[winner].color)
+}
+
+/// Team wearing `jersey_color`, uncertain if both teams or neither wear it
+fn team_of_jersey(
+    jersey_color: Option<TeamColor>,
+    game_controller_state: Option<&GameControllerState>,
+) -> Team {
+    let (Some(color), Some(state)) = (jersey_color, game_controller_state) else {
+        return Team::Uncertain;
+    };
+    let is_worn_by = |field_player: TeamColor, goal_keeper: TeamColor| {
+        color == field_player || color == goal_keeper
+    };
+    match (
+        is_worn_by(
+            state.hulks_jersey_colors.field_player,
+            state.hulks_jersey_colors.goal_keeper,
+        ),
+        is_worn_by(
+            state.opponent_jersey_colors.field_player,
+            state.opponent_jersey_colors.goal_keeper,
+        ),
+    ) {
+        (true, false) => Team::Hulks,
+        (false, true) => Team::Opponent,
+        _ => Team::Uncertain,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use spl_network_messages::{GamePhase, GameState};
+    use types::{color::YCbCr422, game_controller_state::JerseyColors, players::Players};
+
+    use super::*;
+
+    fn detection(min: (f32, f32), max: (f32, f32), confidence: f32) -> Detection {
+        Detection {
+            bounding_box: Rectangle {
+                min: point![min.0, min.1],
+                max: point![max.0, max.1],
+            },
+            confidence,
+        }
+    }
+
+    fn parameters() -> JerseyColorParameters {
+        JerseyColorParameters {
+            references: vec![
+                JerseyColorReference {
+                    color: TeamColor::Blue,
+                    reference: YCbCr444::new(60, 170, 110),
+                },
+                JerseyColorReference {
+                    color: TeamColor::Red,
+                    reference: YCbCr444::new(80, 100, 190),
+                },
+            ],
+            luminance_weight: 0.3,
+            maximum_distance: 40.0,
+            minimum_vote_ratio: 0.3,
+        }
+    }
+
+    fn grid_output(grid_width: usize, grid_height: usize, cells: &[(usize, [f32; 5])]) -> Vec<f32> {
+        let mut output = vec![0.0; grid_width * grid_height * NUMBER_OF_CELL_OUTPUTS];
+        for (index, cell) in cells {
+            output[index * NUMBER_OF_CELL_OUTPUTS..(index + 1) * NUMBER_OF_CELL_OUTPUTS]
+                .copy_from_slice(cell);
+        }
+        output
+    }
+
+    #[test]
+    fn cells_above_threshold_are_decoded_to_boxes() {
+        // 4x3 grid on a 640x480 image, cells are 160x160 pixels
+        let output = grid_output(
+            4,
+            3,
+            &[
+                (5, [0.9, 0.5, 0.5, 0.25, 0.5]),
+                (11, [0.3, 0.5, 0.5, 0.1, 0.1]),
+            ],
+        );
+
+        let detections = decode_detections(&output, 4, 3, 640.0, 480.0, 0.5);
+
+        assert_eq!(
+            detections,
+            vec![detection((160.0, 120.0), (320.0, 360.0), 0.9)]
+        );
+    }
+
+    #[test]
+    fn overlapping_detections_are_suppressed() {
+        let detections = vec![
+            detection((100.0, 100.0), (200.0, 300.0), 0.7),
+            detection((110.0, 100.0), (210.0, 300.0), 0.9),
+            detection((300.0, 100.0), (400.0, 300.0), 0.8),
+        ];
+
+        let kept = non_maximum_suppression(detections, 0.4);
+
+        assert_eq!(
+            kept,
+            vec![
+                detection((110.0, 100.0), (210.0, 300.0), 0.9),
+                detection((300.0, 100.0), (400.0, 300.0), 0.8),
+            ]
+        );
+    }
+
+    fn image_with_jersey(jersey: YCbCr444) -> YCbCr422Image {
+        let background = YCbCr444::new(100, 90, 80);
+        let buffer = (0..240)
+            .flat_map(|y| {
+                (0..160).map(move |x_422| {
+                    let x = x_422 * 2;
+                    let color = if (120..200).contains(&x) && (100..160).contains(&y) {
+                        jersey
+                    } else {
+                        background
+                    };
+                    YCbCr422::from([color, color])
+                })
+            })
+            .collect();
+        YCbCr422Image::from_ycbcr_buffer(160, 240, buffer)
+    }
+
+    fn game_controller_state() -> GameControllerState {
+        GameControllerState {
+            game_state: GameState::Playing,
+            game_phase: GamePhase::Normal,
+            kicking_team: Team::Hulks,
+            last_game_state_change: UNIX_EPOCH,
+            penalties: Players::default(),
+            remaining_amount_of_messages: 1200,
+            sub_state: None,
+            hulks_team_is_home_after_coin_toss: true,
+            hulks_jersey_colors: JerseyColors {
+                field_player: TeamColor::Blue,
+                goal_keeper: TeamColor::Blue,
+            },
+            opponent_jersey_colors: JerseyColors {
+                field_player: TeamColor::Red,
+                goal_keeper: TeamColor::Red,
+            },
+        }
+    }
+
+    #[test]
+    fn jersey_color_determines_team() {
+        let image = image_with_jersey(YCbCr444::new(85, 105, 185));
+        let bounding_box = Rectangle {
+            min: point![100.0, 60.0],
+            max: point![220.0, 260.0],
+        };
+        let state = game_controller_state();
+        let colors = jersey_colors_in_game(Some(&state));
+
+        let jersey_color = classify_jersey(&image, bounding_box, colors.as_deref(), &parameters());
+
+        assert_eq!(jersey_color, Some(TeamColor::Red));
+        assert_eq!(team_of_jersey(jersey_color, Some(&state)), Team::Opponent);
+        assert_eq!(team_of_jersey(jersey_color, None), Team::Uncertain);
+    }
+}
@@ -0,0 +1,168 @@
+use std::{fs::read_to_string, ops::AddAssign, path::Path};
+
+use color_eyre::{eyre::WrapErr, Result};
+use serde::Deserialize;
+
+use coordinate_systems::Pixel;
+use geometry::rectangle::Rectangle;
+use linear_algebra::point;
+use types::ycbcr422_image::YCbCr422Image;
+
+use crate::robot_detection::intersection_over_union;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+pub enum AnnotationClass {
+    Ball,
+    Robot,
+    GoalPost,
+    PenaltySpot,
+    LSpot,
+    TSpot,
+    XSpot,
+}
+
+/// Bounding box as written by annotato
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Annotation {
+    pub points: [[f32; 2]; 2],
+    pub class: AnnotationClass,
+}
+
+impl Annotation {
+    pub fn bounding_box(&self) -> Rectangle<Pixel> {
+        let [[min_x, min_y], [max_x, max_y]] = self.points;
+        Rectangle {
+            min: point![min_x, min_y],
+            max: point![max_x, max_y],
+        }
+    }
+}
+
+/// Reads the annotations annotato stores next to the image, e.g. `image.json` for `image.png`
+pub fn load_annotations(image_path: impl AsRef<Path>) -> Result<Vec<Annotation>> {
+    let label_path = image_path.as_ref().with_extension("json");
+    let annotations = read_to_string(&label_path)
+        .wrap_err_with(|| format!("failed to read annotations from {label_path:?}"))?;
+    serde_json::from_str(&annotations)
+        .wrap_err_with(|| format!("failed to parse annotations from {label_path:?}"))
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Evaluation {
+    pub true_positives: usize,
+    pub number_of_detections: usize,
+    pub found_robots: usize,
+    pub number_of_robots: usize,
+}
+
+impl Evaluation {
+    /// Fraction of detections matching an annotated robot, 1 without detections
+    pub fn precision(&self) -> f32 {
+        if self.number_of_detections == 0 {
+            return 1.0;
+        }
+        self.true_positives as f32 / self.number_of_detections as f32
+    }
+
+    /// Fraction of annotated robots matched by a detection, 1 without annotated robots
+    pub fn recall(&self) -> f32 {
+        if self.number_of_robots == 0 {
+            return 1.0;
+        }
+        self.found_robots as f32 / self.number_of_robots as f32
+    }
+}
+
+impl AddAssign for Evaluation {
+    fn add_assign(&mut self, other: Self) {
+        self.true_positives += other.true_positives;
+        self.number_of_detections += other.number_of_detections;
+        self.found_robots += other.found_robots;
+        self.number_of_robots += other.number_of_robots;
+    }
+}
+
+/// Matches detections against the annotated robots with at least `minimum_overlap` intersection
+/// over union
+pub fn evaluate(
+    detections: &[Rectangle<Pixel>],
+    annotations: &[Annotation],
+    minimum_overlap: f32,
+) -> Evaluation {
+    let robots: Vec<_> = annotations
+        .iter()
+        .filter(|annotation| annotation.class == AnnotationClass::Robot)
+        .map(Annotation::bounding_box)
+        .collect();
+    let matches = |detection: Rectangle<Pixel>, robot: Rectangle<Pixel>| {
+        intersection_over_union(detection, robot) >= minimum_overlap
+    };
+    Evaluation {
+        true_positives: detections
+            .iter()
+            .filter(|&&detection| robots.iter().any(|&robot| matches(detection, robot)))
+            .count(),
+        number_of_detections: detections.len(),
+        found_robots: robots
+            .iter()
+            .filter(|&&robot| {
+                detections
+                    .iter()
+                    .any(|&detection| matches(detection, robot))
+            })
+            .count(),
+        number_of_robots: robots.len(),
+    }
+}
+
+/// Loads an annotato image with its annotations from disk and evaluates the detections in it
+pub fn evaluate_annotated_image(
+    image_path: impl AsRef<Path>,
+    minimum_overlap: f32,
+    detect: impl FnOnce(&YCbCr422Image) -> Vec<Rectangle<Pixel>>,
+) -> Result<Evaluation> {
+    let image_path = image_path.as_ref();
+    let image = YCbCr422Image::load_from_rgb_file(image_path)
+        .wrap_err_with(|| format!("failed to load image {image_path:?}"))?;
+    let annotations = load_annotations(image_path)?;
+    Ok(evaluate(&detect(&image), &annotations, minimum_overlap))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle(min: (f32, f32), max: (f32, f32)) -> Rectangle<Pixel> {
+        Rectangle {
+            min: point![min.0, min.1],
+            max: point![max.0, max.1],
+        }
+    }
+
+    #[test]
+    fn detections_are_matched_with_annotated_robots() {
+        let annotations = [
+            Annotation {
+                points: [[150.0, 110.0], [330.0, 370.0]],
+                class: AnnotationClass::Robot,
+            },
+            Annotation {
+                points: [[480.0, 200.0], [560.0, 320.0]],
+                class: AnnotationClass::Robot,
+            },
+            Annotation {
+                points: [[400.0, 400.0], [420.0, 420.0]],
+                class: AnnotationClass::Ball,
+            },
+        ];
+        let detections = [
+            rectangle((160.0, 120.0), (320.0, 360.0)),
+            rectangle((380.0, 390.0), (430.0, 430.0)),
+        ];
+
+        let evaluation = evaluate(&detections, &annotations, 0.5);
+
+        assert_eq!(evaluation.precision(), 0.5);
+        assert_eq!(evaluation.recall(), 0.5);
+    }
+}
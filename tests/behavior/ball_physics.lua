@@ -0,0 +1,54 @@
+function spawn_robot(number)
+  table.insert(state.robots, create_robot(number))
+end
+
+spawn_robot(1)
+
+local goals = 0
+local balls_out = 0
+
+function on_goal()
+  goals = goals + 1
+end
+
+function on_ball_out()
+  balls_out = balls_out + 1
+end
+
+function roll_ball(position, velocity)
+  state.ball = {
+    position = position,
+    velocity = velocity,
+  }
+end
+
+function on_cycle()
+  if state.cycle_count == 10 then
+    roll_ball({ 3.0, 0.0 }, { 2.5, 0.0 })
+  end
+
+  if state.cycle_count == 500 then
+    expect(goals == 1 and balls_out == 0, "ball rolling between the posts scores a goal")
+    roll_ball({ 0.0, 2.0 }, { 0.0, 2.0 })
+  end
+
+  if state.cycle_count == 1000 then
+    expect(goals == 1 and balls_out == 1, "ball rolling over the side line is out")
+    roll_ball({ 3.5, 0.8 }, { 2.0, 0.0 })
+  end
+
+  if state.cycle_count == 1500 then
+    expect(
+      goals == 1 and balls_out == 1 and state.ball.position[1] < 4.4,
+      "ball bounces off the goal post"
+    )
+  end
+
+  if state.cycle_count == 2000 then
+    expect(
+      state.ball.velocity[1] == 0.0 and state.ball.velocity[2] == 0.0,
+      "rolling ball comes to rest"
+    )
+    state.finished = true
+  end
+end
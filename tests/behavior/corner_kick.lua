@@ -0,0 +1,3 @@
+local set_play = require 'lib.set_play'
+
+set_play.run("CornerKick", { 4.5, 3.0 }, { -4.5, -3.0 })
@@ -0,0 +1,44 @@
+function spawn_robot(number)
+  local robot = create_robot(number)
+  -- dribble planning is disabled by default
+  robot.parameters.behavior.dribbling.planning.enable = true
+  table.insert(state.robots, robot)
+end
+
+function spawn_opponent(position, policy)
+  table.insert(state.opponents, create_opponent(position, policy))
+  return #state.opponents
+end
+
+spawn_robot(1)
+spawn_robot(4)
+
+-- opponents walk around in the way to the goal without touching the ball on purpose
+spawn_opponent({ 1.5, 0.5 }, "RandomWalk")
+spawn_opponent({ 2.5, -0.5 }, "RandomWalk")
+spawn_opponent({ 3.5, 0.5 }, "RandomWalk")
+
+function on_goal()
+  state.finished = true
+end
+
+function on_cycle()
+  if state.cycle_count == 10 then
+    state.ball = {
+      position = { 0.0, 0.0 },
+      velocity = { 0.0, 0.0 },
+    }
+    state.game_controller_state.game_state = "Playing"
+    state.filtered_game_state = {
+      Playing = {
+        ball_is_free = true,
+        kick_off = false,
+      },
+    }
+    expect_goal_within(3000)
+  end
+
+  if state.cycle_count == 3100 then
+    state.finished = true
+  end
+end
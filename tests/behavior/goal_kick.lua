@@ -0,0 +1,3 @@
+local set_play = require 'lib.set_play'
+
+set_play.run("GoalKick", { -3.9, 1.1 }, { 3.9, -1.1 })
@@ -14,14 +14,15 @@ spawn_robot(6)
 spawn_robot(7)
 
 local game_end_time = 10000
-local goal_scored = false
+
+expect_goal_within(game_end_time)
+expect_unique_roles(250)
 
 function on_goal()
     print("Goal scored, resetting ball!")
     print("Ball: " .. inspect(state.ball))
     print("Ball was at x: " .. state.ball.position[1] .. " y: " .. state.ball.position[2])
     state.ball = nil
-    goal_scored = true
     game_end_time = state.cycle_count + 200
 end
 
@@ -58,9 +59,6 @@ function on_cycle()
     end
 
     if state.cycle_count == game_end_time then
-        if not goal_scored then
-          error("No goal was scored!")
-        end
         state.finished = true
     end
 end
@@ -0,0 +1,66 @@
+function spawn_robot(number)
+    table.insert(state.robots, create_robot(number))
+end
+
+spawn_robot(1)
+spawn_robot(2)
+spawn_robot(3)
+spawn_robot(4)
+spawn_robot(5)
+spawn_robot(6)
+spawn_robot(7)
+
+state.network = {
+    packet_loss_probability = 0.3,
+    latency = { secs = 0, nanos = 50000000 },
+    jitter = { secs = 0, nanos = 100000000 },
+    partitions = { { "One", "Four" }, { "Two", "Five" } },
+}
+
+local game_end_time = 10000
+
+expect_goal_within(game_end_time)
+-- lost messages delay the role negotiation, duplicates have to be resolved nevertheless
+expect_unique_roles(500)
+
+function on_goal()
+    state.ball = nil
+    game_end_time = state.cycle_count + 200
+end
+
+function on_cycle()
+    if state.ball == nil and state.cycle_count % 1000 == 0 then
+        state.ball = {
+            position = { 0.0, 0.0 },
+            velocity = { 0.0, 0.0 },
+        }
+    end
+
+    if state.cycle_count == 100 then
+        state.game_controller_state.game_state = "Ready"
+        state.filtered_game_state = {
+            Ready = {
+                kicking_team = "Hulks",
+            }
+        }
+    end
+
+    if state.cycle_count == 1600 then
+        state.game_controller_state.game_state = "Set"
+        state.filtered_game_state = "Set"
+    end
+
+    if state.cycle_count == 1700 then
+        state.game_controller_state.game_state = "Playing"
+        state.filtered_game_state = {
+            Playing = {
+                ball_is_free = true,
+                kick_off = true
+            }
+        }
+    end
+
+    if state.cycle_count == game_end_time then
+        state.finished = true
+    end
+end
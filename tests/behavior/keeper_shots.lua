@@ -0,0 +1,95 @@
+function spawn_robot(number)
+  table.insert(state.robots, create_robot(number))
+end
+
+spawn_robot(1)
+spawn_robot(4)
+
+-- cycles after a shot in which the keeper has to react
+local reaction_cycles = 100
+
+local shots = {
+  -- straight at the keeper, blocked by squatting
+  [400] = { position = { -3.2, 0.0 }, velocity = { -3.0, 0.0 }, motion = "ArmsUpSquat" },
+  -- into the left corner, blocked by jumping to the left
+  [800] = { position = { -3.2, 0.0 }, velocity = { -3.0, 1.5 }, motion = "JumpLeft" },
+  -- slowly rolling, blocked by stepping
+  [1200] = { position = { -3.6, -0.3 }, velocity = { -1.8, 0.2 }, motion = "Walk" },
+  -- wide of the goal, ignored
+  [1600] = { position = { -3.2, 0.0 }, velocity = { -2.0, 2.0 }, motion = nil },
+}
+
+-- motions which only make sense to block a shot
+local blocking_motions = { ArmsUpSquat = true, JumpLeft = true, JumpRight = true }
+
+local current_shot = nil
+local observed_motions = {}
+
+function keeper_motion()
+  local command = motion_command(1)
+  if type(command) == "string" then
+    return command
+  end
+  if command.Jump ~= nil then
+    return "Jump" .. command.Jump.direction
+  end
+  local variant = next(command)
+  return variant
+end
+
+function check_reaction(shot)
+  if shot.motion ~= nil then
+    expect(
+      observed_motions[shot.motion] ~= nil,
+      "keeper reacts with " .. shot.motion .. " to the shot at cycle " .. shot.cycle
+    )
+  end
+  for motion, _ in pairs(observed_motions) do
+    expect(
+      motion == shot.motion or blocking_motions[motion] == nil,
+      "keeper does not react with " .. motion .. " to the shot at cycle " .. shot.cycle
+    )
+  end
+end
+
+function on_goal()
+  error("Goal conceded at cycle " .. state.cycle_count)
+end
+
+function on_cycle()
+  if state.cycle_count == 10 then
+    state.game_controller_state.game_state = "Playing"
+    state.filtered_game_state = {
+      Playing = {
+        ball_is_free = true,
+        kick_off = false,
+      },
+    }
+    state.ball = {
+      position = { -2.5, 0.0 },
+      velocity = { 0.0, 0.0 },
+    }
+  end
+
+  local shot = shots[state.cycle_count]
+  if shot ~= nil then
+    state.ball = {
+      position = { shot.position[1], shot.position[2] },
+      velocity = { shot.velocity[1], shot.velocity[2] },
+    }
+    current_shot = { cycle = state.cycle_count, motion = shot.motion }
+    observed_motions = {}
+  end
+
+  if current_shot ~= nil then
+    observed_motions[keeper_motion()] = true
+    if state.cycle_count == current_shot.cycle + reaction_cycles then
+      check_reaction(current_shot)
+      current_shot = nil
+    end
+  end
+
+  if state.cycle_count == 2000 then
+    state.finished = true
+  end
+end
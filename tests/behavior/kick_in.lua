@@ -0,0 +1,3 @@
+local set_play = require 'lib.set_play'
+
+set_play.run("KickIn", { 1.0, 3.0 }, { -1.0, -3.0 })
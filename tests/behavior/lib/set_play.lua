@@ -0,0 +1,89 @@
+-- Shared scenario for set plays: the Hulks have to play the ball during their own set play and
+-- must not touch it or come closer than the rule distance during the set play of the opponent
+
+local set_play = {}
+
+-- radius of the free kick rule obstacle around the ball
+local rule_distance = 0.75
+-- robots may graze the rule obstacle while walking around it
+local rule_distance_tolerance = 0.1
+
+local function distance(a, b)
+  return math.sqrt((a[1] - b[1]) ^ 2 + (a[2] - b[2]) ^ 2)
+end
+
+local function spawn_robot(number)
+  table.insert(state.robots, create_robot(number))
+end
+
+set_play.distance = distance
+set_play.spawn_robot = spawn_robot
+
+function set_play.run(sub_state, attacking_ball_position, defending_ball_position)
+  spawn_robot(1)
+  spawn_robot(2)
+  spawn_robot(3)
+  spawn_robot(4)
+  spawn_robot(5)
+
+  local function start_set_play(kicking_team, ball_position)
+    state.game_controller_state.game_state = "Playing"
+    state.game_controller_state.sub_state = sub_state
+    state.game_controller_state.kicking_team = kicking_team
+    state.filtered_game_state = {
+      Playing = {
+        ball_is_free = kicking_team == "Hulks",
+        kick_off = false,
+      },
+    }
+    state.ball = {
+      position = { ball_position[1], ball_position[2] },
+      velocity = { 0.0, 0.0 },
+    }
+  end
+
+  function on_cycle()
+    if state.cycle_count == 10 then
+      start_set_play("Hulks", attacking_ball_position)
+    end
+
+    if state.cycle_count == 2500 then
+      expect(
+        distance(state.ball.position, attacking_ball_position) >= 0.5,
+        "ball is played during own " .. sub_state
+      )
+      start_set_play("Opponent", defending_ball_position)
+    end
+
+    if state.cycle_count > 2500 and state.cycle_count < 3750 then
+      expect(
+        distance(state.ball.position, defending_ball_position) <= 0.05,
+        "ball is not touched during opponent " .. sub_state
+      )
+      for number = 1, 5 do
+        local position = robot_position(number)
+        local minimum_distance = rule_distance - rule_distance_tolerance
+        expect(
+          position == nil or distance(position, defending_ball_position) >= minimum_distance,
+          "robot " .. number .. " keeps the rule distance during opponent " .. sub_state
+        )
+      end
+    end
+
+    if state.cycle_count == 3750 then
+      state.game_controller_state.sub_state = nil
+      state.filtered_game_state = {
+        Playing = {
+          ball_is_free = true,
+          kick_off = false,
+        },
+      }
+    end
+
+    if state.cycle_count == 4000 then
+      state.finished = true
+    end
+  end
+end
+
+return set_play
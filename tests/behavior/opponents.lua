@@ -0,0 +1,72 @@
+function spawn_robot(number)
+  table.insert(state.robots, create_robot(number))
+end
+
+function spawn_opponent(position, policy)
+  table.insert(state.opponents, create_opponent(position, policy))
+  return #state.opponents
+end
+
+spawn_robot(1)
+spawn_robot(2)
+spawn_robot(3)
+spawn_robot(4)
+spawn_robot(5)
+
+local chaser = spawn_opponent({ 1.0, 0.5 }, "ChaseBall")
+local defender = spawn_opponent({ 4.0, 0.0 }, "DefendGoal")
+local walker = spawn_opponent({ 2.0, -2.0 }, "RandomWalk")
+local penalized = spawn_opponent({ 2.0, 2.0 }, "ChaseBall")
+state.opponents[penalized].is_penalized = true
+
+expect_unique_roles(250)
+
+function distance(a, b)
+  return math.sqrt((a[1] - b[1]) ^ 2 + (a[2] - b[2]) ^ 2)
+end
+
+function on_goal()
+  state.ball = {
+    position = { 0.0, 0.0 },
+    velocity = { 0.0, 0.0 },
+  }
+end
+
+function on_cycle()
+  if state.cycle_count == 10 then
+    state.ball = {
+      position = { 0.0, 0.0 },
+      velocity = { 0.0, 0.0 },
+    }
+    state.game_controller_state.game_state = "Playing"
+    state.filtered_game_state = {
+      Playing = {
+        ball_is_free = true,
+        kick_off = false,
+      },
+    }
+  end
+
+  if state.cycle_count == 1000 then
+    expect(
+      distance(state.opponents[chaser].position, { 1.0, 0.5 }) > 0.1,
+      "ball chasing opponent walks"
+    )
+    expect(
+      distance(state.opponents[walker].position, { 2.0, -2.0 }) > 0.1,
+      "randomly walking opponent walks"
+    )
+    expect(
+      distance(state.opponents[penalized].position, { 2.0, 2.0 }) < 0.01,
+      "penalized opponent does not move"
+    )
+  end
+
+  if state.cycle_count == 5000 then
+    expect(
+      distance(state.opponents[defender].position, { 4.5, 0.0 }) < 1.5,
+      "defending opponent stays in front of its goal"
+    )
+    state.finished = true
+  end
+end
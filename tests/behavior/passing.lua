@@ -0,0 +1,59 @@
+function spawn_robot(number)
+  local robot = create_robot(number)
+  -- the pass planner is disabled by default
+  robot.parameters.pass_planner.enable = true
+  table.insert(state.robots, robot)
+end
+
+spawn_robot(1)
+spawn_robot(4)
+spawn_robot(7)
+
+local pass_target = nil
+local initial_distance_to_target = nil
+
+function distance(a, b)
+  return math.sqrt((a[1] - b[1]) ^ 2 + (a[2] - b[2]) ^ 2)
+end
+
+function on_cycle()
+  if state.cycle_count == 10 then
+    set_robot_pose(7, { 0.7, -1.0 }, 0.0)
+    set_robot_pose(4, { 2.5, 1.5 }, -1.57)
+    create_obstacle(7, { 3.0, -1.0 }, 0.3)
+    -- block the goal so that passing is more promising than shooting
+    for _, y in ipairs({ -0.6, 0.0, 0.6 }) do
+      create_obstacle(7, { 4.0, y }, 0.4)
+    end
+    state.ball = {
+      position = { 1.0, -1.0 },
+      velocity = { 0.0, 0.0 },
+    }
+    state.filtered_game_state = {
+      Playing = {
+        ball_is_free = true,
+        kick_off = false,
+      },
+    }
+  end
+
+  if pass_target == nil then
+    for _, sender_and_message in ipairs(state.messages) do
+      local pass = sender_and_message[2].pass
+      if pass ~= nil then
+        pass_target = pass.target
+        initial_distance_to_target = distance(state.ball.position, pass_target)
+      end
+    end
+  end
+
+  if state.cycle_count == 1500 then
+    if pass_target == nil then
+      error("No pass was announced!")
+    end
+    if distance(state.ball.position, pass_target) > initial_distance_to_target - 0.5 then
+      error("Ball was not passed towards the announced target!")
+    end
+    state.finished = true
+  end
+end
@@ -0,0 +1,3 @@
+local set_play = require 'lib.set_play'
+
+set_play.run("PushingFreeKick", { 1.5, 0.5 }, { -1.5, -0.5 })
@@ -1,3 +1,9 @@
+use std::{
+    env::var,
+    fs::{read_dir, write},
+    path::Path,
+};
+
 use code_generation::{generate, write_to_file::WriteToFile};
 use color_eyre::eyre::{Result, WrapErr};
 use source_analyzer::{
@@ -22,6 +28,8 @@ fn main() -> Result<()> {
                     "control::game_controller_state_filter",
                     "control::kick_selector",
                     "control::motion::look_around",
+                    "control::motion::step_planner",
+                    "control::pass_planner",
                     "control::role_assignment",
                     "control::rule_obstacle_composer",
                     "control::time_to_reach_kick_position",
@@ -51,5 +59,35 @@ fn main() -> Result<()> {
     let structs = Structs::try_from_cyclers(&cyclers)?;
     generate(&cyclers, &structs)
         .write_to_file("generated_code.rs")
-        .wrap_err("failed to write generated code to file")
+        .wrap_err("failed to write generated code to file")?;
+
+    generate_scenario_tests("../../tests/behavior").wrap_err("failed to generate scenario tests")
+}
+
+/// Generates one test per scenario script, included by `tests/scenarios.rs`
+fn generate_scenario_tests(scenario_directory: &str) -> Result<()> {
+    println!("cargo:rerun-if-changed={scenario_directory}");
+    let mut scenario_paths = read_dir(scenario_directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    scenario_paths.retain(|path| path.extension().is_some_and(|extension| extension == "lua"));
+    scenario_paths.sort();
+
+    let tests: String = scenario_paths
+        .iter()
+        .map(|path| {
+            let name = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .replace(|character: char| !character.is_ascii_alphanumeric(), "_");
+            format!(
+                "#[test]\nfn test_{name}() -> Result<()> {{\n    test_scenario({path:?})\n}}\n\n"
+            )
+        })
+        .collect();
+
+    let out_dir = var("OUT_DIR")?;
+    write(Path::new(&out_dir).join("scenario_tests.rs"), tests)?;
+    Ok(())
 }
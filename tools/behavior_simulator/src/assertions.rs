@@ -0,0 +1,298 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use linear_algebra::{distance, Point2};
+use spl_network_messages::PlayerNumber;
+use types::roles::Role;
+
+use crate::state::State;
+
+/// Condition registered by a scenario script which is checked while the simulation runs
+pub struct Assertion {
+    description: String,
+    kind: AssertionKind,
+    failure: Option<Failure>,
+}
+
+enum AssertionKind {
+    GoalWithin {
+        deadline: usize,
+        is_scored: bool,
+    },
+    NeverInPenaltyArea {
+        player_number: PlayerNumber,
+    },
+    UniqueRoles {
+        tolerated_cycles: usize,
+        duplicated_since: Option<usize>,
+    },
+    BallUnattendedAtMost {
+        maximum_duration: Duration,
+        distance: f32,
+        unattended_since: Option<Duration>,
+    },
+    Check,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Failure {
+    pub cycle: usize,
+    pub message: String,
+}
+
+impl Assertion {
+    pub fn goal_within(cycles: usize, current_cycle: usize) -> Self {
+        Self {
+            description: format!("goal within {cycles} cycles after cycle {current_cycle}"),
+            kind: AssertionKind::GoalWithin {
+                deadline: current_cycle + cycles,
+                is_scored: false,
+            },
+            failure: None,
+        }
+    }
+
+    pub fn never_in_penalty_area(player_number: PlayerNumber) -> Self {
+        Self {
+            description: format!("player {player_number:?} never enters the own penalty area"),
+            kind: AssertionKind::NeverInPenaltyArea { player_number },
+            failure: None,
+        }
+    }
+
+    pub fn unique_roles(tolerated_cycles: usize) -> Self {
+        Self {
+            description: format!(
+                "roles are unique, duplicates are tolerated for {tolerated_cycles} cycles"
+            ),
+            kind: AssertionKind::UniqueRoles {
+                tolerated_cycles,
+                duplicated_since: None,
+            },
+            failure: None,
+        }
+    }
+
+    pub fn ball_unattended_at_most(maximum_duration: Duration, distance: f32) -> Self {
+        Self {
+            description: format!(
+                "ball is at most {:.2} seconds farther than {distance} m from all robots",
+                maximum_duration.as_secs_f32()
+            ),
+            kind: AssertionKind::BallUnattendedAtMost {
+                maximum_duration,
+                distance,
+                unattended_since: None,
+            },
+            failure: None,
+        }
+    }
+
+    /// Checks with the same description are merged, the first failing one is recorded
+    pub fn check(description: String) -> Self {
+        Self {
+            description,
+            kind: AssertionKind::Check,
+            failure: None,
+        }
+    }
+
+    pub fn is_check_of(&self, description: &str) -> bool {
+        matches!(self.kind, AssertionKind::Check) && self.description == description
+    }
+
+    pub fn record_check(&mut self, is_fulfilled: bool, current_cycle: usize) {
+        if !is_fulfilled && self.failure.is_none() {
+            self.failure = Some(Failure {
+                cycle: current_cycle,
+                message: "condition was false".to_string(),
+            });
+        }
+    }
+
+    pub fn on_goal(&mut self) {
+        if let AssertionKind::GoalWithin { is_scored, .. } = &mut self.kind {
+            *is_scored = true;
+        }
+    }
+
+    pub fn check_cycle(&mut self, state: &State) {
+        if self.failure.is_some() {
+            return;
+        }
+        let message = match &mut self.kind {
+            AssertionKind::GoalWithin {
+                deadline,
+                is_scored,
+            } => (!*is_scored && state.cycle_count > *deadline)
+                .then(|| format!("no goal was scored until cycle {deadline}")),
+            AssertionKind::NeverInPenaltyArea { player_number } => {
+                state.robots.get(player_number).and_then(|robot| {
+                    let field_dimensions = &robot.parameters.field_dimensions;
+                    let position = robot.database.main_outputs.ground_to_field? * Point2::origin();
+                    let is_inside_penalty_area = position.x()
+                        < -field_dimensions.length / 2.0 + field_dimensions.penalty_area_length
+                        && position.y().abs() < field_dimensions.penalty_area_width / 2.0;
+                    is_inside_penalty_area.then(|| {
+                        format!(
+                            "entered the own penalty area at ({:.2}, {:.2})",
+                            position.x(),
+                            position.y()
+                        )
+                    })
+                })
+            }
+            AssertionKind::UniqueRoles {
+                tolerated_cycles,
+                duplicated_since,
+            } => {
+                let mut players_by_role: Vec<(Role, Vec<PlayerNumber>)> = Vec::new();
+                for (player_number, robot) in &state.robots {
+                    let role = robot.database.main_outputs.role;
+                    if robot.is_penalized || matches!(role, Role::Loser | Role::Searcher) {
+                        continue;
+                    }
+                    match players_by_role
+                        .iter_mut()
+                        .find(|(existing_role, _)| *existing_role == role)
+                    {
+                        Some((_, player_numbers)) => player_numbers.push(*player_number),
+                        None => players_by_role.push((role, vec![*player_number])),
+                    }
+                }
+                let duplicate = players_by_role
+                    .into_iter()
+                    .find(|(_, player_numbers)| player_numbers.len() > 1);
+                match duplicate {
+                    Some((role, player_numbers)) => {
+                        let since = *duplicated_since.get_or_insert(state.cycle_count);
+                        (state.cycle_count - since > *tolerated_cycles).then(|| {
+                            format!("players {player_numbers:?} were {role:?} since cycle {since}")
+                        })
+                    }
+                    None => {
+                        *duplicated_since = None;
+                        None
+                    }
+                }
+            }
+            AssertionKind::BallUnattendedAtMost {
+                maximum_duration,
+                distance: maximum_distance,
+                unattended_since,
+            } => match &state.ball {
+                Some(ball) => {
+                    let is_attended = state.robots.values().any(|robot| {
+                        !robot.is_penalized
+                            && robot.database.main_outputs.ground_to_field.is_some_and(
+                                |ground_to_field| {
+                                    distance(ground_to_field * Point2::origin(), ball.position)
+                                        <= *maximum_distance
+                                },
+                            )
+                    });
+                    if is_attended {
+                        *unattended_since = None;
+                        None
+                    } else {
+                        let since = *unattended_since.get_or_insert(state.time_elapsed);
+                        (state.time_elapsed - since > *maximum_duration).then(|| {
+                            format!(
+                                "ball was unattended for {:.2} seconds",
+                                (state.time_elapsed - since).as_secs_f32()
+                            )
+                        })
+                    }
+                }
+                None => {
+                    *unattended_since = None;
+                    None
+                }
+            },
+            AssertionKind::Check => None,
+        };
+        self.failure = message.map(|message| Failure {
+            cycle: state.cycle_count,
+            message,
+        });
+    }
+
+    /// Fails assertions which could not be fulfilled before the simulation finished
+    pub fn finish(&mut self, state: &State) {
+        if self.failure.is_some() {
+            return;
+        }
+        if let AssertionKind::GoalWithin {
+            is_scored: false, ..
+        } = self.kind
+        {
+            self.failure = Some(Failure {
+                cycle: state.cycle_count,
+                message: "simulation finished before a goal was scored".to_string(),
+            });
+        }
+    }
+
+    pub fn result(&self) -> AssertionResult {
+        AssertionResult {
+            description: self.description.clone(),
+            failure: self.failure.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AssertionResult {
+    pub description: String,
+    pub failure: Option<Failure>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ScenarioReport {
+    pub scenario: String,
+    pub cycles: usize,
+    pub simulated_time: Duration,
+    pub results: Vec<AssertionResult>,
+}
+
+impl ScenarioReport {
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|result| result.failure.is_none())
+    }
+
+    pub fn number_of_failures(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| result.failure.is_some())
+            .count()
+    }
+}
+
+impl Display for ScenarioReport {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        writeln!(
+            formatter,
+            "Scenario {}: {}/{} assertions passed after {} cycles ({:.2} seconds simulated)",
+            self.scenario,
+            self.results.len() - self.number_of_failures(),
+            self.results.len(),
+            self.cycles,
+            self.simulated_time.as_secs_f32(),
+        )?;
+        for result in &self.results {
+            match &result.failure {
+                Some(failure) => writeln!(
+                    formatter,
+                    "  FAIL  {}: {} (cycle {})",
+                    result.description, failure.message, failure.cycle
+                )?,
+                None => writeln!(formatter, "  PASS  {}", result.description)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -6,7 +6,11 @@ use control::{
     ball_state_composer::{self, BallStateComposer},
     behavior::node::{self, Behavior},
     kick_selector::{self, KickSelector},
-    motion::look_around::LookAround,
+    motion::{
+        look_around::LookAround,
+        step_planner::{self, StepPlanner},
+    },
+    pass_planner::{self, PassPlanner},
     role_assignment::{self, RoleAssignment},
     rule_obstacle_composer::RuleObstacleComposer,
     time_to_reach_kick_position::{self, TimeToReachKickPosition},
@@ -41,8 +45,10 @@ pub struct BehaviorCycler {
     behavior: Behavior,
     kick_selector: KickSelector,
     look_around: LookAround,
+    pass_planner: PassPlanner,
     role_assignment: RoleAssignment,
     rule_obstacle_composer: RuleObstacleComposer,
+    step_planner: StepPlanner,
     world_state_composer: WorldStateComposer,
     time_to_reach_kick_position: TimeToReachKickPosition,
 }
@@ -70,12 +76,19 @@ impl BehaviorCycler {
             control::motion::look_around::CreationContext::new(),
         )
         .wrap_err("failed to create node `LookAround`")?;
+        let pass_planner = PassPlanner::new(pass_planner::CreationContext::new(
+            &parameters.pass_planner,
+            &parameters.spl_network.pose_message_send_interval,
+        ))
+        .wrap_err("failed to create node `PassPlanner`")?;
         let role_assignment = RoleAssignment::new(role_assignment::CreationContext::new())
             .wrap_err("failed to create node `RoleAssignment`")?;
         let rule_obstacle_composer = control::rule_obstacle_composer::RuleObstacleComposer::new(
             control::rule_obstacle_composer::CreationContext {},
         )
         .wrap_err("failed to create node `RuleObstacleComposer`")?;
+        let step_planner = StepPlanner::new(step_planner::CreationContext {})
+            .wrap_err("failed to create node `StepPlanner`")?;
         let world_state_composer =
             WorldStateComposer::new(world_state_composer::CreationContext::new())
                 .wrap_err("failed to create node `WorldStateComposer`")?;
@@ -89,8 +102,10 @@ impl BehaviorCycler {
             behavior,
             kick_selector,
             look_around,
+            pass_planner,
             role_assignment,
             rule_obstacle_composer,
+            step_planner,
             world_state_composer,
         })
     }
@@ -136,21 +151,28 @@ impl BehaviorCycler {
                         .main_outputs
                         .filtered_game_controller_state
                         .as_ref(),
+                    own_database.main_outputs.heard_whistle.as_ref(),
                     &own_database.main_outputs.primary_state,
                     own_database.main_outputs.ground_to_field.as_ref(),
+                    own_database.main_outputs.localization_confidence.as_ref(),
                     &own_database.main_outputs.cycle_time,
                     PerceptionInput {
-                        persistent: incoming_messages,
+                        persistent: incoming_messages.clone(),
                         temporary: Default::default(),
                     },
                     &mut cycler_state.time_to_reach_kick_position,
+                    &mut cycler_state.announced_pass,
                     &parameters.field_dimensions,
                     parameters.role_assignment.forced_role.as_ref(),
                     &parameters
                         .role_assignment
                         .keeper_replacementkeeper_switch_time,
+                    &parameters.role_assignment.mode,
+                    &parameters.role_assignment.cost_based,
                     &parameters.localization.initial_poses,
                     &parameters.behavior.optional_roles,
+                    &parameters.pass_planner.enable,
+                    &parameters.behavior.role_positions,
                     &parameters.player_number,
                     &parameters.spl_network,
                     &self.hardware_interface,
@@ -192,11 +214,40 @@ impl BehaviorCycler {
                     &own_database.main_outputs.obstacles,
                     &parameters.behavior.look_action,
                     own_database.main_outputs.ground_to_field.as_ref(),
+                    own_database.main_outputs.localization_confidence.as_ref(),
                 ))
                 .wrap_err("failed to execute cycle of node `ActiveVision`")?;
             own_database.main_outputs.position_of_interest =
                 main_outputs.position_of_interest.value;
         }
+        {
+            let main_outputs = self
+                .pass_planner
+                .cycle(pass_planner::CycleContext::new(
+                    own_database.main_outputs.ground_to_field.as_ref(),
+                    own_database.main_outputs.ball_state.as_ref(),
+                    &own_database.main_outputs.obstacles,
+                    &own_database.main_outputs.role,
+                    &own_database.main_outputs.primary_state,
+                    &own_database.main_outputs.cycle_time,
+                    PerceptionInput {
+                        persistent: incoming_messages,
+                        temporary: Default::default(),
+                    },
+                    &mut cycler_state.announced_pass,
+                    &parameters.field_dimensions,
+                    &parameters.pass_planner,
+                    &parameters.ball_filter.rolling_deceleration,
+                    &parameters.player_number,
+                    AdditionalOutput::new(
+                        true,
+                        &mut own_database.additional_outputs.pass_candidates,
+                    ),
+                ))
+                .wrap_err("failed to execute cycle of node `PassPlanner`")?;
+            own_database.main_outputs.pass_target = main_outputs.pass_target.value;
+            own_database.main_outputs.expected_pass = main_outputs.expected_pass.value;
+        }
         {
             if own_database.main_outputs.ground_to_field.as_ref().is_some()
                 && own_database.main_outputs.ball_state.as_ref().is_some()
@@ -207,6 +258,8 @@ impl BehaviorCycler {
                             own_database.main_outputs.ground_to_field.as_ref().unwrap(),
                             own_database.main_outputs.ball_state.as_ref().unwrap(),
                             &own_database.main_outputs.obstacles,
+                            own_database.main_outputs.localization_confidence.as_ref(),
+                            own_database.main_outputs.pass_target.as_ref(),
                             &parameters.field_dimensions,
                             &parameters.in_walk_kicks,
                             &parameters.kick_selector.angle_distance_weight,
@@ -220,6 +273,12 @@ impl BehaviorCycler {
                             &parameters.kick_selector.goal_accuracy_margin,
                             &parameters.kick_selector.default_kick_strength,
                             &parameters.kick_selector.corner_kick_strength,
+                            &parameters
+                                .kick_selector
+                                .minimum_localization_confidence_for_long_kicks,
+                            &parameters
+                                .kick_selector
+                                .maximum_kick_distance_when_uncertain,
                             framework::AdditionalOutput::new(
                                 true,
                                 &mut own_database.additional_outputs.kick_targets,
@@ -252,6 +311,7 @@ impl BehaviorCycler {
                     own_database.main_outputs.ground_to_field.as_ref(),
                     own_database.main_outputs.kick_decisions.as_ref(),
                     own_database.main_outputs.instant_kick_decisions.as_ref(),
+                    own_database.main_outputs.expected_pass.as_ref(),
                     &parameters.player_number,
                     &own_database.main_outputs.fall_state,
                     &own_database.main_outputs.has_ground_contact,
@@ -276,10 +336,20 @@ impl BehaviorCycler {
                         true,
                         &mut own_database.additional_outputs.dribble_path_obstacles,
                     ),
+                    AdditionalOutput::new(false, &mut own_database.additional_outputs.dribble_plan),
                     AdditionalOutput::new(true, &mut own_database.additional_outputs.active_action),
+                    AdditionalOutput::new(
+                        true,
+                        &mut own_database.additional_outputs.active_behavior_path,
+                    ),
+                    AdditionalOutput::new(
+                        false,
+                        &mut own_database.additional_outputs.positioning_heatmap,
+                    ),
                     &true,
                     &own_database.main_outputs.world_state,
                     &own_database.main_outputs.cycle_time,
+                    own_database.main_outputs.ball_trajectory.as_ref(),
                     &parameters.behavior,
                     &parameters.in_walk_kicks,
                     &parameters.field_dimensions,
@@ -308,6 +378,21 @@ impl BehaviorCycler {
             };
             own_database.main_outputs.look_around = main_outputs.look_around.value;
         }
+        {
+            let main_outputs = self
+                .step_planner
+                .cycle(step_planner::CycleContext::new(
+                    &own_database.main_outputs.motion_command,
+                    parameters.step_planner.injected_step.as_ref(),
+                    &parameters.step_planner.max_step_size,
+                    &parameters.step_planner.max_step_size_backwards,
+                    &parameters.step_planner.rotation_exponent,
+                    &parameters.step_planner.translation_exponent,
+                    &mut cycler_state.walk_return_offset,
+                ))
+                .wrap_err("failed to execute cycle of node `StepPlanner`")?;
+            own_database.main_outputs.step_plan = main_outputs.step_plan.value;
+        }
         {
             let _main_outputs = self
                 .time_to_reach_kick_position
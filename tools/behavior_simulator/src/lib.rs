@@ -1,7 +1,11 @@
 use hardware::{NetworkInterface, RecordingInterface, TimeInterface};
 
+pub mod assertions;
 pub mod cycler;
 pub mod interfake;
+pub mod opponent;
+pub mod physics;
+pub mod relay;
 pub mod robot;
 pub mod server;
 pub mod simulator;
@@ -2,17 +2,25 @@ use std::{io::stdout, path::PathBuf, time::Instant};
 
 use chrono::Local;
 use clap::Parser;
-use color_eyre::{eyre::Context, install, Result};
+use color_eyre::{
+    eyre::{bail, Context},
+    install, Result,
+};
 use fern::{Dispatch, InitError};
 use log::LevelFilter;
 use tokio_util::sync::CancellationToken;
 
-use behavior_simulator::{server, simulator::Simulator};
+use behavior_simulator::{
+    relay::{self, RelayConfiguration},
+    server,
+    simulator::Simulator,
+};
 
 #[derive(Parser)]
 enum Arguments {
     Run(RunArguments),
     Serve(ServeArguments),
+    Relay(RelayArguments),
 }
 
 #[derive(Parser)]
@@ -27,6 +35,11 @@ struct ServeArguments {
     scenario_file: PathBuf,
 }
 
+#[derive(Parser)]
+struct RelayArguments {
+    configuration_file: PathBuf,
+}
+
 fn setup_logger(is_verbose: bool) -> Result<(), InitError> {
     Dispatch::new()
         .format(|out, message, record| {
@@ -56,6 +69,7 @@ fn main() -> Result<()> {
     match arguments {
         Arguments::Run(arguments) => run(arguments),
         Arguments::Serve(arguments) => serve(arguments),
+        Arguments::Relay(arguments) => run_relay(arguments),
     }
 }
 
@@ -68,6 +82,12 @@ fn run(arguments: RunArguments) -> Result<()> {
     let duration = Instant::now() - start;
     println!("Took {:.2} seconds", duration.as_secs_f32());
 
+    let report = simulator.report();
+    print!("{report}");
+    if !report.is_success() {
+        bail!("{} assertions failed", report.number_of_failures());
+    }
+
     Ok(())
 }
 
@@ -87,3 +107,17 @@ fn serve(arguments: ServeArguments) -> Result<()> {
         arguments.scenario_file,
     )
 }
+
+fn run_relay(arguments: RelayArguments) -> Result<()> {
+    let configuration = RelayConfiguration::try_from_file(arguments.configuration_file)?;
+    let keep_running = CancellationToken::new();
+    {
+        let keep_running = keep_running.clone();
+        ctrlc::set_handler(move || {
+            println!("Cancelling...");
+            keep_running.cancel();
+        })?;
+    }
+
+    relay::run(configuration, keep_running)
+}
@@ -0,0 +1,232 @@
+use std::{f32::consts::PI, time::Duration};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use coordinate_systems::{Field, Ground};
+use geometry::{angle::normalize_angle, rectangle::Rectangle};
+use linear_algebra::{distance, point, Isometry2, Point2, Vector2};
+use serialize_hierarchy::SerializeHierarchy;
+use spl_network_messages::{Team, TeamColor};
+use types::{
+    detected_robots::DetectedRobot,
+    field_dimensions::FieldDimensions,
+    obstacles::{Obstacle, ObstacleKind},
+    step_plan::Step,
+};
+
+use crate::{physics, state::Ball};
+
+/// Distance behind the ball a ball chasing opponent walks to before kicking
+const BALL_APPROACH_DISTANCE: f32 = 0.2;
+/// Distance in front of their goal at which a defending opponent blocks the ball
+const DEFENDING_DISTANCE: f32 = 1.0;
+/// Maximum distance of the ball for an opponent to kick it
+const KICK_DISTANCE: f32 = 0.3;
+/// Maximum angle between the opponent's orientation and the ball for an opponent to kick it
+const KICK_ANGLE: f32 = 0.5;
+const KICK_SPEED: f32 = 2.0;
+const KICK_COOLDOWN: Duration = Duration::from_secs(1);
+/// Distance to the target below which opponents turn to the target orientation
+const TURN_TO_TARGET_DISTANCE: f32 = 0.5;
+/// Distance to the target below which a walk target counts as reached
+const TARGET_REACHED_DISTANCE: f32 = 0.1;
+const RADIUS_AT_FOOT_HEIGHT: f32 = 0.2;
+const RADIUS_AT_HIP_HEIGHT: f32 = 0.15;
+/// Distance up to which our robots detect opponents as obstacles
+pub const DETECTION_DISTANCE: f32 = 3.0;
+
+/// Behavior of an opponent, opponents attack the goal of our team
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+pub enum Policy {
+    /// Walks behind the ball and kicks it towards our goal
+    #[default]
+    ChaseBall,
+    /// Blocks the way from the ball to their goal and clears the ball
+    DefendGoal,
+    /// Walks to random positions on the field
+    RandomWalk,
+    /// Walks to the target set by the scenario script
+    WalkToTarget,
+}
+
+/// Robot of the opponent team which does not run our software but follows a simple policy
+#[derive(Clone, Deserialize, Serialize, SerializeHierarchy)]
+pub struct Opponent {
+    pub position: Point2<Field>,
+    pub orientation: f32,
+    pub velocity: Vector2<Field>,
+    pub policy: Policy,
+    pub target: Option<Point2<Field>>,
+    pub is_penalized: bool,
+    pub last_kick_time: Duration,
+}
+
+impl Opponent {
+    pub fn new(position: Point2<Field>, policy: Policy) -> Self {
+        Self {
+            position,
+            orientation: PI,
+            velocity: Vector2::zeros(),
+            policy,
+            target: None,
+            is_penalized: false,
+            last_kick_time: Duration::ZERO,
+        }
+    }
+
+    pub fn ground_to_field(&self) -> Isometry2<Ground, Field> {
+        Isometry2::new(self.position.coords(), self.orientation)
+    }
+
+    /// Walks towards the target pose of the policy with the step size of our robots
+    pub fn walk(
+        &mut self,
+        ball: Option<&Ball>,
+        field_dimensions: &FieldDimensions,
+        maximum_step_size: &Step,
+        time_step: Duration,
+        random_number_generator: &mut impl Rng,
+    ) {
+        let Some((target_position, target_orientation)) =
+            self.target_pose(ball, field_dimensions, random_number_generator)
+        else {
+            self.velocity = Vector2::zeros();
+            return;
+        };
+
+        let ground_to_field = self.ground_to_field();
+        let target = ground_to_field.inverse() * target_position;
+        let turn = if target.coords().norm() > TURN_TO_TARGET_DISTANCE {
+            target.y().atan2(target.x())
+        } else {
+            normalize_angle(target_orientation - self.orientation)
+        };
+        let step = Step {
+            forward: target
+                .x()
+                .clamp(-maximum_step_size.forward, maximum_step_size.forward),
+            left: target
+                .y()
+                .clamp(-maximum_step_size.left, maximum_step_size.left),
+            turn: turn.clamp(-maximum_step_size.turn, maximum_step_size.turn),
+        };
+
+        let ground_to_field = physics::walk(ground_to_field, step, time_step);
+        let position = ground_to_field * Point2::origin();
+        self.velocity = (position - self.position) * (1.0 / time_step.as_secs_f32());
+        self.position = position;
+        self.orientation = ground_to_field.orientation().angle();
+    }
+
+    /// Kicks the ball if it lies right in front of the opponent
+    pub fn kick(
+        &mut self,
+        ball: &mut Ball,
+        field_dimensions: &FieldDimensions,
+        time_elapsed: Duration,
+    ) {
+        let kick_direction = match self.policy {
+            Policy::ChaseBall => hulks_goal(field_dimensions) - ball.position,
+            Policy::DefendGoal => ball.position - opponent_goal(field_dimensions),
+            Policy::RandomWalk | Policy::WalkToTarget => return,
+        };
+        if time_elapsed.saturating_sub(self.last_kick_time) < KICK_COOLDOWN {
+            return;
+        }
+        let ball_in_ground = self.ground_to_field().inverse() * ball.position;
+        let angle_to_ball = ball_in_ground.y().atan2(ball_in_ground.x());
+        if ball_in_ground.coords().norm() > KICK_DISTANCE || angle_to_ball.abs() > KICK_ANGLE {
+            return;
+        }
+        if let Some(direction) = kick_direction.try_normalize(f32::EPSILON) {
+            ball.velocity = direction * KICK_SPEED;
+            self.last_kick_time = time_elapsed;
+        }
+    }
+
+    /// The opponent as it is perceived by one of our robots
+    pub fn to_obstacle(&self, ground_to_field: Isometry2<Ground, Field>) -> Obstacle {
+        Obstacle {
+            kind: ObstacleKind::Opponent,
+            position: ground_to_field.inverse() * self.position,
+            radius_at_foot_height: RADIUS_AT_FOOT_HEIGHT,
+            radius_at_hip_height: RADIUS_AT_HIP_HEIGHT,
+            velocity: ground_to_field.inverse() * self.velocity,
+        }
+    }
+
+    /// The opponent as it is detected by the robot detection of one of our robots
+    pub fn to_detected_robot(
+        &self,
+        ground_to_field: Isometry2<Ground, Field>,
+        jersey_color: TeamColor,
+    ) -> DetectedRobot {
+        DetectedRobot {
+            // simulated robots have no camera images to detect the opponent in
+            bounding_box: Rectangle {
+                min: Point2::origin(),
+                max: Point2::origin(),
+            },
+            confidence: 1.0,
+            position: ground_to_field.inverse() * self.position,
+            jersey_color: Some(jersey_color),
+            team: Team::Opponent,
+        }
+    }
+
+    fn target_pose(
+        &mut self,
+        ball: Option<&Ball>,
+        field_dimensions: &FieldDimensions,
+        random_number_generator: &mut impl Rng,
+    ) -> Option<(Point2<Field>, f32)> {
+        match self.policy {
+            Policy::ChaseBall => {
+                let ball = ball?;
+                let kick_direction =
+                    (hulks_goal(field_dimensions) - ball.position).try_normalize(f32::EPSILON)?;
+                Some((
+                    ball.position - kick_direction * BALL_APPROACH_DISTANCE,
+                    angle(kick_direction),
+                ))
+            }
+            Policy::DefendGoal => {
+                let goal = opponent_goal(field_dimensions);
+                let direction = (ball?.position - goal).try_normalize(f32::EPSILON)?;
+                Some((goal + direction * DEFENDING_DISTANCE, angle(direction)))
+            }
+            Policy::RandomWalk => {
+                let has_open_target = self.target.is_some_and(|target| {
+                    distance(target, self.position) >= TARGET_REACHED_DISTANCE
+                });
+                if !has_open_target {
+                    let half_length = field_dimensions.length / 2.0;
+                    let half_width = field_dimensions.width / 2.0;
+                    self.target = Some(point![
+                        random_number_generator.gen_range(-half_length..half_length),
+                        random_number_generator.gen_range(-half_width..half_width)
+                    ]);
+                }
+                Some((self.target?, self.orientation))
+            }
+            Policy::WalkToTarget => {
+                let target = self.target?;
+                (distance(target, self.position) >= TARGET_REACHED_DISTANCE)
+                    .then_some((target, self.orientation))
+            }
+        }
+    }
+}
+
+fn hulks_goal(field_dimensions: &FieldDimensions) -> Point2<Field> {
+    point![-field_dimensions.length / 2.0, 0.0]
+}
+
+fn opponent_goal(field_dimensions: &FieldDimensions) -> Point2<Field> {
+    point![field_dimensions.length / 2.0, 0.0]
+}
+
+fn angle(direction: Vector2<Field>) -> f32 {
+    direction.y().atan2(direction.x())
+}
@@ -0,0 +1,272 @@
+use std::time::Duration;
+
+use coordinate_systems::{Field, Ground};
+use linear_algebra::{distance, point, vector, Isometry2, Point2, Vector2};
+use types::{
+    field_dimensions::FieldDimensions,
+    motion_command::{JumpDirection, MotionCommand},
+    parameters::InWalkKickInfoParameters,
+    step_plan::Step,
+    support_foot::Side,
+};
+
+use crate::state::{Ball, Event};
+
+/// Duration of a single walking step, matches `walking_engine.base_step_duration`
+pub const STEP_DURATION: Duration = Duration::from_millis(260);
+/// Radius of the robot body used for collisions between robots
+pub const ROBOT_RADIUS: f32 = 0.15;
+/// Radius around the robot center in which the feet touch the ball
+pub const FOOT_RADIUS: f32 = 0.12;
+/// Half the width covered by the spread legs and arms of a squatting robot
+pub const SQUAT_REACH: f32 = 0.25;
+/// Lateral distance covered by the body of a robot lying on its side after a jump
+pub const JUMP_REACH: f32 = 0.8;
+/// Maximum distance of the ball to the position the kick is aligned to for the foot to hit it
+pub const KICK_RANGE: f32 = 0.15;
+/// Initial ball speed in m/s of an in-walk kick with strength 1
+pub const BALL_SPEED_PER_KICK_STRENGTH: f32 = 2.5;
+/// Fraction of the normal velocity the ball keeps when bouncing off robots, posts and boundaries
+pub const BALL_RESTITUTION: f32 = 0.5;
+/// Fraction of the normal velocity the ball keeps when it is caught in a goal net
+pub const NET_RESTITUTION: f32 = 0.1;
+
+/// Moves the robot by the part of the planned step which is walked within the time step
+pub fn walk(
+    ground_to_field: Isometry2<Ground, Field>,
+    step: Step,
+    time_step: Duration,
+) -> Isometry2<Ground, Field> {
+    let fraction = (time_step.as_secs_f32() / STEP_DURATION.as_secs_f32()).min(1.0);
+    let translation: Vector2<Ground> = vector![step.forward, step.left] * fraction;
+    Isometry2::new(
+        (ground_to_field * translation.as_point()).coords(),
+        ground_to_field.orientation().angle() + step.turn * fraction,
+    )
+}
+
+/// Pushes overlapping robots apart, both robots are moved by half of the overlap
+pub fn separate_robots(positions: &mut [Point2<Field>]) {
+    for first in 0..positions.len() {
+        for second in first + 1..positions.len() {
+            let offset = positions[second] - positions[first];
+            let overlap = 2.0 * ROBOT_RADIUS - offset.norm();
+            if overlap <= 0.0 {
+                continue;
+            }
+            let direction = offset
+                .try_normalize(f32::EPSILON)
+                .unwrap_or(Vector2::x_axis());
+            positions[first] -= direction * overlap / 2.0;
+            positions[second] += direction * overlap / 2.0;
+        }
+    }
+}
+
+/// Pushes a robot out of the goal posts it overlaps with
+pub fn separate_from_goal_posts(
+    position: Point2<Field>,
+    field_dimensions: &FieldDimensions,
+) -> Point2<Field> {
+    let minimum_distance = ROBOT_RADIUS + field_dimensions.goal_post_diameter / 2.0;
+    goal_posts(field_dimensions)
+        .into_iter()
+        .fold(position, |position, goal_post| {
+            let offset = position - goal_post;
+            if offset.norm() >= minimum_distance {
+                return position;
+            }
+            let direction = offset
+                .try_normalize(f32::EPSILON)
+                .unwrap_or(Vector2::x_axis());
+            goal_post + direction * minimum_distance
+        })
+}
+
+/// Clamps a position to the carpet, i.e. the field including the border strip
+pub fn clamp_to_carpet(
+    position: Point2<Field>,
+    field_dimensions: &FieldDimensions,
+) -> Point2<Field> {
+    let half_length = field_dimensions.length / 2.0 + field_dimensions.border_strip_width;
+    let half_width = field_dimensions.width / 2.0 + field_dimensions.border_strip_width;
+    point![
+        position.x().clamp(-half_length, half_length),
+        position.y().clamp(-half_width, half_width)
+    ]
+}
+
+/// Whether the ball is close to the position relative to the robot the kick is aligned to
+pub fn is_ball_in_kick_range(
+    ball: Point2<Ground>,
+    kick_info: &InWalkKickInfoParameters,
+    kicking_side: Side,
+) -> bool {
+    // the kick pose is given relative to the ball, its inverse is the ball relative to the robot
+    let (position, orientation) = kick_pose_relative_to_ball(kick_info, kicking_side);
+    let (sin, cos) = orientation.sin_cos();
+    let (x, y) = (-position.x, -position.y);
+    let expected_ball = point![cos * x + sin * y, -sin * x + cos * y];
+    distance(ball, expected_ball) <= KICK_RANGE
+}
+
+/// Direction the ball is kicked to, i.e. towards the target the kick pose is aligned to
+pub fn kick_direction(kick_info: &InWalkKickInfoParameters, kicking_side: Side) -> Vector2<Ground> {
+    let (_, orientation) = kick_pose_relative_to_ball(kick_info, kicking_side);
+    vector![orientation.cos(), -orientation.sin()]
+}
+
+/// Kick pose in the frame of the ball aligned to the kick target, mirrored for the right foot
+fn kick_pose_relative_to_ball(
+    kick_info: &InWalkKickInfoParameters,
+    kicking_side: Side,
+) -> (nalgebra::Point2<f32>, f32) {
+    match kicking_side {
+        Side::Left => (kick_info.position, kick_info.orientation),
+        Side::Right => (
+            nalgebra::point![kick_info.position.x, -kick_info.position.y],
+            -kick_info.orientation,
+        ),
+    }
+}
+
+/// Decelerates the ball by rolling friction and moves it
+pub fn roll_ball(ball: &mut Ball, rolling_deceleration: f32, time_step: Duration) {
+    let time_step = time_step.as_secs_f32();
+    let speed = (ball.velocity.norm() - rolling_deceleration * time_step).max(0.0);
+    ball.velocity = ball
+        .velocity
+        .try_normalize(f32::EPSILON)
+        .map_or(Vector2::zeros(), |direction| direction * speed);
+    ball.position += ball.velocity * time_step;
+}
+
+/// Circles in ground coordinates with which the robot blocks the ball during the motion
+pub fn blocking_circles(motion_command: &MotionCommand) -> Vec<(Point2<Ground>, f32)> {
+    match motion_command {
+        MotionCommand::ArmsUpSquat => vec![(Point2::origin(), SQUAT_REACH)],
+        MotionCommand::Jump { direction } => {
+            let side = match direction {
+                JumpDirection::Left => 1.0,
+                JumpDirection::Right => -1.0,
+            };
+            // the lying body is covered by overlapping circles from the feet to the head
+            let radius = ROBOT_RADIUS;
+            let number_of_circles = (JUMP_REACH / radius).ceil() as usize;
+            (0..number_of_circles)
+                .map(|index| {
+                    let lateral_offset = radius
+                        + index as f32 * (JUMP_REACH - 2.0 * radius)
+                            / (number_of_circles - 1) as f32;
+                    (point![0.0, side * lateral_offset], radius)
+                })
+                .collect()
+        }
+        _ => vec![(Point2::origin(), FOOT_RADIUS)],
+    }
+}
+
+/// Pushes the ball out of a circular body and reflects its velocity at the contact normal
+pub fn collide_ball_with_circle(
+    ball: &mut Ball,
+    center: Point2<Field>,
+    radius: f32,
+    ball_radius: f32,
+) {
+    let offset = ball.position - center;
+    let minimum_distance = radius + ball_radius;
+    if offset.norm() >= minimum_distance {
+        return;
+    }
+    let normal = offset
+        .try_normalize(f32::EPSILON)
+        .unwrap_or(Vector2::x_axis());
+    ball.position = center + normal * minimum_distance;
+    let normal_velocity = ball.velocity.dot(normal);
+    if normal_velocity < 0.0 {
+        ball.velocity -= normal * (1.0 + BALL_RESTITUTION) * normal_velocity;
+    }
+}
+
+/// Keeps the ball on the carpet by bouncing it off the carpet edges
+pub fn collide_ball_with_boundary(ball: &mut Ball, field_dimensions: &FieldDimensions) {
+    let half_length = field_dimensions.length / 2.0 + field_dimensions.border_strip_width
+        - field_dimensions.ball_radius;
+    let half_width = field_dimensions.width / 2.0 + field_dimensions.border_strip_width
+        - field_dimensions.ball_radius;
+    let (x, velocity_x) = bounce(
+        ball.position.x(),
+        ball.velocity.x(),
+        half_length,
+        BALL_RESTITUTION,
+    );
+    let (y, velocity_y) = bounce(
+        ball.position.y(),
+        ball.velocity.y(),
+        half_width,
+        BALL_RESTITUTION,
+    );
+    ball.position = point![x, y];
+    ball.velocity = vector![velocity_x, velocity_y];
+}
+
+/// Catches a ball behind the goal line between the posts in the net
+pub fn collide_ball_with_goal_nets(ball: &mut Ball, field_dimensions: &FieldDimensions) {
+    let goal_line_x = field_dimensions.length / 2.0;
+    let is_inside_goal = ball.position.x().abs() > goal_line_x
+        && ball.position.y().abs() < field_dimensions.goal_inner_width / 2.0;
+    if !is_inside_goal {
+        return;
+    }
+    let (x, velocity_x) = bounce(
+        ball.position.x(),
+        ball.velocity.x(),
+        goal_line_x + field_dimensions.goal_depth - field_dimensions.ball_radius,
+        NET_RESTITUTION,
+    );
+    let (y, velocity_y) = bounce(
+        ball.position.y(),
+        ball.velocity.y(),
+        field_dimensions.goal_inner_width / 2.0 - field_dimensions.ball_radius,
+        NET_RESTITUTION,
+    );
+    ball.position = point![x, y];
+    ball.velocity = vector![velocity_x, velocity_y];
+}
+
+fn bounce(position: f32, velocity: f32, limit: f32, restitution: f32) -> (f32, f32) {
+    if position.abs() <= limit {
+        return (position, velocity);
+    }
+    let velocity = if position * velocity > 0.0 {
+        -restitution * velocity
+    } else {
+        velocity
+    };
+    (position.clamp(-limit, limit), velocity)
+}
+
+pub fn goal_posts(field_dimensions: &FieldDimensions) -> [Point2<Field>; 4] {
+    let x = field_dimensions.length / 2.0;
+    let y = field_dimensions.goal_inner_width / 2.0 + field_dimensions.goal_post_diameter / 2.0;
+    [point![x, y], point![x, -y], point![-x, y], point![-x, -y]]
+}
+
+/// Detects the ball completely crossing a goal line between the posts or any other field line
+pub fn ball_event(
+    previous_position: Point2<Field>,
+    position: Point2<Field>,
+    field_dimensions: &FieldDimensions,
+) -> Option<Event> {
+    let margin = field_dimensions.line_width / 2.0 + field_dimensions.ball_radius;
+    let is_out = |position: Point2<Field>| {
+        position.x().abs() > field_dimensions.length / 2.0 + margin
+            || position.y().abs() > field_dimensions.width / 2.0 + margin
+    };
+    if is_out(previous_position) || !is_out(position) {
+        return None;
+    }
+    let is_goal = position.x().abs() > field_dimensions.length / 2.0 + margin
+        && position.y().abs() < field_dimensions.goal_inner_width / 2.0;
+    Some(if is_goal { Event::Goal } else { Event::BallOut })
+}
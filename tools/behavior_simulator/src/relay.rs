@@ -0,0 +1,87 @@
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    net::SocketAddr,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use color_eyre::{eyre::WrapErr, Result};
+use log::warn;
+use serde::Deserialize;
+use spl_network::{
+    endpoint::SplMessageCodec,
+    network_emulator::{NetworkEmulator, NetworkEmulatorParameters},
+};
+use spl_network_messages::PlayerNumber;
+use tokio::{net::UdpSocket, select, time::interval};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Deserialize)]
+pub struct RelayConfiguration {
+    /// Address the robots send their SPL messages to (`spl_network_ports.spl_relay`)
+    pub listen_address: SocketAddr,
+    /// Address each robot receives SPL messages on (`spl_network_ports.spl`)
+    pub robots: HashMap<PlayerNumber, SocketAddr>,
+    pub network: NetworkEmulatorParameters,
+    pub seed: u64,
+    /// Has to match `spl_network_codec` of the robots
+    #[serde(default)]
+    pub codec: SplMessageCodec,
+}
+
+impl RelayConfiguration {
+    pub fn try_from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let configuration = read_to_string(&path).wrap_err("failed to read relay configuration")?;
+        serde_json::from_str(&configuration).wrap_err("failed to parse relay configuration")
+    }
+}
+
+/// Forwards SPL messages between robots (e.g. webots controllers) through a network emulator
+pub fn run(configuration: RelayConfiguration, keep_running: CancellationToken) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(relay(configuration, keep_running))
+}
+
+async fn relay(configuration: RelayConfiguration, keep_running: CancellationToken) -> Result<()> {
+    let socket = UdpSocket::bind(configuration.listen_address)
+        .await
+        .wrap_err("failed to bind relay socket")?;
+    let mut network = NetworkEmulator::new(configuration.network, configuration.seed);
+    let mut delivery_interval = interval(Duration::from_millis(1));
+    let mut buffer = [0; 1024];
+
+    loop {
+        select! {
+            result = socket.recv_from(&mut buffer) => {
+                let (received_bytes, address) = result.wrap_err("failed to receive from relay socket")?;
+                let payload = &buffer[0..received_bytes];
+                match configuration.codec.decode(payload) {
+                    Ok(message) => network.send(
+                        SystemTime::now(),
+                        message.player_number,
+                        configuration.robots.keys().copied(),
+                        received_bytes,
+                        payload.to_vec(),
+                    ),
+                    Err(error) => {
+                        warn!("Failed to parse SPL message from {address} (will be discarded): {error:?}");
+                    }
+                }
+            }
+            _ = delivery_interval.tick() => {
+                let now = SystemTime::now();
+                for (&player_number, address) in &configuration.robots {
+                    for payload in network.receive(now, player_number) {
+                        if let Err(error) = socket.send_to(&payload, address).await {
+                            warn!("Failed to relay SPL message to {address}: {error:?}");
+                        }
+                    }
+                }
+            }
+            _ = keep_running.cancelled() => {
+                break Ok(());
+            }
+        }
+    }
+}
@@ -6,6 +6,7 @@ use std::{
 
 use crate::{
     cycler::Database,
+    opponent::Opponent,
     robot::to_player_number,
     simulator::{Frame, Simulator},
     state::Ball,
@@ -30,6 +31,7 @@ struct MainOutputs {
     frame_count: usize,
     ball: Option<Ball>,
     databases: Players<Option<Database>>,
+    opponents: Vec<Opponent>,
 }
 
 #[derive(Clone, Default, Serialize, Deserialize, SerializeHierarchy)]
@@ -70,6 +72,7 @@ async fn timeline_server(
             let frame = &frames[parameters.selected_frame];
             outputs.main_outputs.ball = frame.ball.clone();
             outputs.main_outputs.databases = frame.robots.clone();
+            outputs.main_outputs.opponents = frame.opponents.clone();
         }
         outputs_changed.notify_waiters();
 
@@ -4,7 +4,7 @@ use color_eyre::{
     eyre::{eyre, WrapErr},
     Result,
 };
-use mlua::{Error as LuaError, Function, Lua, LuaSerdeExt, SerializeOptions, Value};
+use mlua::{Error as LuaError, Function, Lua, LuaSerdeExt, SerializeOptions, Table, Value};
 use parking_lot::Mutex;
 
 use coordinate_systems::Field;
@@ -12,7 +12,9 @@ use linear_algebra::{Isometry2, Point2, Vector2};
 use types::{obstacles::Obstacle, players::Players};
 
 use crate::{
+    assertions::{Assertion, ScenarioReport},
     cycler::Database,
+    opponent::{Opponent, Policy},
     robot::to_player_number,
     robot::Robot,
     state::Ball,
@@ -24,11 +26,14 @@ const SERIALIZE_OPTIONS: SerializeOptions = SerializeOptions::new().serialize_no
 pub struct Frame {
     pub ball: Option<Ball>,
     pub robots: Players<Option<Database>>,
+    pub opponents: Vec<Opponent>,
 }
 
 pub struct Simulator {
     pub state: Arc<Mutex<State>>,
     pub frames: Vec<Frame>,
+    assertions: Arc<Mutex<Vec<Assertion>>>,
+    scenario: String,
     lua: Lua,
 }
 
@@ -47,6 +52,16 @@ impl Simulator {
         lua.globals()
             .set("create_robot", create_robot)
             .wrap_err("failed to insert create_robot")?;
+        let create_opponent = lua
+            .create_function(|lua, (position, policy): (Value, Value)| {
+                let position: Point2<Field> = lua.from_value(position)?;
+                let policy: Policy = lua.from_value(policy)?;
+                lua.to_value_with(&Opponent::new(position, policy), SERIALIZE_OPTIONS)
+            })
+            .wrap_err("failed to create function create_opponent")?;
+        lua.globals()
+            .set("create_opponent", create_opponent)
+            .wrap_err("failed to insert create_opponent")?;
         let error = lua
             .create_function(|_lua, message: String| -> Result<(), LuaError> {
                 Err(LuaError::external(message))
@@ -56,15 +71,36 @@ impl Simulator {
             .set("error", error)
             .wrap_err("failed to insert create_robot")?;
 
+        let assertions = Arc::new(Mutex::new(Vec::new()));
+        register_assertion_functions(&lua, &state, &assertions)
+            .wrap_err("failed to register assertion functions")?;
+
         Ok(Self {
             state,
             lua,
             frames: Vec::new(),
+            assertions,
+            scenario: String::new(),
         })
     }
 
     pub fn execute_script(&mut self, file_name: impl AsRef<Path>) -> Result<()> {
         self.serialze_state()?;
+        self.scenario = file_name
+            .as_ref()
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        // scenarios may require shared modules relative to their own directory
+        if let Some(directory) = file_name.as_ref().parent() {
+            let package: Table = self.lua.globals().get("package")?;
+            let path: String = package.get("path")?;
+            package.set(
+                "path",
+                format!("{};{path}", directory.join("?.lua").display()),
+            )?;
+        }
 
         let script_text = read_to_string(&file_name)?;
         let script = self.lua.load(&script_text).set_name(
@@ -94,9 +130,13 @@ impl Simulator {
             self.frames.push(Frame {
                 robots,
                 ball: state.ball.clone(),
+                opponents: state.opponents.clone(),
             });
 
             if state.finished {
+                for assertion in self.assertions.lock().iter_mut() {
+                    assertion.finish(&state);
+                }
                 break;
             }
         }
@@ -104,6 +144,21 @@ impl Simulator {
         Ok(())
     }
 
+    pub fn report(&self) -> ScenarioReport {
+        let state = self.state.lock();
+        ScenarioReport {
+            scenario: self.scenario.clone(),
+            cycles: state.cycle_count,
+            simulated_time: state.time_elapsed,
+            results: self
+                .assertions
+                .lock()
+                .iter()
+                .map(Assertion::result)
+                .collect(),
+        }
+    }
+
     pub fn cycle(&mut self) -> Result<()> {
         let events = {
             let mut state = self.state.lock();
@@ -204,6 +259,43 @@ impl Simulator {
                 )?,
             )?;
 
+            self.lua.globals().set(
+                "motion_command",
+                scope.create_function(|lua, player_number: usize| {
+                    let player_number =
+                        to_player_number(player_number).map_err(LuaError::external)?;
+                    let state = self.state.lock();
+                    let motion_command = &state
+                        .robots
+                        .get(&player_number)
+                        .unwrap()
+                        .database
+                        .main_outputs
+                        .motion_command;
+
+                    lua.to_value_with(motion_command, SERIALIZE_OPTIONS)
+                })?,
+            )?;
+
+            self.lua.globals().set(
+                "robot_position",
+                scope.create_function(|lua, player_number: usize| {
+                    let player_number =
+                        to_player_number(player_number).map_err(LuaError::external)?;
+                    let state = self.state.lock();
+                    let position: Option<Point2<Field>> = state
+                        .robots
+                        .get(&player_number)
+                        .unwrap()
+                        .database
+                        .main_outputs
+                        .ground_to_field
+                        .map(|ground_to_field| ground_to_field * Point2::origin());
+
+                    lua.to_value_with(&position, SERIALIZE_OPTIONS)
+                })?,
+            )?;
+
             self.lua.globals().set(
                 "clear_obstacles",
                 scope.create_function(|_, player_number: usize| {
@@ -227,14 +319,26 @@ impl Simulator {
             for event in events {
                 match event {
                     Event::Cycle => self.execute_event_callback("on_cycle")?,
-                    Event::Goal => self.execute_event_callback("on_goal")?,
+                    Event::Goal => {
+                        for assertion in self.assertions.lock().iter_mut() {
+                            assertion.on_goal();
+                        }
+                        self.execute_event_callback("on_goal")?
+                    }
+                    Event::BallOut => self.execute_event_callback("on_ball_out")?,
                 }
             }
 
             Ok(())
         })?;
 
-        self.deserialize_state()
+        self.deserialize_state()?;
+
+        let state = self.state.lock();
+        for assertion in self.assertions.lock().iter_mut() {
+            assertion.check_cycle(&state);
+        }
+        Ok(())
     }
 
     fn execute_event_callback(&self, name: &str) -> Result<(), LuaError> {
@@ -273,3 +377,87 @@ impl Simulator {
             .wrap_err("failed to load lua state")
     }
 }
+
+fn register_assertion_functions(
+    lua: &Lua,
+    state: &Arc<Mutex<State>>,
+    assertions: &Arc<Mutex<Vec<Assertion>>>,
+) -> Result<(), LuaError> {
+    let expect_goal_within = {
+        let state = state.clone();
+        let assertions = assertions.clone();
+        lua.create_function(move |_lua, cycles: usize| {
+            let current_cycle = state.lock().cycle_count;
+            assertions
+                .lock()
+                .push(Assertion::goal_within(cycles, current_cycle));
+            Ok(())
+        })?
+    };
+    lua.globals()
+        .set("expect_goal_within", expect_goal_within)?;
+
+    let expect_never_in_penalty_area = {
+        let assertions = assertions.clone();
+        lua.create_function(move |_lua, player_number: usize| {
+            let player_number = to_player_number(player_number).map_err(LuaError::external)?;
+            assertions
+                .lock()
+                .push(Assertion::never_in_penalty_area(player_number));
+            Ok(())
+        })?
+    };
+    lua.globals()
+        .set("expect_never_in_penalty_area", expect_never_in_penalty_area)?;
+
+    let expect_unique_roles = {
+        let assertions = assertions.clone();
+        lua.create_function(move |_lua, tolerated_cycles: Option<usize>| {
+            assertions
+                .lock()
+                .push(Assertion::unique_roles(tolerated_cycles.unwrap_or(0)));
+            Ok(())
+        })?
+    };
+    lua.globals()
+        .set("expect_unique_roles", expect_unique_roles)?;
+
+    let expect_ball_unattended_at_most = {
+        let assertions = assertions.clone();
+        lua.create_function(move |_lua, (seconds, distance): (f32, f32)| {
+            let duration = Duration::try_from_secs_f32(seconds).map_err(LuaError::external)?;
+            assertions
+                .lock()
+                .push(Assertion::ball_unattended_at_most(duration, distance));
+            Ok(())
+        })?
+    };
+    lua.globals().set(
+        "expect_ball_unattended_at_most",
+        expect_ball_unattended_at_most,
+    )?;
+
+    let expect = {
+        let state = state.clone();
+        let assertions = assertions.clone();
+        lua.create_function(move |_lua, (is_fulfilled, description): (bool, String)| {
+            let current_cycle = state.lock().cycle_count;
+            let mut assertions = assertions.lock();
+            let index = match assertions
+                .iter()
+                .position(|assertion| assertion.is_check_of(&description))
+            {
+                Some(index) => index,
+                None => {
+                    assertions.push(Assertion::check(description));
+                    assertions.len() - 1
+                }
+            };
+            assertions[index].record_check(is_fulfilled, current_cycle);
+            Ok(())
+        })?
+    };
+    lua.globals().set("expect", expect)?;
+
+    Ok(())
+}
@@ -1,42 +1,44 @@
 use std::{
     collections::{BTreeMap, HashMap},
-    f32::consts::FRAC_PI_4,
     mem::take,
     time::{Duration, UNIX_EPOCH},
 };
 
-use color_eyre::Result;
+use color_eyre::{eyre::WrapErr, Result};
+use rand::{rngs::StdRng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
 use coordinate_systems::{Field, Head};
-use geometry::line_segment::LineSegment;
-use linear_algebra::{vector, Isometry2, Orientation2, Point2, UnitComplex, Vector2};
+use linear_algebra::{distance, Isometry2, Point2, UnitComplex, Vector2};
 use serialize_hierarchy::SerializeHierarchy;
-use spl_network_messages::{GamePhase, GameState, HulkMessage, PlayerNumber, Team};
+use spl_network::network_emulator::{NetworkEmulator, NetworkEmulatorParameters};
+use spl_network_messages::{GamePhase, GameState, HulkMessage, PlayerNumber, Team, TeamColor};
 use types::{
     ball_position::BallPosition,
+    ball_trajectory::BallTrajectory,
     filtered_game_controller_state::FilteredGameControllerState,
     filtered_game_state::FilteredGameState,
-    game_controller_state::GameControllerState,
+    game_controller_state::{GameControllerState, JerseyColors},
     messages::{IncomingMessage, OutgoingMessage},
-    motion_command::KickVariant,
+    motion_command::HeadMotion,
     motion_command::MotionCommand,
-    motion_command::{HeadMotion, OrientationMode},
-    planned_path::PathSegment,
+    obstacles::ObstacleKind,
     players::Players,
     primary_state::PrimaryState,
-    support_foot::Side,
 };
 
 use crate::{
     cycler::Database,
-    robot::Robot,
+    opponent::{self, Opponent},
+    physics,
+    robot::{from_player_number, Robot},
     structs::{control::AdditionalOutputs, Parameters},
 };
 
 pub enum Event {
     Cycle,
     Goal,
+    BallOut,
 }
 
 #[derive(Default, Clone, Deserialize, Serialize, SerializeHierarchy)]
@@ -49,11 +51,14 @@ pub struct State {
     pub time_elapsed: Duration,
     pub cycle_count: usize,
     pub robots: HashMap<PlayerNumber, Robot>,
+    pub opponents: Vec<Opponent>,
     pub ball: Option<Ball>,
     pub messages: Vec<(PlayerNumber, HulkMessage)>,
+    pub network: NetworkEmulator<HulkMessage>,
     pub finished: bool,
     pub game_controller_state: GameControllerState,
     pub filtered_game_state: FilteredGameState,
+    random_number_generator: StdRng,
 }
 
 impl State {
@@ -63,6 +68,8 @@ impl State {
         let mut events = vec![Event::Cycle];
 
         self.move_robots(time_step);
+        self.move_opponents(time_step);
+        self.separate_robots();
         self.cycle_robots(now)?;
         events.extend(self.move_ball(time_step));
 
@@ -83,40 +90,13 @@ impl State {
 
             robot.database.additional_outputs = AdditionalOutputs::default();
             let head_motion = match &robot.database.main_outputs.motion_command {
-                MotionCommand::Walk {
-                    head,
-                    path,
-                    orientation_mode,
-                    ..
-                } => {
-                    let step = match path[0] {
-                        PathSegment::LineSegment(LineSegment(_start, end)) => end.coords(),
-                        PathSegment::Arc(arc, direction) => {
-                            direction.rotate_vector_90_degrees(arc.start - arc.circle.center)
-                        }
-                    }
-                    .cap_magnitude(0.3 * time_step.as_secs_f32());
-
-                    let orientation = match orientation_mode {
-                        OrientationMode::AlignWithPath => {
-                            if step.norm_squared() < f32::EPSILON {
-                                Orientation2::identity()
-                            } else {
-                                Orientation2::from_vector(step)
-                            }
-                        }
-                        OrientationMode::Override(orientation) => *orientation,
-                    };
-
+                MotionCommand::Walk { head, .. } => {
                     let previous_ground_to_field = *ground_to_field;
 
-                    *ground_to_field = Isometry2::new(
-                        (*ground_to_field * step.as_point()).coords(),
-                        ground_to_field.orientation().angle()
-                            + orientation.angle().clamp(
-                                -FRAC_PI_4 * time_step.as_secs_f32(),
-                                FRAC_PI_4 * time_step.as_secs_f32(),
-                            ),
+                    *ground_to_field = physics::walk(
+                        *ground_to_field,
+                        robot.database.main_outputs.step_plan,
+                        time_step,
                     );
 
                     for obstacle in robot.database.main_outputs.obstacles.iter_mut() {
@@ -134,20 +114,19 @@ impl State {
                     strength,
                 } => {
                     if let Some(ball) = self.ball.as_mut() {
-                        let side = match kicking_side {
-                            Side::Left => 1.0,
-                            Side::Right => -1.0,
-                        };
-
-                        // TODO: Check if ball is even in range
-                        // let kick_location = ground_to_field * ();
-                        if (self.time_elapsed - robot.last_kick_time).as_secs_f32() > 1.0 {
-                            let direction = match kick {
-                                KickVariant::Forward => vector![1.0, 0.0],
-                                KickVariant::Turn => vector![0.707, 0.707 * side],
-                                KickVariant::Side => vector![0.0, 1.0 * -side],
-                            };
-                            ball.velocity += *ground_to_field * direction * *strength * 2.5;
+                        let kick_info = &robot.parameters.in_walk_kicks[*kick];
+                        let is_in_range = physics::is_ball_in_kick_range(
+                            ground_to_field.inverse() * ball.position,
+                            kick_info,
+                            *kicking_side,
+                        );
+                        if is_in_range
+                            && (self.time_elapsed - robot.last_kick_time).as_secs_f32() > 1.0
+                        {
+                            ball.velocity += *ground_to_field
+                                * physics::kick_direction(kick_info, *kicking_side)
+                                * *strength
+                                * physics::BALL_SPEED_PER_KICK_STRENGTH;
                             robot.last_kick_time = self.time_elapsed;
                         };
                     }
@@ -183,15 +162,117 @@ impl State {
         }
     }
 
+    fn move_opponents(&mut self, time_step: Duration) {
+        // all robots share the field and walking parameters
+        let Some(parameters) = self.robots.values().next().map(|robot| &robot.parameters) else {
+            return;
+        };
+        let is_playing = matches!(self.filtered_game_state, FilteredGameState::Playing { .. });
+        // opponents must not touch the ball during our set plays
+        let may_play_ball = self.game_controller_state.sub_state.is_none()
+            || self.game_controller_state.kicking_team == Team::Opponent;
+
+        for opponent in self
+            .opponents
+            .iter_mut()
+            .filter(|opponent| !opponent.is_penalized)
+        {
+            if !is_playing {
+                opponent.velocity = Vector2::zeros();
+                continue;
+            }
+            opponent.walk(
+                self.ball.as_ref(),
+                &parameters.field_dimensions,
+                &parameters.step_planner.max_step_size,
+                time_step,
+                &mut self.random_number_generator,
+            );
+            if let Some(ball) = self.ball.as_mut().filter(|_| may_play_ball) {
+                opponent.kick(ball, &parameters.field_dimensions, self.time_elapsed);
+            }
+        }
+    }
+
+    fn separate_robots(&mut self) {
+        // all robots share the field parameters
+        let Some(field_dimensions) = self
+            .robots
+            .values()
+            .next()
+            .map(|robot| robot.parameters.field_dimensions.clone())
+        else {
+            return;
+        };
+        let mut robots: Vec<_> = self
+            .robots
+            .values_mut()
+            .filter(|robot| !robot.is_penalized)
+            .collect();
+        robots.sort_by_key(|robot| from_player_number(robot.parameters.player_number));
+        let mut opponents: Vec<_> = self
+            .opponents
+            .iter_mut()
+            .filter(|opponent| !opponent.is_penalized)
+            .collect();
+
+        let mut positions: Vec<_> = robots
+            .iter()
+            .map(|robot| {
+                robot
+                    .database
+                    .main_outputs
+                    .ground_to_field
+                    .expect("simulated robots should always have a known pose")
+                    * Point2::origin()
+            })
+            .chain(opponents.iter().map(|opponent| opponent.position))
+            .collect();
+        physics::separate_robots(&mut positions);
+        let positions: Vec<_> = positions
+            .into_iter()
+            .map(|position| {
+                let position = physics::separate_from_goal_posts(position, &field_dimensions);
+                physics::clamp_to_carpet(position, &field_dimensions)
+            })
+            .collect();
+        let (robot_positions, opponent_positions) = positions.split_at(robots.len());
+
+        for (robot, position) in robots.iter_mut().zip(robot_positions) {
+            let ground_to_field = robot
+                .database
+                .main_outputs
+                .ground_to_field
+                .as_mut()
+                .expect("simulated robots should always have a known pose");
+            *ground_to_field =
+                Isometry2::new(position.coords(), ground_to_field.orientation().angle());
+        }
+        for (opponent, position) in opponents.iter_mut().zip(opponent_positions) {
+            opponent.position = *position;
+        }
+    }
+
     fn cycle_robots(&mut self, now: std::time::SystemTime) -> Result<()> {
-        let incoming_messages = take(&mut self.messages);
+        let player_numbers: Vec<_> = self.robots.keys().copied().collect();
+        for (sender, message) in take(&mut self.messages) {
+            let size = bincode::serialized_size(&message)
+                .wrap_err("failed to determine size of SPL message")?;
+            self.network.send(
+                now,
+                sender,
+                player_numbers.iter().copied(),
+                size as usize,
+                message,
+            );
+        }
 
         for (player_number, robot) in self.robots.iter_mut() {
-            let incoming_messages: Vec<_> = incoming_messages
-                .iter()
-                .filter_map(|(sender, message)| {
-                    (sender != player_number).then_some(IncomingMessage::Spl(*message))
-                })
+            let incoming_messages: Vec<_> = self
+                .network
+                .receive(now, *player_number)
+                .into_iter()
+                .map(IncomingMessage::Spl)
                 .collect();
             let messages_with_time =
                 BTreeMap::from_iter([(now, incoming_messages.iter().collect())]);
@@ -229,6 +310,15 @@ impl State {
                 } else {
                     None
                 };
+            robot.database.main_outputs.ball_trajectory = robot
+                .database
+                .main_outputs
+                .ball_position
+                .map(|ball| BallTrajectory {
+                    position: ball.position,
+                    velocity: ball.velocity,
+                    deceleration: robot.parameters.ball_filter.rolling_deceleration,
+                });
             robot.database.main_outputs.primary_state =
                 match (robot.is_penalized, self.filtered_game_state) {
                     (true, _) => PrimaryState::Penalized,
@@ -241,10 +331,47 @@ impl State {
             robot.database.main_outputs.filtered_game_controller_state =
                 Some(FilteredGameControllerState {
                     game_state: self.filtered_game_state,
+                    game_phase: self.game_controller_state.game_phase,
+                    kicking_team: self.game_controller_state.kicking_team,
+                    penalties: self.game_controller_state.penalties,
+                    remaining_number_of_messages: self
+                        .game_controller_state
+                        .remaining_amount_of_messages,
+                    sub_state: self.game_controller_state.sub_state,
+                    own_team_is_home_after_coin_toss: self
+                        .game_controller_state
+                        .hulks_team_is_home_after_coin_toss,
                     ..Default::default()
                 });
             robot.database.main_outputs.game_controller_state = Some(self.game_controller_state);
 
+            let detected_opponents: Vec<_> = self
+                .opponents
+                .iter()
+                .filter(|opponent| {
+                    !opponent.is_penalized
+                        && distance(opponent.position, ground_to_field * Point2::origin())
+                            < opponent::DETECTION_DISTANCE
+                })
+                .collect();
+            let obstacles = &mut robot.database.main_outputs.obstacles;
+            obstacles.retain(|obstacle| obstacle.kind != ObstacleKind::Opponent);
+            obstacles.extend(
+                detected_opponents
+                    .iter()
+                    .map(|opponent| opponent.to_obstacle(ground_to_field)),
+            );
+            let jersey_color = self
+                .game_controller_state
+                .opponent_jersey_colors
+                .field_player;
+            robot.database.main_outputs.detected_robots = Some(
+                detected_opponents
+                    .iter()
+                    .map(|opponent| opponent.to_detected_robot(ground_to_field, jersey_color))
+                    .collect(),
+            );
+
             robot.cycle(messages_with_time)?;
 
             for message in robot.interface.take_outgoing_messages() {
@@ -260,13 +387,57 @@ impl State {
 
     fn move_ball(&mut self, time_step: Duration) -> Vec<Event> {
         let mut events = Vec::new();
+        // all robots share the field and ball parameters
+        let Some(parameters) = self.robots.values().next().map(|robot| &robot.parameters) else {
+            return events;
+        };
+        let field_dimensions = &parameters.field_dimensions;
         if let Some(ball) = self.ball.as_mut() {
-            ball.position += ball.velocity * time_step.as_secs_f32();
-            ball.velocity *= 0.98;
-
-            if ball.position.x().abs() > 4.5 && ball.position.y() < 0.75 {
-                events.push(Event::Goal);
+            let previous_position = ball.position;
+            physics::roll_ball(ball, parameters.ball_filter.rolling_deceleration, time_step);
+
+            for robot in self.robots.values().filter(|robot| !robot.is_penalized) {
+                if let Some(ground_to_field) = robot.database.main_outputs.ground_to_field {
+                    for (center, radius) in
+                        physics::blocking_circles(&robot.database.main_outputs.motion_command)
+                    {
+                        physics::collide_ball_with_circle(
+                            ball,
+                            ground_to_field * center,
+                            radius,
+                            field_dimensions.ball_radius,
+                        );
+                    }
+                }
             }
+            for opponent in self
+                .opponents
+                .iter()
+                .filter(|opponent| !opponent.is_penalized)
+            {
+                physics::collide_ball_with_circle(
+                    ball,
+                    opponent.position,
+                    physics::FOOT_RADIUS,
+                    field_dimensions.ball_radius,
+                );
+            }
+            for goal_post in physics::goal_posts(field_dimensions) {
+                physics::collide_ball_with_circle(
+                    ball,
+                    goal_post,
+                    field_dimensions.goal_post_diameter / 2.0,
+                    field_dimensions.ball_radius,
+                );
+            }
+            physics::collide_ball_with_goal_nets(ball, field_dimensions);
+            physics::collide_ball_with_boundary(ball, field_dimensions);
+
+            events.extend(physics::ball_event(
+                previous_position,
+                ball.position,
+                field_dimensions,
+            ));
         }
         events
     }
@@ -278,8 +449,10 @@ impl State {
             // TODO: Expose robot data to lua again
             // robots: self.robots.iter().map(LuaRobot::new).collect(),
             robots: Default::default(),
+            opponents: self.opponents.clone(),
             ball: self.ball.clone(),
             messages: self.messages.clone(),
+            network: self.network.parameters.clone(),
 
             finished: self.finished,
 
@@ -290,7 +463,9 @@ impl State {
 
     pub fn load_lua_state(&mut self, lua_state: LuaState) -> Result<()> {
         self.ball = lua_state.ball;
+        self.opponents = lua_state.opponents;
         self.cycle_count = lua_state.cycle_count;
+        self.network.parameters = lua_state.network;
         for lua_robot in lua_state.robots {
             let mut robot = Robot::try_new(lua_robot.parameters.player_number)
                 .expect("Creating dummy robot should never fail");
@@ -329,6 +504,14 @@ impl Default for State {
             remaining_amount_of_messages: 1200,
             sub_state: None,
             hulks_team_is_home_after_coin_toss: false,
+            hulks_jersey_colors: JerseyColors {
+                field_player: TeamColor::Blue,
+                goal_keeper: TeamColor::Yellow,
+            },
+            opponent_jersey_colors: JerseyColors {
+                field_player: TeamColor::Red,
+                goal_keeper: TeamColor::Black,
+            },
         };
 
         Self {
@@ -337,9 +520,12 @@ impl Default for State {
             robots,
             ball: None,
             messages: Vec::new(),
+            network: NetworkEmulator::new(Default::default(), 0),
             finished: false,
             game_controller_state,
             filtered_game_state: FilteredGameState::Initial,
+            opponents: Vec::new(),
+            random_number_generator: StdRng::seed_from_u64(0),
         }
     }
 }
@@ -349,8 +535,10 @@ pub struct LuaState {
     pub time_elapsed: f32,
     pub cycle_count: usize,
     pub robots: Vec<LuaRobot>,
+    pub opponents: Vec<Opponent>,
     pub ball: Option<Ball>,
     pub messages: Vec<(PlayerNumber, HulkMessage)>,
+    pub network: NetworkEmulatorParameters,
     pub finished: bool,
     pub game_controller_state: GameControllerState,
     pub filtered_game_state: FilteredGameState,
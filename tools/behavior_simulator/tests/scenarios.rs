@@ -1,6 +1,9 @@
 use std::{path::Path, time::Instant};
 
-use color_eyre::{eyre::Context, Result};
+use color_eyre::{
+    eyre::{bail, Context},
+    Result,
+};
 
 use behavior_simulator::simulator::Simulator;
 
@@ -13,15 +16,19 @@ fn test_scenario(path: impl AsRef<Path>) -> Result<()> {
     let duration = Instant::now() - start;
     eprintln!("Took {:.2} seconds", duration.as_secs_f32());
 
-    Ok(())
-}
+    let report = simulator.report();
+    eprint!("{report}");
+    if !report.is_success() {
+        bail!(
+            "{} of {} assertions failed in scenario {}",
+            report.number_of_failures(),
+            report.results.len(),
+            report.scenario
+        );
+    }
 
-#[test]
-fn test_golden_goal() -> Result<()> {
-    test_scenario("../../tests/behavior/golden_goal.lua")
+    Ok(())
 }
 
-#[test]
-fn test_demonstration() -> Result<()> {
-    test_scenario("../../tests/behavior/demonstration.lua")
-}
+// one test per script in `tests/behavior/`, generated by the build script
+include!(concat!(env!("OUT_DIR"), "/scenario_tests.rs"));
@@ -0,0 +1,488 @@
+use std::{collections::BTreeMap, time::SystemTime};
+
+use color_eyre::{eyre::WrapErr, Result};
+use nalgebra::{Matrix3, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+
+use control::{
+    localization::{self, Localization},
+    localization_recorder::RecordedCycleContext,
+};
+use coordinate_systems::{Field, Ground};
+use framework::{AdditionalOutput, PerceptionInput};
+use linear_algebra::{distance, Isometry2};
+use serialize_hierarchy::SerializeHierarchy;
+use spl_network_messages::PlayerNumber;
+use types::{
+    cycle_time::CycleTime,
+    field_dimensions::FieldDimensions,
+    initial_pose::InitialPose,
+    line_data::LineData,
+    localization::LocalizationBackend,
+    parameters::{
+        LocalizationConfidenceParameters, ParticleFilterParameters,
+        SymmetryDisambiguationParameters,
+    },
+    players::Players,
+    primary_state::PrimaryState,
+};
+
+/// Parameters of the localization node, the backend is chosen by the replay
+#[derive(Clone, Debug, Deserialize, Serialize, SerializeHierarchy)]
+pub struct LocalizationParameters {
+    pub circle_measurement_noise: Vector2<f32>,
+    pub confidence: LocalizationConfidenceParameters,
+    pub good_matching_threshold: f32,
+    pub gradient_convergence_threshold: f32,
+    pub gradient_descent_step_size: f32,
+    pub hypothesis_prediction_score_reduction_factor: f32,
+    pub hypothesis_retain_factor: f32,
+    pub hypothesis_score_base_increase: f32,
+    pub initial_hypothesis_covariance: Matrix3<f32>,
+    pub initial_hypothesis_score: f32,
+    pub initial_poses: Players<InitialPose>,
+    pub landmark_measurement_noise: Vector2<f32>,
+    pub line_length_acceptance_factor: f32,
+    pub line_measurement_noise: Vector2<f32>,
+    pub maximum_amount_of_gradient_descent_iterations: usize,
+    pub maximum_amount_of_outer_iterations: usize,
+    pub maximum_landmark_association_distance: f32,
+    pub minimum_fit_error: f32,
+    pub odometry_noise: Vector3<f32>,
+    pub particle_filter: ParticleFilterParameters,
+    pub score_per_good_match: f32,
+    pub symmetry_disambiguation: SymmetryDisambiguationParameters,
+    pub use_landmark_measurements: bool,
+    pub use_line_measurements: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ComparedFrame {
+    pub recorded_ground_to_field: Option<Isometry2<Ground, Field>>,
+    pub multiple_hypotheses_ground_to_field: Option<Isometry2<Ground, Field>>,
+    pub particle_filter_ground_to_field: Option<Isometry2<Ground, Field>>,
+}
+
+impl ComparedFrame {
+    /// Translational and rotational difference between both replayed backends
+    pub fn deviation(&self) -> Option<(f32, f32)> {
+        Some(deviation(
+            self.multiple_hypotheses_ground_to_field?,
+            self.particle_filter_ground_to_field?,
+        ))
+    }
+}
+
+pub fn deviation(left: Isometry2<Ground, Field>, right: Isometry2<Ground, Field>) -> (f32, f32) {
+    let left = left.as_pose();
+    let right = right.as_pose();
+    let angle_difference = (right.orientation().angle() - left.orientation().angle()).sin_cos();
+    (
+        distance(left.position(), right.position()),
+        angle_difference.0.atan2(angle_difference.1).abs(),
+    )
+}
+
+/// Replays the same recording through both localization backends
+pub fn compare_backends(
+    frames: &[RecordedCycleContext],
+    field_dimensions: &FieldDimensions,
+    player_number: PlayerNumber,
+    parameters: &LocalizationParameters,
+) -> Result<Vec<ComparedFrame>> {
+    let multiple_hypotheses = replay(
+        frames,
+        LocalizationBackend::MultipleHypotheses,
+        field_dimensions,
+        player_number,
+        parameters,
+    )
+    .wrap_err("failed to replay multiple hypotheses localization")?;
+    let particle_filter = replay(
+        frames,
+        LocalizationBackend::ParticleFilter,
+        field_dimensions,
+        player_number,
+        parameters,
+    )
+    .wrap_err("failed to replay particle filter localization")?;
+    Ok(frames
+        .iter()
+        .zip(multiple_hypotheses)
+        .zip(particle_filter)
+        .map(
+            |((frame, multiple_hypotheses_ground_to_field), particle_filter_ground_to_field)| {
+                ComparedFrame {
+                    recorded_ground_to_field: frame.ground_to_field,
+                    multiple_hypotheses_ground_to_field,
+                    particle_filter_ground_to_field,
+                }
+            },
+        )
+        .collect())
+}
+
+/// Replays a recording through the localization node, starting at the first recorded pose while
+/// localizing
+pub fn replay(
+    frames: &[RecordedCycleContext],
+    backend: LocalizationBackend,
+    field_dimensions: &FieldDimensions,
+    player_number: PlayerNumber,
+    parameters: &LocalizationParameters,
+) -> Result<Vec<Option<Isometry2<Ground, Field>>>> {
+    let mut localization = Localization::new(localization::CreationContext::new(field_dimensions))?;
+    let mut ground_to_field = None;
+    frames
+        .iter()
+        .enumerate()
+        .map(|(index, frame)| {
+            if ground_to_field.is_none() {
+                let is_localizing = matches!(
+                    frame.primary_state,
+                    PrimaryState::Ready | PrimaryState::Set | PrimaryState::Playing
+                );
+                let Some(recorded_ground_to_field) =
+                    frame.ground_to_field.filter(|_| is_localizing)
+                else {
+                    return Ok(None);
+                };
+                localization.reset_to(
+                    frame.primary_state,
+                    recorded_ground_to_field,
+                    parameters.initial_hypothesis_covariance,
+                    parameters.initial_hypothesis_score,
+                );
+                ground_to_field = Some(recorded_ground_to_field);
+            }
+            cycle(
+                &mut localization,
+                frame,
+                backend,
+                field_dimensions,
+                player_number,
+                parameters,
+                ground_to_field.as_mut().unwrap(),
+            )
+            .wrap_err_with(|| format!("failed to replay frame {index}"))
+        })
+        .collect()
+}
+
+fn cycle(
+    localization: &mut Localization,
+    frame: &RecordedCycleContext,
+    backend: LocalizationBackend,
+    field_dimensions: &FieldDimensions,
+    player_number: PlayerNumber,
+    parameters: &LocalizationParameters,
+    ground_to_field: &mut Isometry2<Ground, Field>,
+) -> Result<Option<Isometry2<Ground, Field>>> {
+    let current_odometry_to_last_odometry: BTreeMap<_, _> = frame
+        .current_odometry_to_last_odometry
+        .iter()
+        .map(|(&timestamp, odometry)| (timestamp, odometry.as_ref()))
+        .collect();
+    let cycle_time = CycleTime {
+        start_time: frame.cycle_start_time,
+        last_cycle_duration: Default::default(),
+    };
+    let mut correspondence_lines = None;
+    let mut fit_errors = None;
+    let mut measured_landmarks_in_field = None;
+    let mut measured_lines_in_field = None;
+    let mut particles = None;
+    let mut pose_hypotheses = None;
+    let mut symmetry_events = None;
+    let mut updates = None;
+
+    let main_outputs = localization.cycle(localization::CycleContext::new(
+        AdditionalOutput::new(false, &mut correspondence_lines),
+        AdditionalOutput::new(false, &mut fit_errors),
+        AdditionalOutput::new(false, &mut measured_landmarks_in_field),
+        AdditionalOutput::new(false, &mut measured_lines_in_field),
+        AdditionalOutput::new(false, &mut particles),
+        AdditionalOutput::new(false, &mut pose_hypotheses),
+        AdditionalOutput::new(false, &mut symmetry_events),
+        AdditionalOutput::new(false, &mut updates),
+        current_odometry_to_last_odometry.into(),
+        None,
+        &cycle_time,
+        frame.filtered_game_controller_state.as_ref(),
+        &frame.has_ground_contact,
+        &frame.primary_state,
+        &backend,
+        &parameters.circle_measurement_noise,
+        &parameters.confidence,
+        field_dimensions,
+        &parameters.good_matching_threshold,
+        &parameters.gradient_convergence_threshold,
+        &parameters.gradient_descent_step_size,
+        &parameters.hypothesis_prediction_score_reduction_factor,
+        &parameters.hypothesis_retain_factor,
+        &parameters.hypothesis_score_base_increase,
+        &parameters.initial_hypothesis_covariance,
+        &parameters.initial_hypothesis_score,
+        &parameters.initial_poses,
+        &parameters.landmark_measurement_noise,
+        &parameters.line_length_acceptance_factor,
+        &parameters.line_measurement_noise,
+        &parameters.maximum_amount_of_gradient_descent_iterations,
+        &parameters.maximum_amount_of_outer_iterations,
+        &parameters.maximum_landmark_association_distance,
+        &parameters.minimum_fit_error,
+        &parameters.odometry_noise,
+        &parameters.particle_filter,
+        &player_number,
+        &parameters.score_per_good_match,
+        &parameters.symmetry_disambiguation,
+        &parameters.use_landmark_measurements,
+        &parameters.use_line_measurements,
+        None,
+        // goal posts and penalty marks are not recorded
+        empty_perception_input(),
+        empty_perception_input(),
+        line_data_perception_input(
+            &frame.line_data_bottom_persistent,
+            &frame.line_data_bottom_temporary,
+        ),
+        line_data_perception_input(
+            &frame.line_data_top_persistent,
+            &frame.line_data_top_temporary,
+        ),
+        empty_perception_input(),
+        empty_perception_input(),
+        empty_perception_input(),
+        ground_to_field,
+    ))?;
+    Ok(main_outputs.ground_to_field.value)
+}
+
+fn line_data_perception_input<'a>(
+    persistent: &'a BTreeMap<SystemTime, Vec<Option<LineData>>>,
+    temporary: &'a BTreeMap<SystemTime, Vec<Option<LineData>>>,
+) -> PerceptionInput<Vec<Option<&'a LineData>>> {
+    PerceptionInput {
+        persistent: borrow_line_data(persistent),
+        temporary: borrow_line_data(temporary),
+    }
+}
+
+fn borrow_line_data(
+    line_data: &BTreeMap<SystemTime, Vec<Option<LineData>>>,
+) -> BTreeMap<SystemTime, Vec<Option<&LineData>>> {
+    line_data
+        .iter()
+        .map(|(&timestamp, line_data)| (timestamp, line_data.iter().map(Option::as_ref).collect()))
+        .collect()
+}
+
+fn empty_perception_input<DataType>() -> PerceptionInput<DataType> {
+    PerceptionInput {
+        persistent: BTreeMap::new(),
+        temporary: BTreeMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::FRAC_PI_2, time::Duration};
+
+    use linear_algebra::{point, IntoTransform, Point2};
+    use nalgebra::vector;
+    use types::{
+        field_marks::{field_marks_from_field_dimensions, FieldMark},
+        line::{Line, Line2},
+    };
+
+    use super::*;
+
+    fn parameters() -> LocalizationParameters {
+        LocalizationParameters {
+            circle_measurement_noise: vector![1000.0, 1000.0],
+            confidence: Default::default(),
+            good_matching_threshold: 0.5,
+            gradient_convergence_threshold: 0.01,
+            gradient_descent_step_size: 0.01,
+            hypothesis_prediction_score_reduction_factor: 0.9,
+            hypothesis_retain_factor: 0.05,
+            hypothesis_score_base_increase: 0.1,
+            initial_hypothesis_covariance: Matrix3::from_diagonal_element(0.001),
+            initial_hypothesis_score: 10.0,
+            initial_poses: Default::default(),
+            landmark_measurement_noise: vector![0.5, 0.5],
+            line_length_acceptance_factor: 1.5,
+            line_measurement_noise: vector![1000.0, 320.0],
+            maximum_amount_of_gradient_descent_iterations: 20,
+            maximum_amount_of_outer_iterations: 10,
+            maximum_landmark_association_distance: 1.0,
+            minimum_fit_error: 0.001,
+            odometry_noise: vector![0.05, 0.01, 0.008],
+            particle_filter: ParticleFilterParameters {
+                number_of_particles: 200,
+                process_noise: vector![0.01, 0.01, 0.01],
+                odometry_noise: vector![0.1, 0.1, 0.1],
+                line_measurement_standard_deviation: 0.15,
+                maximum_line_point_distance: 0.5,
+                landmark_measurement_standard_deviation: 0.2,
+                maximum_landmark_distance: 0.7,
+                slow_averaging_factor: 0.02,
+                fast_averaging_factor: 0.3,
+                maximum_sensor_reset_fraction: 0.2,
+                minimum_effective_sample_size_fraction: 0.5,
+                cluster_radius: 0.5,
+                cluster_angle: 0.5,
+            },
+            score_per_good_match: 1.0,
+            symmetry_disambiguation: Default::default(),
+            use_landmark_measurements: false,
+            use_line_measurements: true,
+        }
+    }
+
+    /// Parts of the field mark lines within three meters around the robot
+    fn observed_lines(
+        ground_to_field: Isometry2<Ground, Field>,
+        field_marks: &[FieldMark],
+    ) -> Vec<Line2<Ground>> {
+        let field_to_ground = ground_to_field.inverse();
+        let robot_position = ground_to_field * Point2::origin();
+        field_marks
+            .iter()
+            .filter_map(|field_mark| match field_mark {
+                FieldMark::Line { line, .. } => Some(*line),
+                FieldMark::Circle { .. } | FieldMark::Intersection { .. } => None,
+            })
+            .filter_map(|line| {
+                let visible_points: Vec<_> = (0..=100)
+                    .map(|index| line.0 + (line.1 - line.0) * (index as f32 / 100.0))
+                    .filter(|&point| distance(point, robot_position) < 3.0)
+                    .collect();
+                let first = *visible_points.first()?;
+                let last = *visible_points.last()?;
+                (distance(first, last) > 0.3)
+                    .then(|| Line(field_to_ground * first, field_to_ground * last))
+            })
+            .collect()
+    }
+
+    /// Frames of a robot standing at the given true poses while the recorded pose stays fixed
+    fn recording(
+        true_poses: &[Isometry2<Ground, Field>],
+        recorded_ground_to_field: Isometry2<Ground, Field>,
+        field_marks: &[FieldMark],
+    ) -> Vec<RecordedCycleContext> {
+        true_poses
+            .iter()
+            .enumerate()
+            .map(|(index, &true_ground_to_field)| {
+                let timestamp = SystemTime::UNIX_EPOCH + Duration::from_millis(index as u64 * 12);
+                let line_data = LineData {
+                    lines: observed_lines(true_ground_to_field, field_marks),
+                    used_segments: Default::default(),
+                    intersections: Vec::new(),
+                };
+                RecordedCycleContext {
+                    cycle_start_time: timestamp,
+                    current_odometry_to_last_odometry: BTreeMap::from([(
+                        timestamp,
+                        Some(nalgebra::Isometry2::identity()),
+                    )]),
+                    current_cycle_odometry_to_last_odometry: Some(nalgebra::Isometry2::identity()),
+                    filtered_game_controller_state: None,
+                    has_ground_contact: true,
+                    primary_state: PrimaryState::Playing,
+                    ground_to_field: Some(recorded_ground_to_field),
+                    line_data_bottom_persistent: BTreeMap::from([(timestamp, vec![None])]),
+                    line_data_bottom_temporary: BTreeMap::new(),
+                    line_data_top_persistent: BTreeMap::from([(timestamp, vec![Some(line_data)])]),
+                    line_data_top_temporary: BTreeMap::new(),
+                }
+            })
+            .collect()
+    }
+
+    fn ground_to_field(position: Point2<Field>, angle: f32) -> Isometry2<Ground, Field> {
+        nalgebra::Isometry2::new(position.inner.coords, angle).framed_transform()
+    }
+
+    #[test]
+    fn backends_agree_with_consistent_recording() {
+        let field_marks = field_marks_from_field_dimensions(&FieldDimensions::SPL);
+        let pose = ground_to_field(point![-3.0, 1.0], 0.3);
+        let frames = recording(&[pose; 30], pose, &field_marks);
+
+        let compared_frames = compare_backends(
+            &frames,
+            &FieldDimensions::SPL,
+            PlayerNumber::Seven,
+            &parameters(),
+        )
+        .unwrap();
+
+        let last_frame = compared_frames.last().unwrap();
+        let (translation_deviation, rotation_deviation) = last_frame.deviation().unwrap();
+        assert!(translation_deviation < 0.3);
+        assert!(rotation_deviation < 0.2);
+        let (translation_deviation, rotation_deviation) = deviation(
+            pose,
+            last_frame.multiple_hypotheses_ground_to_field.unwrap(),
+        );
+        assert!(translation_deviation < 0.3);
+        assert!(rotation_deviation < 0.2);
+    }
+
+    #[test]
+    fn particle_filter_recovers_from_kidnapping_in_replay() {
+        let field_marks = field_marks_from_field_dimensions(&FieldDimensions::SPL);
+        let pose_before_kidnapping = ground_to_field(point![-3.0, 1.0], 0.3);
+        let pose_after_kidnapping = ground_to_field(point![-1.5, -2.0], FRAC_PI_2);
+        let true_poses: Vec<_> = [pose_before_kidnapping; 20]
+            .into_iter()
+            .chain([pose_after_kidnapping; 60])
+            .collect();
+        let frames = recording(&true_poses, pose_before_kidnapping, &field_marks);
+
+        let compared_frames = compare_backends(
+            &frames,
+            &FieldDimensions::SPL,
+            PlayerNumber::Seven,
+            &parameters(),
+        )
+        .unwrap();
+
+        let last_frame = compared_frames.last().unwrap();
+        assert!(last_frame.multiple_hypotheses_ground_to_field.is_some());
+        let particle_filter_position = last_frame
+            .particle_filter_ground_to_field
+            .unwrap()
+            .as_pose()
+            .position();
+        let true_position = pose_after_kidnapping.as_pose().position();
+        let mirrored_true_position = point![-true_position.x(), -true_position.y()];
+        assert!(
+            distance(particle_filter_position, true_position) < 0.3
+                || distance(particle_filter_position, mirrored_true_position) < 0.3
+        );
+    }
+
+    #[test]
+    fn replay_starts_at_first_localizing_frame() {
+        let field_marks = field_marks_from_field_dimensions(&FieldDimensions::SPL);
+        let pose = ground_to_field(point![-3.0, 1.0], 0.3);
+        let mut frames = recording(&[pose; 10], pose, &field_marks);
+        frames[0].primary_state = PrimaryState::Initial;
+
+        let replayed = replay(
+            &frames,
+            LocalizationBackend::MultipleHypotheses,
+            &FieldDimensions::SPL,
+            PlayerNumber::Seven,
+            &parameters(),
+        )
+        .unwrap();
+
+        assert!(replayed[0].is_none());
+        assert!(replayed[1..].iter().all(Option::is_some));
+    }
+}
@@ -1,15 +1,18 @@
 use std::{
     collections::{BTreeMap, HashSet},
     fs::File,
-    io::BufReader,
-    path::PathBuf,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
     sync::Arc,
     time::{Duration, SystemTime},
 };
 
 use bincode::deserialize_from;
 use clap::Parser;
-use color_eyre::Result;
+use color_eyre::{
+    eyre::{bail, WrapErr},
+    Result,
+};
 use serde::{Deserialize, Serialize};
 use tokio::{select, sync::Notify, time::interval};
 use tokio_util::sync::CancellationToken;
@@ -20,12 +23,15 @@ use control::{
         get_fitted_field_mark_correspondence,
         goal_support_structure_line_marks_from_field_dimensions,
     },
-    localization_recorder::RecordedCycleContext,
+    localization_recorder::{RecordedCycleContext, RECORDING_FORMAT_VERSION, RECORDING_MAGIC},
 };
 use coordinate_systems::{Field, Ground};
 use framework::{multiple_buffer_with_slots, Reader, Writer};
 use linear_algebra::Isometry2;
+use parameters::directory::deserialize;
+use repository::get_repository_root;
 use serialize_hierarchy::SerializeHierarchy;
+use spl_network_messages::PlayerNumber;
 use types::{
     field_dimensions::FieldDimensions,
     field_marks::{field_marks_from_field_dimensions, FieldMark},
@@ -35,15 +41,60 @@ use types::{
     primary_state::PrimaryState,
 };
 
+use crate::{
+    comparison::{compare_backends, deviation, ComparedFrame, LocalizationParameters},
+    odometry_calibration::{
+        fit_odometry_correction, read_odometry_correction, write_odometry_correction,
+    },
+};
+
+mod comparison;
+mod odometry_calibration;
+
+const ODOMETRY_CALIBRATION_WINDOW_DURATION: Duration = Duration::from_secs(2);
+const MAXIMUM_RECORDING_GAP: Duration = Duration::from_millis(100);
+
 #[derive(Parser)]
 struct Arguments {
     #[arg(short, long, default_value = "[::]:1337")]
     listen_address: String,
+    /// Replay the recording with both localization backends, print how far they deviate from
+    /// each other and exit
+    #[arg(long)]
+    compare: bool,
+    /// Fit the odometry correction of the given body to the recorded poses and write it to the
+    /// body parameters
+    #[arg(long, value_name = "BODY_ID")]
+    calibrate_odometry: Option<String>,
+    /// Alternative parameters directory (if not given etc/parameters of the repository is used)
+    #[arg(long)]
+    parameters_directory: Option<PathBuf>,
     log_file: PathBuf,
 }
 
 fn main() -> Result<()> {
     let arguments = Arguments::parse();
+    let runtime = tokio::runtime::Runtime::new()?;
+    let parameters_directory = match arguments.parameters_directory {
+        Some(parameters_directory) => parameters_directory,
+        None => runtime
+            .block_on(get_repository_root())
+            .wrap_err("failed to get repository root")?
+            .join("etc/parameters"),
+    };
+
+    if let Some(body_id) = &arguments.calibrate_odometry {
+        let reader = BufReader::new(File::open(&arguments.log_file)?);
+        return calibrate_odometry(reader, &parameters_directory, body_id);
+    }
+
+    if arguments.compare {
+        let reader = BufReader::new(File::open(arguments.log_file)?);
+        let parameters: Parameters = runtime
+            .block_on(deserialize(&parameters_directory, "", ""))
+            .wrap_err_with(|| format!("failed to load parameters from {parameters_directory:?}"))?;
+        return print_comparison(reader, &parameters);
+    }
 
     let (
         keep_running,
@@ -54,11 +105,10 @@ fn main() -> Result<()> {
         database_changed,
         parameters_reader,
         parameters_changed,
-    ) = start_communication_server(arguments.listen_address)?;
+    ) = start_communication_server(arguments.listen_address, parameters_directory)?;
 
     let reader = BufReader::new(File::open(arguments.log_file)?);
 
-    let runtime = tokio::runtime::Runtime::new()?;
     runtime.block_on(recording_player(
         reader,
         simulator_writer,
@@ -74,16 +124,126 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+fn read_frames(mut reader: BufReader<File>) -> Result<Vec<RecordedCycleContext>> {
+    let (magic, format_version): ([u8; 4], u32) =
+        deserialize_from(&mut reader).wrap_err("failed to read recording header")?;
+    if magic != RECORDING_MAGIC {
+        bail!("recording has no header, it was recorded before format versions were introduced");
+    }
+    if format_version != RECORDING_FORMAT_VERSION {
+        bail!("recording has format version {format_version}, expected {RECORDING_FORMAT_VERSION}");
+    }
+    let mut frames = Vec::new();
+    while !reader
+        .fill_buf()
+        .wrap_err("failed to read recording")?
+        .is_empty()
+    {
+        let frame = deserialize_from(&mut reader)
+            .wrap_err_with(|| format!("failed to deserialize frame {}", frames.len()))?;
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+fn compare_frames(
+    frames: &[RecordedCycleContext],
+    parameters: &Parameters,
+) -> Result<Vec<ComparedFrame>> {
+    compare_backends(
+        frames,
+        &parameters.field_dimensions,
+        parameters.player_number,
+        &parameters.localization,
+    )
+}
+
+fn print_comparison(reader: BufReader<File>, parameters: &Parameters) -> Result<()> {
+    let frames = read_frames(reader)?;
+    let compared_frames = compare_frames(&frames, parameters)?;
+    for (index, frame) in compared_frames.iter().enumerate() {
+        if let Some((translation_deviation, rotation_deviation)) = frame.deviation() {
+            println!("{index}: {translation_deviation:.3} m, {rotation_deviation:.3} rad");
+        }
+    }
+    print_mean_deviation(
+        "particle filter to multiple hypotheses",
+        compared_frames.iter().filter_map(ComparedFrame::deviation),
+    );
+    print_mean_deviation(
+        "multiple hypotheses to recording",
+        compared_frames.iter().filter_map(|frame| {
+            Some(deviation(
+                frame.recorded_ground_to_field?,
+                frame.multiple_hypotheses_ground_to_field?,
+            ))
+        }),
+    );
+    print_mean_deviation(
+        "particle filter to recording",
+        compared_frames.iter().filter_map(|frame| {
+            Some(deviation(
+                frame.recorded_ground_to_field?,
+                frame.particle_filter_ground_to_field?,
+            ))
+        }),
+    );
+    Ok(())
+}
+
+fn print_mean_deviation(name: &str, deviations: impl Iterator<Item = (f32, f32)>) {
+    let (number_of_deviations, translation_sum, rotation_sum) = deviations.fold(
+        (0, 0.0, 0.0),
+        |(number_of_deviations, translation_sum, rotation_sum), (translation, rotation)| {
+            (
+                number_of_deviations + 1,
+                translation_sum + translation,
+                rotation_sum + rotation,
+            )
+        },
+    );
+    if number_of_deviations == 0 {
+        println!("{name}: no frames to compare");
+        return;
+    }
+    println!(
+        "{name}: mean over {number_of_deviations} frames: {:.3} m, {:.3} rad",
+        translation_sum / number_of_deviations as f32,
+        rotation_sum / number_of_deviations as f32,
+    );
+}
+
+fn calibrate_odometry(
+    reader: BufReader<File>,
+    parameters_directory: &Path,
+    body_id: &str,
+) -> Result<()> {
+    let frames = read_frames(reader)?;
+    let recording_correction = read_odometry_correction(parameters_directory, body_id)?;
+    let Some(correction) = fit_odometry_correction(
+        &frames,
+        &recording_correction,
+        ODOMETRY_CALIBRATION_WINDOW_DURATION,
+        MAXIMUM_RECORDING_GAP,
+    ) else {
+        bail!("not enough walking with recorded poses to fit the odometry correction");
+    };
+    println!("{correction:#?}");
+    write_odometry_correction(parameters_directory, body_id, &correction)
+}
+
 fn merge_line_data(line_data: &BTreeMap<SystemTime, Vec<Option<LineData>>>) -> LineData {
-    let lines = line_data
-        .values()
-        .flatten()
-        .flatten()
-        .flat_map(|line_data| line_data.lines.clone())
-        .collect();
+    let line_data: Vec<_> = line_data.values().flatten().flatten().collect();
     LineData {
-        lines,
+        lines: line_data
+            .iter()
+            .flat_map(|line_data| line_data.lines.clone())
+            .collect(),
         used_segments: HashSet::new(),
+        intersections: line_data
+            .iter()
+            .flat_map(|line_data| line_data.intersections.clone())
+            .collect(),
     }
 }
 
@@ -130,7 +290,7 @@ fn generate_field_marks(field_dimensions: FieldDimensions) -> Vec<FieldMark> {
 
 #[allow(clippy::too_many_arguments)]
 async fn recording_player(
-    mut reader: BufReader<File>,
+    reader: BufReader<File>,
     simulator_writer: Writer<SimulatorDatabase>,
     control_writer: Writer<ControlDatabase>,
     vision_top_writer: Writer<VisionDatabase>,
@@ -139,10 +299,9 @@ async fn recording_player(
     parameters_reader: Reader<Parameters>,
     parameters_changed: Arc<Notify>,
 ) -> Result<()> {
-    let mut frames = Vec::new();
-    while let Ok(data) = deserialize_from::<_, RecordedCycleContext>(&mut reader) {
-        frames.push(data);
-    }
+    let frames = read_frames(reader)?;
+    let mut compared_parameters = parameters_reader.next().clone();
+    let mut compared_frames = compare_frames(&frames, &compared_parameters)?;
     {
         simulator_writer.next().main_outputs.frame_count = frames.len();
     }
@@ -156,6 +315,10 @@ async fn recording_player(
         }
 
         let parameters = parameters_reader.next();
+        if !parameters.replays_equally(&compared_parameters)? {
+            compared_parameters = parameters.clone();
+            compared_frames = compare_frames(&frames, &compared_parameters)?;
+        }
 
         {
             let data = &frames[parameters.selected_frame];
@@ -180,6 +343,17 @@ async fn recording_player(
                 database.main_outputs.has_ground_contact = data.has_ground_contact;
                 database.main_outputs.primary_state = data.primary_state;
                 database.main_outputs.ground_to_field = data.ground_to_field;
+                let compared_frame = compared_frames[parameters.selected_frame];
+                database
+                    .additional_outputs
+                    .localization
+                    .multiple_hypotheses_ground_to_field =
+                    compared_frame.multiple_hypotheses_ground_to_field;
+                database
+                    .additional_outputs
+                    .localization
+                    .particle_filter_ground_to_field =
+                    compared_frame.particle_filter_ground_to_field;
             }
             {
                 let mut database = vision_top_writer.next();
@@ -202,12 +376,30 @@ async fn recording_player(
     }
 }
 
+/// Loaded from the robot parameters, only the selected frame is specific to the localizer
 #[derive(Clone, Debug, Deserialize, Serialize, SerializeHierarchy)]
 struct Parameters {
     field_dimensions: FieldDimensions,
+    localization: LocalizationParameters,
+    player_number: PlayerNumber,
+    #[serde(default)]
     selected_frame: usize,
 }
 
+impl Parameters {
+    /// Only selecting another frame leaves the replayed frames unchanged
+    fn replays_equally(&self, other: &Self) -> Result<bool> {
+        let replayed_parameters = |parameters: &Self| -> Result<serde_json::Value> {
+            Ok(serde_json::to_value((
+                &parameters.field_dimensions,
+                &parameters.localization,
+                parameters.player_number,
+            ))?)
+        };
+        Ok(replayed_parameters(self)? == replayed_parameters(other)?)
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 struct SimulatorMainOutputs {
     frame_count: usize,
@@ -221,6 +413,7 @@ struct SimulatorDatabase {
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 struct ControlDatabase {
     main_outputs: ControlMainOutputs,
+    additional_outputs: ControlAdditionalOutputs,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
@@ -231,6 +424,17 @@ struct ControlMainOutputs {
     pub ground_to_field: Option<Isometry2<Ground, Field>>,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+struct ControlAdditionalOutputs {
+    localization: ControlLocalizationAdditionalOutputs,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
+struct ControlLocalizationAdditionalOutputs {
+    multiple_hypotheses_ground_to_field: Option<Isometry2<Ground, Field>>,
+    particle_filter_ground_to_field: Option<Isometry2<Ground, Field>>,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, SerializeHierarchy)]
 struct VisionDatabase {
     main_outputs: VisionMainOutputs,
@@ -255,6 +459,7 @@ struct LocalizationAdditionalOutputs {
 #[allow(clippy::type_complexity)]
 fn start_communication_server(
     listen_address: String,
+    parameters_directory: PathBuf,
 ) -> Result<(
     CancellationToken,
     Writer<SimulatorDatabase>,
@@ -271,7 +476,7 @@ fn start_communication_server(
 
     let communication_server = Runtime::<Parameters>::start(
         Some(listen_address),
-        ".",
+        parameters_directory,
         "".to_string(),
         "".to_string(),
         parameter_slots,
@@ -0,0 +1,319 @@
+use std::{
+    fs::{read_to_string, write},
+    path::Path,
+    time::Duration,
+};
+
+use color_eyre::{eyre::WrapErr, Result};
+use nalgebra::{vector, Matrix2x3, Matrix3, Rotation2, Vector2, Vector3};
+use serde_json::{from_str, from_value, to_string_pretty, to_value, Value};
+
+use control::{localization_recorder::RecordedCycleContext, odometry::correct_odometry};
+use geometry::angle::normalize_angle;
+use parameters::json::{merge_json, nest_value_at_path};
+use types::{parameters::OdometryCorrectionParameters, primary_state::PrimaryState};
+
+const CORRECTION_PATH: &str = "odometry.correction";
+
+/// Consecutive walking cycles between two recorded poses
+#[derive(Clone, Debug)]
+struct Window {
+    /// Uncorrected odometry of each cycle as (x, y, angle)
+    steps: Vec<Vector3<f32>>,
+    /// Pose at the end of the window relative to the pose at its start
+    ground_truth: Vector3<f32>,
+}
+
+/// Maps uncorrected (x, y, angle) odometry to corrected odometry, the correction is linear
+pub fn correction_matrix(correction: &OdometryCorrectionParameters) -> Matrix3<f32> {
+    let corrected_step = |x, y, angle| {
+        let (offset, rotation) = correct_odometry(linear_algebra::vector![x, y], angle, correction);
+        vector![offset.x(), offset.y(), rotation]
+    };
+    Matrix3::from_columns(&[
+        corrected_step(1.0, 0.0, 0.0),
+        corrected_step(0.0, 1.0, 0.0),
+        corrected_step(0.0, 0.0, 1.0),
+    ])
+}
+
+fn is_walking(frame: &RecordedCycleContext) -> bool {
+    matches!(
+        frame.primary_state,
+        PrimaryState::Ready | PrimaryState::Playing
+    ) && frame.has_ground_contact
+        && frame.ground_to_field.is_some()
+        && frame.current_cycle_odometry_to_last_odometry.is_some()
+}
+
+/// Splits the recording into windows of at least `window_duration`
+///
+/// The odometry was corrected with `recording_correction` while recording, which is undone to
+/// obtain the uncorrected steps. Windows never span cycles without ground contact or gaps in the
+/// recording, e.g. while the robot was penalized.
+fn collect_windows(
+    frames: &[RecordedCycleContext],
+    recording_correction: &OdometryCorrectionParameters,
+    window_duration: Duration,
+    maximum_cycle_gap: Duration,
+) -> Vec<Window> {
+    let Some(uncorrection) = correction_matrix(recording_correction).try_inverse() else {
+        return Vec::new();
+    };
+    let mut windows = Vec::new();
+    let mut start: Option<&RecordedCycleContext> = None;
+    let mut steps = Vec::new();
+    let mut last_time = None;
+    for frame in frames {
+        let is_continuous = last_time.is_some_and(|last_time| {
+            frame
+                .cycle_start_time
+                .duration_since(last_time)
+                .is_ok_and(|gap| gap <= maximum_cycle_gap)
+        });
+        last_time = Some(frame.cycle_start_time);
+        if !is_walking(frame) || !is_continuous {
+            start = is_walking(frame).then_some(frame);
+            steps.clear();
+            continue;
+        }
+        let Some(start_frame) = start else {
+            start = Some(frame);
+            continue;
+        };
+
+        let odometry = frame.current_cycle_odometry_to_last_odometry.unwrap();
+        let corrected_step = vector![
+            odometry.translation.x,
+            odometry.translation.y,
+            odometry.rotation.angle()
+        ];
+        steps.push(uncorrection * corrected_step);
+
+        let elapsed = frame
+            .cycle_start_time
+            .duration_since(start_frame.cycle_start_time)
+            .unwrap_or_default();
+        if elapsed >= window_duration {
+            let start_pose = start_frame.ground_to_field.unwrap().inner;
+            let end_pose = frame.ground_to_field.unwrap().inner;
+            let relative_pose = start_pose.inverse() * end_pose;
+            windows.push(Window {
+                steps: steps.clone(),
+                ground_truth: vector![
+                    relative_pose.translation.x,
+                    relative_pose.translation.y,
+                    relative_pose.rotation.angle()
+                ],
+            });
+            start = Some(frame);
+            steps.clear();
+        }
+    }
+    windows
+}
+
+/// Least squares fit of the correction factors to windows of recorded walking
+///
+/// The rotation factors are fitted first. The translation factors are fitted afterwards with the
+/// headings integrated from the corrected rotation, which keeps both problems linear.
+fn fit_windows(windows: &[Window]) -> Option<OdometryCorrectionParameters> {
+    let mut rotation_normal_matrix = Matrix3::zeros();
+    let mut rotation_normal_vector = Vector3::zeros();
+    for window in windows {
+        let row: Vector3<f32> = window.steps.iter().sum();
+        rotation_normal_matrix += row * row.transpose();
+        rotation_normal_vector += row * normalize_angle(window.ground_truth.z);
+    }
+    let rotation_factors = rotation_normal_matrix.lu().solve(&rotation_normal_vector)?;
+
+    let mut translation_normal_matrix = Matrix3::zeros();
+    let mut translation_normal_vector = Vector3::zeros();
+    for window in windows {
+        let mut heading = 0.0;
+        let mut jacobian = Matrix2x3::zeros();
+        for step in &window.steps {
+            let rotation = Rotation2::new(heading);
+            jacobian += Matrix2x3::from_columns(&[
+                rotation * vector![step.x, 0.0],
+                rotation * vector![0.0, step.x],
+                rotation * vector![0.0, step.y],
+            ]);
+            heading += rotation_factors.dot(step);
+        }
+        let target: Vector2<f32> = window.ground_truth.xy();
+        translation_normal_matrix += jacobian.transpose() * jacobian;
+        translation_normal_vector += jacobian.transpose() * target;
+    }
+    let translation_factors = translation_normal_matrix
+        .lu()
+        .solve(&translation_normal_vector)?;
+
+    Some(OdometryCorrectionParameters {
+        forward_scale: translation_factors.x,
+        lateral_scale: translation_factors.z,
+        rotation_scale: rotation_factors.z,
+        lateral_per_forward: translation_factors.y,
+        rotation_per_forward: rotation_factors.x,
+        rotation_per_lateral: rotation_factors.y,
+    })
+}
+
+pub fn fit_odometry_correction(
+    frames: &[RecordedCycleContext],
+    recording_correction: &OdometryCorrectionParameters,
+    window_duration: Duration,
+    maximum_cycle_gap: Duration,
+) -> Option<OdometryCorrectionParameters> {
+    let windows = collect_windows(
+        frames,
+        recording_correction,
+        window_duration,
+        maximum_cycle_gap,
+    );
+    fit_windows(&windows)
+}
+
+fn read_json(path: &Path) -> Result<Value> {
+    let content = read_to_string(path).wrap_err_with(|| format!("failed to read {path:?}"))?;
+    from_str(&content).wrap_err_with(|| format!("failed to parse {path:?}"))
+}
+
+/// Correction which was active on the robot, i.e. the default overlaid with its body parameters
+pub fn read_odometry_correction(
+    parameters_directory: &Path,
+    body_id: &str,
+) -> Result<OdometryCorrectionParameters> {
+    let mut parameters = read_json(&parameters_directory.join("default.json"))?;
+    let body_path = parameters_directory.join(format!("body.{body_id}.json"));
+    if body_path.exists() {
+        merge_json(&mut parameters, &read_json(&body_path)?);
+    }
+    from_value(parameters["odometry"]["correction"].clone())
+        .wrap_err("failed to parse odometry correction")
+}
+
+pub fn write_odometry_correction(
+    parameters_directory: &Path,
+    body_id: &str,
+    correction: &OdometryCorrectionParameters,
+) -> Result<()> {
+    let body_path = parameters_directory.join(format!("body.{body_id}.json"));
+    let mut parameters = if body_path.exists() {
+        read_json(&body_path)?
+    } else {
+        Value::Object(Default::default())
+    };
+    merge_json(
+        &mut parameters,
+        &nest_value_at_path(CORRECTION_PATH, to_value(correction)?),
+    );
+    write(&body_path, to_string_pretty(&parameters)? + "\n")
+        .wrap_err_with(|| format!("failed to write {body_path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, time::SystemTime};
+
+    use approx::assert_relative_eq;
+    use linear_algebra::IntoTransform;
+    use nalgebra::Isometry2;
+
+    use super::*;
+
+    const CYCLE_TIME: Duration = Duration::from_millis(12);
+
+    fn true_correction() -> OdometryCorrectionParameters {
+        OdometryCorrectionParameters {
+            forward_scale: 1.2,
+            lateral_scale: 0.9,
+            rotation_scale: 1.05,
+            lateral_per_forward: 0.05,
+            rotation_per_forward: 0.1,
+            rotation_per_lateral: -0.2,
+        }
+    }
+
+    fn recording_correction() -> OdometryCorrectionParameters {
+        OdometryCorrectionParameters {
+            forward_scale: 1.1,
+            lateral_scale: 1.1,
+            rotation_scale: 1.0,
+            ..Default::default()
+        }
+    }
+
+    /// Robot walking a varying path, the ground truth pose integrates the true correction
+    fn recording() -> Vec<RecordedCycleContext> {
+        let true_matrix = correction_matrix(&true_correction());
+        let recording_matrix = correction_matrix(&recording_correction());
+        let mut pose = Isometry2::identity();
+        (0..5000)
+            .map(|cycle| {
+                let phase = cycle as f32 * 0.003;
+                let uncorrected = vector![
+                    0.002 * (1.0 + phase.sin()),
+                    0.0015 * (2.0 * phase).cos(),
+                    0.004 * (1.5 * phase).sin()
+                ];
+                let true_step = true_matrix * uncorrected;
+                pose *= Isometry2::new(true_step.xy(), true_step.z);
+                let recorded_step = recording_matrix * uncorrected;
+                let time = SystemTime::UNIX_EPOCH + CYCLE_TIME * cycle;
+                RecordedCycleContext {
+                    cycle_start_time: time,
+                    current_odometry_to_last_odometry: BTreeMap::new(),
+                    current_cycle_odometry_to_last_odometry: Some(Isometry2::new(
+                        recorded_step.xy(),
+                        recorded_step.z,
+                    )),
+                    filtered_game_controller_state: None,
+                    has_ground_contact: true,
+                    primary_state: PrimaryState::Playing,
+                    ground_to_field: Some(pose.framed_transform()),
+                    line_data_bottom_persistent: BTreeMap::new(),
+                    line_data_bottom_temporary: BTreeMap::new(),
+                    line_data_top_persistent: BTreeMap::new(),
+                    line_data_top_temporary: BTreeMap::new(),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn true_correction_is_recovered_from_recording() {
+        let correction = fit_odometry_correction(
+            &recording(),
+            &recording_correction(),
+            Duration::from_secs(1),
+            Duration::from_millis(100),
+        )
+        .expect("fit should succeed");
+
+        assert_relative_eq!(
+            correction_matrix(&correction),
+            correction_matrix(&true_correction()),
+            epsilon = 1e-2
+        );
+    }
+
+    #[test]
+    fn windows_do_not_span_gaps() {
+        let mut frames = recording();
+        for frame in &mut frames[1000..] {
+            frame.cycle_start_time += Duration::from_secs(30);
+        }
+        frames[3000].has_ground_contact = false;
+
+        let windows = collect_windows(
+            &frames,
+            &recording_correction(),
+            Duration::from_secs(1),
+            Duration::from_millis(100),
+        );
+
+        // one second are 84 cycles, the segments contain 1000, 2000 and 1999 cycles
+        assert_eq!(windows.len(), 11 + 23 + 23);
+    }
+}
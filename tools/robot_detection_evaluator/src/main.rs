@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use color_eyre::{
+    eyre::{bail, WrapErr},
+    Result,
+};
+use vision::{
+    robot_detection::{non_maximum_suppression, RobotDetectionNetwork},
+    robot_detection_evaluation::{evaluate_annotated_image, Evaluation},
+};
+
+/// Precision and recall of the robot detection on images annotated with annotato
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct CommandlineArguments {
+    /// Path to the robot detection network, e.g. etc/neural_networks/robot_detection.hdf5
+    network: PathBuf,
+    /// Annotated images, the annotations are read from the JSON file next to each image
+    #[clap(required = true)]
+    images: Vec<PathBuf>,
+    #[clap(long, default_value = "0.6")]
+    detection_threshold: f32,
+    /// Intersection over union above which the less confident of two detections is suppressed
+    #[clap(long, default_value = "0.4")]
+    maximum_overlap: f32,
+    /// Intersection over union a detection needs with an annotated robot to count as correct
+    #[clap(long, default_value = "0.5")]
+    minimum_overlap: f32,
+    /// Fails the evaluation if the total precision is lower
+    #[clap(long, default_value = "0.8")]
+    minimum_precision: f32,
+    /// Fails the evaluation if the total recall is lower
+    #[clap(long, default_value = "0.7")]
+    minimum_recall: f32,
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    let arguments = CommandlineArguments::parse();
+
+    let mut network = RobotDetectionNetwork::load(&arguments.network)
+        .wrap_err_with(|| format!("failed to load network {:?}", arguments.network))?;
+    let mut total = Evaluation::default();
+    for image_path in &arguments.images {
+        let evaluation =
+            evaluate_annotated_image(image_path, arguments.minimum_overlap, |image| {
+                let candidates = network.candidates(image, arguments.detection_threshold);
+                non_maximum_suppression(candidates, arguments.maximum_overlap)
+                    .into_iter()
+                    .map(|detection| detection.bounding_box)
+                    .collect()
+            })?;
+        println!(
+            "{}: precision {:.2}, recall {:.2}",
+            image_path.display(),
+            evaluation.precision(),
+            evaluation.recall()
+        );
+        total += evaluation;
+    }
+    println!(
+        "total: precision {:.2} ({} of {} detections), recall {:.2} ({} of {} robots)",
+        total.precision(),
+        total.true_positives,
+        total.number_of_detections,
+        total.recall(),
+        total.found_robots,
+        total.number_of_robots
+    );
+    if total.number_of_robots == 0 {
+        bail!("the annotations contain no robots, the detection cannot be evaluated");
+    }
+    if total.precision() < arguments.minimum_precision || total.recall() < arguments.minimum_recall
+    {
+        bail!(
+            "robot detection is below the minimum precision {} or recall {}",
+            arguments.minimum_precision,
+            arguments.minimum_recall
+        );
+    }
+
+    Ok(())
+}
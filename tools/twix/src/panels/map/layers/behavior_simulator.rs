@@ -2,10 +2,11 @@ use std::{str::FromStr, sync::Arc};
 
 use color_eyre::Result;
 use eframe::epaint::{Color32, Stroke};
+use serde::Deserialize;
 
 use communication::client::CyclerOutput;
 use coordinate_systems::{Field, Ground};
-use linear_algebra::{IntoFramed, Isometry2, Point2};
+use linear_algebra::{IntoFramed, Isometry2, Orientation2, Point2, Pose};
 use types::{field_dimensions::FieldDimensions, motion_command::MotionCommand};
 
 use crate::{
@@ -16,11 +17,19 @@ use crate::{
 const TRANSPARENT_BLUE: Color32 = Color32::from_rgba_premultiplied(0, 0, 202, 150);
 const TRANSPARENT_LIGHT_BLUE: Color32 = Color32::from_rgba_premultiplied(136, 170, 182, 150);
 
+#[derive(Deserialize)]
+struct Opponent {
+    position: Point2<Field>,
+    orientation: f32,
+    is_penalized: bool,
+}
+
 pub struct BehaviorSimulator {
     ground_to_field: PlayersValueBuffer,
     motion_command: PlayersValueBuffer,
     head_yaw: PlayersValueBuffer,
     ball: ValueBuffer,
+    opponents: ValueBuffer,
 }
 
 impl Layer for BehaviorSimulator {
@@ -48,11 +57,15 @@ impl Layer for BehaviorSimulator {
         let ball = nao.subscribe_output(
             CyclerOutput::from_str("BehaviorSimulator.main_outputs.ball.position").unwrap(),
         );
+        let opponents = nao.subscribe_output(
+            CyclerOutput::from_str("BehaviorSimulator.main_outputs.opponents").unwrap(),
+        );
         Self {
             ground_to_field,
             motion_command,
             head_yaw: sensor_data,
             ball,
+            opponents,
         }
     }
 
@@ -117,6 +130,22 @@ impl Layer for BehaviorSimulator {
             );
         }
 
+        if let Ok(opponents) = self.opponents.parse_latest::<Vec<Opponent>>() {
+            let opponent_stroke = Stroke {
+                width: 0.02,
+                color: Color32::RED,
+            };
+            for opponent in opponents.iter().filter(|opponent| !opponent.is_penalized) {
+                painter.pose(
+                    Pose::from_parts(opponent.position, Orientation2::new(opponent.orientation)),
+                    0.15,
+                    0.25,
+                    Color32::from_white_alpha(63),
+                    opponent_stroke,
+                );
+            }
+        }
+
         if let Ok(ball_position) = self.ball.parse_latest::<Point2<Field>>() {
             painter.ball(ball_position, 0.05);
         }
@@ -0,0 +1,68 @@
+use std::{str::FromStr, sync::Arc};
+
+use color_eyre::Result;
+use communication::client::CyclerOutput;
+use eframe::epaint::{Color32, Stroke};
+
+use coordinate_systems::{Field, Ground};
+use linear_algebra::Isometry2;
+use types::{dribble_plan, field_dimensions::FieldDimensions};
+
+use crate::{
+    nao::Nao, panels::map::layer::Layer, twix_painter::TwixPainter, value_buffer::ValueBuffer,
+};
+
+pub struct DribblePlan {
+    ground_to_field: ValueBuffer,
+    dribble_plan: ValueBuffer,
+}
+
+impl Layer for DribblePlan {
+    const NAME: &'static str = "Dribble Plan";
+
+    fn new(nao: Arc<Nao>) -> Self {
+        let ground_to_field =
+            nao.subscribe_output(CyclerOutput::from_str("Control.main.ground_to_field").unwrap());
+        let dribble_plan = nao
+            .subscribe_output(CyclerOutput::from_str("Control.additional.dribble_plan").unwrap());
+        Self {
+            ground_to_field,
+            dribble_plan,
+        }
+    }
+
+    fn paint(
+        &self,
+        painter: &TwixPainter<Field>,
+        _field_dimensions: &FieldDimensions,
+    ) -> Result<()> {
+        let ground_to_field: Isometry2<Ground, Field> = self.ground_to_field.require_latest()?;
+        let plan: dribble_plan::DribblePlan = self.dribble_plan.require_latest()?;
+
+        for (index, step) in plan.steps.iter().enumerate() {
+            let color = if index == 0 {
+                Color32::YELLOW
+            } else {
+                Color32::from_rgb(255, 140, 0)
+            };
+            painter.pose(
+                ground_to_field * step.kick_decision.kick_pose,
+                0.05,
+                0.1,
+                Color32::from_white_alpha(10),
+                Stroke { width: 0.01, color },
+            );
+            painter.line_segment(
+                ground_to_field * step.ball_start,
+                ground_to_field * step.ball_end,
+                Stroke { width: 0.02, color },
+            );
+            painter.circle_stroke(
+                ground_to_field * step.ball_end,
+                0.05,
+                Stroke { width: 0.01, color },
+            );
+        }
+        Ok(())
+    }
+}
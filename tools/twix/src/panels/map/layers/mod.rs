@@ -1,6 +1,7 @@
 mod ball_filter;
 mod ball_position;
 mod behavior_simulator;
+mod dribble_plan;
 mod feet_detection;
 mod field;
 mod image_segments;
@@ -11,11 +12,13 @@ mod obstacle_filter;
 mod obstacles;
 mod path;
 mod path_obstacles;
+mod positioning;
 mod robot_pose;
 
 pub use self::behavior_simulator::BehaviorSimulator;
 pub use ball_filter::BallFilter;
 pub use ball_position::BallPosition;
+pub use dribble_plan::DribblePlan;
 pub use feet_detection::FeetDetection;
 pub use field::Field;
 pub use image_segments::ImageSegments;
@@ -26,4 +29,5 @@ pub use obstacle_filter::ObstacleFilter;
 pub use obstacles::Obstacles;
 pub use path::Path;
 pub use path_obstacles::PathObstacles;
+pub use positioning::Positioning;
 pub use robot_pose::RobotPose;
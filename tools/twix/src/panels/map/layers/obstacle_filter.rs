@@ -50,8 +50,12 @@ impl Layer for ObstacleFilter {
 
         for hypothesis in hypotheses.iter() {
             let position =
-                ground_to_field.unwrap_or_default() * Point2::from(hypothesis.state.mean);
-            let covariance = hypothesis.state.covariance;
+                ground_to_field.unwrap_or_default() * Point2::from(hypothesis.state.mean.xy());
+            let covariance = hypothesis
+                .state
+                .covariance
+                .fixed_view::<2, 2>(0, 0)
+                .into_owned();
             let stroke = Stroke::new(0.01, Color32::BLACK);
             let fill_color = Color32::from_rgba_unmultiplied(255, 255, 0, 20);
             painter.covariance(position, covariance, stroke, fill_color);
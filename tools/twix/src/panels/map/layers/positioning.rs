@@ -0,0 +1,68 @@
+use std::{str::FromStr, sync::Arc};
+
+use color_eyre::Result;
+use eframe::epaint::Color32;
+
+use communication::client::CyclerOutput;
+use coordinate_systems::Field;
+use linear_algebra::vector;
+use types::{field_dimensions::FieldDimensions, positioning::PositioningHeatmap};
+
+use crate::{
+    nao::Nao, panels::map::layer::Layer, twix_painter::TwixPainter, value_buffer::ValueBuffer,
+};
+
+pub struct Positioning {
+    positioning_heatmap: ValueBuffer,
+}
+
+impl Layer for Positioning {
+    const NAME: &'static str = "Positioning";
+
+    fn new(nao: Arc<Nao>) -> Self {
+        let positioning_heatmap = nao.subscribe_output(
+            CyclerOutput::from_str("Control.additional.positioning_heatmap").unwrap(),
+        );
+        Self {
+            positioning_heatmap,
+        }
+    }
+
+    fn paint(
+        &self,
+        painter: &TwixPainter<Field>,
+        _field_dimensions: &FieldDimensions,
+    ) -> Result<()> {
+        let heatmap: PositioningHeatmap = self.positioning_heatmap.require_latest()?;
+
+        let feasible_utilities = heatmap
+            .candidates
+            .iter()
+            .filter(|candidate| candidate.is_feasible)
+            .map(|candidate| candidate.utility);
+        let minimum = feasible_utilities.clone().fold(f32::INFINITY, f32::min);
+        let maximum = feasible_utilities.fold(f32::NEG_INFINITY, f32::max);
+        let range = (maximum - minimum).max(f32::EPSILON);
+
+        let half_cell = vector![heatmap.cell_size, heatmap.cell_size] / 2.0;
+        for candidate in heatmap.candidates {
+            let color = if candidate.is_feasible {
+                let normalized = (candidate.utility - minimum) / range;
+                Color32::from_rgba_unmultiplied(
+                    (255.0 * normalized) as u8,
+                    0,
+                    (255.0 * (1.0 - normalized)) as u8,
+                    100,
+                )
+            } else {
+                Color32::from_black_alpha(100)
+            };
+            painter.rect_filled(
+                candidate.position - half_cell,
+                candidate.position + half_cell,
+                color,
+            );
+        }
+        Ok(())
+    }
+}
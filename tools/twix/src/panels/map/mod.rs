@@ -31,6 +31,8 @@ pub struct MapPanel {
     feet_detection: EnabledLayer<layers::FeetDetection>,
     ball_filter: EnabledLayer<layers::BallFilter>,
     obstacle_filter: EnabledLayer<layers::ObstacleFilter>,
+    positioning: EnabledLayer<layers::Positioning>,
+    dribble_plan: EnabledLayer<layers::DribblePlan>,
 }
 
 impl Panel for MapPanel {
@@ -51,6 +53,8 @@ impl Panel for MapPanel {
         let feet_detection = EnabledLayer::new(nao.clone(), value, false);
         let ball_filter = EnabledLayer::new(nao.clone(), value, false);
         let obstacle_filter = EnabledLayer::new(nao.clone(), value, false);
+        let positioning = EnabledLayer::new(nao.clone(), value, false);
+        let dribble_plan = EnabledLayer::new(nao.clone(), value, false);
 
         let field_dimensions = nao.subscribe_parameter("field_dimensions");
         let transformation = Similarity2::identity();
@@ -72,6 +76,8 @@ impl Panel for MapPanel {
             feet_detection,
             ball_filter,
             obstacle_filter,
+            positioning,
+            dribble_plan,
         }
     }
 
@@ -91,6 +97,8 @@ impl Panel for MapPanel {
             "feet_detection": self.feet_detection.save(),
             "ball_filter": self.ball_filter.save(),
             "obstacle_filter": self.obstacle_filter.save(),
+            "positioning": self.positioning.save(),
+            "dribble_plan": self.dribble_plan.save(),
         })
     }
 }
@@ -112,6 +120,8 @@ impl Widget for &mut MapPanel {
             self.feet_detection.checkbox(ui);
             self.ball_filter.checkbox(ui);
             self.obstacle_filter.checkbox(ui);
+            self.positioning.checkbox(ui);
+            self.dribble_plan.checkbox(ui);
         });
 
         let field_dimensions: FieldDimensions = match self.field_dimensions.get_latest() {
@@ -137,6 +147,8 @@ impl Widget for &mut MapPanel {
         let _ = self.feet_detection.paint(&painter, &field_dimensions);
         let _ = self.ball_filter.paint(&painter, &field_dimensions);
         let _ = self.obstacle_filter.paint(&painter, &field_dimensions);
+        let _ = self.positioning.paint(&painter, &field_dimensions);
+        let _ = self.dribble_plan.paint(&painter, &field_dimensions);
 
         self.apply_zoom_and_pan(ui, &mut painter, &response);
         if response.double_clicked() {